@@ -6,6 +6,11 @@ use std::path::Path;
 // Must be public so the build script of `std` can call it.
 pub fn main() {
     match env::var("CARGO_CFG_TARGET_OS").unwrap_or_default().as_str() {
+        // Without a C compiler the API level can't be detected, so it's taken
+        // to be recent enough.
+        "android" if env::var_os("CARGO_FEATURE_PURE_RUST").is_some() => {
+            println!("cargo:rustc-cfg=feature=\"dl_iterate_phdr\"");
+        }
         "android" => build_android(),
         _ => {}
     }
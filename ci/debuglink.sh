@@ -20,6 +20,12 @@ strip -g $exefile
 (cd `dirname $exefile` && objcopy --add-gnu-debuglink=debuglink.debug debuglink)
 $exefile $cratedir
 
+# Debug file whose CRC doesn't match the debug link should fail
+cp $debugfile1 $debugfile1.tmp
+echo >> $debugfile1
+! $exefile $cratedir
+mv $debugfile1.tmp $debugfile1
+
 # Separate debug in .debug subdir
 debugfile2=`dirname $exefile`/.debug/debuglink.debug
 mkdir -p `dirname $debugfile2`
@@ -44,6 +50,15 @@ mv $idfile $debugfile3
 ln -s $debugfile3 $idfile
 $exefile $cratedir
 
+# Separate debug in a global debug directory from the environment
+customdir=$cratedir/target/debug-dirs
+debugfile4="$customdir/$cratedir/target/debug/debuglink.debug"
+mkdir -p `dirname $debugfile4`
+mv $debugfile3 $debugfile4
+! $exefile $cratedir
+BACKTRACE_DEBUG_DIRS=$customdir $exefile $cratedir
+mv $debugfile4 $debugfile3
+
 # Supplementary object file using relative path
 dwzfile="/usr/lib/debug/.dwz/debuglink.debug"
 mkdir -p `dirname $dwzfile`
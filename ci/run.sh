@@ -4,3 +4,13 @@ set -ex
 
 cargo test --target $TARGET
 cargo build --target $TARGET --manifest-path crates/as-if-std/Cargo.toml
+
+# Test statically linked PIE binaries explicitly, which musl targets build by
+# default, as their C libraries work out where the program is loaded
+# themselves.
+case "$TARGET" in
+  *-linux-musl*)
+    RUSTFLAGS="$RUSTFLAGS -C target-feature=+crt-static -C relocation-model=pie" \
+      cargo test --target $TARGET
+    ;;
+esac
@@ -0,0 +1,78 @@
+/*
+ * C interface to the `backtrace` crate, built from the `backtrace-ffi` crate
+ * as a shared or static library.
+ *
+ * Symbols are resolved with the same cache as Rust code of the process using
+ * the `backtrace` crate, and all functions are thread-safe. Callbacks must
+ * not unwind, and must not call these functions themselves.
+ */
+
+#ifndef BACKTRACE_FFI_H
+#define BACKTRACE_FFI_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/*
+ * Called with the instruction pointer of each frame of a backtrace, starting
+ * with the caller of `backtrace_capture`. Returning zero stops the backtrace.
+ */
+typedef int (*backtrace_frame_fn)(void *data, uintptr_t ip);
+
+/*
+ * Calls `cb` with `data` for the frames of the current thread's stack, and
+ * returns how many frames were passed to it, or just counts them if `cb` is
+ * NULL.
+ *
+ * On platforms where the start of functions isn't known while tracing, like
+ * macOS and 32-bit ARM, the frames of `backtrace_capture` itself come first.
+ */
+size_t backtrace_capture(backtrace_frame_fn cb, void *data);
+
+/*
+ * A symbol found for an address. Strings are NUL-terminated and only valid
+ * during the callback they're passed to.
+ */
+struct backtrace_symbol {
+    /* The demangled name of the function, or NULL if unknown. */
+    const char *name;
+    /* The name of the function as found in the binary, or NULL if unknown. */
+    const char *raw_name;
+    /* The address the function starts at, or 0 if unknown. */
+    uintptr_t addr;
+    /* The source file of the address, or NULL if unknown. */
+    const char *filename;
+    /* The line of the address in `filename`, or 0 if unknown. */
+    uint32_t lineno;
+    /* The column of the address in `filename`, or 0 if unknown. */
+    uint32_t colno;
+};
+
+/*
+ * Called for each symbol found for an address, starting with the innermost
+ * function inlined at it.
+ */
+typedef void (*backtrace_symbol_fn)(void *data, const struct backtrace_symbol *symbol);
+
+/*
+ * Calls `cb` with `data` for the symbols of the return address `ip`, as
+ * passed to a `backtrace_frame_fn`, and returns how many were found. `cb` may
+ * be NULL to only count them.
+ */
+size_t backtrace_resolve(uintptr_t ip, backtrace_symbol_fn cb, void *data);
+
+/*
+ * Like `backtrace_resolve`, but for the address of an instruction itself
+ * rather than a return address, like the address of a faulting instruction.
+ */
+size_t backtrace_resolve_precise(uintptr_t ip, backtrace_symbol_fn cb, void *data);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
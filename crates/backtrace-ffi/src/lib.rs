@@ -0,0 +1,112 @@
+//! A C interface to capture backtraces and resolve their symbols with the
+//! `backtrace` crate, so that C and C++ code in the same process can use it in
+//! place of libbacktrace and share its cache of symbols.
+//!
+//! The functions are declared for C in `include/backtrace_ffi.h`.
+
+use backtrace::{AddressKind, Symbol};
+use std::ffi::{c_char, c_int, c_void, CString};
+use std::ptr;
+
+/// A symbol passed to a `BacktraceSymbolFn`, `struct backtrace_symbol` in C.
+#[repr(C)]
+pub struct BacktraceSymbol {
+    pub name: *const c_char,
+    pub raw_name: *const c_char,
+    pub addr: usize,
+    pub filename: *const c_char,
+    pub lineno: u32,
+    pub colno: u32,
+}
+
+/// Called with the instruction pointer of each frame by `backtrace_capture`,
+/// which stops once it returns zero. `None` is a NULL pointer in C.
+pub type BacktraceFrameFn = Option<extern "C" fn(data: *mut c_void, ip: usize) -> c_int>;
+
+/// Called with each symbol found by `backtrace_resolve`. `None` is a NULL
+/// pointer in C.
+pub type BacktraceSymbolFn =
+    Option<extern "C" fn(data: *mut c_void, symbol: *const BacktraceSymbol)>;
+
+/// Passes the instruction pointers of the frames of the current thread's
+/// stack to `cb`, starting with the caller, and returns how many were passed,
+/// or just counts them if `cb` is `None`.
+///
+/// The frames up to this function are found with `Frame::symbol_address`, so
+/// on platforms where it only returns the instruction pointer, like macOS and
+/// 32-bit ARM, they're passed as well.
+#[no_mangle]
+pub extern "C" fn backtrace_capture(cb: BacktraceFrameFn, data: *mut c_void) -> usize {
+    let this = backtrace_capture as *const () as usize;
+    let mut ips = Vec::new();
+    backtrace::trace(|frame| {
+        ips.push(frame.ip() as usize);
+        // Frames up to this function are of no interest to the caller.
+        if frame.symbol_address() as usize == this {
+            ips.clear();
+        }
+        true
+    });
+
+    // The callback is only called once the backtrace lock is released.
+    let cb = match cb {
+        Some(cb) => cb,
+        None => return ips.len(),
+    };
+    let mut count = 0;
+    for ip in ips {
+        count += 1;
+        if cb(data, ip) == 0 {
+            break;
+        }
+    }
+    count
+}
+
+/// Passes the symbols found for the return address `ip` to `cb`, if it's not
+/// `None`, and returns how many were found.
+#[no_mangle]
+pub extern "C" fn backtrace_resolve(ip: usize, cb: BacktraceSymbolFn, data: *mut c_void) -> usize {
+    resolve(ip, AddressKind::ReturnAddress, cb, data)
+}
+
+/// Passes the symbols found for the address of the instruction `ip` to `cb`,
+/// if it's not `None`, and returns how many were found.
+#[no_mangle]
+pub extern "C" fn backtrace_resolve_precise(
+    ip: usize,
+    cb: BacktraceSymbolFn,
+    data: *mut c_void,
+) -> usize {
+    resolve(ip, AddressKind::Precise, cb, data)
+}
+
+fn resolve(ip: usize, kind: AddressKind, cb: BacktraceSymbolFn, data: *mut c_void) -> usize {
+    let mut count = 0;
+    backtrace::resolve_address(ip as *mut c_void, kind, |symbol| {
+        count += 1;
+        if let Some(cb) = cb {
+            with_symbol(symbol, |symbol| cb(data, symbol));
+        }
+    });
+    count
+}
+
+/// Converts `symbol` for C, with strings that live as long as `f` runs.
+fn with_symbol(symbol: &Symbol, f: impl FnOnce(&BacktraceSymbol)) {
+    let name = symbol.name();
+    let demangled = name.as_ref().and_then(|n| CString::new(n.to_string()).ok());
+    let raw_name = name.and_then(|n| CString::new(n.as_bytes()).ok());
+    let filename = symbol
+        .filename_raw()
+        .and_then(|f| CString::new(f.to_str_lossy().into_owned()).ok());
+    let as_ptr = |s: &Option<CString>| s.as_ref().map_or(ptr::null(), |s| s.as_ptr());
+    f(&BacktraceSymbol {
+        name: as_ptr(&demangled),
+        raw_name: as_ptr(&raw_name),
+        addr: symbol.addr().map_or(0, |addr| addr as usize),
+        filename: as_ptr(&filename),
+        lineno: symbol.lineno().unwrap_or(0),
+        colno: symbol.colno().unwrap_or(0),
+    })
+}
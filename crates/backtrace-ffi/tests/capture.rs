@@ -0,0 +1,74 @@
+use backtrace_ffi::{backtrace_capture, backtrace_resolve, backtrace_resolve_precise};
+use backtrace_ffi::{BacktraceSymbol, BacktraceSymbolFn};
+use std::ffi::{c_int, c_void, CStr};
+
+extern "C" fn push_ip(data: *mut c_void, ip: usize) -> c_int {
+    let ips = unsafe { &mut *(data as *mut Vec<usize>) };
+    ips.push(ip);
+    1
+}
+
+extern "C" fn stop(_data: *mut c_void, _ip: usize) -> c_int {
+    0
+}
+
+extern "C" fn push_name(data: *mut c_void, symbol: *const BacktraceSymbol) {
+    let names = unsafe { &mut *(data as *mut Vec<String>) };
+    let symbol = unsafe { &*symbol };
+    if !symbol.name.is_null() {
+        let name = unsafe { CStr::from_ptr(symbol.name) };
+        names.push(name.to_string_lossy().into_owned());
+    }
+}
+
+fn names(
+    ip: usize,
+    resolve: extern "C" fn(usize, BacktraceSymbolFn, *mut c_void) -> usize,
+) -> Vec<String> {
+    let mut names = Vec::new();
+    let count = resolve(
+        ip,
+        Some(push_name),
+        &mut names as *mut Vec<String> as *mut c_void,
+    );
+    assert_eq!(count, names.len());
+    assert_eq!(resolve(ip, None, std::ptr::null_mut()), count);
+    names
+}
+
+#[inline(never)]
+fn capture() -> Vec<usize> {
+    let mut ips = Vec::new();
+    let count = backtrace_capture(Some(push_ip), &mut ips as *mut Vec<usize> as *mut c_void);
+    assert_eq!(count, ips.len());
+    ips
+}
+
+#[test]
+fn captures_and_resolves() {
+    let ips = capture();
+    assert!(ips.len() > 1);
+    assert_eq!(backtrace_capture(Some(stop), std::ptr::null_mut()), 1);
+    assert!(backtrace_capture(None, std::ptr::null_mut()) > 1);
+
+    // The first frame is the caller of `backtrace_capture`, except where
+    // `symbol_address` doesn't find the start of functions.
+    if cfg!(not(any(target_vendor = "apple", target_arch = "arm"))) {
+        let first = names(ips[0], backtrace_resolve);
+        assert!(
+            first.iter().any(|n| n.contains("capture::capture")),
+            "{first:?}"
+        );
+        assert!(
+            !first.iter().any(|n| n.contains("backtrace_capture")),
+            "{first:?}"
+        );
+    }
+
+    let this = captures_and_resolves as *const () as usize;
+    let precise = names(this, backtrace_resolve_precise);
+    assert!(
+        precise.iter().any(|n| n.contains("captures_and_resolves")),
+        "{precise:?}"
+    );
+}
@@ -0,0 +1,242 @@
+//! Routing the allocations of this crate to a separate allocator.
+
+use core::alloc::{GlobalAlloc, Layout};
+use core::ptr;
+use core::sync::atomic::{AtomicPtr, Ordering};
+
+static HOOKS: AtomicPtr<AllocatorHooks> = AtomicPtr::new(ptr::null_mut());
+
+/// All hooks ever set, in the order they were first set, followed by nulls.
+static OWNERS: [AtomicPtr<AllocatorHooks>; MAX_HOOKS] = [
+    AtomicPtr::new(ptr::null_mut()),
+    AtomicPtr::new(ptr::null_mut()),
+    AtomicPtr::new(ptr::null_mut()),
+    AtomicPtr::new(ptr::null_mut()),
+    AtomicPtr::new(ptr::null_mut()),
+    AtomicPtr::new(ptr::null_mut()),
+    AtomicPtr::new(ptr::null_mut()),
+    AtomicPtr::new(ptr::null_mut()),
+    AtomicPtr::new(ptr::null_mut()),
+    AtomicPtr::new(ptr::null_mut()),
+    AtomicPtr::new(ptr::null_mut()),
+    AtomicPtr::new(ptr::null_mut()),
+    AtomicPtr::new(ptr::null_mut()),
+    AtomicPtr::new(ptr::null_mut()),
+    AtomicPtr::new(ptr::null_mut()),
+    AtomicPtr::new(ptr::null_mut()),
+];
+const MAX_HOOKS: usize = 16;
+
+/// Functions managing a private pool of memory, like an arena, which
+/// allocations made while capturing backtraces and resolving symbols are
+/// routed to by `SymbolizerAllocator`, see `set_allocator_hooks`.
+///
+/// # Required features
+///
+/// This type requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default.
+#[derive(Copy, Clone, Debug)]
+pub struct AllocatorHooks {
+    /// Allocates memory for `layout` from the pool, like `GlobalAlloc::alloc`,
+    /// returning null if it's exhausted.
+    pub alloc: unsafe fn(Layout) -> *mut u8,
+    /// Frees memory allocated with `alloc`, like `GlobalAlloc::dealloc`. This
+    /// may be a no-op for arenas which are freed as a whole.
+    pub dealloc: unsafe fn(*mut u8, Layout),
+    /// Returns whether the memory at the given address was allocated with
+    /// `alloc`.
+    ///
+    /// Memory allocated from the pool may also be freed outside of this
+    /// crate, so this decides which allocator it's returned to.
+    pub owns: fn(*mut u8) -> bool,
+}
+
+/// Sets the functions allocating memory for this crate while it captures
+/// backtraces or resolves symbols, or removes them with `None`.
+///
+/// Rust's collections always allocate from the global allocator, so this only
+/// has an effect if the global allocator of the program is a
+/// `SymbolizerAllocator`. It then passes allocations made by a thread while
+/// it holds the internal lock of this crate, which it does while tracing and
+/// resolving symbols, to `hooks` instead of the allocator it wraps. This
+/// allows allocators instrumenting allocations to capture backtraces without
+/// re-entering themselves, and keeps the caches of debug information out of
+/// the heap of the application.
+///
+/// Closures passed to functions like `trace` and `resolve` also run with the
+/// lock held, so their allocations are routed to `hooks` as well. That
+/// includes data returned to the caller, like the frames and symbols of a
+/// `Backtrace`, so the pool has to keep memory valid until it's freed.
+///
+/// Memory allocated with hooks is always returned to them, even once they've
+/// been replaced or removed, as caches of this crate and data returned to
+/// callers may keep it for as long as the process runs. Removing hooks only
+/// stops new allocations from being routed to them. Freed memory goes to the
+/// first hooks which own it, so the pools of different hooks shouldn't
+/// overlap.
+///
+/// # Panics
+///
+/// Panics if more than 16 different hooks are set over the lifetime of the
+/// process.
+///
+/// # Required features
+///
+/// This function requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default.
+pub fn set_allocator_hooks(hooks: Option<&'static AllocatorHooks>) {
+    let hooks: *mut AllocatorHooks =
+        hooks.map_or(ptr::null_mut(), |hooks| hooks as *const _ as *mut _);
+    if !hooks.is_null() {
+        // Remember the hooks before anything is allocated with them.
+        let registered = OWNERS.iter().any(|owner| {
+            match owner.compare_exchange(
+                ptr::null_mut(),
+                hooks,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => true,
+                Err(current) => current == hooks,
+            }
+        });
+        assert!(
+            registered,
+            "at most {} different allocator hooks can be set",
+            MAX_HOOKS
+        );
+    }
+    HOOKS.store(hooks, Ordering::Release);
+}
+
+fn hooks() -> Option<&'static AllocatorHooks> {
+    // SAFETY: non-null values of `HOOKS` are only ever stored by
+    // `set_allocator_hooks`, from a `&'static AllocatorHooks`.
+    unsafe { HOOKS.load(Ordering::Acquire).as_ref() }
+}
+
+/// Returns all hooks ever set.
+fn owners() -> impl Iterator<Item = &'static AllocatorHooks> {
+    // SAFETY: like `HOOKS`, `OWNERS` only holds `&'static AllocatorHooks`.
+    OWNERS
+        .iter()
+        .map_while(|owner| unsafe { owner.load(Ordering::Acquire).as_ref() })
+}
+
+/// A global allocator passing the allocations of this crate to the hooks set
+/// with `set_allocator_hooks`, and all others to the allocator it wraps.
+///
+/// # Required features
+///
+/// This type requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default.
+///
+/// # Example
+///
+/// ```no_run
+/// extern crate backtrace;
+///
+/// use backtrace::{AllocatorHooks, SymbolizerAllocator};
+/// use std::alloc::{Layout, System};
+/// use std::cell::UnsafeCell;
+/// use std::ptr;
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+///
+/// #[global_allocator]
+/// static ALLOCATOR: SymbolizerAllocator<System> = SymbolizerAllocator(System);
+///
+/// // An arena which is never freed, for all allocations of `backtrace`.
+/// const SIZE: usize = 256 << 20;
+/// struct Arena(UnsafeCell<[u8; SIZE]>);
+/// unsafe impl Sync for Arena {}
+/// static ARENA: Arena = Arena(UnsafeCell::new([0; SIZE]));
+/// static USED: AtomicUsize = AtomicUsize::new(0);
+///
+/// unsafe fn alloc(layout: Layout) -> *mut u8 {
+///     let size = layout.size() + layout.align();
+///     let offset = USED.fetch_add(size, Ordering::Relaxed);
+///     if offset + size > SIZE {
+///         return ptr::null_mut();
+///     }
+///     let addr = ARENA.0.get() as usize + offset;
+///     ((addr + layout.align() - 1) & !(layout.align() - 1)) as *mut u8
+/// }
+///
+/// unsafe fn dealloc(_: *mut u8, _: Layout) {}
+///
+/// fn owns(ptr: *mut u8) -> bool {
+///     let start = ARENA.0.get() as usize;
+///     (start..start + SIZE).contains(&(ptr as usize))
+/// }
+///
+/// static POOL: AllocatorHooks = AllocatorHooks { alloc, dealloc, owns };
+///
+/// fn main() {
+///     backtrace::set_allocator_hooks(Some(&POOL));
+///     println!("{:?}", backtrace::Backtrace::new());
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct SymbolizerAllocator<A>(pub A);
+
+impl<A> SymbolizerAllocator<A> {
+    /// Returns the hooks to allocate from, if this thread is running code of
+    /// this crate.
+    fn routed() -> Option<&'static AllocatorHooks> {
+        if crate::lock::held() {
+            hooks()
+        } else {
+            None
+        }
+    }
+
+    /// Returns the hooks `ptr` was allocated with, if it wasn't allocated
+    /// with the wrapped allocator.
+    fn owner(ptr: *mut u8) -> Option<&'static AllocatorHooks> {
+        owners().find(|hooks| (hooks.owns)(ptr))
+    }
+}
+
+unsafe impl<A: GlobalAlloc> GlobalAlloc for SymbolizerAllocator<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        match Self::routed() {
+            Some(hooks) => (hooks.alloc)(layout),
+            None => self.0.alloc(layout),
+        }
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        match Self::routed() {
+            Some(hooks) => {
+                let ptr = (hooks.alloc)(layout);
+                if !ptr.is_null() {
+                    ptr::write_bytes(ptr, 0, layout.size());
+                }
+                ptr
+            }
+            None => self.0.alloc_zeroed(layout),
+        }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        match Self::owner(ptr) {
+            Some(hooks) => (hooks.dealloc)(ptr, layout),
+            None => self.0.dealloc(ptr, layout),
+        }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        if Self::routed().is_none() && Self::owner(ptr).is_none() {
+            return self.0.realloc(ptr, layout, new_size);
+        }
+        // Move the memory between allocators, which is what the default
+        // implementation does too.
+        let new_layout = Layout::from_size_align_unchecked(new_size, layout.align());
+        let new_ptr = self.alloc(new_layout);
+        if !new_ptr.is_null() {
+            ptr::copy_nonoverlapping(ptr, new_ptr, layout.size().min(new_size));
+            self.dealloc(ptr, layout);
+        }
+        new_ptr
+    }
+}
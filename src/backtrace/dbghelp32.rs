@@ -41,6 +41,16 @@ impl Frame {
         self.addr_stack().Offset as *mut _
     }
 
+    pub fn cfa(&self) -> *mut c_void {
+        // `StackWalk64` reports the frame pointer instead, which isn't the same
+        // thing.
+        core::ptr::null_mut()
+    }
+
+    pub fn registers(&self) -> Option<super::Registers> {
+        None
+    }
+
     pub fn symbol_address(&self) -> *mut c_void {
         self.ip()
     }
@@ -49,9 +59,19 @@ impl Frame {
         Some(self.base_address)
     }
 
+    pub fn is_scanned(&self) -> bool {
+        false
+    }
+
     #[cfg(not(target_env = "gnu"))]
     pub fn inline_context(&self) -> Option<DWORD> {
         match self.stack_frame {
+            // Frames which `StackWalkEx` couldn't place in an inline trace
+            // have their inlined functions looked up when they're resolved,
+            // like frames of `StackWalk64`.
+            StackFrame::New(ref new) if new.InlineFrameContext == INLINE_FRAME_CONTEXT_IGNORE => {
+                None
+            }
             StackFrame::New(ref new) => Some(new.InlineFrameContext),
             StackFrame::Old(_) => None,
         }
@@ -99,7 +119,6 @@ struct MyContext(CONTEXT);
 #[inline(always)]
 pub unsafe fn trace(cb: &mut dyn FnMut(&super::Frame) -> bool) {
     // Allocate necessary structures for doing the stack walk
-    let process = GetCurrentProcess();
     let thread = GetCurrentThread();
 
     let mut context = mem::zeroed::<MyContext>();
@@ -110,6 +129,7 @@ pub unsafe fn trace(cb: &mut dyn FnMut(&super::Frame) -> bool) {
         Ok(dbghelp) => dbghelp,
         Err(()) => return, // oh well...
     };
+    let process = dbghelp.process();
 
     // On x86_64 and ARM64 we opt to not use the default `Sym*` functions from
     // dbghelp for getting the function table and module base. Instead we use
@@ -122,8 +142,6 @@ pub unsafe fn trace(cb: &mut dyn FnMut(&super::Frame) -> bool) {
     let function_table_access = dbghelp.SymFunctionTableAccess64();
     let get_module_base = dbghelp.SymGetModuleBase64();
 
-    let process_handle = GetCurrentProcess();
-
     // Attempt to use `StackWalkEx` if we can, but fall back to `StackWalk64`
     // since it's in theory supported on more systems.
     match (*dbghelp.dbghelp()).StackWalkEx() {
@@ -155,7 +173,7 @@ pub unsafe fn trace(cb: &mut dyn FnMut(&super::Frame) -> bool) {
                 0,
             ) == TRUE
             {
-                frame.inner.base_address = get_module_base(process_handle, frame.ip() as _) as _;
+                frame.inner.base_address = get_module_base(process, frame.ip() as _) as _;
 
                 if !cb(&frame) {
                     break;
@@ -187,7 +205,7 @@ pub unsafe fn trace(cb: &mut dyn FnMut(&super::Frame) -> bool) {
                 None,
             ) == TRUE
             {
-                frame.inner.base_address = get_module_base(process_handle, frame.ip() as _) as _;
+                frame.inner.base_address = get_module_base(process, frame.ip() as _) as _;
 
                 if !cb(&frame) {
                     break;
@@ -16,6 +16,8 @@ pub struct Frame {
     base_address: *mut c_void,
     ip: *mut c_void,
     sp: *mut c_void,
+    cfa: *mut c_void,
+    registers: super::Registers,
     #[cfg(not(target_env = "gnu"))]
     inline_context: Option<DWORD>,
 }
@@ -34,6 +36,14 @@ impl Frame {
         self.sp
     }
 
+    pub fn cfa(&self) -> *mut c_void {
+        self.cfa
+    }
+
+    pub fn registers(&self) -> Option<super::Registers> {
+        Some(self.registers)
+    }
+
     pub fn symbol_address(&self) -> *mut c_void {
         self.ip
     }
@@ -42,6 +52,10 @@ impl Frame {
         Some(self.base_address)
     }
 
+    pub fn is_scanned(&self) -> bool {
+        false
+    }
+
     #[cfg(not(target_env = "gnu"))]
     pub fn inline_context(&self) -> Option<DWORD> {
         self.inline_context
@@ -62,6 +76,20 @@ impl MyContext {
     fn sp(&self) -> DWORD64 {
         self.0.Rsp
     }
+
+    fn registers(&self) -> super::Registers {
+        let c = &self.0;
+        super::Registers::read(|reg| match reg {
+            3 => c.Rbx,
+            4 => c.Rsi,
+            5 => c.Rdi,
+            6 => c.Rbp,
+            12 => c.R12,
+            13 => c.R13,
+            14 => c.R14,
+            _ => c.R15,
+        } as usize)
+    }
 }
 
 #[cfg(target_arch = "aarch64")]
@@ -75,6 +103,14 @@ impl MyContext {
     fn sp(&self) -> DWORD64 {
         self.0.Sp
     }
+
+    fn registers(&self) -> super::Registers {
+        let s = unsafe { self.0.u.s() };
+        let x = [
+            s.X19, s.X20, s.X21, s.X22, s.X23, s.X24, s.X25, s.X26, s.X27, s.X28, s.Fp,
+        ];
+        super::Registers::read(|reg| x[usize::from(reg) - 19] as usize)
+    }
 }
 
 #[cfg(any(
@@ -105,26 +141,10 @@ pub unsafe fn trace(cb: &mut dyn FnMut(&super::Frame) -> bool) {
             break;
         }
 
-        let frame = super::Frame {
-            inner: Frame {
-                base_address: base as *mut c_void,
-                ip: ip as *mut c_void,
-                sp: context.sp() as *mut c_void,
-                #[cfg(not(target_env = "gnu"))]
-                inline_context: None,
-            },
-        };
-
-        // We've loaded all the info about the current frame, so now call the
-        // callback.
-        if !cb(&frame) {
-            // Callback told us to stop, so we're done.
-            break;
-        }
-
-        // Unwind to the next frame.
-        let previous_ip = ip;
-        let previous_sp = context.sp();
+        // Unwind to the caller first, whose stack pointer at the call is the
+        // CFA of this frame.
+        let sp = context.sp();
+        let registers = context.registers();
         let mut handler_data = 0usize;
         let mut establisher_frame = 0;
         RtlVirtualUnwind(
@@ -142,9 +162,31 @@ pub unsafe fn trace(cb: &mut dyn FnMut(&super::Frame) -> bool) {
         // * On x64, it sets the instruction pointer to 0.
         // * On ARM64, it leaves the context unchanged (easiest way to check is
         //   to see if the instruction and stack pointers are the same).
-        // If we detect either of these, then unwinding is completed.
-        let ip = context.ip();
-        if ip == 0 || (ip == previous_ip && context.sp() == previous_sp) {
+        // If we detect either of these, then unwinding is completed after this
+        // frame.
+        let unchanged = context.ip() == ip && context.sp() == sp;
+        let done = context.ip() == 0 || unchanged;
+
+        let frame = super::Frame {
+            inner: Frame {
+                base_address: base as *mut c_void,
+                ip: ip as *mut c_void,
+                sp: sp as *mut c_void,
+                cfa: if unchanged {
+                    ptr::null_mut()
+                } else {
+                    context.sp() as *mut c_void
+                },
+                registers,
+                #[cfg(not(target_env = "gnu"))]
+                inline_context: None,
+            },
+        };
+
+        // We've loaded all the info about the current frame, so now call the
+        // callback.
+        if !cb(&frame) || done {
+            // Callback told us to stop, or there's nothing left, so we're done.
             break;
         }
     }
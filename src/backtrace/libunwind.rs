@@ -15,6 +15,7 @@
 //!
 //! This is the default unwinding API for all non-Windows platforms currently.
 
+use alloc::boxed::Box;
 use core::ffi::c_void;
 use core::ptr::addr_of_mut;
 
@@ -24,6 +25,9 @@ pub enum Frame {
         ip: *mut c_void,
         sp: *mut c_void,
         symbol_address: *mut c_void,
+        // Boxed as they're rarely recorded, and frames are cloned a lot.
+        registers: Option<Box<super::Registers>>,
+        scanned: bool,
     },
 }
 
@@ -36,22 +40,10 @@ unsafe impl Sync for Frame {}
 
 impl Frame {
     pub fn ip(&self) -> *mut c_void {
-        let ctx = match *self {
-            Frame::Raw(ctx) => ctx,
-            Frame::Cloned { ip, .. } => return ip,
-        };
-        #[allow(unused_mut)]
-        let mut ip = unsafe { uw::_Unwind_GetIP(ctx) as *mut c_void };
-
-        // To reduce TCB size in SGX enclaves, we do not want to implement
-        // symbol resolution functionality. Rather, we can print the offset of
-        // the address here, which could be later mapped to correct function.
-        #[cfg(all(target_env = "sgx", target_vendor = "fortanix"))]
-        {
-            let image_base = super::sgx_image_base::get_image_base();
-            ip = usize::wrapping_sub(ip as usize, image_base as _) as _;
+        match *self {
+            Frame::Raw(ctx) => unsafe { get_ip(ctx) },
+            Frame::Cloned { ip, .. } => ip,
         }
-        ip
     }
 
     pub fn sp(&self) -> *mut c_void {
@@ -61,6 +53,23 @@ impl Frame {
         }
     }
 
+    pub fn cfa(&self) -> *mut c_void {
+        // The CFA is the stack pointer of the caller at the call, but neither
+        // `_Unwind_GetCFA` nor anything else reports it before the unwinder
+        // moved on to the caller.
+        core::ptr::null_mut()
+    }
+
+    pub fn registers(&self) -> Option<super::Registers> {
+        match *self {
+            Frame::Raw(ctx) if !super::registers::CALLEE_SAVED.is_empty() => unsafe {
+                Some(super::Registers::read(|reg| uw::get_gr(ctx, reg)))
+            },
+            Frame::Raw(_) => None,
+            Frame::Cloned { ref registers, .. } => registers.as_deref().copied(),
+        }
+    }
+
     pub fn symbol_address(&self) -> *mut c_void {
         if let Frame::Cloned { symbol_address, .. } = *self {
             return symbol_address;
@@ -84,7 +93,20 @@ impl Frame {
         }
     }
 
+    pub fn is_scanned(&self) -> bool {
+        matches!(*self, Frame::Cloned { scanned: true, .. })
+    }
+
     pub fn module_base_address(&self) -> Option<*mut c_void> {
+        // dyld knows which image contains an address without reading any
+        // files, and reports where its Mach-O header is loaded.
+        #[cfg(all(target_vendor = "apple", not(feature = "pure-rust")))]
+        unsafe {
+            let mut info: libc::Dl_info = core::mem::zeroed();
+            if libc::dladdr(self.ip(), &mut info) != 0 && !info.dli_fbase.is_null() {
+                return Some(info.dli_fbase);
+            }
+        }
         None
     }
 }
@@ -95,10 +117,32 @@ impl Clone for Frame {
             ip: self.ip(),
             sp: self.sp(),
             symbol_address: self.symbol_address(),
+            registers: if super::registers::capture_registers() {
+                self.registers().map(Box::new)
+            } else {
+                None
+            },
+            scanned: self.is_scanned(),
         }
     }
 }
 
+/// Returns the instruction pointer of the frame of `ctx`.
+unsafe fn get_ip(ctx: *mut uw::_Unwind_Context) -> *mut c_void {
+    #[allow(unused_mut)]
+    let mut ip = uw::_Unwind_GetIP(ctx) as *mut c_void;
+
+    // To reduce TCB size in SGX enclaves, we do not want to implement
+    // symbol resolution functionality. Rather, we can print the offset of
+    // the address here, which could be later mapped to correct function.
+    #[cfg(all(target_env = "sgx", target_vendor = "fortanix"))]
+    {
+        let image_base = super::sgx_image_base::get_image_base();
+        ip = usize::wrapping_sub(ip as usize, image_base as _) as _;
+    }
+    ip
+}
+
 struct Bomb {
     enabled: bool,
 }
@@ -111,31 +155,123 @@ impl Drop for Bomb {
     }
 }
 
+/// What `trace_fn` needs, and what it learned about the last frame.
+struct TraceState<'a> {
+    cb: &'a mut dyn FnMut(&super::Frame) -> bool,
+    stopped: bool,
+    last_sp: *mut c_void,
+}
+
 #[inline(always)]
-pub unsafe fn trace(mut cb: &mut dyn FnMut(&super::Frame) -> bool) {
-    uw::_Unwind_Backtrace(trace_fn, addr_of_mut!(cb).cast());
+pub unsafe fn trace(cb: &mut dyn FnMut(&super::Frame) -> bool) {
+    let mut state = TraceState {
+        cb,
+        stopped: false,
+        last_sp: core::ptr::null_mut(),
+    };
+    uw::_Unwind_Backtrace(trace_fn, addr_of_mut!(state).cast());
+    if !state.stopped && !state.last_sp.is_null() && super::scan::stack_scanning() {
+        scan(&mut state);
+    }
 
     extern "C" fn trace_fn(
         ctx: *mut uw::_Unwind_Context,
         arg: *mut c_void,
     ) -> uw::_Unwind_Reason_Code {
-        let cb = unsafe { &mut *arg.cast::<&mut dyn FnMut(&super::Frame) -> bool>() };
+        let state = unsafe { &mut *arg.cast::<TraceState<'_>>() };
         let cx = super::Frame {
             inner: Frame::Raw(ctx),
         };
+        state.last_sp = cx.sp();
 
         let mut bomb = Bomb { enabled: true };
-        let keep_going = cb(&cx);
+        let keep_going = (state.cb)(&cx);
         bomb.enabled = false;
 
         if keep_going {
             uw::_URC_NO_REASON
+        } else {
+            state.stopped = true;
+            uw::_URC_FAILURE
+        }
+    }
+}
+
+/// Where `trace_ips` writes instruction pointers to.
+struct IpsState<'a> {
+    ips: &'a mut [usize],
+    len: usize,
+}
+
+/// Writes the instruction pointer of each frame into `ips`, which mustn't be
+/// empty, without creating `Frame`s or scanning the stack.
+#[inline(always)]
+pub unsafe fn trace_ips(ips: &mut [usize]) -> usize {
+    let mut state = IpsState { ips, len: 0 };
+    uw::_Unwind_Backtrace(trace_fn, addr_of_mut!(state).cast());
+    return state.len;
+
+    extern "C" fn trace_fn(
+        ctx: *mut uw::_Unwind_Context,
+        arg: *mut c_void,
+    ) -> uw::_Unwind_Reason_Code {
+        let state = unsafe { &mut *arg.cast::<IpsState<'_>>() };
+        state.ips[state.len] = unsafe { get_ip(ctx) } as usize;
+        state.len += 1;
+        if state.len < state.ips.len() {
+            uw::_URC_NO_REASON
         } else {
             uw::_URC_FAILURE
         }
     }
 }
 
+/// Continues a trace by scanning the stack above the last frame the unwinder
+/// reported, see `set_stack_scanning`.
+///
+/// Unwinders differ in whether they report the frame they don't find call
+/// frame information for, so this doesn't try to tell whether the trace ended
+/// early or at the outermost frame of the thread. Above the latter, there's
+/// rarely anything that looks like a return address.
+#[cfg(all(
+    any(
+        all(target_os = "linux", any(target_env = "gnu", target_env = "musl")),
+        target_os = "android",
+    ),
+    any(target_arch = "x86_64", target_arch = "x86", target_arch = "aarch64"),
+))]
+unsafe fn scan(state: &mut TraceState<'_>) {
+    let function_start = |ip: usize| {
+        let start = uw::_Unwind_FindEnclosingFunction(ip as *mut c_void);
+        if start.is_null() {
+            None
+        } else {
+            Some(start as usize)
+        }
+    };
+    super::scan::scan(state.last_sp as usize, function_start, |ip, sp| {
+        let frame = super::Frame {
+            inner: Frame::Cloned {
+                ip: ip as *mut c_void,
+                sp: sp as *mut c_void,
+                symbol_address: uw::_Unwind_FindEnclosingFunction(ip as *mut c_void),
+                registers: None,
+                scanned: true,
+            },
+        };
+        (state.cb)(&frame)
+    });
+}
+
+#[cfg(not(all(
+    any(
+        all(target_os = "linux", any(target_env = "gnu", target_env = "musl")),
+        target_os = "android",
+    ),
+    any(target_arch = "x86_64", target_arch = "x86", target_arch = "aarch64"),
+)))]
+unsafe fn scan(_state: &mut TraceState<'_>) {}
+
 /// Unwind library interface used for backtraces
 ///
 /// Note that dead code is allowed as here are just bindings
@@ -187,6 +323,7 @@ mod uw {
             extern "C" {
                 pub fn _Unwind_GetIP(ctx: *mut _Unwind_Context) -> libc::uintptr_t;
                 pub fn _Unwind_FindEnclosingFunction(pc: *mut c_void) -> *mut c_void;
+                pub fn _Unwind_GetGR(ctx: *mut _Unwind_Context, index: libc::c_int) -> libc::uintptr_t;
 
                 #[cfg(not(all(target_os = "linux", target_arch = "s390x")))]
                 // This function is a misnomer: rather than getting this frame's
@@ -204,11 +341,12 @@ mod uw {
             // instead of relying on _Unwind_GetCFA.
             #[cfg(all(target_os = "linux", target_arch = "s390x"))]
             pub unsafe fn get_sp(ctx: *mut _Unwind_Context) -> libc::uintptr_t {
-                extern "C" {
-                    pub fn _Unwind_GetGR(ctx: *mut _Unwind_Context, index: libc::c_int) -> libc::uintptr_t;
-                }
                 _Unwind_GetGR(ctx, 15)
             }
+
+            pub unsafe fn get_gr(ctx: *mut _Unwind_Context, reg: u16) -> usize {
+                _Unwind_GetGR(ctx, libc::c_int::from(reg))
+            }
         } else {
             use core::ptr::addr_of_mut;
 
@@ -283,6 +421,19 @@ mod uw {
                 val as libc::uintptr_t
             }
 
+            pub unsafe fn get_gr(ctx: *mut _Unwind_Context, reg: u16) -> usize {
+                let mut val: _Unwind_Word = 0;
+                let ptr = addr_of_mut!(val);
+                let _ = _Unwind_VRS_Get(
+                    ctx,
+                    _Unwind_VRS_RegClass::_UVRSC_CORE,
+                    _Unwind_Word::from(reg),
+                    _Unwind_VRS_DataRepresentation::_UVRSD_UINT32,
+                    ptr.cast::<c_void>(),
+                );
+                val as usize
+            }
+
             // This function also doesn't exist on Android or ARM/Linux, so make it
             // a no-op.
             pub unsafe fn _Unwind_FindEnclosingFunction(pc: *mut c_void) -> *mut c_void {
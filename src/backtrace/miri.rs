@@ -47,6 +47,14 @@ impl Frame {
         core::ptr::null_mut()
     }
 
+    pub fn cfa(&self) -> *mut c_void {
+        core::ptr::null_mut()
+    }
+
+    pub fn registers(&self) -> Option<super::Registers> {
+        None
+    }
+
     pub fn symbol_address(&self) -> *mut c_void {
         self.inner.fn_ptr
     }
@@ -54,6 +62,10 @@ impl Frame {
     pub fn module_base_address(&self) -> Option<*mut c_void> {
         None
     }
+
+    pub fn is_scanned(&self) -> bool {
+        false
+    }
 }
 
 pub fn trace<F: FnMut(&super::Frame) -> bool>(cb: F) {
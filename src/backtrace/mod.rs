@@ -1,6 +1,11 @@
 use core::ffi::c_void;
 use core::fmt;
 
+pub use self::registers::{set_capture_registers, Registers};
+pub(crate) mod registers;
+pub use self::scan::set_stack_scanning;
+mod scan;
+
 /// Inspects the current call-stack, passing all active frames into the closure
 /// provided to calculate a stack trace.
 ///
@@ -59,13 +64,135 @@ pub fn trace<F: FnMut(&Frame) -> bool>(cb: F) {
 /// when the `std` feature of this crate isn't compiled in. See the `trace`
 /// function for more documentation and examples.
 ///
+/// # Stack usage
+///
+/// Tracing itself, not counting the frames of `cb`, uses at most
+/// `MAX_TRACE_STACK` bytes of stack, so this can be called from signal
+/// handlers running on a small alternate stack set up with `sigaltstack`.
+/// On Linux, Android and Apple platforms, nothing is traced when this is
+/// called on an alternate signal stack with less than that left, rather than
+/// overflowing it.
+///
 /// # Panics
 ///
 /// See information on `trace` for caveats on `cb` panicking.
 pub unsafe fn trace_unsynchronized<F: FnMut(&Frame) -> bool>(mut cb: F) {
+    if !enough_stack() {
+        return;
+    }
     trace_imp(&mut cb)
 }
 
+/// Writes the instruction pointers of the current call-stack into `ips`,
+/// returning how many were written.
+///
+/// This is the same as `trace` collecting `Frame::ip` of each frame until
+/// `ips` is full, but as cheap as the unwinder allows: where the unwinder
+/// reports frames through libunwind, no `Frame` is created, and nothing but
+/// the instruction pointer is read from the unwinder, so this is meant for
+/// sampling stacks thousands of times per second. The addresses can be
+/// turned into a `Backtrace` with `Backtrace::from_ips` and resolved later,
+/// for those samples which are still of interest by then. The stack isn't
+/// scanned for frames the unwinder missed, see `set_stack_scanning`.
+///
+/// Like with `trace`, the first frames are those of this crate.
+///
+/// # Required features
+///
+/// This function requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default.
+///
+/// # Example
+///
+/// ```
+/// let mut ips = [0; 64];
+/// let len = backtrace::trace_ips(&mut ips);
+/// let bt = backtrace::Backtrace::from_ips(&ips[..len]);
+/// assert_eq!(bt.frames().len(), len);
+/// ```
+#[cfg(feature = "std")]
+pub fn trace_ips(ips: &mut [usize]) -> usize {
+    let _guard = crate::lock::lock();
+    unsafe { trace_ips_unsynchronized(ips) }
+}
+
+/// Same as `trace_ips`, only unsafe as it's unsynchronized.
+///
+/// This function does not have synchronization guarantees but is available
+/// when the `std` feature of this crate isn't compiled in. It doesn't
+/// allocate, so like `trace_unsynchronized` it can be called from signal
+/// handlers, and likewise returns zero if there's not enough of an alternate
+/// signal stack left. See the `trace_ips` function for more documentation.
+///
+/// # Safety
+///
+/// No locks are taken, so this has the same requirements as
+/// `trace_unsynchronized`.
+pub unsafe fn trace_ips_unsynchronized(ips: &mut [usize]) -> usize {
+    if ips.is_empty() || !enough_stack() {
+        return 0;
+    }
+    trace_ips_imp(ips)
+}
+
+/// Tests whether tracing may use `MAX_TRACE_STACK` more bytes of stack, which
+/// is only ever in doubt on an alternate signal stack.
+#[cfg(all(
+    any(target_os = "linux", target_os = "android", target_vendor = "apple"),
+    not(miri)
+))]
+fn enough_stack() -> bool {
+    let mut current = core::mem::MaybeUninit::<libc::stack_t>::uninit();
+    // `sigaltstack` is async-signal-safe, and only reads the settings here.
+    if unsafe { libc::sigaltstack(core::ptr::null(), current.as_mut_ptr()) } != 0 {
+        return true;
+    }
+    let current = unsafe { current.assume_init() };
+    if current.ss_flags & libc::SS_ONSTACK == 0 {
+        return true;
+    }
+    // Stacks grow down on all of these platforms.
+    let marker = 0u8;
+    let sp = core::ptr::addr_of!(marker) as usize;
+    sp.saturating_sub(current.ss_sp as usize) >= MAX_TRACE_STACK
+}
+
+#[cfg(not(all(
+    any(target_os = "linux", target_os = "android", target_vendor = "apple"),
+    not(miri)
+)))]
+fn enough_stack() -> bool {
+    true
+}
+
+/// Implements `trace_ips_unsynchronized` on top of `trace_imp`, for unwinders
+/// without a faster way to get at the instruction pointers.
+#[allow(dead_code)] // unused with libunwind
+unsafe fn trace_ips_generic(ips: &mut [usize]) -> usize {
+    let mut len = 0;
+    trace_imp(&mut |frame| {
+        ips[len] = frame.ip() as usize;
+        len += 1;
+        len < ips.len()
+    });
+    len
+}
+
+/// The most stack in bytes `trace_unsynchronized` uses on its own, not
+/// counting the closure passed to it.
+///
+/// The unwinders this crate uses walk the stack iteratively with fixed-size
+/// state, and this crate doesn't recurse or keep large buffers on the stack
+/// while tracing, so this bounds the stack used regardless of how deep the
+/// stack being traced is. The first trace of a process uses the most as the
+/// unwinder sets itself up. Alternate signal stacks need this much space on
+/// top of whatever the signal handler uses itself.
+///
+/// This has only been measured on Linux with the unwinder of libgcc, which
+/// uses well below it. On other platforms and with other unwinders it's an
+/// estimate with some room to spare, but isn't verified.
+pub const MAX_TRACE_STACK: usize = 8 * 1024;
+
 /// A trait representing one frame of a backtrace, yielded to the `trace`
 /// function of this crate.
 ///
@@ -98,6 +225,40 @@ impl Frame {
         self.inner.sp()
     }
 
+    /// Returns the canonical frame address (CFA) of this frame.
+    ///
+    /// This is the value of the stack pointer in the calling frame just before
+    /// the call into this one, which DWARF call frame information and the
+    /// locations of saved registers and local variables are given relative to.
+    ///
+    /// Most unwinders only learn it once they've moved on to the caller, so
+    /// this is only known on Windows on x86_64 and AArch64, and a null pointer
+    /// is returned elsewhere. `BacktraceFrame::cfa` fills it in from the next
+    /// frame on more platforms.
+    pub fn cfa(&self) -> *mut c_void {
+        self.inner.cfa()
+    }
+
+    /// Returns the values of the callee-saved registers in this frame.
+    ///
+    /// This is known on x86_64, x86, AArch64 and ARM, for frames passed to the
+    /// closure of `trace` on all platforms except 32-bit Windows. Clones of
+    /// frames, like the ones of a captured `Backtrace`, only keep them on
+    /// Windows, or if enabled with `set_capture_registers` elsewhere. `None` is
+    /// returned if they're unknown.
+    pub fn registers(&self) -> Option<Registers> {
+        self.inner.registers()
+    }
+
+    /// Returns whether this frame was found by scanning the stack for return
+    /// addresses rather than by the unwinder, see `set_stack_scanning`.
+    ///
+    /// Scanned frames are only guesses and may not be on the call stack at
+    /// all, so they should be marked as unreliable wherever they're shown.
+    pub fn is_scanned(&self) -> bool {
+        self.inner.is_scanned()
+    }
+
     /// Returns the starting symbol address of the frame of this function.
     ///
     /// This will attempt to rewind the instruction pointer returned by `ip` to
@@ -111,6 +272,10 @@ impl Frame {
     }
 
     /// Returns the base address of the module to which the frame belongs.
+    ///
+    /// This is only known on platforms where the unwinder or operating system
+    /// reports it cheaply, like Windows and macOS. Resolving the symbols of a
+    /// frame uses it as a hint to find the module faster.
     pub fn module_base_address(&self) -> Option<*mut c_void> {
         self.inner.module_base_address()
     }
@@ -169,7 +334,9 @@ cfg_if::cfg_if! {
     if #[cfg(miri)] {
         pub(crate) mod miri;
         use self::miri::trace as trace_imp;
+        use self::trace_ips_generic as trace_ips_imp;
         pub(crate) use self::miri::Frame as FrameImp;
+        pub(crate) const SUPPORTED: bool = true;
     } else if #[cfg(
         any(
             all(
@@ -186,7 +353,9 @@ cfg_if::cfg_if! {
     )] {
         mod libunwind;
         use self::libunwind::trace as trace_imp;
+        use self::libunwind::trace_ips as trace_ips_imp;
         pub(crate) use self::libunwind::Frame as FrameImp;
+        pub(crate) const SUPPORTED: bool = true;
     } else if #[cfg(all(windows, not(target_vendor = "uwp")))] {
         cfg_if::cfg_if! {
             if #[cfg(any(target_arch = "x86_64", target_arch = "aarch64", target_arch = "arm64ec"))] {
@@ -198,10 +367,14 @@ cfg_if::cfg_if! {
             }
         }
         use self::dbghelp::trace as trace_imp;
+        use self::trace_ips_generic as trace_ips_imp;
         pub(crate) use self::dbghelp::Frame as FrameImp;
+        pub(crate) const SUPPORTED: bool = true;
     } else {
         mod noop;
         use self::noop::trace as trace_imp;
+        use self::trace_ips_generic as trace_ips_imp;
         pub(crate) use self::noop::Frame as FrameImp;
+        pub(crate) const SUPPORTED: bool = false;
     }
 }
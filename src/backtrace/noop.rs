@@ -19,6 +19,14 @@ impl Frame {
         null_mut()
     }
 
+    pub fn cfa(&self) -> *mut c_void {
+        null_mut()
+    }
+
+    pub fn registers(&self) -> Option<super::Registers> {
+        None
+    }
+
     pub fn symbol_address(&self) -> *mut c_void {
         null_mut()
     }
@@ -26,4 +34,8 @@ impl Frame {
     pub fn module_base_address(&self) -> Option<*mut c_void> {
         None
     }
+
+    pub fn is_scanned(&self) -> bool {
+        false
+    }
 }
@@ -0,0 +1,101 @@
+//! Values of callee-saved registers in frames, see `Frame::registers`.
+
+use core::fmt;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+static CAPTURE: AtomicBool = AtomicBool::new(false);
+
+/// The callee-saved registers recorded on this architecture, by their DWARF
+/// register numbers.
+#[cfg(all(target_arch = "x86_64", windows))]
+pub(crate) const CALLEE_SAVED: &[u16] = &[3, 4, 5, 6, 12, 13, 14, 15];
+#[cfg(all(target_arch = "x86_64", not(windows)))]
+pub(crate) const CALLEE_SAVED: &[u16] = &[3, 6, 12, 13, 14, 15];
+#[cfg(target_arch = "x86")]
+pub(crate) const CALLEE_SAVED: &[u16] = &[3, 5, 6, 7];
+#[cfg(target_arch = "aarch64")]
+pub(crate) const CALLEE_SAVED: &[u16] = &[19, 20, 21, 22, 23, 24, 25, 26, 27, 28, 29];
+#[cfg(target_arch = "arm")]
+pub(crate) const CALLEE_SAVED: &[u16] = &[4, 5, 6, 7, 8, 9, 10, 11];
+#[cfg(not(any(
+    target_arch = "x86_64",
+    target_arch = "x86",
+    target_arch = "aarch64",
+    target_arch = "arm"
+)))]
+pub(crate) const CALLEE_SAVED: &[u16] = &[];
+
+/// Sets whether frames record the values of their callee-saved registers when
+/// they're cloned, which is disabled by default.
+///
+/// Frames passed to the closure of `trace` can always report their registers
+/// with `Frame::registers`, but most unwinders only know them while they're at
+/// the frame. With this enabled, cloning a frame, which also happens when a
+/// `Backtrace` is captured, takes a snapshot of them, so crash reporters can
+/// include them in their reports. Outside of Windows, the snapshot is
+/// allocated on the heap, which makes capturing backtraces slower, so it's
+/// best enabled only where needed.
+pub fn set_capture_registers(enabled: bool) {
+    CAPTURE.store(enabled, Ordering::Relaxed);
+}
+
+/// Returns the setting last passed to `set_capture_registers`.
+#[allow(dead_code)] // unused on platforms where frames always carry registers
+pub(crate) fn capture_registers() -> bool {
+    CAPTURE.load(Ordering::Relaxed)
+}
+
+/// The values of the callee-saved registers of a frame, as returned by
+/// `Frame::registers`.
+///
+/// Registers are identified by their DWARF register numbers, like in call
+/// frame information:
+///
+/// - x86_64: `rbx` (3), `rbp` (6) and `r12` to `r15` (12 to 15), and also
+///   `rsi` (4) and `rdi` (5) on Windows,
+/// - x86: `ebx` (3), `ebp` (5), `esi` (6) and `edi` (7),
+/// - AArch64: `x19` to `x28` (19 to 28) and the frame pointer `x29` (29),
+/// - ARM: `r4` to `r11` (4 to 11).
+///
+/// These are the values the registers have while the frame executes, i.e. at
+/// the instruction following the call of the frame before it.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Registers {
+    values: [usize; CALLEE_SAVED.len()],
+}
+
+impl Registers {
+    /// Reads the registers of a frame with `get`, given the number of each.
+    #[allow(dead_code)] // unused on platforms without any registers recorded
+    pub(crate) fn read(mut get: impl FnMut(u16) -> usize) -> Registers {
+        let mut values = [0; CALLEE_SAVED.len()];
+        for (value, &reg) in values.iter_mut().zip(CALLEE_SAVED) {
+            *value = get(reg);
+        }
+        Registers { values }
+    }
+
+    /// Returns the value of the register with DWARF register number `reg`, if
+    /// it's recorded.
+    pub fn get(&self, reg: u16) -> Option<usize> {
+        let index = CALLEE_SAVED.iter().position(|&saved| saved == reg)?;
+        Some(self.values[index])
+    }
+
+    /// Returns the DWARF register numbers and values of all registers
+    /// recorded.
+    pub fn iter(&self) -> impl Iterator<Item = (u16, usize)> + '_ {
+        CALLEE_SAVED
+            .iter()
+            .copied()
+            .zip(self.values.iter().copied())
+    }
+}
+
+impl fmt::Debug for Registers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(self.iter().map(|(reg, value)| (reg, value as *const u8)))
+            .finish()
+    }
+}
@@ -0,0 +1,136 @@
+//! Finding frames the unwinder can't by scanning the stack, see
+//! `set_stack_scanning`.
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Sets whether tracing falls back to scanning the stack for return addresses
+/// where the unwinder stops early, which is disabled by default.
+///
+/// Unwinders stop at the first frame they don't find call frame information
+/// for, like frames of hand-written assembly or JIT compiled code, or frames
+/// whose return address was overwritten. With this enabled, the stack above
+/// the last frame the unwinder found is scanned for words which look like
+/// return addresses: they point right after a call instruction in a function
+/// the unwinder knows about. Each of them is reported as a frame for which
+/// `Frame::is_scanned` returns `true`. Traces which aren't cut short rarely
+/// end up with any scanned frames, as there's hardly anything on the stack
+/// above the outermost frame of a thread.
+///
+/// Scanning also finds stale return addresses left on the stack by calls
+/// which already returned, and may miss frames, so scanned frames are only
+/// guesses. Crash reporters can use them to show more than nothing past a gap
+/// in the call frame information, but should mark them as unreliable.
+///
+/// Scanning is only supported on Linux and Android on x86_64, x86 and
+/// AArch64, and at most 64 KiB of the stack are scanned. It doesn't allocate,
+/// but each word which might be a return address is checked with
+/// `_Unwind_FindEnclosingFunction`, which may take the lock of the dynamic
+/// loader to find the unwind tables, so tracing with scanning enabled isn't
+/// async-signal-safe.
+pub fn set_stack_scanning(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Returns the setting last passed to `set_stack_scanning`.
+#[allow(dead_code)] // unused on platforms without scanning
+pub(crate) fn stack_scanning() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(all(
+        any(
+            all(target_os = "linux", any(target_env = "gnu", target_env = "musl")),
+            target_os = "android",
+        ),
+        any(target_arch = "x86_64", target_arch = "x86", target_arch = "aarch64"),
+        not(miri),
+    ))] {
+        use core::mem;
+
+        /// How many bytes of the stack are scanned at most.
+        const MAX_SCAN: usize = 64 * 1024;
+
+        /// How many bytes are read at once. Pages are a multiple of this, so
+        /// chunks aligned to it are either entirely readable or not at all.
+        const CHUNK: usize = 512;
+
+        /// Scans the stack upwards from `sp` for return addresses, passing
+        /// each one to `found` along with the stack pointer right above where
+        /// it was found, until `found` returns `false`.
+        ///
+        /// `function_start` returns the start of the function containing the
+        /// instruction before a return address, or `None` if it isn't code.
+        pub(crate) unsafe fn scan(
+            sp: usize,
+            function_start: impl Fn(usize) -> Option<usize>,
+            mut found: impl FnMut(usize, usize) -> bool,
+        ) {
+            let word = mem::size_of::<usize>();
+            let mut words = [0usize; CHUNK / mem::size_of::<usize>()];
+            let mut addr = (sp + word - 1) & !(word - 1);
+            let end = addr.saturating_add(MAX_SCAN);
+            while addr < end {
+                // The stack might end anywhere, so read it with a system call
+                // which fails instead of faulting past the end.
+                let len = (CHUNK - addr % CHUNK).min(end - addr);
+                let local = libc::iovec {
+                    iov_base: words.as_mut_ptr().cast(),
+                    iov_len: len,
+                };
+                let remote = libc::iovec {
+                    iov_base: addr as *mut _,
+                    iov_len: len,
+                };
+                let read = libc::process_vm_readv(libc::getpid(), &local, 1, &remote, 1, 0);
+                if read != len as isize {
+                    return;
+                }
+                for (i, &ip) in words[..len / word].iter().enumerate() {
+                    if is_return_address(ip, &function_start)
+                        && !found(ip, addr + (i + 1) * word)
+                    {
+                        return;
+                    }
+                }
+                addr += len;
+            }
+        }
+
+        /// Returns whether `ip` points right after a call instruction.
+        unsafe fn is_return_address(
+            ip: usize,
+            function_start: impl Fn(usize) -> Option<usize>,
+        ) -> bool {
+            let start = match function_start(ip) {
+                Some(start) => start,
+                None => return false,
+            };
+            // Only the code of the function is known to be readable.
+            let byte = |back: usize| {
+                let addr = ip.checked_sub(back).filter(|&addr| addr >= start)?;
+                Some(*(addr as *const u8))
+            };
+            if cfg!(target_arch = "aarch64") {
+                if ip % 4 != 0 || ip.checked_sub(4).map_or(true, |addr| addr < start) {
+                    return false;
+                }
+                let insn = u32::from_le(((ip - 4) as *const u32).read());
+                // `bl`, `blr` and the `blraa` family.
+                insn & 0xfc00_0000 == 0x9400_0000
+                    || insn & 0xffff_fc1f == 0xd63f_0000
+                    || insn & 0xfeff_f800 == 0xd63f_0800
+            } else {
+                // A relative `call`, or an indirect `call` through a register
+                // or memory with any addressing mode.
+                byte(5) == Some(0xe8)
+                    || [2, 3, 4, 6, 7].iter().any(|&back| {
+                        byte(back) == Some(0xff)
+                            && byte(back - 1).map_or(false, |modrm| modrm & 0x38 == 0x10)
+                    })
+            }
+        }
+    }
+}
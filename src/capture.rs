@@ -1,15 +1,41 @@
-#[cfg(feature = "serde")]
-use crate::resolve;
+use crate::symbolize::{AddressKind, ResolveWhat};
 use crate::PrintFmt;
-use crate::{resolve_frame, trace, BacktraceFmt, Symbol, SymbolName};
+use crate::{
+    resolve, resolve_frame, trace, BacktraceFmt, BytesOrWideString, FrameKind, ResolveError,
+    Symbol, SymbolName,
+};
 use std::ffi::c_void;
 use std::fmt;
 use std::path::{Path, PathBuf};
 use std::prelude::v1::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
+mod builder;
+mod diff;
+mod handle;
+mod intern;
+mod lazy;
+mod metadata;
+mod panic_hook;
+mod std_interop;
+mod task;
+mod tree;
+
+pub use self::builder::BacktraceBuilder;
+pub use self::diff::FrameDiff;
+pub use self::handle::CaptureHandle;
+pub use self::intern::{FrameId, TraceId, TraceStore};
+pub use self::lazy::LazyBacktrace;
+pub use self::metadata::Metadata;
+pub use self::panic_hook::{install_panic_hook, PanicHookOptions};
+pub use self::task::in_task;
+pub use self::tree::{NodeId, StackTree};
+
 /// Representation of an owned and self-contained backtrace.
 ///
 /// This structure can be used to capture a backtrace at various points in a
@@ -27,11 +53,17 @@ use serde::{Deserialize, Serialize};
 pub struct Backtrace {
     // Frames here are listed from top-to-bottom of the stack
     frames: Vec<BacktraceFrame>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Metadata::is_empty")
+    )]
+    metadata: Metadata,
 }
 
 fn _assert_send_sync() {
     fn _assert<T: Send + Sync>() {}
     _assert::<Backtrace>();
+    _assert::<BacktraceSymbol>();
 }
 
 /// Captured version of a frame in a backtrace.
@@ -47,63 +79,100 @@ fn _assert_send_sync() {
 pub struct BacktraceFrame {
     frame: Frame,
     symbols: Option<Vec<BacktraceSymbol>>,
+    metadata: Metadata,
 }
 
 #[derive(Clone)]
 enum Frame {
-    Raw(crate::Frame),
-    #[cfg(feature = "serde")]
-    Deserialized {
+    Raw {
+        frame: crate::Frame,
+        // The CFA worked out from the next frame if the unwinder didn't know
+        // it, see `set_cfas`.
+        cfa: usize,
+    },
+    // A frame which was only given as addresses, e.g. after deserialization.
+    Address {
         ip: usize,
+        // Zero if unknown, like the null pointers `Frame::sp` and `Frame::cfa`
+        // return.
+        sp: usize,
+        cfa: usize,
         symbol_address: usize,
         module_base_address: Option<usize>,
+        scanned: bool,
     },
 }
 
 impl Frame {
     fn ip(&self) -> *mut c_void {
         match *self {
-            Frame::Raw(ref f) => f.ip(),
-            #[cfg(feature = "serde")]
-            Frame::Deserialized { ip, .. } => ip as *mut c_void,
+            Frame::Raw { frame: ref f, .. } => f.ip(),
+            Frame::Address { ip, .. } => ip as *mut c_void,
+        }
+    }
+
+    fn sp(&self) -> *mut c_void {
+        match *self {
+            Frame::Raw { frame: ref f, .. } => f.sp(),
+            Frame::Address { sp, .. } => sp as *mut c_void,
+        }
+    }
+
+    fn cfa(&self) -> *mut c_void {
+        match *self {
+            Frame::Raw { ref frame, cfa } if frame.cfa().is_null() => cfa as *mut c_void,
+            Frame::Raw { ref frame, .. } => frame.cfa(),
+            Frame::Address { cfa, .. } => cfa as *mut c_void,
+        }
+    }
+
+    fn registers(&self) -> Option<crate::Registers> {
+        match *self {
+            Frame::Raw { frame: ref f, .. } => f.registers(),
+            Frame::Address { .. } => None,
+        }
+    }
+
+    fn is_scanned(&self) -> bool {
+        match *self {
+            Frame::Raw { frame: ref f, .. } => f.is_scanned(),
+            Frame::Address { scanned, .. } => scanned,
         }
     }
 
     fn symbol_address(&self) -> *mut c_void {
         match *self {
-            Frame::Raw(ref f) => f.symbol_address(),
-            #[cfg(feature = "serde")]
-            Frame::Deserialized { symbol_address, .. } => symbol_address as *mut c_void,
+            Frame::Raw { frame: ref f, .. } => f.symbol_address(),
+            Frame::Address { symbol_address, .. } => symbol_address as *mut c_void,
         }
     }
 
     fn module_base_address(&self) -> Option<*mut c_void> {
         match *self {
-            Frame::Raw(ref f) => f.module_base_address(),
-            #[cfg(feature = "serde")]
-            Frame::Deserialized {
+            Frame::Raw { frame: ref f, .. } => f.module_base_address(),
+            Frame::Address {
                 module_base_address,
                 ..
             } => module_base_address.map(|addr| addr as *mut c_void),
         }
     }
 
+    fn resolve_what(&self) -> ResolveWhat<'_> {
+        match *self {
+            Frame::Raw { frame: ref f, .. } => ResolveWhat::Frame(f),
+            Frame::Address { ip, .. } => {
+                ResolveWhat::Address(ip as *mut c_void, AddressKind::ReturnAddress)
+            }
+        }
+    }
+
     /// Resolve all addresses in the frame to their symbolic names.
     fn resolve_symbols(&self) -> Vec<BacktraceSymbol> {
         let mut symbols = Vec::new();
-        let sym = |symbol: &Symbol| {
-            symbols.push(BacktraceSymbol {
-                name: symbol.name().map(|m| m.as_bytes().to_vec()),
-                addr: symbol.addr().map(|a| a as usize),
-                filename: symbol.filename().map(|m| m.to_owned()),
-                lineno: symbol.lineno(),
-                colno: symbol.colno(),
-            });
-        };
+        let sym = |symbol: &Symbol| symbols.push(BacktraceSymbol::from(symbol));
         match *self {
-            Frame::Raw(ref f) => resolve_frame(f, sym),
-            #[cfg(feature = "serde")]
-            Frame::Deserialized { ip, .. } => {
+            Frame::Raw { frame: ref f, .. } => resolve_frame(f, sym),
+            Frame::Address { ip, .. } => {
                 resolve(ip as *mut c_void, sym);
             }
         }
@@ -111,6 +180,63 @@ impl Frame {
     }
 }
 
+/// Resolves an address to owned symbols, see `resolve` for details.
+///
+/// Unlike `resolve`, this returns symbols which own all of their data and can
+/// be sent to and shared with other threads, so resolution can happen on a
+/// different thread than the one using the symbols. The returned symbols also
+/// record the path of the module containing `addr`, see
+/// `BacktraceSymbol::module`.
+///
+/// # Required features
+///
+/// This function requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default.
+pub fn resolve_owned(addr: *mut c_void) -> Vec<BacktraceSymbol> {
+    let mut symbols = Vec::new();
+    resolve(addr, |symbol| symbols.push(BacktraceSymbol::from(symbol)));
+    set_modules([(addr, &mut symbols[..])]);
+    symbols
+}
+
+/// Records the paths of the modules containing the instruction pointers of
+/// `frames` in all of the symbols paired with them.
+///
+/// The modules are looked up all at once, and each path is shared by all
+/// symbols of the same module.
+fn set_modules<'a>(frames: impl IntoIterator<Item = (*mut c_void, &'a mut [BacktraceSymbol])>) {
+    let (ips, mut symbols): (Vec<_>, Vec<_>) = frames
+        .into_iter()
+        .filter(|(_, symbols)| !symbols.is_empty())
+        .unzip();
+    if ips.is_empty() {
+        return;
+    }
+    let mut paths: Vec<(usize, Arc<Path>)> = Vec::new();
+    crate::symbolize::with_modules(&ips, &mut |i, module| {
+        let path = match paths.iter().find(|(base, _)| *base == module.base) {
+            Some((_, path)) => path.clone(),
+            None => {
+                let path = match module.name {
+                    BytesOrWideString::Bytes(name) if !name.is_empty() => {
+                        BytesOrWideString::Bytes(name).into_path_buf()
+                    }
+                    BytesOrWideString::Wide(name) if !name.is_empty() => {
+                        BytesOrWideString::Wide(name).into_path_buf()
+                    }
+                    _ => return,
+                };
+                let path = Arc::<Path>::from(path);
+                paths.push((module.base, path.clone()));
+                path
+            }
+        };
+        for symbol in symbols[i].iter_mut() {
+            symbol.module = Some(path.clone());
+        }
+    });
+}
+
 /// Captured version of a symbol in a backtrace.
 ///
 /// This type is returned as a list from `BacktraceFrame::symbols` and
@@ -128,6 +254,8 @@ pub struct BacktraceSymbol {
     filename: Option<PathBuf>,
     lineno: Option<u32>,
     colno: Option<u32>,
+    #[cfg_attr(feature = "serde", serde(default, with = "serde_impls::module"))]
+    module: Option<Arc<Path>>,
 }
 
 impl Backtrace {
@@ -193,23 +321,85 @@ impl Backtrace {
     }
 
     fn create(ip: usize) -> Backtrace {
+        Self::create_limited(ip, 0, usize::MAX)
+    }
+
+    /// Captures the frames starting at the caller of the function at `ip`,
+    /// leaving out the first `skip` of them and any after `max_frames` more.
+    fn create_limited(ip: usize, skip: usize, max_frames: usize) -> Backtrace {
+        let limit = skip.saturating_add(max_frames);
         let mut frames = Vec::new();
+        let mut found = false;
         trace(|frame| {
             frames.push(BacktraceFrame {
-                frame: Frame::Raw(frame.clone()),
+                frame: Frame::Raw {
+                    frame: frame.clone(),
+                    cfa: 0,
+                },
                 symbols: None,
+                metadata: Metadata::new(),
             });
 
             // clear inner frames, and start with call site.
             if frame.symbol_address() as usize == ip {
                 frames.clear();
+                found = true;
             }
 
-            true
+            // One frame more than kept is needed for the CFA of the last one.
+            !found || frames.len() <= limit
         });
+        set_cfas(&mut frames);
+        task::splice(&mut frames);
+        frames.drain(..skip.min(frames.len()));
+        frames.truncate(max_frames);
         frames.shrink_to_fit();
 
-        Backtrace { frames }
+        Backtrace {
+            frames,
+            metadata: Metadata::new(),
+        }
+    }
+
+    /// Creates an unresolved backtrace from a list of instruction pointers,
+    /// from the most recent frame to the oldest one.
+    ///
+    /// This allows addresses obtained elsewhere, for example from sanitizers,
+    /// eBPF programs, the kernel or custom unwinders, to be resolved and
+    /// printed like captured backtraces. See `BacktraceFrame::from_ip` for
+    /// how the addresses are interpreted.
+    ///
+    /// # Required features
+    ///
+    /// This function requires the `std` feature of the `backtrace` crate to be
+    /// enabled, and the `std` feature is enabled by default.
+    pub fn from_ips(ips: &[usize]) -> Backtrace {
+        Backtrace {
+            frames: ips.iter().map(|ip| BacktraceFrame::from_ip(*ip)).collect(),
+            metadata: Metadata::new(),
+        }
+    }
+
+    /// Returns whether this backtrace has frames, or why it doesn't.
+    ///
+    /// This is `BacktraceStatus::Captured` for any backtrace with frames, and
+    /// `BacktraceStatus::Unsupported` or `BacktraceStatus::CaptureFailed` for
+    /// ones without, depending on whether the target platform supports
+    /// capturing backtraces at all. Backtraces made from an empty list of
+    /// frames count as failed captures too.
+    ///
+    /// # Required features
+    ///
+    /// This function requires the `std` feature of the `backtrace` crate to be
+    /// enabled, and the `std` feature is enabled by default.
+    pub fn status(&self) -> BacktraceStatus {
+        if !self.frames.is_empty() {
+            BacktraceStatus::Captured
+        } else if !crate::backtrace::SUPPORTED {
+            BacktraceStatus::Unsupported
+        } else {
+            BacktraceStatus::CaptureFailed
+        }
     }
 
     /// Returns the frames from when this backtrace was captured.
@@ -226,6 +416,43 @@ impl Backtrace {
         self.frames.as_slice()
     }
 
+    /// Returns the frames of this backtrace for modification, e.g. to attach
+    /// metadata to them with `BacktraceFrame::metadata_mut`.
+    ///
+    /// # Required features
+    ///
+    /// This function requires the `std` feature of the `backtrace` crate to be
+    /// enabled, and the `std` feature is enabled by default.
+    pub fn frames_mut(&mut self) -> &mut [BacktraceFrame] {
+        self.frames.as_mut_slice()
+    }
+
+    /// Returns the user data attached to this backtrace as a whole.
+    ///
+    /// # Required features
+    ///
+    /// This function requires the `std` feature of the `backtrace` crate to be
+    /// enabled, and the `std` feature is enabled by default.
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// Returns the user data attached to this backtrace for modification.
+    ///
+    /// ```
+    /// let mut bt = backtrace::Backtrace::new_unresolved();
+    /// bt.metadata_mut().insert("task", 3);
+    /// bt.frames_mut()[0].metadata_mut().insert("size", 128);
+    /// ```
+    ///
+    /// # Required features
+    ///
+    /// This function requires the `std` feature of the `backtrace` crate to be
+    /// enabled, and the `std` feature is enabled by default.
+    pub fn metadata_mut(&mut self) -> &mut Metadata {
+        &mut self.metadata
+    }
+
     /// If this backtrace was created from `new_unresolved` then this function
     /// will resolve all addresses in the backtrace to their symbolic names.
     ///
@@ -237,21 +464,455 @@ impl Backtrace {
     /// This function requires the `std` feature of the `backtrace` crate to be
     /// enabled, and the `std` feature is enabled by default.
     pub fn resolve(&mut self) {
-        self.frames.iter_mut().for_each(BacktraceFrame::resolve);
+        let mut frames = self
+            .frames
+            .iter_mut()
+            .filter(|frame| !frame.is_resolved())
+            .map(|frame| {
+                let symbols = frame.frame.resolve_symbols();
+                (frame, symbols)
+            })
+            .collect::<Vec<_>>();
+        set_modules(
+            frames
+                .iter_mut()
+                .map(|(frame, symbols)| (frame.ip(), &mut symbols[..])),
+        );
+        for (frame, symbols) in frames {
+            frame.symbols = Some(symbols);
+        }
+    }
+
+    /// Like `resolve`, but resolves frames from different modules in parallel
+    /// when the `rayon` feature is enabled.
+    ///
+    /// See `resolve_all_parallel` for details.
+    ///
+    /// # Required features
+    ///
+    /// This function requires the `std` feature of the `backtrace` crate to be
+    /// enabled, and the `std` feature is enabled by default.
+    pub fn resolve_parallel(&mut self) {
+        Backtrace::resolve_all_parallel(std::slice::from_mut(self))
+    }
+
+    /// Resolves all frames of all of `traces` which haven't been resolved yet.
+    ///
+    /// Unlike resolving each backtrace with `resolve`, this groups the frames
+    /// of all backtraces by the module containing them and parses the debug
+    /// information of each module only once. With the `rayon` feature enabled,
+    /// the modules are then processed in parallel on the rayon thread pool,
+    /// which helps when symbolizing batches of backtraces, like a queue of
+    /// crash reports, which span many modules.
+    ///
+    /// This doesn't use or populate the global cache of debug information used
+    /// by `resolve`, so it's best suited to large batches. On platforms where
+    /// symbols aren't resolved with the `gimli` crate, like Windows MSVC, frames
+    /// are resolved one after another just like `resolve` does.
+    ///
+    /// # Required features
+    ///
+    /// This function requires the `std` feature of the `backtrace` crate to be
+    /// enabled, and the `std` feature is enabled by default.
+    pub fn resolve_all_parallel(traces: &mut [Backtrace]) {
+        let what = traces
+            .iter()
+            .flat_map(|trace| &trace.frames)
+            .filter(|frame| !frame.is_resolved())
+            .map(|frame| frame.frame.resolve_what())
+            .collect::<Vec<_>>();
+        let symbols = what
+            .iter()
+            .map(|_| Mutex::new(Vec::new()))
+            .collect::<Vec<_>>();
+        crate::symbolize::resolve_batch(&what, &|i, symbol| {
+            symbols[i]
+                .lock()
+                .unwrap()
+                .push(BacktraceSymbol::from(symbol));
+        });
+
+        let mut frames = traces
+            .iter_mut()
+            .flat_map(|trace| &mut trace.frames)
+            .filter(|frame| !frame.is_resolved())
+            .zip(symbols)
+            .map(|(frame, symbols)| (frame, symbols.into_inner().unwrap()))
+            .collect::<Vec<_>>();
+        set_modules(
+            frames
+                .iter_mut()
+                .map(|(frame, symbols)| (frame.ip(), &mut symbols[..])),
+        );
+        for (frame, symbols) in frames {
+            frame.symbols = Some(symbols);
+        }
+    }
+
+    /// Like `resolve`, but stops resolving frames once `deadline` has passed.
+    ///
+    /// Resolving symbols may require loading and parsing large amounts of
+    /// debug information, which can take a while. This bounds the time spent
+    /// for latency sensitive callers, which can then print the partially
+    /// resolved backtrace or resolve the remaining frames later on. The
+    /// deadline is checked before each frame, and the resolution of a single
+    /// frame isn't interrupted, so this may overrun the deadline by the time
+    /// it takes to resolve one frame. Frames which weren't resolved can be
+    /// identified with `BacktraceFrame::is_resolved`.
+    ///
+    /// # Required features
+    ///
+    /// This function requires the `std` feature of the `backtrace` crate to be
+    /// enabled, and the `std` feature is enabled by default.
+    pub fn resolve_with_deadline(&mut self, deadline: Instant) -> ResolveStatus {
+        self.resolve_while(|| {
+            if Instant::now() >= deadline {
+                Some(ResolveStatus::TimedOut)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Like `resolve`, but stops resolving frames once `token` is cancelled.
+    ///
+    /// This allows another thread, like a watchdog or a thread shutting down
+    /// a crash reporter, to abort resolution cleanly. The token is checked
+    /// before each frame, which for the first frame in each module is before
+    /// its debug information is loaded, and frames resolved until then are
+    /// kept, see `BacktraceFrame::is_resolved`.
+    ///
+    /// # Required features
+    ///
+    /// This function requires the `std` feature of the `backtrace` crate to be
+    /// enabled, and the `std` feature is enabled by default.
+    pub fn resolve_cancellable(&mut self, token: &CancellationToken) -> ResolveStatus {
+        self.resolve_while(|| {
+            if token.is_cancelled() {
+                Some(ResolveStatus::Cancelled)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Like `resolve`, but reports why frames couldn't be resolved.
+    ///
+    /// This returns the result of `BacktraceFrame::try_resolve` for each
+    /// frame. If `deadline` is given, frames which haven't been resolved
+    /// before it passes are skipped like with `resolve_with_deadline`, and
+    /// `ResolveError::Timeout` is returned for them.
+    ///
+    /// # Required features
+    ///
+    /// This function requires the `std` feature of the `backtrace` crate to be
+    /// enabled, and the `std` feature is enabled by default.
+    pub fn try_resolve(&mut self, deadline: Option<Instant>) -> Vec<Result<(), ResolveError>> {
+        self.frames
+            .iter_mut()
+            .map(|frame| {
+                let expired = deadline.map_or(false, |deadline| Instant::now() >= deadline);
+                if expired && !frame.is_resolved() {
+                    Err(ResolveError::Timeout)
+                } else {
+                    frame.try_resolve()
+                }
+            })
+            .collect()
+    }
+
+    /// Resolves frames until `stop` returns a reason to stop, which is asked
+    /// before each frame.
+    fn resolve_while(&mut self, mut stop: impl FnMut() -> Option<ResolveStatus>) -> ResolveStatus {
+        let what = self
+            .frames
+            .iter()
+            .filter(|frame| !frame.is_resolved())
+            .map(|frame| frame.frame.resolve_what())
+            .collect::<Vec<_>>();
+        let mut started = vec![false; what.len()];
+        let mut symbols = what.iter().map(|_| Vec::new()).collect::<Vec<_>>();
+        let mut status = ResolveStatus::Complete;
+        crate::symbolize::resolve_many_while(
+            &what,
+            &mut |i| match stop() {
+                Some(stopped) => {
+                    status = stopped;
+                    false
+                }
+                None => {
+                    started[i] = true;
+                    true
+                }
+            },
+            &mut |i, symbol| symbols[i].push(BacktraceSymbol::from(symbol)),
+        );
+
+        // Frames which nothing was looked up for have no symbols either if
+        // resolution wasn't stopped.
+        let mut frames = self
+            .frames
+            .iter_mut()
+            .filter(|frame| !frame.is_resolved())
+            .zip(started)
+            .zip(symbols)
+            .filter(|((_, started), _)| *started || status == ResolveStatus::Complete)
+            .map(|((frame, _), symbols)| (frame, symbols))
+            .collect::<Vec<_>>();
+        set_modules(
+            frames
+                .iter_mut()
+                .map(|(frame, symbols)| (frame.ip(), &mut symbols[..])),
+        );
+        for (frame, symbols) in frames {
+            frame.symbols = Some(symbols);
+        }
+        status
+    }
+
+    /// Computes a hash identifying the stack this backtrace was captured from.
+    ///
+    /// Each frame contributes the offset of its instruction pointer within the
+    /// module containing it, along with the module's build ID, or its path if
+    /// the build ID isn't known. This doesn't depend on where modules happen
+    /// to be loaded in memory and doesn't require resolving symbols, so it's
+    /// cheap to compute and the same crash in different runs of the same build
+    /// of a program has the same fingerprint. Frames outside of any known
+    /// module contribute their absolute address instead.
+    ///
+    /// The hash function is fixed, so fingerprints can be compared across
+    /// processes and versions of this crate.
+    ///
+    /// # Required features
+    ///
+    /// This function requires the `std` feature of the `backtrace` crate to be
+    /// enabled, and the `std` feature is enabled by default.
+    pub fn fingerprint(&self) -> u64 {
+        let mut hasher = Fnv1a::new();
+        for frame in &self.frames {
+            let ip = frame.ip();
+            let found = crate::symbolize::with_module(ip, |module| {
+                match (module.build_id, &module.name) {
+                    (Some(id), _) => hasher.write(id),
+                    (None, BytesOrWideString::Bytes(name)) => hasher.write(name),
+                    (None, BytesOrWideString::Wide(name)) => {
+                        name.iter().for_each(|c| hasher.write(&c.to_le_bytes()))
+                    }
+                }
+                hasher.write(&((ip as usize).wrapping_sub(module.base) as u64).to_le_bytes());
+            });
+            if found.is_none() {
+                hasher.write(&(ip as u64).to_le_bytes());
+            }
+        }
+        hasher.finish()
+    }
+
+    /// Removes frames from both ends of this backtrace which typically aren't
+    /// interesting, see `trim_start` and `trim_end`.
+    ///
+    /// # Required features
+    ///
+    /// This function requires the `std` feature of the `backtrace` crate to be
+    /// enabled, and the `std` feature is enabled by default.
+    pub fn trim(&mut self) {
+        self.trim_start();
+        self.trim_end();
+    }
+
+    /// Removes the frames of the machinery which captured this backtrace from
+    /// its start, i.e. the most recent frames.
+    ///
+    /// This is mostly useful for backtraces captured in panic hooks, where
+    /// this removes the hook itself and the standard library's code raising
+    /// the panic, leaving the function which panicked as the first frame.
+    /// Frames are recognized through their symbol names, so this resolves the
+    /// backtrace's symbols if that hasn't been done yet.
+    ///
+    /// # Required features
+    ///
+    /// This function requires the `std` feature of the `backtrace` crate to be
+    /// enabled, and the `std` feature is enabled by default.
+    pub fn trim_start(&mut self) {
+        self.resolve();
+
+        // Look for the last symbol of the panic machinery before the code
+        // which panicked.
+        let mut cut = None;
+        'frames: for (i, frame) in self.frames.iter().enumerate() {
+            for (j, symbol) in frame.symbols().iter().enumerate() {
+                let name = match symbol.name() {
+                    Some(name) => name,
+                    None => continue,
+                };
+                if is_panic_machinery(&format!("{name:#}")) {
+                    cut = Some((i, j));
+                    continue;
+                }
+                if cut.is_some() {
+                    let filename = symbol
+                        .filename()
+                        .and_then(|p| Some(BytesOrWideString::Bytes(p.to_str()?.as_bytes())));
+                    match FrameKind::classify(Some(&name), filename.as_ref()) {
+                        FrameKind::User | FrameKind::Dependency => break 'frames,
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        if let Some((i, j)) = cut {
+            let symbols = self.frames[i].symbols.get_or_insert_with(Vec::new);
+            symbols.drain(..=j);
+            let keep = if symbols.is_empty() { i + 1 } else { i };
+            self.frames.drain(..keep);
+        }
+    }
+
+    /// Removes the frames of the runtime which started the program or thread
+    /// from the end of this backtrace, i.e. the oldest frames.
+    ///
+    /// This leaves `main` or the closure passed to `thread::spawn` as the
+    /// last frame. Frames are recognized through their symbol names, so this
+    /// resolves the backtrace's symbols if that hasn't been done yet.
+    ///
+    /// # Required features
+    ///
+    /// This function requires the `std` feature of the `backtrace` crate to be
+    /// enabled, and the `std` feature is enabled by default.
+    pub fn trim_end(&mut self) {
+        self.resolve();
+
+        for (i, frame) in self.frames.iter_mut().enumerate() {
+            let start = frame.symbols().iter().position(|symbol| {
+                symbol
+                    .name()
+                    .map_or(false, |name| is_runtime_start(&format!("{name:#}")))
+            });
+            if let Some(j) = start {
+                let symbols = frame.symbols.get_or_insert_with(Vec::new);
+                symbols.truncate(j);
+                let keep = if symbols.is_empty() { i } else { i + 1 };
+                self.frames.truncate(keep);
+                return;
+            }
+        }
+    }
+}
+
+/// The 64-bit FNV-1a hash function, which unlike `DefaultHasher` is guaranteed
+/// to never change.
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Fnv1a {
+        Fnv1a(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ u64::from(*byte)).wrapping_mul(0x100_0000_01b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
     }
 }
 
+/// Returns whether the demangled `name` belongs to the code raising panics or
+/// capturing backtraces.
+fn is_panic_machinery(name: &str) -> bool {
+    // Recent versions of Rust put some of the standard library's unmangled
+    // symbols in a `__rustc` namespace.
+    let name = name.trim_start_matches("__rustc::");
+    const PREFIXES: &[&str] = &[
+        "backtrace::",
+        "core::panicking::",
+        "std::panicking::begin_panic",
+        "std::panicking::default_hook",
+        "std::panicking::rust_panic",
+        "std::panic::panic_any",
+        "core::result::unwrap_failed",
+        "core::option::unwrap_failed",
+        "core::option::expect_failed",
+        "rust_begin_unwind",
+        "rust_panic",
+        "__rust_start_panic",
+    ];
+    name.contains("__rust_end_short_backtrace")
+        || PREFIXES.iter().any(|prefix| name.starts_with(prefix))
+}
+
+/// Returns whether the demangled `name` belongs to the code starting the
+/// program or a thread, before any code of the application runs.
+fn is_runtime_start(name: &str) -> bool {
+    let name = name.trim_start_matches("__rustc::");
+    const NAMES: &[&str] = &[
+        "main",
+        "_start",
+        "__libc_start_main",
+        "__libc_start_call_main",
+        "start_thread",
+        "thread_start",
+        "_pthread_start",
+        "__pthread_start",
+        "clone",
+        "clone3",
+        "BaseThreadInitThunk",
+        "RtlUserThreadStart",
+        "mainCRTStartup",
+        "invoke_main",
+        "__scrt_common_main_seh",
+    ];
+    name.contains("__rust_begin_short_backtrace")
+        || name.starts_with("std::rt::lang_start")
+        || NAMES.contains(&name)
+}
+
 impl From<Vec<BacktraceFrame>> for Backtrace {
     fn from(frames: Vec<BacktraceFrame>) -> Self {
-        Backtrace { frames }
+        Backtrace {
+            frames,
+            metadata: Metadata::new(),
+        }
+    }
+}
+
+/// Works out the CFAs of captured frames from the stack pointers of their
+/// callers, where the unwinder didn't report them.
+///
+/// The stack pointer a caller is unwound to is the CFA of its callee on all
+/// architectures except s390x, where the CFA is biased. Inlined frames share
+/// the stack pointer of the frame they're inlined into, so they're skipped.
+fn set_cfas(frames: &mut [BacktraceFrame]) {
+    if cfg!(target_arch = "s390x") {
+        return;
+    }
+    for i in 0..frames.len() {
+        let sp = frames[i].sp() as usize;
+        if sp == 0 {
+            continue;
+        }
+        let caller_sp = frames[i + 1..]
+            .iter()
+            .map(|frame| frame.sp() as usize)
+            .find(|&caller_sp| caller_sp != sp);
+        if let (Frame::Raw { cfa, .. }, Some(caller_sp)) = (&mut frames[i].frame, caller_sp) {
+            // The stack grows down, anything else means the unwinder got lost.
+            if caller_sp > sp {
+                *cfa = caller_sp;
+            }
+        }
     }
 }
 
 impl From<crate::Frame> for BacktraceFrame {
     fn from(frame: crate::Frame) -> Self {
         BacktraceFrame {
-            frame: Frame::Raw(frame),
+            frame: Frame::Raw { frame, cfa: 0 },
             symbols: None,
+            metadata: Metadata::new(),
         }
     }
 }
@@ -266,6 +927,33 @@ impl Into<Vec<BacktraceFrame>> for Backtrace {
 }
 
 impl BacktraceFrame {
+    /// Creates an unresolved frame from an instruction pointer in the current
+    /// process.
+    ///
+    /// Like the instruction pointers of captured frames, `ip` is expected to
+    /// be a return address, i.e. the instruction after a call, and is
+    /// adjusted accordingly when resolving symbols. The address of the
+    /// enclosing function isn't known, so `symbol_address` returns `ip` too.
+    ///
+    /// # Required features
+    ///
+    /// This function requires the `std` feature of the `backtrace` crate to be
+    /// enabled, and the `std` feature is enabled by default.
+    pub fn from_ip(ip: usize) -> BacktraceFrame {
+        BacktraceFrame {
+            frame: Frame::Address {
+                ip,
+                sp: 0,
+                cfa: 0,
+                symbol_address: ip,
+                module_base_address: None,
+                scanned: false,
+            },
+            symbols: None,
+            metadata: Metadata::new(),
+        }
+    }
+
     /// Same as `Frame::ip`
     ///
     /// # Required features
@@ -276,6 +964,60 @@ impl BacktraceFrame {
         self.frame.ip()
     }
 
+    /// Same as `Frame::sp`
+    ///
+    /// Frames which were only given as addresses, like with `from_ip`, have no
+    /// known stack pointer, so this returns a null pointer for them.
+    ///
+    /// # Required features
+    ///
+    /// This function requires the `std` feature of the `backtrace` crate to be
+    /// enabled, and the `std` feature is enabled by default.
+    pub fn sp(&self) -> *mut c_void {
+        self.frame.sp()
+    }
+
+    /// Same as `Frame::cfa`, except that frames of captured backtraces get the
+    /// CFA from the stack pointer of their caller where the unwinder didn't
+    /// report it.
+    ///
+    /// That's the case on most platforms where `Frame::sp` is known, but not
+    /// for the outermost frame, frames converted from a single `Frame`, or
+    /// frames only given as addresses, for which this returns a null pointer.
+    ///
+    /// # Required features
+    ///
+    /// This function requires the `std` feature of the `backtrace` crate to be
+    /// enabled, and the `std` feature is enabled by default.
+    pub fn cfa(&self) -> *mut c_void {
+        self.frame.cfa()
+    }
+
+    /// Same as `Frame::registers`
+    ///
+    /// Outside of Windows, frames only keep their registers if
+    /// `set_capture_registers` was enabled when they were captured. They're
+    /// never serialized, so this returns `None` for deserialized frames and
+    /// others only given as addresses.
+    ///
+    /// # Required features
+    ///
+    /// This function requires the `std` feature of the `backtrace` crate to be
+    /// enabled, and the `std` feature is enabled by default.
+    pub fn registers(&self) -> Option<crate::Registers> {
+        self.frame.registers()
+    }
+
+    /// Same as `Frame::is_scanned`
+    ///
+    /// # Required features
+    ///
+    /// This function requires the `std` feature of the `backtrace` crate to be
+    /// enabled, and the `std` feature is enabled by default.
+    pub fn is_scanned(&self) -> bool {
+        self.frame.is_scanned()
+    }
+
     /// Same as `Frame::symbol_address`
     ///
     /// # Required features
@@ -324,9 +1066,137 @@ impl BacktraceFrame {
     /// enabled, and the `std` feature is enabled by default.
     pub fn resolve(&mut self) {
         if self.symbols.is_none() {
-            self.symbols = Some(self.frame.resolve_symbols());
+            let mut symbols = self.frame.resolve_symbols();
+            set_modules([(self.ip(), &mut symbols[..])]);
+            self.symbols = Some(symbols);
+        }
+    }
+
+    /// Like `resolve`, but reports why no symbols were found for this frame.
+    ///
+    /// This returns `Ok` if the frame has any symbols, and otherwise the reason
+    /// it couldn't be resolved, see the `try_resolve` function for details.
+    /// This works for frames which have already been resolved too.
+    ///
+    /// # Required features
+    ///
+    /// This function requires the `std` feature of the `backtrace` crate to be
+    /// enabled, and the `std` feature is enabled by default.
+    pub fn try_resolve(&mut self) -> Result<(), ResolveError> {
+        self.resolve();
+        if self.symbols().is_empty() {
+            Err(crate::symbolize::diagnose(self.frame.resolve_what()))
+        } else {
+            Ok(())
         }
     }
+
+    /// Returns whether the symbols of this frame have been resolved.
+    ///
+    /// Note that resolved frames may still have no symbols if none could be
+    /// found.
+    ///
+    /// # Required features
+    ///
+    /// This function requires the `std` feature of the `backtrace` crate to be
+    /// enabled, and the `std` feature is enabled by default.
+    pub fn is_resolved(&self) -> bool {
+        self.symbols.is_some()
+    }
+
+    /// Returns the user data attached to this frame.
+    ///
+    /// # Required features
+    ///
+    /// This function requires the `std` feature of the `backtrace` crate to be
+    /// enabled, and the `std` feature is enabled by default.
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// Returns the user data attached to this frame for modification.
+    ///
+    /// # Required features
+    ///
+    /// This function requires the `std` feature of the `backtrace` crate to be
+    /// enabled, and the `std` feature is enabled by default.
+    pub fn metadata_mut(&mut self) -> &mut Metadata {
+        &mut self.metadata
+    }
+}
+
+/// The outcome of resolving a backtrace with `Backtrace::resolve_with_deadline`
+/// or `Backtrace::resolve_cancellable`.
+///
+/// # Required features
+///
+/// This type requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum ResolveStatus {
+    /// All frames have been resolved.
+    Complete,
+    /// The deadline passed before all frames could be resolved.
+    TimedOut,
+    /// Resolution was cancelled before all frames could be resolved.
+    Cancelled,
+}
+
+/// Whether a backtrace has frames, or why it doesn't, as returned by
+/// `Backtrace::status` and `Backtrace::capture_status`.
+///
+/// # Required features
+///
+/// This type requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum BacktraceStatus {
+    /// The backtrace has frames.
+    Captured,
+    /// Backtraces can't be captured on the target platform, as this crate
+    /// doesn't know how to unwind the stack there.
+    Unsupported,
+    /// Backtraces are disabled through the environment, see
+    /// `Backtrace::capture`.
+    Disabled,
+    /// Backtraces are supported, but no frames were found, like when the
+    /// unwinder failed at the very first frame, or the unwinding library
+    /// couldn't be loaded.
+    CaptureFailed,
+}
+
+/// A token used to cancel the resolution of backtraces in progress, see
+/// `Backtrace::resolve_cancellable`.
+///
+/// Clones of a token share its state, so one clone can be used to cancel
+/// resolution with another one on a different thread.
+///
+/// # Required features
+///
+/// This type requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    /// Creates a token which isn't cancelled yet.
+    pub fn new() -> CancellationToken {
+        CancellationToken::default()
+    }
+
+    /// Cancels this token and all of its clones.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns whether this token has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
 }
 
 impl BacktraceSymbol {
@@ -379,6 +1249,33 @@ impl BacktraceSymbol {
     pub fn colno(&self) -> Option<u32> {
         self.colno
     }
+
+    /// Returns the path of the executable or shared library containing this
+    /// symbol, if known.
+    ///
+    /// This is only known for symbols resolved through `Backtrace::resolve`,
+    /// `BacktraceFrame::resolve` or `resolve_owned`.
+    ///
+    /// # Required features
+    ///
+    /// This function requires the `std` feature of the `backtrace` crate to be
+    /// enabled, and the `std` feature is enabled by default.
+    pub fn module(&self) -> Option<&Path> {
+        self.module.as_deref()
+    }
+}
+
+impl From<&Symbol> for BacktraceSymbol {
+    fn from(symbol: &Symbol) -> BacktraceSymbol {
+        BacktraceSymbol {
+            name: symbol.name().map(|m| m.as_bytes().to_vec()),
+            addr: symbol.addr().map(|a| a as usize),
+            filename: symbol.filename().map(|m| m.to_owned()),
+            lineno: symbol.lineno(),
+            colno: symbol.colno(),
+            module: None,
+        }
+    }
 }
 
 impl fmt::Debug for Backtrace {
@@ -409,9 +1306,8 @@ impl fmt::Debug for Backtrace {
 
         let mut f = BacktraceFmt::new(fmt, style, &mut print_path);
         f.add_context()?;
-        for frame in &self.frames {
-            f.frame().backtrace_frame(frame)?;
-        }
+        f.backtrace_frames(&self.frames)?;
+        f.backtrace_metadata(&self.metadata)?;
         f.finish()?;
         Ok(())
     }
@@ -440,6 +1336,7 @@ impl fmt::Debug for BacktraceSymbol {
             .field("filename", &self.filename())
             .field("lineno", &self.lineno())
             .field("colno", &self.colno())
+            .field("module", &self.module())
             .finish()
     }
 }
@@ -451,12 +1348,39 @@ mod serde_impls {
     use serde::ser::Serializer;
     use serde::{Deserialize, Serialize};
 
+    /// Serializes the shared module paths of symbols like plain paths.
+    pub(super) mod module {
+        use super::*;
+
+        pub fn serialize<S>(module: &Option<Arc<Path>>, s: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            module.as_deref().serialize(s)
+        }
+
+        pub fn deserialize<'a, D>(d: D) -> Result<Option<Arc<Path>>, D::Error>
+        where
+            D: Deserializer<'a>,
+        {
+            Ok(Option::<PathBuf>::deserialize(d)?.map(Arc::from))
+        }
+    }
+
     #[derive(Serialize, Deserialize)]
     struct SerializedFrame {
         ip: usize,
+        #[serde(default)]
+        sp: usize,
+        #[serde(default)]
+        cfa: usize,
         symbol_address: usize,
         module_base_address: Option<usize>,
         symbols: Option<Vec<BacktraceSymbol>>,
+        #[serde(default, skip_serializing_if = "Metadata::is_empty")]
+        metadata: Metadata,
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        scanned: bool,
     }
 
     impl Serialize for BacktraceFrame {
@@ -464,12 +1388,20 @@ mod serde_impls {
         where
             S: Serializer,
         {
-            let BacktraceFrame { frame, symbols } = self;
+            let BacktraceFrame {
+                frame,
+                symbols,
+                metadata,
+            } = self;
             SerializedFrame {
                 ip: frame.ip() as usize,
+                sp: frame.sp() as usize,
+                cfa: frame.cfa() as usize,
                 symbol_address: frame.symbol_address() as usize,
                 module_base_address: frame.module_base_address().map(|addr| addr as usize),
                 symbols: symbols.clone(),
+                metadata: metadata.clone(),
+                scanned: frame.is_scanned(),
             }
             .serialize(s)
         }
@@ -482,12 +1414,16 @@ mod serde_impls {
         {
             let frame: SerializedFrame = SerializedFrame::deserialize(d)?;
             Ok(BacktraceFrame {
-                frame: Frame::Deserialized {
+                frame: Frame::Address {
                     ip: frame.ip,
+                    sp: frame.sp,
+                    cfa: frame.cfa,
                     symbol_address: frame.symbol_address,
                     module_base_address: frame.module_base_address,
+                    scanned: frame.scanned,
                 },
                 symbols: frame.symbols,
+                metadata: frame.metadata,
             })
         }
     }
@@ -517,4 +1453,53 @@ mod tests {
             println!("{:?}", frame.symbols());
         }
     }
+
+    #[test]
+    fn test_frame_sp() {
+        crate::trace(|frame| {
+            assert_eq!(BacktraceFrame::from(frame.clone()).sp(), frame.sp());
+            true
+        });
+        assert!(BacktraceFrame::from_ip(0x1000).sp().is_null());
+    }
+
+    #[test]
+    #[inline(never)]
+    fn test_frame_cfa() {
+        let local = 0u8;
+        let local = unsafe { core::ptr::read_volatile(&&local) } as *const u8 as usize;
+        let backtrace = Backtrace::new_unresolved();
+        let frame = &backtrace.frames()[0];
+        if frame.sp().is_null() || cfg!(target_arch = "s390x") {
+            return;
+        }
+        // The locals of a frame are between its stack pointer and its CFA.
+        let (sp, cfa) = (frame.sp() as usize, frame.cfa() as usize);
+        assert!(sp <= local && local < cfa, "{sp:#x} {local:#x} {cfa:#x}");
+        assert!(BacktraceFrame::from_ip(0x1000).cfa().is_null());
+    }
+
+    #[test]
+    fn test_frame_registers() {
+        assert!(BacktraceFrame::from_ip(0x1000).registers().is_none());
+        if crate::backtrace::registers::CALLEE_SAVED.is_empty()
+            || cfg!(all(windows, target_arch = "x86"))
+            || cfg!(miri)
+        {
+            return;
+        }
+        crate::trace(|frame| {
+            let registers = frame.registers().unwrap();
+            assert_eq!(
+                registers.iter().count(),
+                crate::backtrace::registers::CALLEE_SAVED.len()
+            );
+            false
+        });
+        crate::set_capture_registers(true);
+        let backtrace = Backtrace::new_unresolved();
+        // Other tests in this binary capture backtraces too.
+        crate::set_capture_registers(false);
+        assert!(backtrace.frames()[0].registers().is_some());
+    }
 }
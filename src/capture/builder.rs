@@ -0,0 +1,108 @@
+//! Capturing backtraces with options, see `Backtrace::builder`.
+
+use super::Backtrace;
+
+/// Options for capturing a backtrace, created with `Backtrace::builder`.
+///
+/// This captures a backtrace like `Backtrace::new` by default, and like
+/// `Backtrace::new_unresolved` with `resolve(false)`. Backtraces are always
+/// captured on the current thread, as the unwinders used by this crate can't
+/// walk the stacks of other threads.
+///
+/// Like with `Backtrace::new`, the frames of this crate and of the unwinder
+/// are found with `Frame::symbol_address`. On platforms where it only returns
+/// the instruction pointer, like macOS and 32-bit ARM, they're kept at the
+/// start of the backtrace, counted by `skip`, and the whole stack is walked
+/// whatever `max_frames` is.
+///
+/// # Required features
+///
+/// This type requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default.
+///
+/// # Example
+///
+/// ```
+/// use backtrace::Backtrace;
+///
+/// // Leave out this function's caller, and only keep the 16 frames after it.
+/// let bt = Backtrace::builder()
+///     .skip(1)
+///     .max_frames(16)
+///     .resolve(false)
+///     .capture();
+/// assert!(bt.frames().len() <= 16);
+/// ```
+#[derive(Clone, Debug)]
+pub struct BacktraceBuilder {
+    skip: usize,
+    max_frames: usize,
+    resolve: bool,
+}
+
+impl Backtrace {
+    /// Returns a builder for capturing a backtrace with options, like leaving
+    /// out frames or not resolving symbols.
+    ///
+    /// # Required features
+    ///
+    /// This function requires the `std` feature of the `backtrace` crate to be
+    /// enabled, and the `std` feature is enabled by default.
+    pub fn builder() -> BacktraceBuilder {
+        BacktraceBuilder::new()
+    }
+}
+
+impl Default for BacktraceBuilder {
+    fn default() -> BacktraceBuilder {
+        BacktraceBuilder::new()
+    }
+}
+
+impl BacktraceBuilder {
+    /// Creates a builder with the default options, which capture all frames
+    /// starting at the caller of `capture` and resolve their symbols.
+    pub fn new() -> BacktraceBuilder {
+        BacktraceBuilder {
+            skip: 0,
+            max_frames: usize::MAX,
+            resolve: true,
+        }
+    }
+
+    /// Leaves out the first `frames` frames after the caller of `capture`,
+    /// like the frames of helper functions capturing backtraces for others.
+    pub fn skip(mut self, frames: usize) -> BacktraceBuilder {
+        self.skip = frames;
+        self
+    }
+
+    /// Keeps at most `frames` frames, after leaving out the ones to skip.
+    ///
+    /// The stack is only walked as far as needed for them, which makes
+    /// capturing backtraces of deep stacks cheaper.
+    pub fn max_frames(mut self, frames: usize) -> BacktraceBuilder {
+        self.max_frames = frames;
+        self
+    }
+
+    /// Whether the symbols of the frames are resolved right away, which is
+    /// the default, or left to `Backtrace::resolve` or printing the
+    /// backtrace.
+    pub fn resolve(mut self, resolve: bool) -> BacktraceBuilder {
+        self.resolve = resolve;
+        self
+    }
+
+    /// Captures a backtrace at the callsite of this function with these
+    /// options.
+    #[inline(never)] // want to make sure there's a frame here to remove
+    pub fn capture(&self) -> Backtrace {
+        let ip = BacktraceBuilder::capture as *const () as usize;
+        let mut bt = Backtrace::create_limited(ip, self.skip, self.max_frames);
+        if self.resolve {
+            bt.resolve();
+        }
+        bt
+    }
+}
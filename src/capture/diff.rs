@@ -0,0 +1,197 @@
+//! Comparing backtraces with each other.
+
+use super::{Backtrace, BacktraceFrame, BacktraceSymbol};
+use std::prelude::v1::*;
+
+/// The largest table of common subsequence lengths `Backtrace::diff` builds
+/// for the frames between the common prefix and suffix, which bounds the time
+/// and memory it takes.
+const MAX_LCS_CELLS: usize = 1 << 16;
+
+/// One entry of the difference between two backtraces, as returned by
+/// `Backtrace::diff`.
+///
+/// # Required features
+///
+/// This type requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default.
+#[derive(Debug, Clone, Copy)]
+pub enum FrameDiff<'a> {
+    /// A frame present in both backtraces, first from the backtrace `diff`
+    /// was called on and then from the one passed to it.
+    Both(&'a BacktraceFrame, &'a BacktraceFrame),
+    /// A frame only present in the backtrace `diff` was called on.
+    Left(&'a BacktraceFrame),
+    /// A frame only present in the backtrace passed to `diff`.
+    Right(&'a BacktraceFrame),
+}
+
+impl Backtrace {
+    /// Returns whether this backtrace has the same frames as `other`, without
+    /// taking addresses into account.
+    ///
+    /// Frames are compared with `BacktraceFrame::eq_ignoring_address`, so
+    /// backtraces captured at the same place in different processes are
+    /// equal even if their code was loaded at different addresses.
+    ///
+    /// # Required features
+    ///
+    /// This function requires the `std` feature of the `backtrace` crate to be
+    /// enabled, and the `std` feature is enabled by default.
+    pub fn eq_ignoring_addresses(&self, other: &Backtrace) -> bool {
+        self.frames.len() == other.frames.len()
+            && self
+                .frames
+                .iter()
+                .zip(&other.frames)
+                .all(|(a, b)| a.eq_ignoring_address(b))
+    }
+
+    /// Returns the number of frames at the start of this backtrace, i.e. the
+    /// most recent ones, which are equal to those of `other`.
+    ///
+    /// # Required features
+    ///
+    /// This function requires the `std` feature of the `backtrace` crate to be
+    /// enabled, and the `std` feature is enabled by default.
+    pub fn common_prefix_len(&self, other: &Backtrace) -> usize {
+        self.frames
+            .iter()
+            .zip(&other.frames)
+            .take_while(|(a, b)| a.eq_ignoring_address(b))
+            .count()
+    }
+
+    /// Returns the number of frames at the end of this backtrace, i.e. the
+    /// oldest ones, which are equal to those of `other`.
+    ///
+    /// This is typically the part of two backtraces captured in the same
+    /// thread leading up to where their code paths diverged.
+    ///
+    /// # Required features
+    ///
+    /// This function requires the `std` feature of the `backtrace` crate to be
+    /// enabled, and the `std` feature is enabled by default.
+    pub fn common_suffix_len(&self, other: &Backtrace) -> usize {
+        self.frames
+            .iter()
+            .rev()
+            .zip(other.frames.iter().rev())
+            .take_while(|(a, b)| a.eq_ignoring_address(b))
+            .count()
+    }
+
+    /// Computes the difference between the frames of this backtrace and
+    /// `other`.
+    ///
+    /// The result lists the frames of both backtraces from the most recent to
+    /// the oldest, pairing up as many equal frames as possible, like the
+    /// output of `diff` does for lines.
+    ///
+    /// Frames in common at the start and end of both backtraces are always
+    /// paired up. If the frames between them are too many to be compared with
+    /// each other quickly, they're all listed as only present in their own
+    /// backtrace instead.
+    ///
+    /// # Required features
+    ///
+    /// This function requires the `std` feature of the `backtrace` crate to be
+    /// enabled, and the `std` feature is enabled by default.
+    pub fn diff<'a>(&'a self, other: &'a Backtrace) -> Vec<FrameDiff<'a>> {
+        let prefix = self.common_prefix_len(other);
+        let max_suffix = self.frames.len().min(other.frames.len()) - prefix;
+        let suffix = self.common_suffix_len(other).min(max_suffix);
+        let left = &self.frames[prefix..self.frames.len() - suffix];
+        let right = &other.frames[prefix..other.frames.len() - suffix];
+
+        let mut diff = Vec::with_capacity(self.frames.len().max(other.frames.len()));
+        diff.extend(
+            self.frames[..prefix]
+                .iter()
+                .zip(&other.frames[..prefix])
+                .map(|(a, b)| FrameDiff::Both(a, b)),
+        );
+        if (left.len() + 1).saturating_mul(right.len() + 1) <= MAX_LCS_CELLS {
+            diff_common_subsequence(left, right, &mut diff);
+        } else {
+            diff.extend(left.iter().map(FrameDiff::Left));
+            diff.extend(right.iter().map(FrameDiff::Right));
+        }
+        diff.extend(
+            self.frames[self.frames.len() - suffix..]
+                .iter()
+                .zip(&other.frames[other.frames.len() - suffix..])
+                .map(|(a, b)| FrameDiff::Both(a, b)),
+        );
+        diff
+    }
+}
+
+/// Appends the difference between `left` and `right` to `diff`, pairing up
+/// the frames of their longest common subsequence.
+fn diff_common_subsequence<'a>(
+    left: &'a [BacktraceFrame],
+    right: &'a [BacktraceFrame],
+    diff: &mut Vec<FrameDiff<'a>>,
+) {
+    // `lcs[i][j]` is the length of the longest common subsequence of
+    // `left[i..]` and `right[j..]`.
+    let mut lcs = vec![vec![0_usize; right.len() + 1]; left.len() + 1];
+    for i in (0..left.len()).rev() {
+        for j in (0..right.len()).rev() {
+            lcs[i][j] = if left[i].eq_ignoring_address(&right[j]) {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    while i < left.len() && j < right.len() {
+        if left[i].eq_ignoring_address(&right[j]) {
+            diff.push(FrameDiff::Both(&left[i], &right[j]));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            diff.push(FrameDiff::Left(&left[i]));
+            i += 1;
+        } else {
+            diff.push(FrameDiff::Right(&right[j]));
+            j += 1;
+        }
+    }
+    diff.extend(left[i..].iter().map(FrameDiff::Left));
+    diff.extend(right[j..].iter().map(FrameDiff::Right));
+}
+
+impl BacktraceFrame {
+    /// Returns whether this frame is in the same code as `other`, without
+    /// taking addresses into account.
+    ///
+    /// Frames with resolved symbols are equal if their symbols have the same
+    /// names and source locations. Frames whose symbols haven't been resolved
+    /// or couldn't be found can only be compared by their instruction
+    /// pointers.
+    ///
+    /// # Required features
+    ///
+    /// This function requires the `std` feature of the `backtrace` crate to be
+    /// enabled, and the `std` feature is enabled by default.
+    pub fn eq_ignoring_address(&self, other: &BacktraceFrame) -> bool {
+        let (a, b) = (self.symbols(), other.symbols());
+        if a.is_empty() || b.is_empty() {
+            return self.ip() == other.ip();
+        }
+        a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.same_location(b))
+    }
+}
+
+impl BacktraceSymbol {
+    fn same_location(&self, other: &BacktraceSymbol) -> bool {
+        self.name == other.name
+            && self.filename == other.filename
+            && self.lineno == other.lineno
+            && self.colno == other.colno
+    }
+}
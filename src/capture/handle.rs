@@ -0,0 +1,202 @@
+//! Capturing backtraces where memory can't be allocated, like in allocators.
+
+use super::Backtrace;
+use crate::trace_unsynchronized;
+use std::prelude::v1::*;
+use std::ptr;
+use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
+
+// States of a record, stored in its first word. Full records store the number
+// of instruction pointers in them plus one instead.
+const EMPTY: usize = 0;
+const WRITING: usize = usize::MAX;
+const READING: usize = usize::MAX - 1;
+
+/// Records the instruction pointers of backtraces into memory provided by the
+/// caller, without allocating memory or taking locks, so they can be resolved
+/// later.
+///
+/// This is meant for code which can't allocate, most notably heap profilers
+/// capturing a backtrace in their global allocator for every allocation.
+/// `capture` only walks the stack and stores the addresses of the frames in a
+/// ring of fixed-size records in `storage`. Resolving symbols needs memory
+/// and locks, so that's left to `drain` or `resolve`, which are called later
+/// outside of the allocator.
+///
+/// Neither function of this crate called by `capture` allocates, but the
+/// unwinder of the platform may do so the first time it's used, so it's best
+/// to capture one backtrace before installing the allocator using this.
+/// Records are dropped rather than overwritten when all of them are full, see
+/// `dropped`.
+///
+/// # Required features
+///
+/// This type requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default.
+///
+/// # Example
+///
+/// ```no_run
+/// extern crate backtrace;
+///
+/// use backtrace::CaptureHandle;
+/// use std::alloc::{GlobalAlloc, Layout, System};
+/// use std::sync::atomic::AtomicUsize;
+///
+/// const ZERO: AtomicUsize = AtomicUsize::new(0);
+/// static STORAGE: [AtomicUsize; 1 << 16] = [ZERO; 1 << 16];
+/// static HANDLE: CaptureHandle<'static> = CaptureHandle::new(&STORAGE, 32);
+///
+/// struct Profiler;
+///
+/// unsafe impl GlobalAlloc for Profiler {
+///     unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+///         HANDLE.capture();
+///         System.alloc(layout)
+///     }
+///
+///     unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+///         System.dealloc(ptr, layout)
+///     }
+/// }
+///
+/// #[global_allocator]
+/// static ALLOCATOR: Profiler = Profiler;
+///
+/// fn main() {
+///     // ... run the code to profile ...
+///     for trace in HANDLE.resolve() {
+///         println!("{:?}", trace);
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct CaptureHandle<'a> {
+    storage: &'a [AtomicUsize],
+    max_depth: usize,
+    next: AtomicUsize,
+    dropped: AtomicUsize,
+}
+
+impl<'a> CaptureHandle<'a> {
+    /// Creates a handle recording backtraces of up to `max_depth` frames into
+    /// `storage`.
+    ///
+    /// Each record takes `max_depth + 1` words of `storage`, and the words
+    /// have to be zero initially. Frames past `max_depth` are cut off.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_depth` is zero.
+    pub const fn new(storage: &'a [AtomicUsize], max_depth: usize) -> CaptureHandle<'a> {
+        assert!(max_depth > 0, "the maximum depth must not be zero");
+        CaptureHandle {
+            storage,
+            max_depth,
+            next: AtomicUsize::new(0),
+            dropped: AtomicUsize::new(0),
+        }
+    }
+
+    /// Captures the backtrace of the current thread into a free record,
+    /// returning whether there was one.
+    ///
+    /// The first frame recorded is the caller of this function. Like with
+    /// `Backtrace::new`, this relies on `Frame::symbol_address` to find the
+    /// frames to leave out, so on platforms where it only returns the
+    /// instruction pointer, like macOS and 32-bit ARM, the frames of this
+    /// function and of the unwinder are recorded as well.
+    ///
+    /// This can be called from any number of threads at once, and barely uses
+    /// any stack beyond what tracing does, see `MAX_TRACE_STACK`.
+    ///
+    /// # Safety
+    ///
+    /// No locks are taken, so this has the same requirements as
+    /// `trace_unsynchronized`.
+    #[inline(never)]
+    pub unsafe fn capture(&self) -> bool {
+        let records = self.records();
+        if records == 0 {
+            return false;
+        }
+        let index = self.next.fetch_add(1, SeqCst) % records;
+        let record = self.record(index);
+        if record[0]
+            .compare_exchange(EMPTY, WRITING, SeqCst, SeqCst)
+            .is_err()
+        {
+            self.dropped.fetch_add(1, SeqCst);
+            return false;
+        }
+
+        let ips = &record[1..];
+        let mut len = 0;
+        trace_unsynchronized(|frame| {
+            if ptr::eq(
+                frame.symbol_address() as *const (),
+                Self::capture as *const (),
+            ) {
+                // Leave out the frames of this function and the ones it calls.
+                len = 0;
+                return true;
+            }
+            ips[len].store(frame.ip() as usize, SeqCst);
+            len += 1;
+            len < ips.len()
+        });
+        record[0].store(len + 1, SeqCst);
+        true
+    }
+
+    /// Takes all recorded backtraces out of the handle, passing the
+    /// instruction pointers of each one to `f`, from the most recent frame to
+    /// the oldest one.
+    ///
+    /// Backtraces can be captured while they're drained, but whether those
+    /// are passed to `f` this time depends on which record they end up in.
+    pub fn drain<F: FnMut(&[usize])>(&self, mut f: F) {
+        let records = self.records();
+        let mut ips = Vec::with_capacity(self.max_depth);
+        let start = self.next.load(SeqCst);
+        for i in 0..records {
+            let record = self.record((start + i) % records);
+            let state = record[0].load(SeqCst);
+            if matches!(state, EMPTY | WRITING | READING)
+                || record[0]
+                    .compare_exchange(state, READING, SeqCst, SeqCst)
+                    .is_err()
+            {
+                continue;
+            }
+            ips.clear();
+            ips.extend(record[1..state].iter().map(|ip| ip.load(SeqCst)));
+            record[0].store(EMPTY, SeqCst);
+            f(&ips);
+        }
+    }
+
+    /// Takes all recorded backtraces out of the handle like `drain`, and
+    /// resolves their symbols in one batch with
+    /// `Backtrace::resolve_all_parallel`.
+    pub fn resolve(&self) -> Vec<Backtrace> {
+        let mut traces = Vec::new();
+        self.drain(|ips| traces.push(Backtrace::from_ips(ips)));
+        Backtrace::resolve_all_parallel(&mut traces);
+        traces
+    }
+
+    /// Returns the number of backtraces which weren't captured because all
+    /// records were full.
+    pub fn dropped(&self) -> usize {
+        self.dropped.load(SeqCst)
+    }
+
+    fn records(&self) -> usize {
+        self.storage.len() / (self.max_depth + 1)
+    }
+
+    fn record(&self, index: usize) -> &[AtomicUsize] {
+        &self.storage[index * (self.max_depth + 1)..][..self.max_depth + 1]
+    }
+}
@@ -0,0 +1,243 @@
+//! Compact storage of large numbers of backtraces.
+
+use super::{Backtrace, BacktraceSymbol};
+use crate::BytesOrWideString;
+use core::ffi::c_void;
+use std::collections::hash_map::{DefaultHasher, Entry};
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
+use std::prelude::v1::*;
+
+/// Identifies a frame interned in a `TraceStore`.
+///
+/// Identifiers are assigned sequentially starting at zero, so they can be used
+/// to index into side tables.
+///
+/// # Required features
+///
+/// This type requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FrameId(u32);
+
+impl FrameId {
+    /// Returns the index of this frame in the order frames were interned.
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// Identifies a backtrace interned in a `TraceStore`.
+///
+/// Identifiers are assigned sequentially starting at zero, so they can be used
+/// to index into side tables.
+///
+/// # Required features
+///
+/// This type requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TraceId(u32);
+
+impl TraceId {
+    /// Returns the index of this backtrace in the order backtraces were
+    /// interned.
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// A store for large numbers of backtraces, such as the ones collected by
+/// allocation or leak profilers.
+///
+/// Every distinct frame, identified by the module containing it and its
+/// offset in that module, is only stored once, and so is every distinct
+/// backtrace, which is then just a list of `FrameId`s. Symbols are resolved
+/// lazily, once per distinct frame, through `symbols`.
+///
+/// Instruction pointers already seen are assumed to still belong to the same
+/// module, so modules shouldn't be unloaded while a store is in use.
+///
+/// # Required features
+///
+/// This type requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default.
+#[derive(Default)]
+pub struct TraceStore {
+    modules: Vec<Module>,
+    module_ids: HashMap<Vec<u8>, u32>,
+    frames: Vec<Frame>,
+    frame_ids: HashMap<(Option<u32>, usize), FrameId>,
+    ip_cache: HashMap<usize, FrameId>,
+    trace_frames: Vec<FrameId>,
+    traces: Vec<Range<usize>>,
+    trace_ids: HashMap<u64, Vec<TraceId>>,
+}
+
+struct Module {
+    /// Where the module was last seen to be loaded.
+    base: usize,
+}
+
+struct Frame {
+    module: Option<u32>,
+    /// The offset of the frame in its module, or its absolute address if it's
+    /// outside of any known module.
+    offset: usize,
+    symbols: Option<Box<[BacktraceSymbol]>>,
+}
+
+impl TraceStore {
+    /// Creates an empty store.
+    pub fn new() -> TraceStore {
+        TraceStore::default()
+    }
+
+    /// Returns the number of distinct frames in this store.
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Returns the number of distinct backtraces in this store.
+    pub fn trace_count(&self) -> usize {
+        self.traces.len()
+    }
+
+    /// Interns the frame with the instruction pointer `ip`.
+    pub fn intern_frame(&mut self, ip: *mut c_void) -> FrameId {
+        if let Some(id) = self.ip_cache.get(&(ip as usize)) {
+            return *id;
+        }
+
+        let modules = &mut self.modules;
+        let module_ids = &mut self.module_ids;
+        let located = crate::symbolize::with_module(ip, |module| {
+            // Identify modules by their build, so reloaded modules are still
+            // recognized.
+            let wide;
+            let key = match (module.build_id, &module.name) {
+                (Some(id), _) => id,
+                (None, BytesOrWideString::Bytes(name)) => name,
+                (None, BytesOrWideString::Wide(name)) => {
+                    wide = name
+                        .iter()
+                        .flat_map(|c| c.to_le_bytes())
+                        .collect::<Vec<_>>();
+                    &wide
+                }
+            };
+            let id = match module_ids.get(key) {
+                Some(id) => *id,
+                None => {
+                    let id = u32::try_from(modules.len())
+                        .expect("a trace store has at most `u32::MAX` modules");
+                    modules.push(Module { base: module.base });
+                    module_ids.insert(key.to_vec(), id);
+                    id
+                }
+            };
+            modules[id as usize].base = module.base;
+            (Some(id), (ip as usize).wrapping_sub(module.base))
+        });
+        let key = located.unwrap_or((None, ip as usize));
+
+        let frames = &mut self.frames;
+        let id = *self.frame_ids.entry(key).or_insert_with(|| {
+            let id = FrameId(
+                u32::try_from(frames.len()).expect("a trace store has at most `u32::MAX` frames"),
+            );
+            frames.push(Frame {
+                module: key.0,
+                offset: key.1,
+                symbols: None,
+            });
+            id
+        });
+        self.ip_cache.insert(ip as usize, id);
+        id
+    }
+
+    /// Interns a backtrace made of the frames with the instruction pointers
+    /// `ips`, from the most recent one to the oldest one.
+    pub fn intern_ips<I>(&mut self, ips: I) -> TraceId
+    where
+        I: IntoIterator<Item = *mut c_void>,
+    {
+        let start = self.trace_frames.len();
+        for ip in ips {
+            let frame = self.intern_frame(ip);
+            self.trace_frames.push(frame);
+        }
+        let end = self.trace_frames.len();
+
+        let mut hasher = DefaultHasher::new();
+        self.trace_frames[start..end].hash(&mut hasher);
+        let candidates = match self.trace_ids.entry(hasher.finish()) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(Vec::new()),
+        };
+        let existing = candidates.iter().copied().find(|id| {
+            self.trace_frames[self.traces[id.index()].clone()] == self.trace_frames[start..end]
+        });
+        match existing {
+            Some(id) => {
+                self.trace_frames.truncate(start);
+                id
+            }
+            None => {
+                let id = TraceId(
+                    u32::try_from(self.traces.len())
+                        .expect("a trace store has at most `u32::MAX` traces"),
+                );
+                self.traces.push(start..end);
+                candidates.push(id);
+                id
+            }
+        }
+    }
+
+    /// Interns the frames of `backtrace`.
+    pub fn intern(&mut self, backtrace: &Backtrace) -> TraceId {
+        self.intern_ips(backtrace.frames().iter().map(|frame| frame.ip()))
+    }
+
+    /// Returns the frames of the backtrace `id`, from the most recent one to
+    /// the oldest one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` isn't from this store.
+    pub fn trace(&self, id: TraceId) -> &[FrameId] {
+        &self.trace_frames[self.traces[id.index()].clone()]
+    }
+
+    /// Returns the instruction pointer of the frame `id` in the current
+    /// process.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` isn't from this store.
+    pub fn ip(&self, id: FrameId) -> *mut c_void {
+        let frame = &self.frames[id.index()];
+        match frame.module {
+            Some(module) => self.modules[module as usize]
+                .base
+                .wrapping_add(frame.offset) as *mut c_void,
+            None => frame.offset as *mut c_void,
+        }
+    }
+
+    /// Returns the symbols of the frame `id`, resolving them the first time
+    /// they're requested.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` isn't from this store.
+    pub fn symbols(&mut self, id: FrameId) -> &[BacktraceSymbol] {
+        let ip = self.ip(id);
+        self.frames[id.index()]
+            .symbols
+            .get_or_insert_with(|| super::resolve_owned(ip).into_boxed_slice())
+    }
+}
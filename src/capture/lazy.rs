@@ -0,0 +1,128 @@
+//! Backtraces whose symbols are resolved lazily or in the background.
+
+use super::Backtrace;
+use std::fmt;
+use std::prelude::v1::*;
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+use std::thread;
+
+/// A backtrace which is captured eagerly but resolved lazily.
+///
+/// Capturing only the addresses of frames is cheap compared to resolving
+/// their symbols, so this is suitable for error types where backtraces are
+/// captured often but rarely printed. Symbols are resolved either the first
+/// time the backtrace is printed or accessed, or ahead of time on a background
+/// thread when created through `new_background`. Either way printing a
+/// `LazyBacktrace` always shows a fully resolved backtrace, waiting for
+/// background resolution to finish if necessary.
+///
+/// Clones share the same underlying backtrace, so it's only resolved once.
+///
+/// # Required features
+///
+/// This type requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default.
+#[derive(Clone)]
+pub struct LazyBacktrace {
+    inner: Arc<Mutex<Backtrace>>,
+}
+
+impl LazyBacktrace {
+    /// Captures a backtrace whose symbols are resolved the first time it's
+    /// used.
+    #[inline(never)] // want to make sure there's a frame here to remove
+    pub fn new() -> LazyBacktrace {
+        LazyBacktrace {
+            inner: Arc::new(Mutex::new(Backtrace::create(
+                LazyBacktrace::new as *const () as usize,
+            ))),
+        }
+    }
+
+    /// Captures a backtrace and starts resolving its symbols on a background
+    /// thread.
+    ///
+    /// A single thread, started the first time this is called, resolves the
+    /// backtraces of all calls in turn. Backtraces which are dropped before
+    /// their turn come aren't resolved at all.
+    #[inline(never)] // want to make sure there's a frame here to remove
+    pub fn new_background() -> LazyBacktrace {
+        let bt = LazyBacktrace {
+            inner: Arc::new(Mutex::new(Backtrace::create(
+                LazyBacktrace::new_background as *const () as usize,
+            ))),
+        };
+        resolve_in_background(Arc::downgrade(&bt.inner));
+        bt
+    }
+
+    /// Returns whether the symbols of this backtrace have been resolved.
+    ///
+    /// This doesn't wait for background resolution in progress.
+    pub fn is_resolved(&self) -> bool {
+        match self.inner.try_lock() {
+            Ok(bt) => bt.frames().iter().all(|f| f.is_resolved()),
+            Err(_) => false,
+        }
+    }
+
+    /// Returns a copy of the resolved backtrace, resolving it if necessary.
+    pub fn to_backtrace(&self) -> Backtrace {
+        self.resolved().clone()
+    }
+
+    fn resolved(&self) -> MutexGuard<'_, Backtrace> {
+        let mut bt = lock(&self.inner);
+        bt.resolve();
+        bt
+    }
+}
+
+impl Default for LazyBacktrace {
+    fn default() -> LazyBacktrace {
+        LazyBacktrace::new()
+    }
+}
+
+impl fmt::Debug for LazyBacktrace {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&*self.resolved(), fmt)
+    }
+}
+
+fn lock(bt: &Mutex<Backtrace>) -> MutexGuard<'_, Backtrace> {
+    // Resolution never leaves a backtrace in an inconsistent state, even if
+    // it panics.
+    bt.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn resolve_in_background(bt: Weak<Mutex<Backtrace>>) {
+    static WORKER: Mutex<Option<Sender<Weak<Mutex<Backtrace>>>>> = Mutex::new(None);
+
+    let mut worker = WORKER.lock().unwrap_or_else(|e| e.into_inner());
+    // The worker may have died if resolving a backtrace panicked, in which
+    // case we start a new one.
+    let bt = match &*worker {
+        Some(sender) => match sender.send(bt) {
+            Ok(()) => return,
+            Err(mpsc::SendError(bt)) => bt,
+        },
+        None => bt,
+    };
+
+    let (sender, receiver) = mpsc::channel::<Weak<Mutex<Backtrace>>>();
+    let spawned = thread::Builder::new()
+        .name("backtrace-resolver".into())
+        .spawn(move || {
+            for bt in receiver {
+                if let Some(bt) = bt.upgrade() {
+                    lock(&bt).resolve();
+                }
+            }
+        });
+    // If no thread can be spawned the backtrace is resolved when it's used.
+    if spawned.is_ok() && sender.send(bt).is_ok() {
+        *worker = Some(sender);
+    }
+}
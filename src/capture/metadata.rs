@@ -0,0 +1,90 @@
+//! User data attached to backtraces and their frames.
+
+use std::fmt;
+use std::prelude::v1::*;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Small pieces of user data attached to a `Backtrace` or a `BacktraceFrame`,
+/// like the id of an async task or the size of an allocation.
+///
+/// Metadata is a list of key-value pairs kept in the order they were first
+/// inserted. It's carried through serialization and printed along with the
+/// backtrace or frame it's attached to, see `BacktraceFmt::set_metadata_suffix`.
+///
+/// # Required features
+///
+/// This type requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default.
+#[derive(Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct Metadata {
+    entries: Vec<(String, String)>,
+}
+
+impl Metadata {
+    /// Creates metadata without any entries.
+    pub fn new() -> Metadata {
+        Metadata::default()
+    }
+
+    /// Sets the value of `key` to `value`, replacing any previous value but
+    /// keeping its position.
+    pub fn insert(&mut self, key: impl Into<String>, value: impl fmt::Display) {
+        let key = key.into();
+        let value = value.to_string();
+        match self.entries.iter_mut().find(|(k, _)| *k == key) {
+            Some(entry) => entry.1 = value,
+            None => self.entries.push((key, value)),
+        }
+    }
+
+    /// Returns the value of `key`, if any.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Removes `key`, returning its value if it was set.
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        let index = self.entries.iter().position(|(k, _)| k == key)?;
+        Some(self.entries.remove(index).1)
+    }
+
+    /// Returns all keys and their values, in the order they were inserted.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> + '_ {
+        self.entries.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    /// Returns the number of keys set.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns whether no keys are set.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Prints the entries like `task=3, size=128`.
+impl fmt::Display for Metadata {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (key, value)) in self.iter().enumerate() {
+            if i > 0 {
+                fmt.write_str(", ")?;
+            }
+            write!(fmt, "{key}={value}")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Metadata {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_map().entries(self.iter()).finish()
+    }
+}
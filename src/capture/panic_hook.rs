@@ -0,0 +1,175 @@
+//! A panic hook printing backtraces with this crate's formatter.
+
+use super::Backtrace;
+use crate::{BacktraceFmt, BytesOrWideString, ColorMode, FrameKind, PrintFmt, SourceLocator};
+use std::any::Any;
+use std::fmt;
+use std::io::{self, Write};
+use std::panic::{self, Location};
+use std::prelude::v1::*;
+use std::sync::Arc;
+use std::thread;
+
+/// Options for how panics are printed by the hook installed with
+/// `install_panic_hook`.
+///
+/// # Required features
+///
+/// This type requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default.
+#[derive(Clone)]
+pub struct PanicHookOptions {
+    format: PrintFmt,
+    color: ColorMode,
+    source_context: Option<usize>,
+    source_locator: Option<Arc<dyn SourceLocator + Send + Sync>>,
+    collapse_threshold: Option<usize>,
+    hidden_frames: Vec<FrameKind>,
+}
+
+impl PanicHookOptions {
+    /// Creates a new set of options with the default values, which print a
+    /// short backtrace of all frames with colors if stderr supports them.
+    pub fn new() -> PanicHookOptions {
+        PanicHookOptions {
+            format: PrintFmt::Short,
+            color: ColorMode::Auto,
+            source_context: None,
+            source_locator: None,
+            collapse_threshold: None,
+            hidden_frames: Vec::new(),
+        }
+    }
+
+    /// The style the backtrace is printed in, `PrintFmt::Short` by default.
+    pub fn format(mut self, format: PrintFmt) -> PanicHookOptions {
+        self.format = format;
+        self
+    }
+
+    /// Whether the backtrace is printed with colors, see
+    /// `BacktraceFmt::set_color`.
+    pub fn color(mut self, mode: ColorMode) -> PanicHookOptions {
+        self.color = mode;
+        self
+    }
+
+    /// How many lines of source code are printed around the executing line
+    /// of each frame, see `BacktraceFmt::set_source_snippets`.
+    pub fn source_snippets(mut self, context: Option<usize>) -> PanicHookOptions {
+        self.source_context = context;
+        self
+    }
+
+    /// Where the source code of snippets comes from instead of the local
+    /// filesystem, see `BacktraceFmt::set_source_locator`.
+    pub fn source_locator<L>(mut self, locator: L) -> PanicHookOptions
+    where
+        L: SourceLocator + Send + Sync + 'static,
+    {
+        self.source_locator = Some(Arc::new(locator));
+        self
+    }
+
+    /// After how many repetitions recursive frames are collapsed, see
+    /// `BacktraceFmt::set_collapse_recursion`.
+    pub fn collapse_recursion(mut self, threshold: Option<usize>) -> PanicHookOptions {
+        self.collapse_threshold = threshold;
+        self
+    }
+
+    /// Which kinds of frames are left out of the backtrace, see
+    /// `BacktraceFmt::set_hidden_frames`.
+    pub fn hidden_frames(mut self, kinds: &[FrameKind]) -> PanicHookOptions {
+        self.hidden_frames = kinds.to_vec();
+        self
+    }
+}
+
+impl Default for PanicHookOptions {
+    fn default() -> PanicHookOptions {
+        PanicHookOptions::new()
+    }
+}
+
+/// Replaces the panic hook with one which prints panics along with a
+/// backtrace printed by this crate.
+///
+/// Like the standard library's hook, the hook prints the panicking thread,
+/// the location and message of the panic to stderr. It's followed by a fully
+/// resolved backtrace, regardless of the `RUST_BACKTRACE` environment
+/// variable, starting at the function which panicked. The frames of the
+/// standard library raising the panic and starting the program are left out,
+/// see `Backtrace::trim`.
+///
+/// # Example
+///
+/// ```
+/// extern crate backtrace;
+///
+/// use backtrace::{FrameKind, PanicHookOptions};
+///
+/// fn main() {
+///     backtrace::install_panic_hook(
+///         PanicHookOptions::new()
+///             .source_snippets(Some(2))
+///             .hidden_frames(&[FrameKind::Std]),
+///     );
+/// }
+/// ```
+///
+/// # Required features
+///
+/// This function requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default.
+pub fn install_panic_hook(options: PanicHookOptions) {
+    panic::set_hook(Box::new(move |info| {
+        print_panic(info.payload(), info.location(), &options);
+    }));
+}
+
+fn print_panic(payload: &dyn Any, location: Option<&Location<'_>>, options: &PanicHookOptions) {
+    let mut bt = Backtrace::new_unresolved();
+    bt.trim();
+
+    let thread = thread::current();
+    let name = thread.name().unwrap_or("<unnamed>");
+    let msg = match payload.downcast_ref::<&str>() {
+        Some(msg) => *msg,
+        None => match payload.downcast_ref::<String>() {
+            Some(msg) => &msg[..],
+            None => "Box<dyn Any>",
+        },
+    };
+
+    // Panics of several threads could otherwise be interleaved.
+    let stderr = io::stderr();
+    let mut out = stderr.lock();
+    let _ = match location {
+        Some(location) => writeln!(out, "\nthread '{name}' panicked at {location}:\n{msg}"),
+        None => writeln!(out, "\nthread '{name}' panicked:\n{msg}"),
+    };
+    let _ = writeln!(out, "stack backtrace:");
+
+    let cwd = std::env::current_dir().ok();
+    let format = options.format;
+    let mut print_path = |fmt: &mut fmt::Formatter<'_>, path: BytesOrWideString<'_>| {
+        let path = path.into_path_buf();
+        if format == PrintFmt::Short {
+            if let Some(suffix) = cwd.as_ref().and_then(|cwd| path.strip_prefix(cwd).ok()) {
+                return fmt::Display::fmt(&suffix.display(), fmt);
+            }
+        }
+        fmt::Display::fmt(&path.display(), fmt)
+    };
+    let _ = BacktraceFmt::write_io(&mut out, format, &mut print_path, |f| {
+        f.set_color(options.color);
+        f.set_source_snippets(options.source_context);
+        f.set_source_locator(options.source_locator.clone().map(|l| l as _));
+        f.set_collapse_recursion(options.collapse_threshold);
+        f.set_hidden_frames(&options.hidden_frames);
+        f.add_context()?;
+        f.backtrace_frames(bt.frames())?;
+        f.finish()
+    });
+}
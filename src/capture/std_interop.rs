@@ -0,0 +1,229 @@
+//! Conversions from and bridging with `std::backtrace::Backtrace`.
+
+use super::{Backtrace, BacktraceFrame, BacktraceStatus, BacktraceSymbol, Frame, Metadata};
+use crate::PrintFmt;
+use std::backtrace::Backtrace as StdBacktrace;
+use std::env;
+use std::path::PathBuf;
+use std::prelude::v1::*;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+impl Backtrace {
+    /// Captures a backtrace if backtraces are enabled through the environment,
+    /// the same way `std::backtrace::Backtrace::capture` does.
+    ///
+    /// Backtraces are enabled if the `RUST_LIB_BACKTRACE` environment variable
+    /// is set to anything but `0`, or if it isn't set and `RUST_BACKTRACE` is
+    /// set to anything but `0`. The environment is only looked at the first
+    /// time this or `Backtrace::capture_with_style` is called.
+    ///
+    /// Like the backtraces of the standard library, the returned backtrace
+    /// isn't resolved yet, see `Backtrace::resolve`. This returns `None` if
+    /// backtraces are disabled.
+    ///
+    /// # Required features
+    ///
+    /// This function requires the `std` feature of the `backtrace` crate to be
+    /// enabled, and the `std` feature is enabled by default.
+    #[inline(never)] // want to make sure there's a frame here to remove
+    pub fn capture() -> Option<Backtrace> {
+        if env_print_fmt().is_some() {
+            Some(Self::create(Self::capture as *const () as usize))
+        } else {
+            None
+        }
+    }
+
+    /// Captures a backtrace like `Backtrace::capture`, and returns it along
+    /// with the style it should be printed in according to the environment.
+    ///
+    /// This follows the standard library: if the variable deciding whether
+    /// backtraces are enabled is set to `full`, backtraces are printed with
+    /// `PrintFmt::Full`, and otherwise with `PrintFmt::Short`. The style can
+    /// be passed to a `BacktraceFmt`, or used to pick between the `{:?}` and
+    /// `{:#?}` formats of the backtrace. This returns `None` if backtraces
+    /// are disabled, without capturing anything.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use backtrace::{Backtrace, PrintFmt};
+    ///
+    /// if let Some((bt, style)) = Backtrace::capture_with_style() {
+    ///     if style == PrintFmt::Full {
+    ///         println!("{bt:#?}");
+    ///     } else {
+    ///         println!("{bt:?}");
+    ///     }
+    /// }
+    /// ```
+    ///
+    /// # Required features
+    ///
+    /// This function requires the `std` feature of the `backtrace` crate to be
+    /// enabled, and the `std` feature is enabled by default.
+    #[inline(never)] // want to make sure there's a frame here to remove
+    pub fn capture_with_style() -> Option<(Backtrace, PrintFmt)> {
+        let style = env_print_fmt()?;
+        let bt = Self::create(Self::capture_with_style as *const () as usize);
+        Some((bt, style))
+    }
+
+    /// Returns whether `Backtrace::capture` would capture a backtrace, or why
+    /// it wouldn't.
+    ///
+    /// This is `BacktraceStatus::Unsupported` if backtraces can't be captured
+    /// on the target platform, `BacktraceStatus::Disabled` if they're disabled
+    /// through the environment, and `BacktraceStatus::Captured` otherwise,
+    /// so libraries can skip the work of preparing for a capture which won't
+    /// happen. Like `Backtrace::capture`, this only looks at the environment
+    /// the first time either is called.
+    ///
+    /// # Required features
+    ///
+    /// This function requires the `std` feature of the `backtrace` crate to be
+    /// enabled, and the `std` feature is enabled by default.
+    pub fn capture_status() -> BacktraceStatus {
+        if !crate::backtrace::SUPPORTED {
+            BacktraceStatus::Unsupported
+        } else if env_print_fmt().is_none() {
+            BacktraceStatus::Disabled
+        } else {
+            BacktraceStatus::Captured
+        }
+    }
+}
+
+/// Returns the style backtraces are printed in according to the environment,
+/// or `None` if they're disabled.
+fn env_print_fmt() -> Option<PrintFmt> {
+    // 0 = not yet known, 1 = disabled, 2 = short, 3 = full
+    static STYLE: AtomicU8 = AtomicU8::new(0);
+    let style = match STYLE.load(Ordering::Relaxed) {
+        0 => {
+            let value = env::var_os("RUST_LIB_BACKTRACE").or_else(|| env::var_os("RUST_BACKTRACE"));
+            let style = match value {
+                Some(value) if value == "full" => 3,
+                Some(value) if value != "0" => 2,
+                _ => 1,
+            };
+            STYLE.store(style, Ordering::Relaxed);
+            style
+        }
+        style => style,
+    };
+    match style {
+        2 => Some(PrintFmt::Short),
+        3 => Some(PrintFmt::Full),
+        _ => None,
+    }
+}
+
+/// Converts a backtrace of the standard library, so it can be printed,
+/// compared and stored like the backtraces captured by this crate.
+///
+/// This is best-effort: the frames are parsed from the `Debug` output of the
+/// backtrace, whose format isn't guaranteed to stay the same between Rust
+/// versions. Conversion stops at the first frame that can't be parsed, so a
+/// changed format yields fewer frames, or none at all.
+///
+/// The standard library doesn't expose the addresses of frames, only their
+/// function names, file names and line numbers, so the frames of the
+/// converted backtrace are already resolved and can't be resolved again.
+/// Their `ip`, `symbol_address` and `module_base_address` are all null. The
+/// standard library also lists functions inlined into a frame as frames of
+/// their own, so each frame has exactly one symbol.
+///
+/// Backtraces which were disabled or aren't supported on the platform are
+/// converted to a backtrace without frames.
+impl From<&StdBacktrace> for Backtrace {
+    fn from(bt: &StdBacktrace) -> Backtrace {
+        // The `Debug` output is the only way to get to the frames.
+        Backtrace {
+            frames: parse_frames(&format!("{bt:?}")),
+            metadata: Metadata::new(),
+        }
+    }
+}
+
+impl From<StdBacktrace> for Backtrace {
+    fn from(bt: StdBacktrace) -> Backtrace {
+        Backtrace::from(&bt)
+    }
+}
+
+/// Parses the `Debug` output of a `std::backtrace::Backtrace`, which looks like
+/// `Backtrace [{ fn: "name", file: "path", line: 1 }, { fn: "name" }]`.
+fn parse_frames(s: &str) -> Vec<BacktraceFrame> {
+    let mut frames = Vec::new();
+    let mut rest = match s.strip_prefix("Backtrace [") {
+        Some(rest) => rest,
+        None => return frames,
+    };
+    while let Some((symbol, next)) = parse_symbol(rest.trim_start_matches(", ")) {
+        frames.push(BacktraceFrame {
+            frame: Frame::Address {
+                ip: 0,
+                sp: 0,
+                cfa: 0,
+                symbol_address: 0,
+                module_base_address: None,
+                scanned: false,
+            },
+            symbols: Some(vec![symbol]),
+            metadata: Metadata::new(),
+        });
+        rest = next;
+    }
+    frames
+}
+
+fn parse_symbol(s: &str) -> Option<(BacktraceSymbol, &str)> {
+    let mut rest = s.strip_prefix("{ fn: ")?;
+    let name = match rest.strip_prefix("<unknown>") {
+        Some(next) => {
+            rest = next;
+            None
+        }
+        None => {
+            let (name, next) = quoted(rest, &[", file: ", ", line: ", " }"])?;
+            rest = next;
+            Some(name.as_bytes().to_vec())
+        }
+    };
+    let mut filename = None;
+    if let Some(next) = rest.strip_prefix(", file: ") {
+        let (file, next) = quoted(next, &[", line: ", " }"])?;
+        filename = Some(PathBuf::from(file));
+        rest = next;
+    }
+    let mut lineno = None;
+    if let Some(next) = rest.strip_prefix(", line: ") {
+        let end = next.find(|c: char| !c.is_ascii_digit())?;
+        lineno = Some(next[..end].parse().ok()?);
+        rest = &next[end..];
+    }
+    let symbol = BacktraceSymbol {
+        name,
+        addr: None,
+        filename,
+        lineno,
+        colno: None,
+        module: None,
+    };
+    Some((symbol, rest.strip_prefix(" }")?))
+}
+
+/// Splits off a string in quotes from the start of `s`.
+///
+/// Names and paths aren't escaped, so the string ends at the first quote
+/// followed by one of `terminators`.
+fn quoted<'a>(s: &'a str, terminators: &[&str]) -> Option<(&'a str, &'a str)> {
+    let s = s.strip_prefix('"')?;
+    let end = s.match_indices('"').map(|(i, _)| i).find(|&i| {
+        terminators
+            .iter()
+            .any(|terminator| s[i + 1..].starts_with(terminator))
+    })?;
+    Some((&s[..end], &s[end + 1..]))
+}
@@ -0,0 +1,124 @@
+//! Splicing the backtraces of where async tasks were spawned onto backtraces
+//! captured while they're polled.
+
+use super::{Backtrace, BacktraceFrame};
+use std::cell::RefCell;
+use std::prelude::v1::*;
+use std::ptr;
+
+thread_local! {
+    // The tasks being polled on this thread, innermost last, with the address
+    // of a local of the `enter` frame polling them.
+    static TASKS: RefCell<Vec<(usize, *const Backtrace)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Runs `f` as part of an async task, typically to poll its future, so that
+/// backtraces captured within continue with `spawned`.
+///
+/// A backtrace captured while a future is polled ends in the executor, which
+/// says little about how the task came to be. Runtimes can capture a
+/// backtrace where each task is spawned, for example with
+/// `Backtrace::new_unresolved`, and poll the task within this function. Then
+/// backtraces captured with `Backtrace::new` and `Backtrace::new_unresolved`
+/// inside of `f` have the frames of the executor replaced by the frames of
+/// `spawned`, so they show the chain of calls across `.await` points and
+/// spawns which led there.
+///
+/// The first frame taken from `spawned` has the metadata `task=spawned`, see
+/// `BacktraceFrame::metadata`, which is printed along with it to mark where
+/// the backtrace continues in the spawning task. Tasks spawned within other
+/// tasks capture spliced backtraces themselves, so the chain goes back to the
+/// first task. Tasks can be polled within each other, like with `block_on`,
+/// in which case the innermost one is spliced.
+///
+/// Frames are told apart by their stack pointers, so nothing is spliced on
+/// platforms where they're unknown. Backtraces captured with `trace` are
+/// never spliced.
+///
+/// # Required features
+///
+/// This function requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default.
+///
+/// # Example
+///
+/// ```
+/// extern crate backtrace;
+///
+/// use backtrace::Backtrace;
+///
+/// struct Task {
+///     spawned: Backtrace,
+///     poll: Box<dyn FnMut()>,
+/// }
+///
+/// fn spawn(poll: impl FnMut() + 'static) -> Task {
+///     Task {
+///         spawned: Backtrace::new_unresolved(),
+///         poll: Box::new(poll),
+///     }
+/// }
+///
+/// fn main() {
+///     let mut task = spawn(|| println!("{:?}", Backtrace::new()));
+///     // ... later, in the executor ...
+///     backtrace::in_task(&task.spawned, || (task.poll)());
+/// }
+/// ```
+pub fn in_task<R>(spawned: &Backtrace, f: impl FnOnce() -> R) -> R {
+    let mut f = Some(f);
+    let mut result = None;
+    enter(spawned, &mut || result = f.take().map(|f| f()));
+    result.unwrap()
+}
+
+#[inline(never)]
+fn enter(spawned: &Backtrace, f: &mut dyn FnMut()) {
+    struct Exit;
+
+    impl Drop for Exit {
+        fn drop(&mut self) {
+            TASKS.with(|tasks| tasks.borrow_mut().pop());
+        }
+    }
+
+    // Frames called from here are all below this on the stack, and the ones
+    // which called this are all above it.
+    let marker = 0u8;
+    let marker = ptr::addr_of!(marker) as usize;
+    TASKS.with(|tasks| tasks.borrow_mut().push((marker, spawned)));
+    let _exit = Exit;
+    f();
+}
+
+/// Replaces the frames of `frames` outside of the innermost task this thread
+/// polls with the frames of where it was spawned.
+pub(super) fn splice(frames: &mut Vec<BacktraceFrame>) {
+    let _ = TASKS.try_with(|tasks| {
+        let (marker, spawned) = match tasks.borrow().last() {
+            Some(&task) => task,
+            None => return,
+        };
+        // The stack grows down, so callers have higher stack pointers.
+        let outside = match frames.iter().position(|f| f.sp() as usize > marker) {
+            Some(outside) => outside,
+            None => return,
+        };
+        frames.truncate(outside);
+        // Depending on the unwinder, the stack pointer of `enter` itself is
+        // the one of its caller or its own.
+        if frames.last().map_or(false, |f| {
+            ptr::eq(f.symbol_address() as *const (), enter as *const ())
+        }) {
+            frames.pop();
+        }
+
+        // SAFETY: `enter` removes the task before `spawned` goes away.
+        let spawned = unsafe { &*spawned };
+        let start = frames.len();
+        frames.extend(spawned.frames().iter().cloned());
+        if let Some(first) = frames.get_mut(start) {
+            first.metadata_mut().insert("task", "spawned");
+        }
+    });
+}
@@ -0,0 +1,469 @@
+//! Aggregating many backtraces into a tree of the call paths they share.
+
+use super::{resolve_owned, Backtrace, BacktraceSymbol};
+use core::ffi::c_void;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::io;
+use std::prelude::v1::*;
+
+/// Identifies a node of a `StackTree`.
+///
+/// Identifiers are assigned sequentially starting at zero for the root, so
+/// they can be used to index into side tables.
+///
+/// # Required features
+///
+/// This type requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NodeId(u32);
+
+impl NodeId {
+    /// Returns the index of this node in the order nodes were added.
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// A tree of call paths merged from many backtraces, each weighted by how
+/// often or how much it was seen, as done by sampling profilers.
+///
+/// Every node below the root stands for a frame, reached through the frames
+/// of its ancestors, starting with the oldest frame of a backtrace at the
+/// children of the root. Backtraces sharing their oldest frames share the
+/// nodes for them, so the tree grows with the number of distinct call paths
+/// rather than with the number of backtraces added. Each node records the
+/// weight of the backtraces ending at it, and the total weight of those going
+/// through it.
+///
+/// Frames are told apart by their instruction pointer alone, and symbols are
+/// resolved lazily, once per distinct instruction pointer, so backtraces can
+/// be added from raw addresses like the ones of `trace_ips` and resolved
+/// when the tree is exported. Modules shouldn't be unloaded while a tree is
+/// in use.
+///
+/// # Required features
+///
+/// This type requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default.
+///
+/// # Example
+///
+/// ```
+/// use backtrace::StackTree;
+///
+/// let mut tree = StackTree::new();
+/// for _ in 0..3 {
+///     let mut ips = [0; 64];
+///     let len = backtrace::trace_ips(&mut ips);
+///     tree.add_ips(&ips[..len], 1);
+/// }
+/// assert_eq!(tree.total_weight(tree.root()), 3);
+///
+/// let mut folded = Vec::new();
+/// tree.write_folded(&mut folded).unwrap();
+/// ```
+pub struct StackTree {
+    nodes: Vec<Node>,
+    node_ids: HashMap<(NodeId, usize), NodeId>,
+    symbols: HashMap<usize, Box<[BacktraceSymbol]>>,
+}
+
+struct Node {
+    parent: Option<NodeId>,
+    ip: usize,
+    children: Vec<NodeId>,
+    self_weight: u64,
+    total_weight: u64,
+}
+
+impl Default for StackTree {
+    fn default() -> StackTree {
+        StackTree::new()
+    }
+}
+
+impl StackTree {
+    /// Creates a tree without any backtraces, consisting of just its root.
+    pub fn new() -> StackTree {
+        StackTree {
+            nodes: vec![Node {
+                parent: None,
+                ip: 0,
+                children: Vec::new(),
+                self_weight: 0,
+                total_weight: 0,
+            }],
+            node_ids: HashMap::new(),
+            symbols: HashMap::new(),
+        }
+    }
+
+    /// Adds a backtrace made of the frames with the instruction pointers
+    /// `ips`, from the most recent one to the oldest one, with `weight`.
+    ///
+    /// The weight is whatever the backtrace is counted by, like `1` for each
+    /// sample of a profiler or the size of an allocation.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the tree would have more than `u32::MAX` nodes.
+    pub fn add_ips(&mut self, ips: &[usize], weight: u64) {
+        let mut id = self.root();
+        self.nodes[0].total_weight += weight;
+        for &ip in ips.iter().rev() {
+            id = self.child(id, ip);
+            self.nodes[id.index()].total_weight += weight;
+        }
+        self.nodes[id.index()].self_weight += weight;
+    }
+
+    /// Adds the frames of `backtrace` with `weight`, see `add_ips`.
+    pub fn add(&mut self, backtrace: &Backtrace, weight: u64) {
+        let ips = backtrace
+            .frames()
+            .iter()
+            .map(|frame| frame.ip() as usize)
+            .collect::<Vec<_>>();
+        self.add_ips(&ips, weight);
+    }
+
+    /// Returns the child of `parent` for the frame `ip`, adding it if it's
+    /// not there yet.
+    fn child(&mut self, parent: NodeId, ip: usize) -> NodeId {
+        let nodes = &mut self.nodes;
+        *self.node_ids.entry((parent, ip)).or_insert_with(|| {
+            let id = NodeId(
+                u32::try_from(nodes.len()).expect("a stack tree has at most `u32::MAX` nodes"),
+            );
+            nodes.push(Node {
+                parent: Some(parent),
+                ip,
+                children: Vec::new(),
+                self_weight: 0,
+                total_weight: 0,
+            });
+            nodes[parent.index()].children.push(id);
+            id
+        })
+    }
+
+    /// Returns the root of the tree, which doesn't stand for any frame and
+    /// whose total weight is that of all backtraces added.
+    pub fn root(&self) -> NodeId {
+        NodeId(0)
+    }
+
+    /// Returns the number of nodes in the tree, including the root.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns the node the frame of `id` was called from, or `None` for the
+    /// root.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` isn't from this tree.
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.nodes[id.index()].parent
+    }
+
+    /// Returns the nodes of the frames called from the frame of `id`, in the
+    /// order they were first seen.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` isn't from this tree.
+    pub fn children(&self, id: NodeId) -> &[NodeId] {
+        &self.nodes[id.index()].children
+    }
+
+    /// Returns the instruction pointer of the frame of `id`, which is null
+    /// for the root.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` isn't from this tree.
+    pub fn ip(&self, id: NodeId) -> *mut c_void {
+        self.nodes[id.index()].ip as *mut c_void
+    }
+
+    /// Returns the weight of the backtraces ending at `id`, i.e. whose most
+    /// recent frame is the frame of `id`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` isn't from this tree.
+    pub fn self_weight(&self, id: NodeId) -> u64 {
+        self.nodes[id.index()].self_weight
+    }
+
+    /// Returns the weight of the backtraces going through `id`, including
+    /// those ending at it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` isn't from this tree.
+    pub fn total_weight(&self, id: NodeId) -> u64 {
+        self.nodes[id.index()].total_weight
+    }
+
+    /// Resolves the symbols of all frames in the tree which aren't resolved
+    /// yet.
+    pub fn resolve(&mut self) {
+        for node in &self.nodes[1..] {
+            self.symbols
+                .entry(node.ip)
+                .or_insert_with(|| resolve_owned(node.ip as *mut c_void).into_boxed_slice());
+        }
+    }
+
+    /// Returns the symbols of the frame of `id`, resolving them the first time
+    /// they're requested, which are none for the root.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` isn't from this tree.
+    pub fn symbols(&mut self, id: NodeId) -> &[BacktraceSymbol] {
+        if id == self.root() {
+            return &[];
+        }
+        let ip = self.nodes[id.index()].ip;
+        self.symbols
+            .entry(ip)
+            .or_insert_with(|| resolve_owned(ip as *mut c_void).into_boxed_slice())
+    }
+
+    /// Returns the nodes in depth-first order, each followed by its children,
+    /// without the root.
+    fn depth_first(&self) -> Vec<NodeId> {
+        let mut order = Vec::with_capacity(self.nodes.len() - 1);
+        let mut stack = self.children(self.root()).to_vec();
+        stack.reverse();
+        while let Some(id) = stack.pop() {
+            order.push(id);
+            stack.extend(self.children(id).iter().rev());
+        }
+        order
+    }
+
+    /// Writes the tree in the folded format of `flamegraph.pl` and `inferno`,
+    /// with a line for each call path backtraces end at.
+    ///
+    /// Each line lists the names of the functions of a call path from the
+    /// oldest frame to the most recent one, separated by `;`, followed by a
+    /// space and the weight of the backtraces ending there, as in
+    /// `main;run;work 42`. Functions inlined into a frame get names of their
+    /// own, and frames without a name are written as their address. Symbols
+    /// are resolved first.
+    pub fn write_folded<W: io::Write + ?Sized>(&mut self, out: &mut W) -> io::Result<()> {
+        self.resolve();
+        // The path of each node on the stack starts with the first `len`
+        // bytes of the path of the node before it.
+        let mut path = String::new();
+        let mut lens = vec![0; self.nodes.len()];
+        for id in self.depth_first() {
+            let node = &self.nodes[id.index()];
+            let parent = node.parent.unwrap_or(self.root());
+            path.truncate(lens[parent.index()]);
+            let symbols = &self.symbols[&node.ip];
+            // Inlined functions come first in the symbols of a frame.
+            let names = symbols.iter().rev().map(|symbol| symbol.name());
+            let names = names.chain(symbols.is_empty().then_some(None));
+            for name in names {
+                if !path.is_empty() {
+                    path.push(';');
+                }
+                // Writing into a `String` can't fail.
+                let _ = match name {
+                    Some(name) => write!(path, "{name:#}"),
+                    None => write!(path, "{:#x}", node.ip),
+                };
+            }
+            lens[id.index()] = path.len();
+            if node.self_weight > 0 {
+                writeln!(out, "{} {}", path, node.self_weight)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the tree as an uncompressed profile in the protocol buffer
+    /// format of `pprof`, with one sample for each call path backtraces end
+    /// at.
+    ///
+    /// The weights are the values of the samples, of the type `sample_type`
+    /// measured in `unit`, like `"samples"` and `"count"` for a sampling
+    /// profiler or `"space"` and `"bytes"` for an allocation profiler. Each
+    /// distinct instruction pointer becomes a location, with a line for each
+    /// function inlined there. Symbols are resolved first.
+    ///
+    /// `pprof` reads both compressed and uncompressed profiles, so the output
+    /// can be used as is or compressed with gzip as most tools write them.
+    pub fn write_pprof<W: io::Write + ?Sized>(
+        &mut self,
+        out: &mut W,
+        sample_type: &str,
+        unit: &str,
+    ) -> io::Result<()> {
+        self.resolve();
+        let mut profile = Profile::default();
+        profile.string("");
+
+        let (sample_type, unit) = (profile.string(sample_type), profile.string(unit));
+        profile.buf.message(1, |m| {
+            m.uint(1, sample_type);
+            m.uint(2, unit);
+        });
+
+        let mut locations = HashMap::new();
+        let mut functions = HashMap::new();
+        for node in &self.nodes[1..] {
+            let next = locations.len() as u64 + 1;
+            let location = *locations.entry(node.ip).or_insert(next);
+            if location != next {
+                continue;
+            }
+            let mut lines = Vec::new();
+            for symbol in self.symbols[&node.ip].iter() {
+                let name = match symbol.name() {
+                    Some(name) => profile.string(&format!("{name:#}")),
+                    None => continue,
+                };
+                let filename = match symbol.filename() {
+                    Some(path) => profile.string(&path.display().to_string()),
+                    None => 0,
+                };
+                let next = functions.len() as u64 + 1;
+                let function = *functions.entry((name, filename)).or_insert(next);
+                if function == next {
+                    profile.buf.message(5, |m| {
+                        m.uint(1, function);
+                        m.uint(2, name);
+                        m.uint(3, name);
+                        m.uint(4, filename);
+                    });
+                }
+                lines.push((function, symbol.lineno().unwrap_or(0)));
+            }
+            profile.buf.message(4, |m| {
+                m.uint(1, location);
+                m.uint(3, node.ip as u64);
+                for (function, line) in lines {
+                    m.message(4, |m| {
+                        m.uint(1, function);
+                        m.uint(2, line.into());
+                    });
+                }
+            });
+        }
+
+        for id in self.depth_first() {
+            let weight = self.nodes[id.index()].self_weight;
+            if weight == 0 {
+                continue;
+            }
+            // Samples list their locations from the most recent one.
+            let mut path = Vec::new();
+            let mut next = Some(id);
+            while let Some(id) = next.filter(|id| *id != self.root()) {
+                path.push(locations[&self.nodes[id.index()].ip]);
+                next = self.parent(id);
+            }
+            profile.buf.message(2, |m| {
+                m.packed(1, &path);
+                m.packed(2, &[weight]);
+            });
+        }
+
+        for s in &profile.strings {
+            profile.buf.bytes(6, s.as_bytes());
+        }
+        out.write_all(&profile.buf.0)
+    }
+}
+
+/// A `pprof` profile being encoded, see `StackTree::write_pprof`.
+#[derive(Default)]
+struct Profile {
+    buf: Proto,
+    strings: Vec<String>,
+    string_ids: HashMap<String, u64>,
+}
+
+impl Profile {
+    /// Returns the index of `s` in the string table, adding it if needed.
+    fn string(&mut self, s: &str) -> u64 {
+        if let Some(id) = self.string_ids.get(s) {
+            return *id;
+        }
+        let id = self.strings.len() as u64;
+        self.strings.push(s.to_string());
+        self.string_ids.insert(s.to_string(), id);
+        id
+    }
+}
+
+/// Just enough of an encoder for protocol buffer messages to write `pprof`
+/// profiles.
+#[derive(Default)]
+struct Proto(Vec<u8>);
+
+impl Proto {
+    fn varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.0.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        self.0.push(value as u8);
+    }
+
+    /// Writes a varint field, leaving it out if it's zero like encoders of
+    /// `proto3` messages do.
+    fn uint(&mut self, field: u64, value: u64) {
+        if value != 0 {
+            self.varint(field << 3);
+            self.varint(value);
+        }
+    }
+
+    fn bytes(&mut self, field: u64, bytes: &[u8]) {
+        self.varint(field << 3 | 2);
+        self.varint(bytes.len() as u64);
+        self.0.extend_from_slice(bytes);
+    }
+
+    fn packed(&mut self, field: u64, values: &[u64]) {
+        let mut packed = Proto::default();
+        for value in values {
+            packed.varint(*value);
+        }
+        self.bytes(field, &packed.0);
+    }
+
+    fn message(&mut self, field: u64, f: impl FnOnce(&mut Proto)) {
+        let mut message = Proto::default();
+        f(&mut message);
+        self.bytes(field, &message.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Proto;
+
+    #[test]
+    fn encodes_protobuf() {
+        let mut proto = Proto::default();
+        proto.uint(1, 150);
+        proto.uint(2, 0);
+        proto.packed(4, &[3, 270]);
+        proto.message(5, |m| m.bytes(2, b"hi"));
+        assert_eq!(
+            proto.0,
+            [0x08, 0x96, 0x01, 0x22, 0x03, 0x03, 0x8e, 0x02, 0x2a, 0x04, 0x12, 0x02, b'h', b'i']
+        );
+    }
+}
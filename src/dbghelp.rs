@@ -23,9 +23,11 @@
 
 #![allow(non_snake_case)]
 
+use alloc::string::String;
 use alloc::vec::Vec;
 
 use super::windows::*;
+use crate::symbolize::SymbolSearchOptions;
 use core::mem;
 use core::ptr;
 use core::slice;
@@ -287,6 +289,33 @@ pub struct Init {
 /// synchronization. Also note that it is safe to call this function multiple
 /// times recursively.
 pub fn init() -> Result<Init, ()> {
+    let ret = lock()?;
+    unsafe {
+        // Ok, phew! Now that we're all safely synchronized, let's actually
+        // start processing everything. First up we need to ensure that
+        // `dbghelp.dll` is actually loaded in this process. We do this
+        // dynamically to avoid a static dependency. This has historically been
+        // done to work around weird linking issues and is intended at making
+        // binaries a bit more portable since this is largely just a debugging
+        // utility.
+        //
+        // Once we've opened `dbghelp.dll` we need to call some initialization
+        // functions in it, and that's detailed more below. We only do this
+        // once, though, so we've got a global boolean indicating whether we're
+        // done yet or not.
+        DBGHELP.ensure_open()?;
+
+        if !INITIALIZED {
+            set_optional_options();
+            INITIALIZED = true;
+        }
+        Ok(ret)
+    }
+}
+
+/// Acquires the lock which synchronizes all calls to `dbghelp`, without
+/// loading or initializing it.
+fn lock() -> Result<Init, ()> {
     use core::sync::atomic::{AtomicUsize, Ordering::SeqCst};
 
     // Helper function for generating a name that's unique to the process.
@@ -359,67 +388,100 @@ pub fn init() -> Result<Init, ()> {
         let lock = lock as HANDLE;
         let r = WaitForSingleObjectEx(lock, INFINITE, FALSE);
         debug_assert_eq!(r, 0);
-        let ret = Init { lock };
+        Ok(Init { lock })
+    }
+}
 
-        // Ok, phew! Now that we're all safely synchronized, let's actually
-        // start processing everything. First up we need to ensure that
-        // `dbghelp.dll` is actually loaded in this process. We do this
-        // dynamically to avoid a static dependency. This has historically been
-        // done to work around weird linking issues and is intended at making
-        // binaries a bit more portable since this is largely just a debugging
-        // utility.
-        //
-        // Once we've opened `dbghelp.dll` we need to call some initialization
-        // functions in it, and that's detailed more below. We only do this
-        // once, though, so we've got a global boolean indicating whether we're
-        // done yet or not.
-        DBGHELP.ensure_open()?;
+/// Whether `dbghelp` was initialized for this process, by this crate or by the
+/// application.
+static mut INITIALIZED: bool = false;
 
-        static mut INITIALIZED: bool = false;
-        if !INITIALIZED {
-            set_optional_options();
-            INITIALIZED = true;
-        }
-        Ok(ret)
+/// The process handle of the symbol handler session the application
+/// initialized itself, or null if this crate initializes its own session for
+/// `GetCurrentProcess()`.
+static mut PROCESS: HANDLE = ptr::null_mut();
+
+/// Returns the process handle the symbol handler session was initialized
+/// with, which all calls to `dbghelp` need to use.
+///
+/// This must only be called while the lock of `init` is held.
+unsafe fn process() -> HANDLE {
+    if PROCESS.is_null() {
+        GetCurrentProcess()
+    } else {
+        PROCESS
     }
 }
+
+impl Init {
+    /// Returns the process handle to pass to `dbghelp` functions.
+    pub fn process(&self) -> HANDLE {
+        unsafe { process() }
+    }
+}
+
+/// Uses the symbol handler session which the application initialized with
+/// `SymInitializeW` for `process`, a handle of the current process, instead of
+/// initializing one for `GetCurrentProcess()`.
+///
+/// Symbol handler sessions are identified by the process handle they were
+/// initialized with, so applications which already initialized `dbghelp.dll`
+/// with their own handle, and keep it initialized, need to pass that handle
+/// here. This crate then doesn't call `SymInitializeW` or change the options
+/// of the session, and only changes its search path when
+/// `set_symbol_search_options` is called. Passing a null handle makes this
+/// crate use its own session again.
+///
+/// This is best called before capturing the first backtrace, since a session
+/// this crate already initialized isn't cleaned up.
+///
+/// # Safety
+///
+/// `process` must be null or a valid handle of the current process, which
+/// stays valid and initialized with `SymInitializeW` for as long as this
+/// crate may call `dbghelp.dll`.
+#[cfg(target_env = "msvc")]
+pub unsafe fn set_dbghelp_process(process: *mut c_void) {
+    let Ok(_lock) = lock() else {
+        return;
+    };
+    PROCESS = process;
+}
+
 fn set_optional_options() -> Option<()> {
     unsafe {
-        let orig = DBGHELP.SymGetOptions()?();
-
-        // Ensure that the `SYMOPT_DEFERRED_LOADS` flag is set, because
-        // according to MSVC's own docs about this: "This is the fastest, most
-        // efficient way to use the symbol handler.", so let's do that!
-        DBGHELP.SymSetOptions()?(orig | SYMOPT_DEFERRED_LOADS);
-
-        // Actually initialize symbols with MSVC. Note that this can fail, but we
-        // ignore it. There's not a ton of prior art for this per se, but LLVM
-        // internally seems to ignore the return value here and one of the
-        // sanitizer libraries in LLVM prints a scary warning if this fails but
-        // basically ignores it in the long run.
-        //
-        // One case this comes up a lot for Rust is that the standard library and
-        // this crate on crates.io both want to compete for `SymInitializeW`. The
-        // standard library historically wanted to initialize then cleanup most of
-        // the time, but now that it's using this crate it means that someone will
-        // get to initialization first and the other will pick up that
-        // initialization.
-        DBGHELP.SymInitializeW()?(GetCurrentProcess(), ptr::null_mut(), TRUE);
-
-        // The default search path for dbghelp will only look in the current working
-        // directory and (possibly) `_NT_SYMBOL_PATH` and `_NT_ALT_SYMBOL_PATH`.
-        // However, we also want to look in the directory of the executable
-        // and each DLL that is loaded. To do this, we need to update the search path
-        // to include these directories.
-        //
-        // See https://learn.microsoft.com/cpp/build/reference/pdbpath for an
-        // example of where symbols are usually searched for.
+        // Sessions the application initialized itself are used as they are.
+        let own_session = PROCESS.is_null();
+        if own_session {
+            let orig = DBGHELP.SymGetOptions()?();
+
+            // Ensure that the `SYMOPT_DEFERRED_LOADS` flag is set, because
+            // according to MSVC's own docs about this: "This is the fastest, most
+            // efficient way to use the symbol handler.", so let's do that!
+            DBGHELP.SymSetOptions()?(orig | SYMOPT_DEFERRED_LOADS);
+
+            // Actually initialize symbols with MSVC. Note that this can fail, but we
+            // ignore it. There's not a ton of prior art for this per se, but LLVM
+            // internally seems to ignore the return value here and one of the
+            // sanitizer libraries in LLVM prints a scary warning if this fails but
+            // basically ignores it in the long run.
+            //
+            // One case this comes up a lot for Rust is that the standard library and
+            // this crate on crates.io both want to compete for `SymInitializeW`. The
+            // standard library historically wanted to initialize then cleanup most of
+            // the time, but now that it's using this crate it means that someone will
+            // get to initialization first and the other will pick up that
+            // initialization.
+            DBGHELP.SymInitializeW()?(GetCurrentProcess(), ptr::null_mut(), TRUE);
+        }
+
+        // Prefill the buffer with the current search path, which is then the
+        // default one, and which the search path is built from whenever it's
+        // reconfigured.
         let mut search_path_buf = Vec::new();
         search_path_buf.resize(1024, 0);
-
-        // Prefill the buffer with the current search path.
         if DBGHELP.SymGetSearchPathW()?(
-            GetCurrentProcess(),
+            process(),
             search_path_buf.as_mut_ptr(),
             search_path_buf.len() as _,
         ) == TRUE
@@ -434,21 +496,70 @@ fn set_optional_options() -> Option<()> {
             search_path_buf.push(utf16_char('.'));
             search_path_buf.push(utf16_char(';'));
         }
+        DEFAULT_SEARCH_PATH = search_path_buf;
 
-        let mut search_path = SearchPath::new(search_path_buf);
+        if own_session {
+            set_search_path();
+        }
+        Some(())
+    }
+}
 
-        // Update the search path to include the directory of the executable and each DLL.
-        DBGHELP.EnumerateLoadedModulesW64()?(
-            GetCurrentProcess(),
-            Some(enum_loaded_modules_callback),
-            ((&mut search_path) as *mut SearchPath) as *mut c_void,
-        );
+/// The search path `dbghelp` started out with, which includes
+/// `_NT_SYMBOL_PATH` and `_NT_ALT_SYMBOL_PATH`.
+static mut DEFAULT_SEARCH_PATH: Vec<u16> = Vec::new();
 
-        let new_search_path = search_path.finalize();
+/// The options last set with `set_search_options`, if any.
+static mut SEARCH_OPTIONS: Option<SymbolSearchOptions> = None;
 
-        // Set the new search path.
-        DBGHELP.SymSetSearchPathW()?(GetCurrentProcess(), new_search_path.as_ptr());
+/// Configures where symbol files are searched for, replacing the options set
+/// before.
+#[cfg(target_env = "msvc")]
+pub fn set_search_options(options: SymbolSearchOptions) {
+    let Ok(_lock) = init() else {
+        return;
+    };
+    unsafe {
+        SEARCH_OPTIONS = Some(options);
+        set_search_path();
     }
+}
+
+/// Sets the search path of `dbghelp` from the default one and the configured
+/// options.
+///
+/// This must only be called while the lock of `init` is held.
+unsafe fn set_search_path() -> Option<()> {
+    // The default search path for dbghelp will only look in the current working
+    // directory and (possibly) `_NT_SYMBOL_PATH` and `_NT_ALT_SYMBOL_PATH`.
+    // However, we also want to look in the directory of the executable
+    // and each DLL that is loaded. To do this, we need to update the search path
+    // to include these directories.
+    //
+    // See https://learn.microsoft.com/cpp/build/reference/pdbpath for an
+    // example of where symbols are usually searched for.
+    let default = &*ptr::addr_of!(DEFAULT_SEARCH_PATH);
+    let search_path_buf = match &*ptr::addr_of!(SEARCH_OPTIONS) {
+        Some(options) => {
+            let path = options.apply(&String::from_utf16_lossy(default));
+            path.encode_utf16().collect()
+        }
+        None => default.clone(),
+    };
+
+    let mut search_path = SearchPath::new(search_path_buf);
+
+    // Update the search path to include the directory of the executable and each DLL.
+    DBGHELP.EnumerateLoadedModulesW64()?(
+        process(),
+        Some(enum_loaded_modules_callback),
+        ((&mut search_path) as *mut SearchPath) as *mut c_void,
+    );
+
+    let new_search_path = search_path.finalize();
+
+    // Set the new search path.
+    DBGHELP.SymSetSearchPathW()?(process(), new_search_path.as_ptr());
     Some(())
 }
 
@@ -538,3 +649,66 @@ impl Drop for Init {
         }
     }
 }
+
+/// Exclusive access to `dbghelp.dll`, shared with this crate, returned by
+/// `lock_dbghelp`.
+///
+/// The functions of `dbghelp.dll` aren't thread-safe and its state is global
+/// to the process, so applications which call it themselves, to write
+/// minidumps for example, need to keep this crate from calling it at the same
+/// time. Holding this guard does that, and also keeps the standard library
+/// from printing backtraces concurrently.
+///
+/// Once this guard is dropped, this crate may call `dbghelp.dll` again.
+#[cfg(target_env = "msvc")]
+pub struct DbghelpGuard {
+    _init: Init,
+    #[cfg(feature = "std")]
+    _lock: crate::lock::LockGuard,
+}
+
+#[cfg(target_env = "msvc")]
+impl DbghelpGuard {
+    /// Returns the process handle the symbol handler session of this crate
+    /// was initialized with, which the application needs to pass to `Sym*`
+    /// functions to use it.
+    pub fn process(&self) -> *mut c_void {
+        self._init.process()
+    }
+}
+
+/// Locks `dbghelp.dll` for the application, returning `None` if it couldn't
+/// be loaded.
+///
+/// The library has already been initialized with `SymInitializeW` for the
+/// current process when this returns, with the search path and options this
+/// crate configures.
+///
+/// The lock is recursive, so the thread holding the guard can still capture
+/// and resolve backtraces with this crate, but other threads wait for the
+/// guard to be dropped before doing so.
+///
+/// # Example
+///
+/// ```no_run
+/// extern crate backtrace;
+///
+/// fn main() {
+///     if let Some(_guard) = backtrace::lock_dbghelp() {
+///         // Call `MiniDumpWriteDump` or other `dbghelp.dll` functions here.
+///     }
+/// }
+/// ```
+#[cfg(target_env = "msvc")]
+pub fn lock_dbghelp() -> Option<DbghelpGuard> {
+    // The crate lock is always taken before the dbghelp lock to avoid
+    // deadlocking with threads capturing backtraces.
+    #[cfg(feature = "std")]
+    let lock = crate::lock::lock();
+    let init = init().ok()?;
+    Some(DbghelpGuard {
+        _init: init,
+        #[cfg(feature = "std")]
+        _lock: lock,
+    })
+}
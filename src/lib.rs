@@ -75,11 +75,16 @@
 //! * Not all platforms are supported. For example there's no way to get a
 //!   backtrace on WebAssembly at the moment.
 //!
-//! * Crate features may be disabled. Currently this crate supports using Gimli
-//!   libbacktrace on non-Windows platforms for reading debuginfo for
-//!   backtraces. If both crate features are disabled, however, then these
-//!   platforms will generate a backtrace but be unable to generate symbols for
-//!   it.
+//! * Except on Windows with MSVC, symbols are resolved with Gimli, which reads
+//!   debug information and symbol tables in pure Rust. The `pure-rust`
+//!   crate feature additionally keeps this crate from asking the dynamic
+//!   linker for symbols with `dladdr` and from running a C compiler at build
+//!   time, so that no C code is involved in resolving symbols.
+//!
+//! * With the `minimal-symbolication` crate feature, Gimli only looks at the
+//!   symbol tables and line tables of debug information, which makes this
+//!   crate smaller and use less memory. Functions are then named after the
+//!   symbol table alone, so inlined functions don't show up in backtraces.
 //!
 //! In most standard workflows for most standard platforms you generally don't
 //! need to worry about these caveats. We'll try to fix ones where we can over
@@ -107,27 +112,92 @@ extern crate std;
 #[allow(unused_extern_crates)]
 extern crate alloc;
 
-pub use self::backtrace::{trace_unsynchronized, Frame};
+pub use self::backtrace::{
+    set_capture_registers, set_stack_scanning, trace_ips_unsynchronized, trace_unsynchronized,
+    Frame, Registers, MAX_TRACE_STACK,
+};
 mod backtrace;
 
 pub use self::symbolize::resolve_frame_unsynchronized;
-pub use self::symbolize::{resolve_unsynchronized, Symbol, SymbolName};
+pub use self::symbolize::set_diagnostics_hook;
+#[cfg(feature = "std")]
+pub use self::symbolize::set_file_access_with;
+pub use self::symbolize::{
+    resolve_address_unsynchronized, resolve_unsynchronized, AddressKind, DemangleOptions,
+    ModuleSymbol, Symbol, SymbolBinding, SymbolLanguage, SymbolName, SymbolVersion,
+    SymbolVisibility,
+};
+pub use self::symbolize::{
+    set_custom_symbol_resolver, set_resolve_data_addresses, set_resolve_mode, set_symbol_sources,
+    ResolveMode, SymbolSource, SymbolSourceMode,
+};
+pub use self::symbolize::{set_file_access, FileAccess};
+pub use self::symbolize::{set_parse_limits, ParseLimits};
+pub use self::symbolize::{set_symbol_search_options, SymbolSearchOptions};
 mod symbolize;
 
 pub use self::types::BytesOrWideString;
 mod types;
 
 #[cfg(feature = "std")]
-pub use self::symbolize::clear_symbol_cache;
+pub use self::symbolize::{
+    clear_symbol_cache, dump_symbol_map, load_symbol_map, notify_modules_changed, preopen_symbols,
+    resolve_file, resolve_module_offset, set_clear_symbol_cache_after_fork, set_compact_symbols,
+    set_debug_search_paths, set_module_path_prefixes, set_symbol_cache_dir, set_wait_before_fork,
+    symbol_files, symbolize_object, symbolizer_stats, DebugSearchPaths, ExternalModule,
+    ModuleOffset, ModuleTable, ObjectSymbolizer, Symbolizer, SymbolizerStats,
+};
+#[cfg(all(feature = "std", any(target_os = "linux", target_os = "android")))]
+pub use self::symbolize::{KernelSymbol, KernelSymbols};
+
+#[cfg(all(
+    feature = "std",
+    any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "freebsd"
+    )
+))]
+pub use self::profiler::{ProfilerOptions, SamplingProfiler};
+#[cfg(all(
+    feature = "std",
+    any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "freebsd"
+    )
+))]
+mod profiler;
+
+#[cfg(all(feature = "std", any(target_os = "linux", target_os = "android")))]
+pub use self::perf::{PerfOptions, PerfSample, PerfSampler};
+#[cfg(all(feature = "std", any(target_os = "linux", target_os = "android")))]
+mod perf;
 
 mod print;
-pub use print::{BacktraceFmt, BacktraceFrameFmt, PrintFmt};
+#[cfg(unix)]
+pub use print::FdWriter;
+#[cfg(feature = "std")]
+pub use print::SourceLocator;
+pub use print::{AddressFmt, BacktraceFmt, BacktraceFrameFmt, ColorMode, FrameKind, PrintFmt};
 
 cfg_if::cfg_if! {
     if #[cfg(feature = "std")] {
-        pub use self::backtrace::trace;
-        pub use self::symbolize::{resolve, resolve_frame};
-        pub use self::capture::{Backtrace, BacktraceFrame, BacktraceSymbol};
+        pub use self::backtrace::{trace, trace_ips};
+        pub use self::symbolize::{
+            dynamic_symbol_count, find_module_symbol, module_symbols, resolve, resolve_address,
+            resolve_frame, resolve_many, try_resolve, try_resolve_frame, ResolveError,
+        };
+        pub use self::capture::{
+            in_task, install_panic_hook, resolve_owned, Backtrace, BacktraceBuilder, BacktraceFrame,
+            BacktraceStatus, BacktraceSymbol, CancellationToken, CaptureHandle, FrameDiff, FrameId,
+            LazyBacktrace, Metadata, NodeId, PanicHookOptions, ResolveStatus, StackTree, TraceId,
+            TraceStore,
+        };
         mod capture;
     }
 }
@@ -138,11 +208,17 @@ cfg_if::cfg_if! {
     }
 }
 
+#[cfg(feature = "std")]
+pub use self::alloc_hooks::{set_allocator_hooks, AllocatorHooks, SymbolizerAllocator};
+#[cfg(feature = "std")]
+mod alloc_hooks;
+
 #[cfg(feature = "std")]
 mod lock {
     use std::boxed::Box;
     use std::cell::Cell;
     use std::ptr;
+    use std::sync::atomic::{AtomicBool, Ordering};
     use std::sync::{Mutex, MutexGuard, Once};
 
     /// A "Maybe" LockGuard
@@ -151,8 +227,15 @@ mod lock {
     /// The global lock, lazily allocated on first use
     static mut LOCK: *mut Mutex<()> = ptr::null_mut();
     static INIT: Once = Once::new();
+    // The lock taken by the thread calling `fork`, see `before_fork`
+    static mut FORK_GUARD: Option<LockGuard> = None;
+    // Whether the process forked since the lock was last taken
+    static FORKED: AtomicBool = AtomicBool::new(false);
+    // Whether a thread which doesn't exist in the child held the lock when
+    // the process forked
+    static STALE: AtomicBool = AtomicBool::new(false);
     // Whether this thread is the one that holds the lock
-    thread_local!(static LOCK_HELD: Cell<bool> = Cell::new(false));
+    thread_local!(static LOCK_HELD: Cell<bool> = const { Cell::new(false) });
 
     impl Drop for LockGuard {
         fn drop(&mut self) {
@@ -232,10 +315,130 @@ mod lock {
             // lazily allocate the lock if necessary
             INIT.call_once(|| {
                 LOCK = Box::into_raw(Box::new(Mutex::new(())));
+                #[cfg(any(
+                    target_os = "linux",
+                    target_os = "android",
+                    target_vendor = "apple",
+                    target_os = "freebsd",
+                    target_os = "netbsd",
+                    target_os = "openbsd",
+                    target_os = "dragonfly",
+                    target_os = "illumos",
+                    target_os = "solaris",
+                ))]
+                libc::pthread_atfork(
+                    Some(before_fork),
+                    Some(after_fork_parent),
+                    Some(after_fork_child),
+                );
             });
-            // ok *actually* try to acquire the lock, blocking as necessary
-            LockGuard(Some((*LOCK).lock().unwrap()))
+            // ok *actually* try to acquire the lock, blocking as necessary.
+            // It's poisoned if a callback passed to `resolve` or `trace`
+            // panicked, which leaves nothing it protects in a bad state.
+            let guard = (*LOCK).lock().unwrap_or_else(|e| e.into_inner());
+            if FORKED.swap(false, Ordering::Relaxed) {
+                #[cfg(unix)]
+                crate::symbolize::after_fork(STALE.swap(false, Ordering::Relaxed));
+            }
+            LockGuard(Some(guard))
+        }
+    }
+
+    /// Returns whether this thread holds the lock.
+    ///
+    /// This doesn't allocate, so it can be called from a global allocator.
+    pub fn held() -> bool {
+        LOCK_HELD.try_with(|l| l.get()).unwrap_or(false)
+    }
+
+    /// Takes the lock before `fork` if that's enabled with
+    /// `set_wait_before_fork`, so that no other thread is halfway through
+    /// using what it protects when the process forks.
+    ///
+    /// Otherwise `fork` doesn't wait for anything, and `after_fork_child`
+    /// deals with a lock held by another thread.
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "android",
+        target_vendor = "apple",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd",
+        target_os = "dragonfly",
+        target_os = "illumos",
+        target_os = "solaris",
+    ))]
+    extern "C" fn before_fork() {
+        if !crate::symbolize::wait_before_fork() {
+            return;
+        }
+        let guard = lock();
+        unsafe {
+            *ptr::addr_of_mut!(FORK_GUARD) = Some(guard);
+        }
+    }
+
+    /// Releases the lock taken by `before_fork` in the parent.
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "android",
+        target_vendor = "apple",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd",
+        target_os = "dragonfly",
+        target_os = "illumos",
+        target_os = "solaris",
+    ))]
+    extern "C" fn after_fork_parent() {
+        unsafe {
+            drop((*ptr::addr_of_mut!(FORK_GUARD)).take());
+        }
+    }
+
+    /// Releases the lock taken by `before_fork` in the child, where the thread
+    /// which took it is the only one left.
+    ///
+    /// Without `before_fork` taking it, the lock may instead be held by
+    /// another thread, which doesn't exist in the child to ever release it.
+    /// It's replaced by a new one then, and what it protected is marked as
+    /// stale. If this thread holds the lock itself, like when it forks from a
+    /// callback passed to `resolve`, it goes on using the state in the child
+    /// as it would have in the parent.
+    ///
+    /// The child may only call async-signal-safe functions until it calls
+    /// `exec`, so this doesn't allocate or free anything. Whatever else has to
+    /// be done is left to the next time the lock is taken, see
+    /// `symbolize::after_fork`.
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "android",
+        target_vendor = "apple",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd",
+        target_os = "dragonfly",
+        target_os = "illumos",
+        target_os = "solaris",
+    ))]
+    extern "C" fn after_fork_child() {
+        use std::sync::TryLockError;
+
+        unsafe {
+            let guard = (*ptr::addr_of_mut!(FORK_GUARD)).take();
+            if guard.is_none() && !held() {
+                match (*LOCK).try_lock() {
+                    Ok(_) | Err(TryLockError::Poisoned(_)) => {}
+                    Err(TryLockError::WouldBlock) => {
+                        // The old lock is leaked, as freeing it isn't safe here.
+                        ptr::write(LOCK, Mutex::new(()));
+                        STALE.store(true, Ordering::Relaxed);
+                    }
+                }
+            }
+            drop(guard);
         }
+        FORKED.store(true, Ordering::Relaxed);
     }
 }
 
@@ -248,5 +451,7 @@ mod lock {
     not(target_vendor = "uwp")
 ))]
 mod dbghelp;
+#[cfg(all(windows, target_env = "msvc", not(target_vendor = "uwp")))]
+pub use self::dbghelp::{lock_dbghelp, set_dbghelp_process, DbghelpGuard};
 #[cfg(windows)]
 mod windows;
@@ -0,0 +1,492 @@
+//! Sampling call chains of any thread on Linux with `perf_event_open`.
+
+use core::mem;
+use core::ptr;
+use core::sync::atomic::{fence, Ordering};
+use std::fs;
+use std::io;
+use std::prelude::v1::*;
+use std::time::Duration;
+
+// Not declared by the versions of `libc` we support, see
+// `include/uapi/linux/perf_event.h`.
+const PERF_TYPE_SOFTWARE: u32 = 1;
+const PERF_COUNT_SW_CPU_CLOCK: u64 = 0;
+const PERF_SAMPLE_TID: u64 = 1 << 1;
+const PERF_SAMPLE_CALLCHAIN: u64 = 1 << 5;
+const PERF_SAMPLE_CPU: u64 = 1 << 7;
+const PERF_RECORD_LOST: u32 = 2;
+const PERF_RECORD_SAMPLE: u32 = 9;
+const PERF_CONTEXT_KERNEL: u64 = -128i64 as u64;
+const PERF_CONTEXT_USER: u64 = -512i64 as u64;
+// Any other context markers are larger, like those of guests.
+const PERF_CONTEXT_MAX: u64 = -4095i64 as u64;
+const PERF_EVENT_IOC_ENABLE: libc::c_ulong = 0x2400;
+const PERF_EVENT_IOC_DISABLE: libc::c_ulong = 0x2401;
+
+// Bits of `PerfEventAttr::flags`.
+const FLAG_DISABLED: u64 = 1 << 0;
+const FLAG_EXCLUDE_KERNEL: u64 = 1 << 5;
+const FLAG_EXCLUDE_HV: u64 = 1 << 6;
+const FLAG_FREQ: u64 = 1 << 10;
+const FLAG_EXCLUDE_CALLCHAIN_KERNEL: u64 = 1 << 21;
+const FLAG_EXCLUDE_CALLCHAIN_USER: u64 = 1 << 22;
+
+/// The fifth version of `struct perf_event_attr`, supported since Linux 4.8.
+#[repr(C)]
+#[derive(Default)]
+struct PerfEventAttr {
+    type_: u32,
+    size: u32,
+    config: u64,
+    sample_freq: u64,
+    sample_type: u64,
+    read_format: u64,
+    flags: u64,
+    wakeup_events: u32,
+    bp_type: u32,
+    config1: u64,
+    config2: u64,
+    branch_sample_type: u64,
+    sample_regs_user: u64,
+    sample_stack_user: u32,
+    clockid: i32,
+    sample_regs_intr: u64,
+    aux_watermark: u32,
+    sample_max_stack: u16,
+    reserved: u16,
+}
+
+/// The offsets of `data_head` and `data_tail` in `struct perf_event_mmap_page`.
+const DATA_HEAD: usize = 1024;
+const DATA_TAIL: usize = 1032;
+
+/// Options for a `PerfSampler`.
+///
+/// # Required features
+///
+/// This type requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default. It's only available
+/// on Linux and Android.
+#[derive(Clone, Debug)]
+pub struct PerfOptions {
+    thread: Option<i32>,
+    frequency: u64,
+    kernel: bool,
+    user: bool,
+    max_depth: u16,
+    buffer_pages: usize,
+}
+
+impl PerfOptions {
+    /// Creates a new set of options with the default values, which sample the
+    /// kernel and user space call chains of up to 127 frames of all threads
+    /// on all CPUs 99 times per second of CPU time, with 64 pages of buffer
+    /// per CPU.
+    pub fn new() -> PerfOptions {
+        PerfOptions {
+            thread: None,
+            frequency: 99,
+            kernel: true,
+            user: true,
+            max_depth: 127,
+            buffer_pages: 64,
+        }
+    }
+
+    /// Only samples the thread with the ID `tid`, as returned by `gettid`,
+    /// wherever it runs, instead of the whole system. The ID of the main
+    /// thread of a process is the process ID.
+    pub fn thread(mut self, tid: i32) -> PerfOptions {
+        self.thread = Some(tid);
+        self
+    }
+
+    /// How many samples are taken per second of CPU time, which the kernel
+    /// may lower if sampling takes too long, see
+    /// `/proc/sys/kernel/perf_event_max_sample_rate`.
+    pub fn frequency(mut self, frequency: u64) -> PerfOptions {
+        self.frequency = frequency;
+        self
+    }
+
+    /// The same as `frequency`, given as the time between two samples.
+    pub fn interval(self, interval: Duration) -> PerfOptions {
+        let nanos = interval.as_nanos().max(1);
+        self.frequency((1_000_000_000 / nanos).max(1) as u64)
+    }
+
+    /// Whether the kernel part of call chains is recorded, which usually
+    /// needs more privileges than recording user space, see
+    /// `/proc/sys/kernel/perf_event_paranoid`.
+    ///
+    /// Without it, no samples are taken while threads run in the kernel, as
+    /// that needs the same privileges, so time spent in system calls isn't
+    /// accounted for.
+    pub fn kernel(mut self, enabled: bool) -> PerfOptions {
+        self.kernel = enabled;
+        self
+    }
+
+    /// Whether the user space part of call chains is recorded.
+    ///
+    /// Samples are still taken while threads run in user space without it,
+    /// just with empty call chains, see `PerfSample::user_ips`.
+    pub fn user(mut self, enabled: bool) -> PerfOptions {
+        self.user = enabled;
+        self
+    }
+
+    /// The most frames recorded per sample, which the kernel limits to
+    /// `/proc/sys/kernel/perf_event_max_stack`.
+    pub fn max_depth(mut self, depth: u16) -> PerfOptions {
+        self.max_depth = depth;
+        self
+    }
+
+    /// How many pages the buffer of each CPU has, rounded up to a power of
+    /// two. Samples are lost when it fills up before it's drained, see
+    /// `PerfSampler::lost`.
+    pub fn buffer_pages(mut self, pages: usize) -> PerfOptions {
+        self.buffer_pages = pages.max(1).next_power_of_two();
+        self
+    }
+}
+
+impl Default for PerfOptions {
+    fn default() -> PerfOptions {
+        PerfOptions::new()
+    }
+}
+
+/// A sampler of the call chains of threads, recorded by the Linux kernel with
+/// `perf_event_open` and `PERF_SAMPLE_CALLCHAIN`.
+///
+/// Unlike `SamplingProfiler`, which unwinds threads of the current process
+/// in a signal handler, this leaves unwinding to the kernel, which walks
+/// kernel stacks and user space stacks of any process by their frame
+/// pointers. That has very little overhead and can sample the whole system,
+/// but the user space stacks of code compiled without frame pointers, like
+/// Rust code by default, are usually cut short.
+///
+/// Samples are buffered by the kernel until they're taken out with `drain`.
+/// Kernel addresses can be resolved with `KernelSymbols`. User space addresses
+/// belong to the sampled process, so they can be resolved with `resolve` for
+/// the current process, and with a `ModuleTable` of the modules loaded into
+/// other processes.
+///
+/// Opening the sampler fails unless the process is privileged enough for the
+/// `perf_event_paranoid` setting of the system. Sampling the whole system or
+/// the kernel usually needs `CAP_PERFMON`.
+///
+/// # Example
+///
+/// ```no_run
+/// extern crate backtrace;
+///
+/// use backtrace::{KernelSymbols, PerfOptions, PerfSampler};
+///
+/// fn main() {
+///     let mut sampler = PerfSampler::open(PerfOptions::new()).unwrap();
+///     std::thread::sleep(std::time::Duration::from_secs(1));
+///     let kallsyms = KernelSymbols::load().unwrap();
+///     sampler.drain(|sample| {
+///         println!("pid {} tid {}", sample.pid(), sample.tid());
+///         for &ip in sample.kernel_ips() {
+///             match kallsyms.resolve(ip) {
+///                 Some(symbol) => println!("    {}", symbol.name()),
+///                 None => println!("    {:#x}", ip),
+///             }
+///         }
+///         for &ip in sample.user_ips() {
+///             println!("    {:#x}", ip);
+///         }
+///     });
+/// }
+/// ```
+///
+/// # Required features
+///
+/// This type requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default. It's only available
+/// on Linux and Android.
+pub struct PerfSampler {
+    rings: Vec<Ring>,
+    lost: u64,
+    record: Vec<u8>,
+    sample: PerfSample,
+}
+
+/// The buffer the kernel writes the samples of one event into.
+struct Ring {
+    fd: libc::c_int,
+    map: *mut u8,
+    len: usize,
+    page_size: usize,
+}
+
+/// A sample taken by a `PerfSampler`, as passed to `PerfSampler::drain`.
+///
+/// # Required features
+///
+/// This type requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default. It's only available
+/// on Linux and Android.
+#[derive(Clone, Debug, Default)]
+pub struct PerfSample {
+    pid: u32,
+    tid: u32,
+    cpu: u32,
+    kernel: Vec<usize>,
+    user: Vec<usize>,
+}
+
+// The rings are only accessed through `&mut PerfSampler`.
+unsafe impl Send for PerfSampler {}
+unsafe impl Sync for PerfSampler {}
+
+impl PerfSampler {
+    /// Starts sampling as configured by `options`.
+    pub fn open(options: PerfOptions) -> io::Result<PerfSampler> {
+        let mut sampler = PerfSampler {
+            rings: Vec::new(),
+            lost: 0,
+            record: Vec::new(),
+            sample: PerfSample::default(),
+        };
+        match options.thread {
+            Some(tid) => sampler.rings.push(Ring::open(&options, tid, -1)?),
+            None => {
+                for cpu in online_cpus()? {
+                    sampler.rings.push(Ring::open(&options, -1, cpu)?);
+                }
+            }
+        }
+        // Start all events at once, rather than some while others are opened.
+        for ring in &sampler.rings {
+            if unsafe { libc::ioctl(ring.fd, PERF_EVENT_IOC_ENABLE as _, 0) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        Ok(sampler)
+    }
+
+    /// Takes all buffered samples out of the sampler, passing each one to
+    /// `f`.
+    ///
+    /// Samples are passed in the order they were taken for each CPU, but not
+    /// across CPUs. The sampler keeps running while samples are drained.
+    pub fn drain<F: FnMut(&PerfSample)>(&mut self, mut f: F) {
+        for ring in &self.rings {
+            let data = unsafe { ring.map.add(ring.page_size) };
+            let size = ring.len - ring.page_size;
+            let head = unsafe { ptr::read_volatile(ring.map.add(DATA_HEAD).cast::<u64>()) };
+            fence(Ordering::Acquire);
+            let mut tail = unsafe { ptr::read_volatile(ring.map.add(DATA_TAIL).cast::<u64>()) };
+            while tail < head {
+                // Records may wrap around the end of the buffer, so they're
+                // copied out first.
+                self.record.clear();
+                let copy = |len: usize, record: &mut Vec<u8>| {
+                    let start = tail as usize + record.len();
+                    for i in 0..len {
+                        record.push(unsafe { *data.add((start + i) % size) });
+                    }
+                };
+                copy(8, &mut self.record);
+                let kind = read_u32(&self.record, 0);
+                let len = usize::from(u16::from_ne_bytes([self.record[6], self.record[7]]));
+                if len < 8 {
+                    break;
+                }
+                copy(len - 8, &mut self.record);
+                tail += len as u64;
+
+                match kind {
+                    PERF_RECORD_SAMPLE if self.sample.parse(&self.record[8..]) => f(&self.sample),
+                    PERF_RECORD_LOST => self.lost += read_u64(&self.record, 16),
+                    _ => {}
+                }
+            }
+            fence(Ordering::Release);
+            unsafe { ptr::write_volatile(ring.map.add(DATA_TAIL).cast::<u64>(), tail) };
+        }
+    }
+
+    /// Returns the number of samples which were lost because a buffer was
+    /// full, as far as they've been drained.
+    pub fn lost(&self) -> u64 {
+        self.lost
+    }
+}
+
+impl Drop for PerfSampler {
+    fn drop(&mut self) {
+        for ring in &self.rings {
+            unsafe { libc::ioctl(ring.fd, PERF_EVENT_IOC_DISABLE as _, 0) };
+        }
+    }
+}
+
+impl Ring {
+    fn open(options: &PerfOptions, tid: i32, cpu: i32) -> io::Result<Ring> {
+        let mut flags = FLAG_DISABLED | FLAG_FREQ | FLAG_EXCLUDE_HV;
+        if !options.kernel {
+            // Sampling code running in the kernel at all needs the same
+            // privileges as recording its call chains.
+            flags |= FLAG_EXCLUDE_KERNEL | FLAG_EXCLUDE_CALLCHAIN_KERNEL;
+        }
+        if !options.user {
+            flags |= FLAG_EXCLUDE_CALLCHAIN_USER;
+        }
+        let attr = PerfEventAttr {
+            type_: PERF_TYPE_SOFTWARE,
+            size: mem::size_of::<PerfEventAttr>() as u32,
+            config: PERF_COUNT_SW_CPU_CLOCK,
+            sample_freq: options.frequency,
+            sample_type: PERF_SAMPLE_TID | PERF_SAMPLE_CPU | PERF_SAMPLE_CALLCHAIN,
+            flags,
+            sample_max_stack: options.max_depth,
+            ..PerfEventAttr::default()
+        };
+        let fd = unsafe {
+            libc::syscall(
+                libc::SYS_perf_event_open,
+                &attr as *const PerfEventAttr,
+                tid,
+                cpu,
+                -1,
+                libc::c_ulong::from(0u8),
+            )
+        };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let fd = fd as libc::c_int;
+
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+        let len = page_size * (1 + options.buffer_pages);
+        let map = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED,
+                fd,
+                0,
+            )
+        };
+        if map == libc::MAP_FAILED {
+            let err = io::Error::last_os_error();
+            unsafe { libc::close(fd) };
+            return Err(err);
+        }
+        Ok(Ring {
+            fd,
+            map: map.cast(),
+            len,
+            page_size,
+        })
+    }
+}
+
+impl Drop for Ring {
+    fn drop(&mut self) {
+        unsafe {
+            libc::munmap(self.map.cast(), self.len);
+            libc::close(self.fd);
+        }
+    }
+}
+
+impl PerfSample {
+    /// Returns the ID of the process which was sampled.
+    pub fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    /// Returns the ID of the thread which was sampled.
+    pub fn tid(&self) -> u32 {
+        self.tid
+    }
+
+    /// Returns the CPU the thread ran on.
+    pub fn cpu(&self) -> u32 {
+        self.cpu
+    }
+
+    /// Returns the addresses of the kernel part of the call chain, from the
+    /// most recent frame to the oldest one.
+    ///
+    /// The first address is where the thread was interrupted, and the others
+    /// are return addresses. They can be resolved with `KernelSymbols`.
+    pub fn kernel_ips(&self) -> &[usize] {
+        &self.kernel
+    }
+
+    /// Returns the addresses of the user space part of the call chain, from
+    /// the most recent frame to the oldest one.
+    ///
+    /// The first address is where the thread was interrupted, or where it
+    /// entered the kernel, and the others are return addresses.
+    pub fn user_ips(&self) -> &[usize] {
+        &self.user
+    }
+
+    /// Reads the body of a `PERF_RECORD_SAMPLE` record, returning whether it
+    /// was complete.
+    fn parse(&mut self, body: &[u8]) -> bool {
+        if body.len() < 24 {
+            return false;
+        }
+        self.pid = read_u32(body, 0);
+        self.tid = read_u32(body, 4);
+        self.cpu = read_u32(body, 8);
+        let nr = read_u64(body, 16) as usize;
+        if body.len() < 24 + nr * 8 {
+            return false;
+        }
+        self.kernel.clear();
+        self.user.clear();
+        let mut user = false;
+        for i in 0..nr {
+            match read_u64(body, 24 + i * 8) {
+                PERF_CONTEXT_KERNEL => user = false,
+                PERF_CONTEXT_USER => user = true,
+                ip if ip >= PERF_CONTEXT_MAX => {}
+                ip if user => self.user.push(ip as usize),
+                ip => self.kernel.push(ip as usize),
+            }
+        }
+        true
+    }
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    let mut buf = [0; 4];
+    buf.copy_from_slice(&bytes[offset..offset + 4]);
+    u32::from_ne_bytes(buf)
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    let mut buf = [0; 8];
+    buf.copy_from_slice(&bytes[offset..offset + 8]);
+    u64::from_ne_bytes(buf)
+}
+
+/// Returns the CPUs which are online, from a list like `0-3,6`.
+fn online_cpus() -> io::Result<Vec<i32>> {
+    let list = fs::read_to_string("/sys/devices/system/cpu/online")?;
+    let mut cpus = Vec::new();
+    for range in list.trim().split(',') {
+        let mut bounds = range.splitn(2, '-').map(str::parse::<i32>);
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "invalid list of CPUs");
+        let start = bounds.next().and_then(Result::ok).ok_or_else(invalid)?;
+        let end = match bounds.next() {
+            Some(end) => end.map_err(|_| invalid())?,
+            None => start,
+        };
+        cpus.extend(start..=end);
+    }
+    Ok(cpus)
+}
@@ -1,13 +1,34 @@
 #[cfg(feature = "std")]
-use super::{BacktraceFrame, BacktraceSymbol};
-use super::{BytesOrWideString, Frame, SymbolName};
+use super::{BacktraceFrame, BacktraceSymbol, Metadata};
+use super::{BytesOrWideString, DemangleOptions, Frame, SymbolLanguage, SymbolName};
 use core::ffi::c_void;
-use core::fmt;
+use core::fmt::{self, Write as _};
+use core::str;
+#[cfg(feature = "std")]
+use std::sync::Arc;
 
 const HEX_WIDTH: usize = 2 + 2 * core::mem::size_of::<usize>();
 
+/// Marks frames found by scanning the stack, see `Frame::is_scanned`.
+const SCANNED: &str = "scanned";
+
+mod classify;
+mod color;
+#[cfg(unix)]
+mod fd;
 #[cfg(target_os = "fuchsia")]
 mod fuchsia;
+#[cfg(feature = "std")]
+mod normalize;
+#[cfg(feature = "std")]
+mod snippet;
+mod write;
+
+pub use self::classify::FrameKind;
+#[cfg(unix)]
+pub use self::fd::FdWriter;
+#[cfg(feature = "std")]
+pub use self::snippet::SourceLocator;
 
 /// A formatter for backtraces.
 ///
@@ -20,8 +41,30 @@ pub struct BacktraceFmt<'a, 'b> {
     format: PrintFmt,
     print_path:
         &'a mut (dyn FnMut(&mut fmt::Formatter<'_>, BytesOrWideString<'_>) -> fmt::Result + 'b),
+    demangle_options: DemangleOptions,
+    symbol_offsets: bool,
+    address_fmt: Option<AddressFmt>,
+    color: bool,
+    source_context: Option<usize>,
+    #[cfg(feature = "std")]
+    source_locator: Option<Arc<dyn SourceLocator>>,
+    /// The sources of the files snippets were printed from, so that frames
+    /// in the same file don't read it again.
+    #[cfg(feature = "std")]
+    sources: snippet::Sources,
+    collapse_threshold: Option<usize>,
+    hidden_frames: u8,
+    classifier: Option<Classifier>,
+    metadata_suffix: bool,
+    deterministic: bool,
+    /// The current directory deterministic paths are relative to, looked up
+    /// once by `set_deterministic`.
+    #[cfg(feature = "std")]
+    cwd: Option<std::string::String>,
 }
 
+type Classifier = fn(Option<&SymbolName<'_>>, Option<&BytesOrWideString<'_>>) -> Option<FrameKind>;
+
 /// The styles of printing that we can print
 #[derive(Copy, Clone, Eq, PartialEq)]
 pub enum PrintFmt {
@@ -33,6 +76,39 @@ pub enum PrintFmt {
     __Nonexhaustive,
 }
 
+/// The ways the address of a frame can be printed, see
+/// `BacktraceFmt::set_address_fmt`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum AddressFmt {
+    /// Prints the absolute address of the instruction pointer in memory.
+    Absolute,
+    /// Prints the offset of the instruction pointer from where its module is
+    /// loaded, along with the module's file name, as in `libfoo.so+0x1234`.
+    ModuleOffset,
+    /// Prints the address the instruction pointer corresponds to in its
+    /// module's object file, which is what tools like `addr2line` expect.
+    StatedVirtual,
+    /// Doesn't print addresses at all.
+    Hidden,
+}
+
+/// Whether backtraces are printed with ANSI colors, see
+/// `BacktraceFmt::set_color`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum ColorMode {
+    /// Never print colors.
+    Never,
+    /// Always print colors.
+    Always,
+    /// Print colors if stderr is a terminal supporting them and they haven't
+    /// been disabled through the `NO_COLOR` environment variable.
+    ///
+    /// This requires the `std` feature and never prints colors otherwise.
+    Auto,
+}
+
 impl<'a, 'b> BacktraceFmt<'a, 'b> {
     /// Create a new `BacktraceFmt` which will write output to the provided
     /// `fmt`.
@@ -52,6 +128,258 @@ impl<'a, 'b> BacktraceFmt<'a, 'b> {
             frame_index: 0,
             format,
             print_path,
+            demangle_options: DemangleOptions::default(),
+            symbol_offsets: false,
+            address_fmt: None,
+            color: false,
+            source_context: None,
+            #[cfg(feature = "std")]
+            source_locator: None,
+            #[cfg(feature = "std")]
+            sources: Default::default(),
+            collapse_threshold: None,
+            hidden_frames: 0,
+            classifier: None,
+            metadata_suffix: true,
+            deterministic: false,
+            #[cfg(feature = "std")]
+            cwd: None,
+        }
+    }
+
+    /// Formats a backtrace directly into `out`, which may be any
+    /// `fmt::Write`.
+    ///
+    /// This is useful when there's no `fmt::Formatter` at hand, for example
+    /// when printing from a crash handler. The `BacktraceFmt` is created with
+    /// the `format` and `print_path` arguments as in `new`, and passed to `f`
+    /// which then prints the backtrace. Nothing is allocated beyond what `f`
+    /// itself allocates, so combined with `FdWriter` and
+    /// `trace_unsynchronized`/`resolve_frame_unsynchronized` this can stream
+    /// frames out of a signal handler.
+    pub fn write_to<W, F>(
+        out: &mut W,
+        format: PrintFmt,
+        print_path: &mut (dyn FnMut(&mut fmt::Formatter<'_>, BytesOrWideString<'_>) -> fmt::Result
+                  + '_),
+        f: F,
+    ) -> fmt::Result
+    where
+        W: fmt::Write + ?Sized,
+        F: FnOnce(&mut BacktraceFmt<'_, '_>) -> fmt::Result,
+    {
+        let adapter = write::Adapter {
+            format,
+            print_path: core::cell::Cell::new(Some(print_path)),
+            f: core::cell::Cell::new(Some(f)),
+        };
+        write!(out, "{adapter}")
+    }
+
+    /// Formats a backtrace directly into `out`, which may be any `io::Write`.
+    ///
+    /// This is the same as `write_to` except that output goes to an
+    /// `io::Write`, like a file or `std::io::stderr()`, without being
+    /// buffered in memory first.
+    ///
+    /// # Required features
+    ///
+    /// This function requires the `std` feature of the `backtrace` crate to be
+    /// enabled, and the `std` feature is enabled by default.
+    #[cfg(feature = "std")]
+    pub fn write_io<W, F>(
+        out: &mut W,
+        format: PrintFmt,
+        print_path: &mut (dyn FnMut(&mut fmt::Formatter<'_>, BytesOrWideString<'_>) -> fmt::Result
+                  + '_),
+        f: F,
+    ) -> std::io::Result<()>
+    where
+        W: std::io::Write + ?Sized,
+        F: FnOnce(&mut BacktraceFmt<'_, '_>) -> fmt::Result,
+    {
+        let adapter = write::Adapter {
+            format,
+            print_path: core::cell::Cell::new(Some(print_path)),
+            f: core::cell::Cell::new(Some(f)),
+        };
+        write!(out, "{adapter}")
+    }
+
+    /// Configures how symbol names are demangled when printing frames.
+    ///
+    /// By default names are printed in full, with the exception of the hash
+    /// of Rust symbols which isn't printed in the `Short` format.
+    pub fn set_demangle_options(&mut self, options: DemangleOptions) {
+        self.demangle_options = options;
+    }
+
+    /// Configures whether the offset of the instruction pointer from the start
+    /// of its function is printed after the function's name, as in
+    /// `foo::bar+0x1c`.
+    ///
+    /// This matches the conventions of gdb and lldb and is particularly
+    /// useful when no line information is available. The offset is only
+    /// printed if the start of the function is known, see
+    /// `BacktraceFrameFmt::set_symbol_address`. It's disabled by default.
+    pub fn set_symbol_offsets(&mut self, enabled: bool) {
+        self.symbol_offsets = enabled;
+    }
+
+    /// Configures how the address of each frame is printed.
+    ///
+    /// By default `PrintFmt::Full` prints absolute addresses and
+    /// `PrintFmt::Short` hides them. Module-relative forms require looking up
+    /// the module containing each address in the current process, and fall
+    /// back to absolute addresses if that isn't possible, which is always the
+    /// case without the `std` feature.
+    pub fn set_address_fmt(&mut self, address_fmt: AddressFmt) {
+        self.address_fmt = Some(address_fmt);
+    }
+
+    /// Configures whether the backtrace is printed with ANSI colors.
+    ///
+    /// Colors make addresses less prominent and highlight names of functions
+    /// in the user's own code, as opposed to functions from the standard
+    /// library or dependencies. Colors are disabled by default.
+    pub fn set_color(&mut self, mode: ColorMode) {
+        self.color = match mode {
+            ColorMode::Never => false,
+            ColorMode::Always => true,
+            ColorMode::Auto => color::stderr_supports_color(),
+        };
+    }
+
+    /// Configures whether snippets of source code are printed for each
+    /// symbol, with `context` lines before and after the executing line.
+    ///
+    /// Snippets are only printed if the symbol's file name and line number
+    /// are known and the file can be read from the local filesystem, or
+    /// found by the locator set with `set_source_locator`. `None`, the
+    /// default, disables snippets.
+    ///
+    /// # Required features
+    ///
+    /// Printing snippets requires the `std` feature of the `backtrace` crate,
+    /// without it this option has no effect.
+    pub fn set_source_snippets(&mut self, context: Option<usize>) {
+        self.source_context = context;
+    }
+
+    /// Configures where the source code of snippets comes from, see
+    /// `set_source_snippets`.
+    ///
+    /// With `None`, the default, files are read from the local filesystem at
+    /// the paths in the debug information. The locator is shared through an
+    /// `Arc`, so the same one can be set on every formatter.
+    ///
+    /// # Required features
+    ///
+    /// This function requires the `std` feature of the `backtrace` crate to be
+    /// enabled, and the `std` feature is enabled by default.
+    #[cfg(feature = "std")]
+    pub fn set_source_locator(&mut self, locator: Option<Arc<dyn SourceLocator>>) {
+        self.source_locator = locator;
+        self.sources.clear();
+    }
+
+    /// Configures whether recursion is collapsed when printing frames with
+    /// `backtrace_frames`.
+    ///
+    /// A sequence of frames repeated at least `threshold` times in a row is
+    /// printed only once, followed by a note of how many more times it was
+    /// repeated. `None`, the default, prints every frame.
+    pub fn set_collapse_recursion(&mut self, threshold: Option<usize>) {
+        self.collapse_threshold = threshold;
+    }
+
+    /// Configures which kinds of frames are hidden from the output.
+    ///
+    /// Symbols are classified with the callback configured with
+    /// `set_frame_classifier`, falling back to `FrameKind::classify`. Hidden
+    /// symbols aren't printed at all, but still count towards the index of
+    /// frames. By default no frames are hidden.
+    pub fn set_hidden_frames(&mut self, kinds: &[FrameKind]) {
+        self.hidden_frames = kinds.iter().fold(0, |bits, kind| bits | kind.bit());
+    }
+
+    /// Configures a callback overriding how symbols are classified, given
+    /// their name and the path of their source file.
+    ///
+    /// The callback returns the kind of the symbol, or `None` to fall back to
+    /// the built-in heuristics of `FrameKind::classify`. The kind of a symbol
+    /// determines whether it's hidden, see `set_hidden_frames`, and how it's
+    /// colored.
+    pub fn set_frame_classifier(&mut self, classifier: Classifier) {
+        self.classifier = Some(classifier);
+    }
+
+    /// Configures whether user data attached to frames and backtraces is
+    /// printed, which is enabled by default.
+    ///
+    /// The metadata of a frame is printed at the end of its first line, like
+    /// `[task=3, size=128]`, and the metadata of a whole backtrace on a line of
+    /// its own after its frames, see `backtrace_metadata`.
+    pub fn set_metadata_suffix(&mut self, enabled: bool) {
+        self.metadata_suffix = enabled;
+    }
+
+    /// Configures whether the output is made deterministic, for comparing
+    /// backtraces in snapshot tests.
+    ///
+    /// This prints the same text for the same code across runs, machines and
+    /// address space layouts: addresses and symbol offsets aren't printed,
+    /// whatever `set_address_fmt` and `set_symbol_offsets` say, the hashes of
+    /// Rust symbols are stripped, and file paths are normalized. Separators
+    /// become `/`, paths in the current directory become relative to it, the
+    /// sources of the standard library are printed under `/rustc/` and those
+    /// of dependencies under `/cargo/registry/` or `/cargo/git/`, without the
+    /// commit hashes and registry names that vary between machines. Normalized
+    /// paths are printed directly rather than with the `print_path` callback.
+    /// It's disabled by default.
+    ///
+    /// # Required features
+    ///
+    /// Normalizing paths requires the `std` feature of the `backtrace` crate,
+    /// without it paths are printed with the `print_path` callback.
+    pub fn set_deterministic(&mut self, enabled: bool) {
+        self.deterministic = enabled;
+        #[cfg(feature = "std")]
+        {
+            self.cwd = if enabled {
+                normalize::current_dir()
+            } else {
+                None
+            };
+        }
+    }
+
+    fn classify(
+        &self,
+        name: Option<&SymbolName<'_>>,
+        filename: Option<&BytesOrWideString<'_>>,
+    ) -> FrameKind {
+        self.classifier
+            .and_then(|classifier| classifier(name, filename))
+            .unwrap_or_else(|| FrameKind::classify(name, filename))
+    }
+
+    /// Writes the escape sequence `style` if colors are enabled.
+    fn style(&mut self, style: &str) -> fmt::Result {
+        if self.color {
+            self.fmt.write_str(style)?;
+        }
+        Ok(())
+    }
+
+    fn address_fmt(&self) -> AddressFmt {
+        if self.deterministic {
+            return AddressFmt::Hidden;
+        }
+        match (self.address_fmt, self.format) {
+            (Some(address_fmt), _) => address_fmt,
+            (None, PrintFmt::Full) => AddressFmt::Absolute,
+            (None, _) => AddressFmt::Hidden,
         }
     }
 
@@ -75,7 +403,71 @@ impl<'a, 'b> BacktraceFmt<'a, 'b> {
         BacktraceFrameFmt {
             fmt: self,
             symbol_index: 0,
+            symbol_address: None,
+            address_width: 0,
+        }
+    }
+
+    /// Prints all of `frames`, one after another.
+    ///
+    /// This is like calling `frame().backtrace_frame(..)` for each frame, but
+    /// also collapses recursion if enabled with `set_collapse_recursion`.
+    /// Frames elided this way still count towards the index of later frames.
+    ///
+    /// # Required features
+    ///
+    /// This function requires the `std` feature of the `backtrace` crate to be
+    /// enabled, and the `std` feature is enabled by default.
+    #[cfg(feature = "std")]
+    pub fn backtrace_frames(&mut self, frames: &[BacktraceFrame]) -> fmt::Result {
+        let mut i = 0;
+        while i < frames.len() {
+            let cycle = self
+                .collapse_threshold
+                .and_then(|threshold| find_cycle(&frames[i..], threshold));
+            match cycle {
+                Some((len, repeats)) => {
+                    for frame in &frames[i..i + len] {
+                        self.frame().backtrace_frame(frame)?;
+                    }
+                    self.style(color::DIM)?;
+                    match len {
+                        1 => write!(self.fmt, "      [previous frame")?,
+                        _ => write!(self.fmt, "      [previous {len} frames")?,
+                    }
+                    write!(self.fmt, " repeated {} more times]", repeats - 1)?;
+                    self.style(color::RESET)?;
+                    self.fmt.write_str("\n")?;
+                    self.frame_index += len * (repeats - 1);
+                    i += len * repeats;
+                }
+                None => {
+                    self.frame().backtrace_frame(&frames[i])?;
+                    i += 1;
+                }
+            }
         }
+        Ok(())
+    }
+
+    /// Prints the user data attached to a whole backtrace, if enabled with
+    /// `set_metadata_suffix` and there is any.
+    ///
+    /// This is meant to be called after printing the frames of the backtrace.
+    ///
+    /// # Required features
+    ///
+    /// This function requires the `std` feature of the `backtrace` crate to be
+    /// enabled, and the `std` feature is enabled by default.
+    #[cfg(feature = "std")]
+    pub fn backtrace_metadata(&mut self, metadata: &Metadata) -> fmt::Result {
+        if !self.metadata_suffix || metadata.is_empty() {
+            return Ok(());
+        }
+        self.style(color::DIM)?;
+        write!(self.fmt, "      [{metadata}]")?;
+        self.style(color::RESET)?;
+        self.fmt.write_str("\n")
     }
 
     /// Completes the backtrace output.
@@ -112,13 +504,18 @@ impl<'a, 'b> BacktraceFmt<'a, 'b> {
 pub struct BacktraceFrameFmt<'fmt, 'a, 'b> {
     fmt: &'fmt mut BacktraceFmt<'a, 'b>,
     symbol_index: usize,
+    symbol_address: Option<*mut c_void>,
+    // How many columns the address printed on this frame's first line takes,
+    // so subsequent lines can be aligned with it.
+    address_width: usize,
 }
 
 impl BacktraceFrameFmt<'_, '_, '_> {
     /// Prints a `BacktraceFrame` with this frame formatter.
     ///
     /// This will recursively print all `BacktraceSymbol` instances within the
-    /// `BacktraceFrame`.
+    /// `BacktraceFrame`. Frames found by scanning the stack are marked with
+    /// `[scanned]`, see `Frame::is_scanned`.
     ///
     /// # Required features
     ///
@@ -126,12 +523,33 @@ impl BacktraceFrameFmt<'_, '_, '_> {
     /// enabled, and the `std` feature is enabled by default.
     #[cfg(feature = "std")]
     pub fn backtrace_frame(&mut self, frame: &BacktraceFrame) -> fmt::Result {
+        let metadata = frame.metadata();
+        let metadata = if self.fmt.metadata_suffix && !metadata.is_empty() {
+            Some(metadata)
+        } else {
+            None
+        };
+        let scanned;
+        let suffix = match (frame.is_scanned(), metadata) {
+            (true, Some(metadata)) => {
+                scanned = format!("{SCANNED}, {metadata}");
+                Some(&scanned as &dyn fmt::Display)
+            }
+            (true, None) => Some(&SCANNED as &dyn fmt::Display),
+            (false, metadata) => metadata.map(|metadata| metadata as &dyn fmt::Display),
+        };
         let symbols = frame.symbols();
-        for symbol in symbols {
-            self.backtrace_symbol(frame, symbol)?;
+        for (i, symbol) in symbols.iter().enumerate() {
+            // The frame's symbol address is the start of the outermost
+            // function, any other symbols are functions inlined into it.
+            if i == symbols.len() - 1 {
+                self.set_symbol_address(Some(frame.symbol_address()));
+            }
+            self.print_backtrace_symbol(frame, symbol, suffix)?;
         }
         if symbols.is_empty() {
-            self.print_raw(frame.ip(), None, None, None)?;
+            self.set_symbol_address(Some(frame.symbol_address()));
+            self.print_symbol(frame.ip(), None, None, None, None, suffix)?;
         }
         Ok(())
     }
@@ -148,7 +566,17 @@ impl BacktraceFrameFmt<'_, '_, '_> {
         frame: &BacktraceFrame,
         symbol: &BacktraceSymbol,
     ) -> fmt::Result {
-        self.print_raw_with_column(
+        self.print_backtrace_symbol(frame, symbol, None)
+    }
+
+    #[cfg(feature = "std")]
+    fn print_backtrace_symbol(
+        &mut self,
+        frame: &BacktraceFrame,
+        symbol: &BacktraceSymbol,
+        suffix: Option<&dyn fmt::Display>,
+    ) -> fmt::Result {
+        self.print_symbol(
             frame.ip(),
             symbol.name(),
             // TODO: this isn't great that we don't end up printing anything
@@ -159,19 +587,33 @@ impl BacktraceFrameFmt<'_, '_, '_> {
                 .and_then(|p| Some(BytesOrWideString::Bytes(p.to_str()?.as_bytes()))),
             symbol.lineno(),
             symbol.colno(),
-        )?;
-        Ok(())
+            suffix,
+        )
+    }
+
+    /// Sets the start address of the function containing the frame's
+    /// instruction pointer, used to print symbol offsets if enabled with
+    /// `BacktraceFmt::set_symbol_offsets`.
+    ///
+    /// The address only applies to the next symbol printed with this
+    /// formatter. As a frame's symbol address is the start of the outermost
+    /// function, this should be called right before printing the last symbol
+    /// of a frame, for example with `frame.symbol_address()` for a `Frame`.
+    /// `backtrace_frame` takes care of this automatically.
+    pub fn set_symbol_address(&mut self, addr: Option<*mut c_void>) {
+        self.symbol_address = addr;
     }
 
     /// Prints a raw traced `Frame` and `Symbol`, typically from within the raw
     /// callbacks of this crate.
     pub fn symbol(&mut self, frame: &Frame, symbol: &super::Symbol) -> fmt::Result {
-        self.print_raw_with_column(
+        self.print_symbol(
             frame.ip(),
             symbol.name(),
             symbol.filename_raw(),
             symbol.lineno(),
             symbol.colno(),
+            frame.is_scanned().then_some(&SCANNED as &dyn fmt::Display),
         )?;
         Ok(())
     }
@@ -203,19 +645,35 @@ impl BacktraceFrameFmt<'_, '_, '_> {
         filename: Option<BytesOrWideString<'_>>,
         lineno: Option<u32>,
         colno: Option<u32>,
+    ) -> fmt::Result {
+        self.print_symbol(frame_ip, symbol_name, filename, lineno, colno, None)
+    }
+
+    /// Prints a symbol like `print_raw_with_column`, with `suffix` at the end
+    /// of the frame's first line.
+    fn print_symbol(
+        &mut self,
+        frame_ip: *mut c_void,
+        symbol_name: Option<SymbolName<'_>>,
+        filename: Option<BytesOrWideString<'_>>,
+        lineno: Option<u32>,
+        colno: Option<u32>,
+        suffix: Option<&dyn fmt::Display>,
     ) -> fmt::Result {
         // Fuchsia is unable to symbolize within a process so it has a special
         // format which can be used to symbolize later. Print that instead of
         // printing addresses in our own format here.
         if cfg!(target_os = "fuchsia") {
             self.print_raw_fuchsia(frame_ip)?;
-        } else {
-            self.print_raw_generic(frame_ip, symbol_name, filename, lineno, colno)?;
+        } else if !self.print_raw_generic(frame_ip, symbol_name, filename, lineno, colno, suffix)? {
+            return Ok(());
         }
         self.symbol_index += 1;
         Ok(())
     }
 
+    /// Prints a symbol in our own format, returning whether it counts as
+    /// printed.
     #[allow(unused_mut)]
     fn print_raw_generic(
         &mut self,
@@ -224,12 +682,23 @@ impl BacktraceFrameFmt<'_, '_, '_> {
         filename: Option<BytesOrWideString<'_>>,
         lineno: Option<u32>,
         colno: Option<u32>,
-    ) -> fmt::Result {
+        suffix: Option<&dyn fmt::Display>,
+    ) -> Result<bool, fmt::Error> {
+        let kind = self.fmt.classify(symbol_name.as_ref(), filename.as_ref());
+        if self.fmt.hidden_frames & kind.bit() != 0 {
+            // Hidden symbols don't count as printed, so the next visible
+            // symbol of this frame is printed with the frame's index.
+            self.symbol_address = None;
+            return Ok(false);
+        }
+
         // No need to print "null" frames, it basically just means that the
-        // system backtrace was a bit eager to trace back super far.
+        // system backtrace was a bit eager to trace back super far. Frames
+        // which are only known by name, like those converted from the
+        // standard library's backtraces, are still printed.
         if let PrintFmt::Short = self.fmt.format {
-            if frame_ip.is_null() {
-                return Ok(());
+            if frame_ip.is_null() && symbol_name.is_none() {
+                return Ok(true);
             }
         }
 
@@ -238,24 +707,40 @@ impl BacktraceFrameFmt<'_, '_, '_> {
         // though we just print appropriate whitespace.
         if self.symbol_index == 0 {
             write!(self.fmt.fmt, "{:4}: ", self.fmt.frame_index)?;
-            if let PrintFmt::Full = self.fmt.format {
-                write!(self.fmt.fmt, "{frame_ip:HEX_WIDTH$?} - ")?;
-            }
+            self.address_width = self.print_address(frame_ip)?;
         } else {
             write!(self.fmt.fmt, "      ")?;
-            if let PrintFmt::Full = self.fmt.format {
-                write!(self.fmt.fmt, "{:1$}", "", HEX_WIDTH + 3)?;
-            }
+            write!(self.fmt.fmt, "{:1$}", "", self.address_width)?;
         }
 
         // Next up write out the symbol name, using the alternate formatting for
         // more information if we're a full backtrace. Here we also handle
         // symbols which don't have a name,
+        let symbol_name = symbol_name.map(|name| name.with_options(self.fmt.demangle_options));
+        let style = match (&symbol_name, kind) {
+            (None, _) => color::DIM,
+            (Some(_), FrameKind::User) => color::USER,
+            (Some(_), _) => color::DEPENDENCY,
+        };
+        self.fmt.style(style)?;
         match (symbol_name, &self.fmt.format) {
+            (Some(name), _) if self.fmt.deterministic => write!(self.fmt.fmt, "{name:#}")?,
             (Some(name), PrintFmt::Short) => write!(self.fmt.fmt, "{name:#}")?,
             (Some(name), PrintFmt::Full) => write!(self.fmt.fmt, "{name}")?,
             (None, _) | (_, PrintFmt::__Nonexhaustive) => write!(self.fmt.fmt, "<unknown>")?,
         }
+        self.fmt.style(color::RESET)?;
+        let symbol_address = self.symbol_address.take();
+        if self.fmt.symbol_offsets && !self.fmt.deterministic {
+            if let Some(offset) = symbol_offset(frame_ip, symbol_address) {
+                write!(self.fmt.fmt, "+{offset:#x}")?;
+            }
+        }
+        if let (0, Some(suffix)) = (self.symbol_index, suffix) {
+            self.fmt.style(color::DIM)?;
+            write!(self.fmt.fmt, " [{suffix}]")?;
+            self.fmt.style(color::RESET)?;
+        }
         self.fmt.fmt.write_str("\n")?;
 
         // And last up, print out the filename/line number if they're available.
@@ -263,7 +748,58 @@ impl BacktraceFrameFmt<'_, '_, '_> {
             self.print_fileline(file, line, colno)?;
         }
 
-        Ok(())
+        Ok(true)
+    }
+
+    /// Prints the address of a frame followed by a separator, returning the
+    /// number of columns written.
+    fn print_address(&mut self, frame_ip: *mut c_void) -> Result<usize, fmt::Error> {
+        let address_fmt = self.fmt.address_fmt();
+        if address_fmt == AddressFmt::Hidden {
+            return Ok(0);
+        }
+        if frame_ip.is_null() {
+            // Keep the columns aligned with frames whose address is known.
+            write!(self.fmt.fmt, "{:1$}", "", HEX_WIDTH + 3)?;
+            return Ok(HEX_WIDTH + 3);
+        }
+
+        self.fmt.style(color::DIM)?;
+        let written = self.print_address_uncolored(frame_ip, address_fmt)?;
+        self.fmt.style(color::RESET)?;
+        Ok(written)
+    }
+
+    #[cfg_attr(not(feature = "std"), allow(unused_variables))]
+    fn print_address_uncolored(
+        &mut self,
+        frame_ip: *mut c_void,
+        address_fmt: AddressFmt,
+    ) -> Result<usize, fmt::Error> {
+        let mut out = CountingWriter {
+            fmt: self.fmt.fmt,
+            written: 0,
+        };
+        #[cfg(feature = "std")]
+        {
+            if address_fmt != AddressFmt::Absolute {
+                let printed = crate::symbolize::with_module(frame_ip, |module| match address_fmt {
+                    AddressFmt::ModuleOffset => {
+                        let name = module.name.to_str_lossy();
+                        let name = name.rsplit(&['/', '\\'][..]).next().unwrap_or("");
+                        let offset = (frame_ip as usize).wrapping_sub(module.base);
+                        write!(out, "{name}+{offset:#x} - ")
+                    }
+                    _ => write!(out, "{:#1$x} - ", module.svma, HEX_WIDTH),
+                });
+                if let Some(result) = printed {
+                    result?;
+                    return Ok(out.written);
+                }
+            }
+        }
+        write!(out, "{frame_ip:HEX_WIDTH$?} - ")?;
+        Ok(out.written)
     }
 
     fn print_fileline(
@@ -274,13 +810,28 @@ impl BacktraceFrameFmt<'_, '_, '_> {
     ) -> fmt::Result {
         // Filename/line are printed on lines under the symbol name, so print
         // some appropriate whitespace to sort of right-align ourselves.
-        if let PrintFmt::Full = self.fmt.format {
-            write!(self.fmt.fmt, "{:1$}", "", HEX_WIDTH)?;
-        }
+        let indent = self.address_width.saturating_sub(3);
+        write!(self.fmt.fmt, "{:1$}", "", indent)?;
         write!(self.fmt.fmt, "             at ")?;
 
+        #[cfg(feature = "std")]
+        let snippet_file = match &file {
+            BytesOrWideString::Bytes(b) => BytesOrWideString::Bytes(b),
+            BytesOrWideString::Wide(w) => BytesOrWideString::Wide(w),
+        };
+
         // Delegate to our internal callback to print the filename and then
         // print out the line number.
+        self.fmt.style(color::PATH)?;
+        #[cfg(feature = "std")]
+        {
+            if self.fmt.deterministic {
+                normalize::print(self.fmt.fmt, &file, self.fmt.cwd.as_deref())?;
+            } else {
+                (self.fmt.print_path)(self.fmt.fmt, file)?;
+            }
+        }
+        #[cfg(not(feature = "std"))]
         (self.fmt.print_path)(self.fmt.fmt, file)?;
         write!(self.fmt.fmt, ":{line}")?;
 
@@ -288,8 +839,16 @@ impl BacktraceFrameFmt<'_, '_, '_> {
         if let Some(colno) = colno {
             write!(self.fmt.fmt, ":{colno}")?;
         }
+        self.fmt.style(color::RESET)?;
 
         write!(self.fmt.fmt, "\n")?;
+
+        #[cfg(feature = "std")]
+        {
+            if let Some(context) = self.fmt.source_context {
+                snippet::print(self.fmt, indent + 13, snippet_file, line, colno, context)?;
+            }
+        }
         Ok(())
     }
 
@@ -304,6 +863,58 @@ impl BacktraceFrameFmt<'_, '_, '_> {
     }
 }
 
+/// A `fmt::Write` adapter counting the number of characters written.
+struct CountingWriter<'a, 'b> {
+    fmt: &'a mut fmt::Formatter<'b>,
+    written: usize,
+}
+
+impl fmt::Write for CountingWriter<'_, '_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.written += s.chars().count();
+        self.fmt.write_str(s)
+    }
+}
+
+/// Computes the offset of `ip` from the start of its function, if known.
+///
+/// Some unwinders report the instruction pointer itself when they don't know
+/// where the function starts, in which case no offset is returned rather than
+/// a misleading `+0x0`.
+fn symbol_offset(ip: *mut c_void, symbol_address: Option<*mut c_void>) -> Option<usize> {
+    let start = symbol_address? as usize;
+    let ip = ip as usize;
+    if start == 0 || start >= ip {
+        return None;
+    }
+    Some(ip - start)
+}
+
+/// The longest sequence of frames recognized as a cycle of recursion.
+#[cfg(feature = "std")]
+const MAX_CYCLE_LEN: usize = 32;
+
+/// Looks for a sequence of frames at the start of `frames` which is repeated
+/// at least `threshold` times, returning its length and how many times it's
+/// repeated.
+#[cfg(feature = "std")]
+fn find_cycle(frames: &[BacktraceFrame], threshold: usize) -> Option<(usize, usize)> {
+    let threshold = threshold.max(2);
+    let max_len = MAX_CYCLE_LEN.min(frames.len() / threshold);
+    (1..=max_len).find_map(|len| {
+        let cycle = &frames[..len];
+        let repeats = frames
+            .chunks_exact(len)
+            .take_while(|chunk| chunk.iter().zip(cycle).all(|(a, b)| a.ip() == b.ip()))
+            .count();
+        if repeats >= threshold {
+            Some((len, repeats))
+        } else {
+            None
+        }
+    })
+}
+
 impl Drop for BacktraceFrameFmt<'_, '_, '_> {
     fn drop(&mut self) {
         self.fmt.frame_index += 1;
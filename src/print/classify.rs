@@ -0,0 +1,118 @@
+//! Heuristics attributing frames to the code they come from.
+
+use super::{BytesOrWideString, SymbolLanguage, SymbolName};
+use core::fmt::{self, Write as _};
+use core::str;
+
+/// Where the code of a frame comes from, see `FrameKind::classify`.
+///
+/// This is used by `BacktraceFmt` to color and optionally hide frames, see
+/// `BacktraceFmt::set_hidden_frames`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum FrameKind {
+    /// Code of the application itself.
+    User,
+    /// Code of the standard library, i.e. the `std`, `core` and `alloc`
+    /// crates.
+    Std,
+    /// Code of this crate, which is typically on top of backtraces captured
+    /// with it.
+    Backtrace,
+    /// Code of a dependency of the application, downloaded by Cargo.
+    Dependency,
+    /// Code which couldn't be attributed to anything, such as frames without
+    /// symbols or system libraries without debug information.
+    Unknown,
+}
+
+impl FrameKind {
+    /// Classifies a symbol based on its name and the path of its source file.
+    ///
+    /// These are heuristics: a symbol is considered to be part of the standard
+    /// library if its path points to the sources of `rustc` or its name is in
+    /// one of the standard library's crates, and part of a dependency if its
+    /// path points into Cargo's registry or git checkouts. Symbols with
+    /// neither a path nor a Rust name are `Unknown`, as are symbols without a
+    /// name.
+    pub fn classify(
+        name: Option<&SymbolName<'_>>,
+        filename: Option<&BytesOrWideString<'_>>,
+    ) -> FrameKind {
+        const STD_PATHS: &[&[u8]] = &[b"/rustc/", b"\\rustc\\"];
+        const DEPENDENCY_PATHS: &[&[u8]] = &[
+            b"/.cargo/registry/",
+            b"/.cargo/git/",
+            b"\\.cargo\\registry\\",
+            b"\\.cargo\\git\\",
+        ];
+        const STD_CRATES: &[&str] = &["std::", "core::", "alloc::"];
+
+        let name = match name {
+            Some(name) => name,
+            None => return FrameKind::Unknown,
+        };
+
+        // Only the start of the demangled name is needed, so avoid allocating.
+        let mut prefix = PrefixWriter {
+            buf: [0; 32],
+            len: 0,
+        };
+        let _ = write!(prefix, "{name:#}");
+        let prefix = str::from_utf8(&prefix.buf[..prefix.len]).unwrap_or("");
+        let prefix = prefix.trim_start_matches('<');
+        if prefix.starts_with("backtrace::") {
+            return FrameKind::Backtrace;
+        }
+        if STD_CRATES.iter().any(|krate| prefix.starts_with(krate)) {
+            return FrameKind::Std;
+        }
+
+        match filename {
+            Some(path) if STD_PATHS.iter().any(|p| contains(path, p)) => FrameKind::Std,
+            Some(path) if DEPENDENCY_PATHS.iter().any(|p| contains(path, p)) => {
+                FrameKind::Dependency
+            }
+            Some(_) => FrameKind::User,
+            None => match name.language() {
+                SymbolLanguage::RustLegacy | SymbolLanguage::RustV0 => FrameKind::User,
+                _ => FrameKind::Unknown,
+            },
+        }
+    }
+
+    pub(super) fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+/// Returns whether `path` contains the ASCII string `needle`.
+fn contains(path: &BytesOrWideString<'_>, needle: &[u8]) -> bool {
+    match path {
+        BytesOrWideString::Bytes(path) => path.windows(needle.len()).any(|w| w == needle),
+        BytesOrWideString::Wide(path) => path
+            .windows(needle.len())
+            .any(|w| w.iter().zip(needle).all(|(a, b)| *a == u16::from(*b))),
+    }
+}
+
+/// A `fmt::Write` adapter which only retains the first bytes written to it.
+struct PrefixWriter {
+    buf: [u8; 32],
+    len: usize,
+}
+
+impl fmt::Write for PrefixWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            let mut encoded = [0; 4];
+            let encoded = c.encode_utf8(&mut encoded).as_bytes();
+            if self.len + encoded.len() > self.buf.len() {
+                break;
+            }
+            self.buf[self.len..][..encoded.len()].copy_from_slice(encoded);
+            self.len += encoded.len();
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,57 @@
+//! Support for ANSI-colored output in `BacktraceFmt`.
+
+pub(super) const RESET: &str = "\x1b[0m";
+/// Used for addresses and other low-importance information.
+pub(super) const DIM: &str = "\x1b[2m";
+/// Used for names of functions in the user's own code.
+pub(super) const USER: &str = "\x1b[1;32m";
+/// Used for names of functions from the standard library and dependencies.
+pub(super) const DEPENDENCY: &str = "\x1b[36m";
+/// Used for file paths.
+pub(super) const PATH: &str = "\x1b[35m";
+
+/// Returns whether stderr is a terminal which colors should be printed to.
+///
+/// This follows the common conventions of the `NO_COLOR` environment variable
+/// and `TERM=dumb` disabling colors.
+#[cfg(feature = "std")]
+pub(super) fn stderr_supports_color() -> bool {
+    if std::env::var_os("NO_COLOR").map_or(false, |v| !v.is_empty()) {
+        return false;
+    }
+    if std::env::var_os("TERM").map_or(false, |v| v == "dumb") {
+        return false;
+    }
+    stderr_is_terminal()
+}
+
+#[cfg(not(feature = "std"))]
+pub(super) fn stderr_supports_color() -> bool {
+    false
+}
+
+#[cfg(all(feature = "std", unix))]
+fn stderr_is_terminal() -> bool {
+    unsafe { libc::isatty(libc::STDERR_FILENO) == 1 }
+}
+
+#[cfg(all(feature = "std", windows))]
+fn stderr_is_terminal() -> bool {
+    use crate::windows::*;
+
+    const STD_ERROR_HANDLE: DWORD = -12i32 as DWORD;
+    const ENABLE_VIRTUAL_TERMINAL_PROCESSING: DWORD = 0x0004;
+
+    // Only consoles which interpret escape sequences themselves are
+    // considered, older consoles would print them verbatim.
+    unsafe {
+        let handle = GetStdHandle(STD_ERROR_HANDLE);
+        let mut mode = 0;
+        GetConsoleMode(handle, &mut mode) != 0 && mode & ENABLE_VIRTUAL_TERMINAL_PROCESSING != 0
+    }
+}
+
+#[cfg(all(feature = "std", not(any(unix, windows))))]
+fn stderr_is_terminal() -> bool {
+    false
+}
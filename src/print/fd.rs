@@ -0,0 +1,59 @@
+//! Writing backtraces directly to file descriptors.
+
+use core::fmt;
+
+/// A `fmt::Write` implementation writing directly to a raw file descriptor,
+/// available on Unix platforms.
+///
+/// Writes aren't buffered and no memory is allocated, and the only function
+/// called on the descriptor is `write(2)`, which is async-signal-safe. This
+/// makes it suitable for printing backtraces from signal handlers in
+/// combination with `BacktraceFmt::write_to`.
+#[derive(Debug)]
+pub struct FdWriter {
+    fd: libc::c_int,
+}
+
+impl FdWriter {
+    /// Creates a writer for the file descriptor `fd`.
+    ///
+    /// The descriptor isn't closed when the writer is dropped.
+    pub fn new(fd: libc::c_int) -> FdWriter {
+        FdWriter { fd }
+    }
+
+    /// Creates a writer for the standard error stream of the process.
+    pub fn stderr() -> FdWriter {
+        FdWriter::new(libc::STDERR_FILENO)
+    }
+}
+
+impl fmt::Write for FdWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let mut buf = s.as_bytes();
+        while !buf.is_empty() {
+            let n = unsafe { libc::write(self.fd, buf.as_ptr().cast(), buf.len()) };
+            if n < 0 && interrupted() {
+                continue;
+            }
+            if n <= 0 {
+                return Err(fmt::Error);
+            }
+            buf = &buf[n as usize..];
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+fn interrupted() -> bool {
+    // Reading the last OS error doesn't allocate.
+    std::io::Error::last_os_error().kind() == std::io::ErrorKind::Interrupted
+}
+
+// Without `std` there's no portable way to read `errno`, so interrupted
+// writes are treated like any other error.
+#[cfg(not(feature = "std"))]
+fn interrupted() -> bool {
+    false
+}
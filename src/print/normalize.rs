@@ -0,0 +1,130 @@
+//! Normalization of paths for deterministic output, see
+//! `BacktraceFmt::set_deterministic`.
+
+use crate::BytesOrWideString;
+use core::fmt;
+use std::env;
+use std::prelude::v1::*;
+
+/// Returns the current directory, which paths are made relative to by
+/// `print`.
+pub(super) fn current_dir() -> Option<String> {
+    let cwd = env::current_dir().ok()?;
+    Some(cwd.to_string_lossy().into_owned())
+}
+
+/// Prints `file` normalized with `normalize`, relative to `cwd`.
+pub(super) fn print(
+    fmt: &mut fmt::Formatter<'_>,
+    file: &BytesOrWideString<'_>,
+    cwd: Option<&str>,
+) -> fmt::Result {
+    fmt.write_str(&normalize(&file.to_str_lossy(), cwd))
+}
+
+/// Rewrites `path` into a form which doesn't depend on the machine it was
+/// built on.
+///
+/// Separators are turned into `/`, the sources of the standard library are
+/// moved to `/rustc/`, whether they're remapped with the commit hash of
+/// `rustc` or in a local `rust-src` component, sources of dependencies are
+/// moved to `/cargo/registry/` and `/cargo/git/` without the index, or the
+/// hash of the repository's URL and the revision they come from, and paths in
+/// `cwd` are made relative to it.
+fn normalize(path: &str, cwd: Option<&str>) -> String {
+    let path = path.replace('\\', "/");
+
+    if let Some((_, rest)) = path.split_once("/rustc/") {
+        let rest = match rest.split_once('/') {
+            Some((hash, tail)) if hash.bytes().all(|b| b.is_ascii_hexdigit()) => tail,
+            _ => rest,
+        };
+        return format!("/rustc/{rest}");
+    }
+    if let Some((_, rest)) = path.split_once("/lib/rustlib/src/rust/") {
+        return format!("/rustc/{rest}");
+    }
+    if let Some((_, rest)) = path.split_once("/registry/src/") {
+        // Skip the directory of the index, which is named after its URL.
+        if let Some((_, rest)) = rest.split_once('/') {
+            return format!("/cargo/registry/{rest}");
+        }
+    }
+    if let Some((_, rest)) = path.split_once("/git/checkouts/") {
+        // Keep the name of the repository, but not the hash of its URL or the
+        // directory of the revision.
+        if let Some((repo, rest)) = rest.split_once('/') {
+            let repo = match repo.rsplit_once('-') {
+                Some((name, hash)) if hash.bytes().all(|b| b.is_ascii_hexdigit()) => name,
+                _ => repo,
+            };
+            if let Some((_, rest)) = rest.split_once('/') {
+                return format!("/cargo/git/{repo}/{rest}");
+            }
+        }
+    }
+
+    if let Some(cwd) = cwd {
+        let cwd = cwd.replace('\\', "/");
+        if let Some(rest) = path.strip_prefix(cwd.trim_end_matches('/')) {
+            if let Some(rest) = rest.strip_prefix('/') {
+                return rest.to_string();
+            }
+        }
+    }
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::normalize;
+
+    #[test]
+    fn normalizes_paths() {
+        let cwd = Some("/home/user/project");
+        assert_eq!(
+            normalize(
+                "/rustc/90b35a6239c3d8bdabc530a6a0816f7ff89a0aaf/library/std/src/rt.rs",
+                cwd
+            ),
+            "/rustc/library/std/src/rt.rs"
+        );
+        assert_eq!(
+            normalize(
+                "/home/user/.rustup/toolchains/stable/lib/rustlib/src/rust/library/core/src/ops/function.rs",
+                cwd
+            ),
+            "/rustc/library/core/src/ops/function.rs"
+        );
+        assert_eq!(
+            normalize(
+                "C:\\Users\\user\\.cargo\\registry\\src\\index.crates.io-6f17d22bba15001f\\log-0.4.20\\src\\lib.rs",
+                cwd
+            ),
+            "/cargo/registry/log-0.4.20/src/lib.rs"
+        );
+        assert_eq!(
+            normalize(
+                "/home/user/.cargo/git/checkouts/gimli-ad2ef8b04bd8bb2a/7e9ab4c/src/read/mod.rs",
+                cwd
+            ),
+            "/cargo/git/gimli/src/read/mod.rs"
+        );
+        assert_eq!(
+            normalize(
+                "/home/user/.cargo/git/checkouts/rust-gdb-1f3e2d/7e9ab4c/src/lib.rs",
+                cwd
+            ),
+            "/cargo/git/rust-gdb/src/lib.rs"
+        );
+        assert_eq!(
+            normalize("/home/user/project/src/main.rs", cwd),
+            "src/main.rs"
+        );
+        assert_eq!(
+            normalize("/home/user/project2/src/main.rs", cwd),
+            "/home/user/project2/src/main.rs"
+        );
+        assert_eq!(normalize("src\\main.rs", None), "src/main.rs");
+    }
+}
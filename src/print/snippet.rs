@@ -0,0 +1,113 @@
+//! Printing of source code snippets around the location of a frame.
+
+use super::{color, BacktraceFmt};
+use crate::BytesOrWideString;
+use core::fmt;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::prelude::v1::*;
+use std::rc::Rc;
+
+/// The sources read by a `BacktraceFmt`, keyed by the paths in the debug
+/// information, with `None` for the ones that couldn't be found.
+pub(super) type Sources = HashMap<PathBuf, Option<Rc<str>>>;
+
+/// Provides the source code printed in snippets, see
+/// `BacktraceFmt::set_source_locator`.
+///
+/// The paths of source files in debug information are the ones they had when
+/// the program was built, which often don't exist where backtraces are
+/// printed. A locator can fetch the source from somewhere else instead, like
+/// a source archive shipped with the program, a git object store or a source
+/// server.
+///
+/// This is implemented for closures taking a path and returning the source,
+/// so a locator can be as simple as
+/// `move |path: &Path| files.get(path).cloned()` for a `HashMap` of files
+/// unpacked from an archive.
+///
+/// # Required features
+///
+/// This trait requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default.
+pub trait SourceLocator {
+    /// Returns the contents of the source file at `path`, as it's named in
+    /// the debug information, or `None` if it can't be found, in which case
+    /// no snippet is printed.
+    fn locate(&self, path: &Path) -> Option<String>;
+}
+
+impl<F: Fn(&Path) -> Option<String>> SourceLocator for F {
+    fn locate(&self, path: &Path) -> Option<String> {
+        self(path)
+    }
+}
+
+/// Prints the lines of source code surrounding `line` of `file`, marking
+/// `line` itself and `colno` if it's known.
+///
+/// The source is read with the formatter's `SourceLocator`, or from the local
+/// filesystem without one, once per file. Nothing is printed if it can't be
+/// found.
+pub(super) fn print(
+    fmt: &mut BacktraceFmt<'_, '_>,
+    indent: usize,
+    file: BytesOrWideString<'_>,
+    line: u32,
+    colno: Option<u32>,
+    context: usize,
+) -> fmt::Result {
+    let path = file.into_path_buf();
+    let source = match fmt.sources.get(&path) {
+        Some(source) => source.clone(),
+        None => {
+            let source = match &fmt.source_locator {
+                Some(locator) => locator.locate(&path),
+                None => fs::read_to_string(&path).ok(),
+            };
+            let source = source.map(Rc::from);
+            fmt.sources.insert(path, source.clone());
+            source
+        }
+    };
+    let source = match source {
+        Some(source) => source,
+        None => return Ok(()),
+    };
+    let line = line as usize;
+    let first = line.saturating_sub(context).max(1);
+    let last = line.saturating_add(context);
+
+    for (lineno, text) in source.lines().enumerate().map(|(i, text)| (i + 1, text)) {
+        if lineno < first {
+            continue;
+        }
+        if lineno > last {
+            break;
+        }
+
+        let marker = if lineno == line { '>' } else { ' ' };
+        write!(fmt.fmt, "{:1$}", "", indent)?;
+        if lineno == line {
+            write!(fmt.fmt, "  {marker} {lineno:5} | {text}")?;
+        } else {
+            fmt.style(color::DIM)?;
+            write!(fmt.fmt, "  {marker} {lineno:5} | {text}")?;
+            fmt.style(color::RESET)?;
+        }
+        fmt.fmt.write_str("\n")?;
+
+        if lineno == line {
+            if let Some(colno) = colno.filter(|c| *c > 0) {
+                // Mirror tabs of the line so the caret lines up with it.
+                write!(fmt.fmt, "{:1$}          | ", "", indent)?;
+                for c in text.chars().take(colno as usize - 1) {
+                    fmt.fmt.write_str(if c == '\t' { "\t" } else { " " })?;
+                }
+                fmt.fmt.write_str("^\n")?;
+            }
+        }
+    }
+    Ok(())
+}
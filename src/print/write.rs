@@ -0,0 +1,41 @@
+//! Formatting backtraces into arbitrary writers instead of a `fmt::Formatter`.
+
+use super::{BacktraceFmt, BytesOrWideString, PrintFmt};
+use core::cell::Cell;
+use core::fmt;
+use core::mem;
+
+type PrintPath<'a> = dyn FnMut(&mut fmt::Formatter<'_>, BytesOrWideString<'_>) -> fmt::Result + 'a;
+
+/// Adapts a closure driving a `BacktraceFmt` into something that can be
+/// passed to `write!`, which is how we get our hands on a `fmt::Formatter`
+/// wrapping an arbitrary writer.
+pub(super) struct Adapter<'a, 'p, F> {
+    pub(super) format: PrintFmt,
+    pub(super) print_path: Cell<Option<&'a mut PrintPath<'p>>>,
+    pub(super) f: Cell<Option<F>>,
+}
+
+impl<F> fmt::Display for Adapter<'_, '_, F>
+where
+    F: FnOnce(&mut BacktraceFmt<'_, '_>) -> fmt::Result,
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // `Display::fmt` is only called once by `write!`, so these are always
+        // present.
+        let (print_path, f) = match (self.print_path.take(), self.f.take()) {
+            (Some(print_path), Some(f)) => (print_path, f),
+            _ => return Err(fmt::Error),
+        };
+        // `BacktraceFmt` requires `print_path` to outlive the formatter's
+        // lifetime parameter, which can't be expressed here even though the
+        // formatter only lives for the duration of the `write!` in
+        // `BacktraceFmt::write_to`, during which `print_path` is borrowed.
+        // Neither the formatter nor `print_path` can escape `f`, so erasing
+        // the lifetime is fine.
+        let print_path =
+            unsafe { mem::transmute::<&mut PrintPath<'_>, &mut PrintPath<'static>>(print_path) };
+        let mut fmt = BacktraceFmt::new(fmt, self.format, print_path);
+        f(&mut fmt)
+    }
+}
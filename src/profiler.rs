@@ -0,0 +1,441 @@
+//! A sampling profiler capturing backtraces on `SIGPROF`.
+
+use crate::trace_unsynchronized;
+use core::cell::UnsafeCell;
+use core::ffi::c_void;
+use core::mem;
+use core::ptr;
+use core::sync::atomic::{AtomicBool, AtomicPtr, AtomicU8, AtomicUsize, Ordering::SeqCst};
+use std::io;
+use std::prelude::v1::*;
+use std::thread;
+use std::time::Duration;
+
+#[cfg(target_os = "android")]
+use libc::__errno as errno_location;
+#[cfg(target_os = "linux")]
+use libc::__errno_location as errno_location;
+#[cfg(any(target_os = "macos", target_os = "ios", target_os = "freebsd"))]
+use libc::__error as errno_location;
+
+// Not declared by the versions of `libc` we support. The value of
+// `ITIMER_PROF` is the same on all supported platforms.
+const ITIMER_PROF: libc::c_int = 2;
+
+extern "C" {
+    fn setitimer(
+        which: libc::c_int,
+        new_value: *const libc::itimerval,
+        old_value: *mut libc::itimerval,
+    ) -> libc::c_int;
+}
+
+/// The most frames a single sample can have, which is how many fit into the
+/// buffer on the stack of the signal handler.
+const MAX_DEPTH: usize = 256;
+
+// States of the slots of a `SampleBuffer`.
+const EMPTY: u8 = 0;
+const WRITING: u8 = 1;
+const FULL: u8 = 2;
+const READING: u8 = 3;
+
+/// The state of the running profiler, shared with the signal handler.
+static STATE: AtomicPtr<State> = AtomicPtr::new(ptr::null_mut());
+/// The number of signal handlers currently looking at `STATE`.
+static ACTIVE: AtomicUsize = AtomicUsize::new(0);
+static RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// Options for a `SamplingProfiler`.
+///
+/// # Required features
+///
+/// This type requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default.
+#[derive(Clone, Debug)]
+pub struct ProfilerOptions {
+    interval: Duration,
+    max_depth: usize,
+    capacity: usize,
+}
+
+impl ProfilerOptions {
+    /// Creates a new set of options with the default values, which take 100
+    /// samples of up to 64 frames per second of CPU time, and buffer up to
+    /// 1000 samples.
+    pub fn new() -> ProfilerOptions {
+        ProfilerOptions {
+            interval: Duration::from_millis(10),
+            max_depth: 64,
+            capacity: 1000,
+        }
+    }
+
+    /// How much CPU time the process spends between two samples.
+    pub fn interval(mut self, interval: Duration) -> ProfilerOptions {
+        self.interval = interval;
+        self
+    }
+
+    /// The most frames recorded per sample, up to 256. The oldest frames of
+    /// deeper stacks are left out.
+    pub fn max_depth(mut self, depth: usize) -> ProfilerOptions {
+        self.max_depth = depth.min(MAX_DEPTH);
+        self
+    }
+
+    /// How many samples are buffered until they're taken out with
+    /// `SamplingProfiler::drain`. Samples taken while the buffer is full are
+    /// dropped.
+    pub fn capacity(mut self, capacity: usize) -> ProfilerOptions {
+        self.capacity = capacity;
+        self
+    }
+}
+
+impl Default for ProfilerOptions {
+    fn default() -> ProfilerOptions {
+        ProfilerOptions::new()
+    }
+}
+
+/// A sampling profiler, which periodically captures the backtrace of whichever
+/// thread of the process is running.
+///
+/// The profiler arms an interval timer with `setitimer(ITIMER_PROF)`, which
+/// sends `SIGPROF` to the process each time it spent the configured amount of
+/// CPU time. The backtrace of the thread handling the signal is captured in the
+/// signal handler with `trace_unsynchronized` and recorded as a list of
+/// instruction pointers, from the most recent frame to the oldest one. The
+/// first one is the precise address where the thread was interrupted, see
+/// `AddressKind::Precise`, and the others are return addresses.
+///
+/// The frames of the signal handler are left out by looking for the
+/// interrupted address, which is read from the context the handler is passed.
+/// That's only done on x86_64 Linux and Android, aarch64 Linux and x86_64
+/// macOS so far, and samples start with the frames of the handler elsewhere.
+/// Stack scanning isn't async-signal-safe, so it shouldn't be enabled with
+/// `set_stack_scanning` while a profiler runs.
+///
+/// Samples are either buffered until they're taken out with `drain`, or
+/// passed to a callback right away, see `start_with_callback`. Either way
+/// they're only addresses: resolving symbols isn't possible in a signal
+/// handler, but the samples can be aggregated with `TraceStore::intern_ips`
+/// and turned into `Backtrace`s with `Backtrace::from_ips` later.
+///
+/// Only one profiler can run at a time, as it takes over the process-wide
+/// `SIGPROF` handler and timer. Dropping the profiler stops it and restores
+/// the previous handler.
+///
+/// # Example
+///
+/// ```no_run
+/// extern crate backtrace;
+///
+/// use backtrace::{ProfilerOptions, SamplingProfiler, TraceStore};
+///
+/// fn main() {
+///     let profiler = SamplingProfiler::start(ProfilerOptions::new()).unwrap();
+///     // ... run the code to profile ...
+///     let mut store = TraceStore::new();
+///     profiler.drain(|ips| {
+///         store.intern_ips(ips.iter().map(|&ip| ip as *mut _));
+///     });
+/// }
+/// ```
+///
+/// # Required features
+///
+/// This type requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default.
+pub struct SamplingProfiler {
+    state: Box<State>,
+    old_action: libc::sigaction,
+}
+
+struct State {
+    max_depth: usize,
+    callback: Option<fn(&[usize])>,
+    buffer: SampleBuffer,
+}
+
+/// A fixed-size buffer of samples, written by signal handlers without locking
+/// or allocating.
+struct SampleBuffer {
+    slots: Box<[Slot]>,
+    ips: Box<[UnsafeCell<usize>]>,
+    next: AtomicUsize,
+    dropped: AtomicUsize,
+}
+
+struct Slot {
+    state: AtomicU8,
+    len: UnsafeCell<usize>,
+}
+
+// The contents of slots are only accessed by whoever moved it into the
+// `WRITING` or `READING` state.
+unsafe impl Sync for SampleBuffer {}
+
+impl SamplingProfiler {
+    /// Starts a profiler buffering samples until they're taken out with
+    /// `drain`.
+    ///
+    /// This fails if another profiler is already running, or if the signal
+    /// handler or timer can't be set up.
+    pub fn start(options: ProfilerOptions) -> io::Result<SamplingProfiler> {
+        unsafe { SamplingProfiler::start_imp(options, None) }
+    }
+
+    /// Starts a profiler passing each sample to `callback`, instead of
+    /// buffering them.
+    ///
+    /// # Safety
+    ///
+    /// `callback` is called from the signal handler, interrupting whatever
+    /// the thread was doing, so it must only do what's async-signal-safe. In
+    /// particular it must not allocate memory, take locks or panic.
+    pub unsafe fn start_with_callback(
+        options: ProfilerOptions,
+        callback: fn(&[usize]),
+    ) -> io::Result<SamplingProfiler> {
+        SamplingProfiler::start_imp(options, Some(callback))
+    }
+
+    unsafe fn start_imp(
+        options: ProfilerOptions,
+        callback: Option<fn(&[usize])>,
+    ) -> io::Result<SamplingProfiler> {
+        if options.interval.is_zero() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the sampling interval must not be zero",
+            ));
+        }
+        if RUNNING.swap(true, SeqCst) {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "a sampling profiler is already running",
+            ));
+        }
+
+        let capacity = if callback.is_some() {
+            0
+        } else {
+            options.capacity
+        };
+        let mut state = Box::new(State {
+            max_depth: options.max_depth,
+            callback,
+            buffer: SampleBuffer::new(capacity, options.max_depth),
+        });
+        STATE.store(&mut *state, SeqCst);
+
+        let mut action: libc::sigaction = mem::zeroed();
+        action.sa_sigaction = on_sigprof as *const () as usize;
+        action.sa_flags = libc::SA_SIGINFO | libc::SA_RESTART;
+        libc::sigemptyset(&mut action.sa_mask);
+        let mut old_action: libc::sigaction = mem::zeroed();
+        if libc::sigaction(libc::SIGPROF, &action, &mut old_action) != 0 {
+            let err = io::Error::last_os_error();
+            STATE.store(ptr::null_mut(), SeqCst);
+            RUNNING.store(false, SeqCst);
+            return Err(err);
+        }
+
+        let profiler = SamplingProfiler { state, old_action };
+        // Intervals below a microsecond would disable the timer.
+        let micros = options.interval.as_micros().max(1);
+        let interval = libc::timeval {
+            tv_sec: (micros / 1_000_000) as _,
+            tv_usec: (micros % 1_000_000) as _,
+        };
+        let timer = libc::itimerval {
+            it_interval: interval,
+            it_value: interval,
+        };
+        if setitimer(ITIMER_PROF, &timer, ptr::null_mut()) != 0 {
+            // Dropping the profiler restores the previous handler.
+            return Err(io::Error::last_os_error());
+        }
+        Ok(profiler)
+    }
+
+    /// Takes all buffered samples out of the profiler, passing each one to
+    /// `f`, roughly from the oldest sample to the most recent one.
+    ///
+    /// The profiler keeps running while samples are drained. Profilers
+    /// started with `start_with_callback` never buffer any samples.
+    pub fn drain<F: FnMut(&[usize])>(&self, mut f: F) {
+        let buffer = &self.state.buffer;
+        let capacity = buffer.slots.len();
+        if capacity == 0 {
+            return;
+        }
+        let start = buffer.next.load(SeqCst) % capacity;
+        for i in 0..capacity {
+            let index = (start + i) % capacity;
+            let slot = &buffer.slots[index];
+            if slot
+                .state
+                .compare_exchange(FULL, READING, SeqCst, SeqCst)
+                .is_err()
+            {
+                continue;
+            }
+            unsafe {
+                let ips = buffer.slot_ips(index);
+                let len = *slot.len.get();
+                let ips = &*(&ips[..len] as *const [UnsafeCell<usize>] as *const [usize]);
+                f(ips);
+            }
+            slot.state.store(EMPTY, SeqCst);
+        }
+    }
+
+    /// Returns the number of samples which were dropped because the buffer
+    /// was full.
+    pub fn dropped(&self) -> usize {
+        self.state.buffer.dropped.load(SeqCst)
+    }
+}
+
+impl Drop for SamplingProfiler {
+    fn drop(&mut self) {
+        unsafe {
+            // Ignoring the signal discards any which are still pending, which
+            // would kill the process with the default action of `SIGPROF`
+            // once the previous handler is back.
+            let mut ignore: libc::sigaction = mem::zeroed();
+            ignore.sa_sigaction = libc::SIG_IGN;
+            libc::sigemptyset(&mut ignore.sa_mask);
+            libc::sigaction(libc::SIGPROF, &ignore, ptr::null_mut());
+            let timer: libc::itimerval = mem::zeroed();
+            setitimer(ITIMER_PROF, &timer, ptr::null_mut());
+        }
+        // Signals may still be handled on other threads, wait for them to let
+        // go of the state before it's freed.
+        STATE.store(ptr::null_mut(), SeqCst);
+        while ACTIVE.load(SeqCst) != 0 {
+            thread::yield_now();
+        }
+        unsafe {
+            libc::sigaction(libc::SIGPROF, &self.old_action, ptr::null_mut());
+        }
+        RUNNING.store(false, SeqCst);
+    }
+}
+
+impl SampleBuffer {
+    fn new(capacity: usize, max_depth: usize) -> SampleBuffer {
+        SampleBuffer {
+            slots: (0..capacity)
+                .map(|_| Slot {
+                    state: AtomicU8::new(EMPTY),
+                    len: UnsafeCell::new(0),
+                })
+                .collect(),
+            ips: (0..capacity * max_depth)
+                .map(|_| UnsafeCell::new(0))
+                .collect(),
+            next: AtomicUsize::new(0),
+            dropped: AtomicUsize::new(0),
+        }
+    }
+
+    fn slot_ips(&self, index: usize) -> &[UnsafeCell<usize>] {
+        let depth = self.ips.len() / self.slots.len();
+        &self.ips[index * depth..][..depth]
+    }
+
+    fn push(&self, ips: &[usize]) {
+        if self.slots.is_empty() {
+            return;
+        }
+        let index = self.next.fetch_add(1, SeqCst) % self.slots.len();
+        let slot = &self.slots[index];
+        if slot
+            .state
+            .compare_exchange(EMPTY, WRITING, SeqCst, SeqCst)
+            .is_err()
+        {
+            self.dropped.fetch_add(1, SeqCst);
+            return;
+        }
+        for (dst, ip) in self.slot_ips(index).iter().zip(ips) {
+            unsafe { *dst.get() = *ip };
+        }
+        unsafe { *slot.len.get() = ips.len() };
+        slot.state.store(FULL, SeqCst);
+    }
+}
+
+extern "C" fn on_sigprof(_signum: libc::c_int, _info: *mut libc::siginfo_t, ctx: *mut c_void) {
+    ACTIVE.fetch_add(1, SeqCst);
+    let state = STATE.load(SeqCst);
+    if !state.is_null() {
+        unsafe {
+            // Unwinding may clobber `errno` of the interrupted code.
+            let errno = *errno_location();
+            sample(&*state, interrupted_ip(ctx));
+            *errno_location() = errno;
+        }
+    }
+    ACTIVE.fetch_sub(1, SeqCst);
+}
+
+/// Returns the address of the instruction a signal interrupted, from the
+/// context passed to its handler, on platforms where it's known where to find
+/// it.
+unsafe fn interrupted_ip(ctx: *mut c_void) -> Option<usize> {
+    let ctx = ctx as *const libc::ucontext_t;
+    if ctx.is_null() {
+        return None;
+    }
+    cfg_if::cfg_if! {
+        if #[cfg(all(any(target_os = "linux", target_os = "android"), target_arch = "x86_64"))] {
+            Some((*ctx).uc_mcontext.gregs[libc::REG_RIP as usize] as usize)
+        } else if #[cfg(all(target_os = "linux", target_arch = "aarch64"))] {
+            Some((*ctx).uc_mcontext.pc as usize)
+        } else if #[cfg(all(target_os = "macos", target_arch = "x86_64"))] {
+            Some((*(*ctx).uc_mcontext).__ss.__rip as usize)
+        } else {
+            None
+        }
+    }
+}
+
+/// Records the backtrace of the interrupted code, starting at the frame of
+/// `interrupted`, or with the frames of the handler if it's not known.
+///
+/// This must not call anything which isn't async-signal-safe, like
+/// `Frame::symbol_address`, which looks up unwind tables under a lock.
+unsafe fn sample(state: &State, interrupted: Option<usize>) {
+    let mut ips = [0; MAX_DEPTH];
+    let mut len = 0;
+    let mut found = interrupted.is_none();
+    trace_unsynchronized(|frame| {
+        let ip = frame.ip() as usize;
+        if !found {
+            // Leave out the frames of the handler and of the trampoline
+            // which called it.
+            if Some(ip) != interrupted {
+                return true;
+            }
+            found = true;
+        }
+        ips[len] = ip;
+        len += 1;
+        len < ips.len()
+    });
+    // The unwinder couldn't get past the trampoline.
+    if let (false, Some(ip)) = (found, interrupted) {
+        ips[0] = ip;
+        len = 1;
+    }
+    let ips = &ips[..len.min(state.max_depth)];
+    match state.callback {
+        Some(callback) => callback(ips),
+        None => state.buffer.push(ips),
+    }
+}
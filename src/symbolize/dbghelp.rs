@@ -18,7 +18,9 @@
 #![allow(bad_style)]
 
 use super::super::{dbghelp, windows::*};
-use super::{BytesOrWideString, ResolveWhat, SymbolName};
+use super::{
+    BytesOrWideString, ResolveWhat, SymbolBinding, SymbolName, SymbolVersion, SymbolVisibility,
+};
 use core::ffi::c_void;
 use core::marker;
 use core::mem;
@@ -36,6 +38,7 @@ const fn ptr_from_ref<T: ?Sized>(r: &T) -> *const T {
 pub struct Symbol<'a> {
     name: *const [u8],
     addr: *mut c_void,
+    module_base: *mut c_void,
     line: Option<u32>,
     filename: Option<*const [u16]>,
     #[cfg(feature = "std")]
@@ -54,6 +57,30 @@ impl Symbol<'_> {
         Some(self.addr)
     }
 
+    pub fn offset(&self) -> Option<usize> {
+        None
+    }
+
+    pub fn module_base(&self) -> Option<*mut c_void> {
+        Some(self.module_base).filter(|base| !base.is_null())
+    }
+
+    pub fn module_path(&self) -> Option<BytesOrWideString<'_>> {
+        None
+    }
+
+    pub fn binding(&self) -> Option<SymbolBinding> {
+        None
+    }
+
+    pub fn visibility(&self) -> Option<SymbolVisibility> {
+        None
+    }
+
+    pub fn version(&self) -> Option<SymbolVersion<'_>> {
+        None
+    }
+
     pub fn filename_raw(&self) -> Option<BytesOrWideString<'_>> {
         self.filename
             .map(|slice| unsafe { BytesOrWideString::Wide(&*slice) })
@@ -83,13 +110,20 @@ pub unsafe fn resolve(what: ResolveWhat<'_>, cb: &mut dyn FnMut(&super::Symbol))
     // Ensure this process's symbols are initialized
     let dbghelp = match dbghelp::init() {
         Ok(dbghelp) => dbghelp,
-        Err(()) => return, // oh well...
+        Err(()) => {
+            // oh well...
+            super::diagnostics::report(format_args!("couldn't initialize dbghelp.dll"));
+            return;
+        }
     };
     match what {
-        ResolveWhat::Address(_) => resolve_with_inline(&dbghelp, what.address_or_ip(), None, cb),
-        ResolveWhat::Frame(frame) => {
-            resolve_with_inline(&dbghelp, frame.ip(), frame.inner.inline_context(), cb)
-        }
+        ResolveWhat::Address(..) => resolve_with_inline(&dbghelp, what.address_or_ip(), None, cb),
+        ResolveWhat::Frame(frame) => resolve_with_inline(
+            &dbghelp,
+            what.address_or_ip(),
+            frame.inner.inline_context(),
+            cb,
+        ),
     };
 }
 
@@ -98,7 +132,11 @@ pub unsafe fn resolve(what: ResolveWhat<'_>, cb: &mut dyn FnMut(&super::Symbol))
     // Ensure this process's symbols are initialized
     let dbghelp = match dbghelp::init() {
         Ok(dbghelp) => dbghelp,
-        Err(()) => return, // oh well...
+        Err(()) => {
+            // oh well...
+            super::diagnostics::report(format_args!("couldn't initialize dbghelp.dll"));
+            return;
+        }
     };
 
     let resolve_inner = if (*dbghelp.dbghelp()).SymAddrIncludeInlineTrace().is_some() {
@@ -111,10 +149,13 @@ pub unsafe fn resolve(what: ResolveWhat<'_>, cb: &mut dyn FnMut(&super::Symbol))
         resolve_legacy
     };
     match what {
-        ResolveWhat::Address(_) => resolve_inner(&dbghelp, what.address_or_ip(), None, cb),
-        ResolveWhat::Frame(frame) => {
-            resolve_inner(&dbghelp, frame.ip(), frame.inner.inline_context(), cb)
-        }
+        ResolveWhat::Address(..) => resolve_inner(&dbghelp, what.address_or_ip(), None, cb),
+        ResolveWhat::Frame(frame) => resolve_inner(
+            &dbghelp,
+            what.address_or_ip(),
+            frame.inner.inline_context(),
+            cb,
+        ),
     };
 }
 
@@ -129,10 +170,10 @@ unsafe fn resolve_legacy(
     _inline_context: Option<DWORD>,
     cb: &mut dyn FnMut(&super::Symbol),
 ) -> Option<()> {
-    let addr = super::adjust_ip(addr) as DWORD64;
+    let addr = addr as DWORD64;
     do_resolve(
-        |info| dbghelp.SymFromAddrW()(GetCurrentProcess(), addr, &mut 0, info),
-        |line| dbghelp.SymGetLineFromAddrW64()(GetCurrentProcess(), addr, &mut 0, line),
+        |info| dbghelp.SymFromAddrW()(dbghelp.process(), addr, &mut 0, info),
+        |line| dbghelp.SymGetLineFromAddrW64()(dbghelp.process(), addr, &mut 0, line),
         cb,
     );
     Some(())
@@ -148,12 +189,12 @@ unsafe fn resolve_with_inline(
     inline_context: Option<DWORD>,
     cb: &mut dyn FnMut(&super::Symbol),
 ) -> Option<()> {
-    let current_process = GetCurrentProcess();
+    let current_process = dbghelp.process();
     // Ensure we have the functions we need. Return if any aren't found.
     let SymFromInlineContextW = (*dbghelp.dbghelp()).SymFromInlineContextW()?;
     let SymGetLineFromInlineContextW = (*dbghelp.dbghelp()).SymGetLineFromInlineContextW()?;
 
-    let addr = super::adjust_ip(addr) as DWORD64;
+    let addr = addr as DWORD64;
 
     let (inlined_frame_count, inline_context) = if let Some(ic) = inline_context {
         (0, ic)
@@ -270,6 +311,7 @@ unsafe fn do_resolve(
         inner: Symbol {
             name,
             addr: info.Address as *mut _,
+            module_base: info.ModBase as *mut _,
             line: lineno,
             filename,
             _filename_cache: cache(filename),
@@ -278,6 +320,120 @@ unsafe fn do_resolve(
     })
 }
 
+pub unsafe fn resolve_many(
+    what: &[ResolveWhat<'_>],
+    next: &mut dyn FnMut(usize) -> bool,
+    cb: &mut dyn FnMut(usize, &super::Symbol),
+) {
+    for (i, what) in what.iter().enumerate() {
+        if !next(i) {
+            return;
+        }
+        resolve(*what, &mut |sym| cb(i, sym));
+    }
+}
+
+#[cfg(feature = "std")]
+pub unsafe fn resolve_batch(what: &[ResolveWhat<'_>], cb: &(dyn Fn(usize, &super::Symbol) + Sync)) {
+    for (i, what) in what.iter().enumerate() {
+        resolve(*what, &mut |sym| cb(i, sym));
+    }
+}
+
+#[cfg(feature = "std")]
+pub unsafe fn diagnose(_what: ResolveWhat<'_>) -> Option<super::ResolveError> {
+    None
+}
+
+pub unsafe fn find_module(addr: *mut c_void, cb: &mut dyn FnMut(&super::ModuleInfo<'_>)) {
+    let dbghelp = match dbghelp::init() {
+        Ok(dbghelp) => dbghelp,
+        Err(()) => return,
+    };
+    let base = dbghelp.SymGetModuleBase64()(dbghelp.process(), addr as DWORD64) as usize;
+    if base == 0 {
+        return;
+    }
+
+    let name = module_file_name(base as HMODULE).unwrap_or_default();
+    let name = &name[..];
+
+    // Debug info refers to the "image base" stated in the PE headers rather
+    // than where the module was actually loaded.
+    let svma = image_base(base)
+        .unwrap_or(base)
+        .wrapping_add(addr as usize - base);
+    let build_id = pe_build_id(base);
+    cb(&super::ModuleInfo {
+        name: BytesOrWideString::Wide(name),
+        base,
+        svma,
+        build_id: Some(&build_id),
+    })
+}
+
+pub unsafe fn find_module_by_build_id(build_id: &[u8]) -> Option<usize> {
+    struct Search<'a> {
+        build_id: &'a [u8],
+        base: Option<usize>,
+    }
+
+    extern "system" fn callback(_: PCWSTR, base: DWORD64, _: ULONG, context: PVOID) -> BOOL {
+        let search = unsafe { &mut *context.cast::<Search<'_>>() };
+        let base = base as usize;
+        if unsafe { pe_build_id(base) }[..] == *search.build_id {
+            search.base = Some(base);
+            return FALSE;
+        }
+        TRUE
+    }
+
+    let dbghelp = dbghelp::init().ok()?;
+    let mut search = Search {
+        build_id,
+        base: None,
+    };
+    dbghelp.EnumerateLoadedModulesW64()(
+        dbghelp.process(),
+        Some(callback),
+        ptr::addr_of_mut!(search).cast::<c_void>(),
+    );
+    search.base
+}
+
+// Symbol servers identify a build of a PE module through the timestamp of the
+// file header and the size of the image, so do the same.
+unsafe fn pe_build_id(base: usize) -> [u8; 8] {
+    let e_lfanew = ptr::read_unaligned((base + 0x3c) as *const u32) as usize;
+    let file_header = base + e_lfanew + 4;
+    let optional_header = file_header + 20;
+    let timestamp = ptr::read_unaligned((file_header + 4) as *const u32);
+    let size_of_image = ptr::read_unaligned((optional_header + 56) as *const u32);
+    let mut id = [0; 8];
+    id[..4].copy_from_slice(&timestamp.to_le_bytes());
+    id[4..].copy_from_slice(&size_of_image.to_le_bytes());
+    id
+}
+
+// Reads the preferred image base out of the headers of a loaded PE module.
+unsafe fn image_base(base: usize) -> Option<usize> {
+    const IMAGE_NT_OPTIONAL_HDR32_MAGIC: u16 = 0x10b;
+    const IMAGE_NT_OPTIONAL_HDR64_MAGIC: u16 = 0x20b;
+
+    let e_lfanew = ptr::read_unaligned((base + 0x3c) as *const u32) as usize;
+    // Skip the `PE\0\0` signature and the file header.
+    let optional_header = base + e_lfanew + 4 + 20;
+    match ptr::read_unaligned(optional_header as *const u16) {
+        IMAGE_NT_OPTIONAL_HDR32_MAGIC => {
+            Some(ptr::read_unaligned((optional_header + 28) as *const u32) as usize)
+        }
+        IMAGE_NT_OPTIONAL_HDR64_MAGIC => {
+            Some(ptr::read_unaligned((optional_header + 24) as *const u64) as usize)
+        }
+        _ => None,
+    }
+}
+
 #[cfg(feature = "std")]
 unsafe fn cache(filename: Option<*const [u16]>) -> Option<::std::ffi::OsString> {
     use std::os::windows::ffi::OsStringExt;
@@ -287,4 +443,91 @@ unsafe fn cache(filename: Option<*const [u16]>) -> Option<::std::ffi::OsString>
 #[cfg(not(feature = "std"))]
 unsafe fn cache(_filename: Option<*const [u16]>) {}
 
+pub unsafe fn module_symbols(_addr: usize, _cb: &mut dyn FnMut(&super::ModuleSymbol<'_>)) {}
+
+pub unsafe fn find_module_symbol<'a>(
+    _addr: usize,
+    _name: &'a [u8],
+) -> Option<super::ModuleSymbol<'a>> {
+    None
+}
+
+pub unsafe fn dynamic_symbol_count(_addr: usize) -> Option<usize> {
+    None
+}
+
 pub unsafe fn clear_symbol_cache() {}
+
+pub unsafe fn refresh_libraries() {}
+
+/// See `super::Symbolizer`, which has nothing of its own to cache here.
+#[cfg(feature = "std")]
+pub struct Symbolizer;
+
+#[cfg(feature = "std")]
+impl Symbolizer {
+    pub fn new() -> Symbolizer {
+        Symbolizer
+    }
+
+    pub fn set_cache_size(&mut self, _size: usize) {}
+
+    pub fn clear_cache(&mut self) {}
+
+    pub unsafe fn resolve(&mut self, what: ResolveWhat<'_>, cb: &mut dyn FnMut(&super::Symbol)) {
+        resolve(what, cb)
+    }
+}
+
+/// See `super::ObjectSymbolizer`, which can't be created here.
+#[cfg(feature = "std")]
+pub enum ObjectSymbolizer {}
+
+#[cfg(feature = "std")]
+impl ObjectSymbolizer {
+    pub fn new(_data: &[u8]) -> Option<ObjectSymbolizer> {
+        None
+    }
+
+    pub fn open(_path: &std::path::Path) -> Option<ObjectSymbolizer> {
+        None
+    }
+
+    pub fn from_process(_pid: u32, _base: u64, _size: u64) -> Option<(ObjectSymbolizer, u64)> {
+        None
+    }
+
+    pub fn image(&self) -> Option<(u64, Option<std::vec::Vec<u8>>)> {
+        match *self {}
+    }
+
+    pub fn resolve(&mut self, _svma: u64, _cb: &mut dyn FnMut(&super::Symbol)) {
+        match *self {}
+    }
+}
+
+pub unsafe fn preopen() {
+    // Symbols of modules are loaded lazily by dbghelp itself, so the best we
+    // can do is to load and initialize dbghelp.dll.
+    if dbghelp::init().is_err() {
+        super::diagnostics::report(format_args!("couldn't initialize dbghelp.dll"));
+    }
+}
+
+#[cfg(feature = "std")]
+pub unsafe fn files() -> std::vec::Vec<std::path::PathBuf> {
+    std::vec::Vec::new()
+}
+
+#[cfg(feature = "std")]
+pub unsafe fn stats(_stats: &mut super::SymbolizerStats) {}
+
+#[cfg(feature = "std")]
+pub unsafe fn dump_symbol_map() -> Option<std::vec::Vec<u8>> {
+    None
+}
+
+#[cfg(feature = "std")]
+pub fn load_symbol_map(_data: &[u8]) -> Option<bool> {
+    None
+}
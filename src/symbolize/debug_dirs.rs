@@ -0,0 +1,162 @@
+use std::env;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::prelude::v1::*;
+use std::sync::Mutex;
+
+static PATHS: Mutex<Option<DebugSearchPaths>> = Mutex::new(None);
+
+/// The environment variable listing global debug directories, which
+/// `DebugSearchPaths::from_env` reads.
+const GLOBAL_DIRS_VAR: &str = "BACKTRACE_DEBUG_DIRS";
+
+/// The environment variable listing per-module debug directories, which
+/// `DebugSearchPaths::from_env` reads.
+const RELATIVE_DIRS_VAR: &str = "BACKTRACE_DEBUG_RELATIVE_DIRS";
+
+/// The directories searched for separate debug information files, as
+/// configured with `set_debug_search_paths`.
+///
+/// Debug files of ELF binaries are first looked for by build ID, as
+/// `.build-id/xx/yyyy.debug` in each global directory. The file named in the
+/// `.gnu_debuglink` section of a binary is then looked for in the directory
+/// of the binary, in each relative directory under it, and in each global
+/// directory under the absolute path of the directory of the binary, like gdb
+/// does with its `debug-file-directory` setting. Binaries without debug
+/// information of their own are finally looked up by their path, under their
+/// own name in each relative directory and as their absolute path with
+/// `.debug` appended in each global directory. Directories are tried in the
+/// order given, and the first file found is used if it matches the binary.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DebugSearchPaths {
+    global_dirs: Vec<PathBuf>,
+    relative_dirs: Vec<PathBuf>,
+}
+
+impl Default for DebugSearchPaths {
+    fn default() -> DebugSearchPaths {
+        DebugSearchPaths::new()
+    }
+}
+
+impl DebugSearchPaths {
+    /// Creates the default search paths, which are the ones of most Linux
+    /// distributions: the global directory `/usr/lib/debug` on Linux,
+    /// FreeBSD and GNU/Hurd, and the relative directory `.debug`.
+    pub fn new() -> DebugSearchPaths {
+        let global_dirs = if cfg!(any(
+            target_os = "freebsd",
+            target_os = "hurd",
+            target_os = "linux"
+        )) {
+            vec![PathBuf::from("/usr/lib/debug")]
+        } else {
+            Vec::new()
+        };
+        DebugSearchPaths {
+            global_dirs,
+            relative_dirs: vec![PathBuf::from(".debug")],
+        }
+    }
+
+    /// Creates the default search paths, with the lists replaced by the ones
+    /// in the `BACKTRACE_DEBUG_DIRS` and `BACKTRACE_DEBUG_RELATIVE_DIRS`
+    /// environment variables if they're set.
+    ///
+    /// The variables list directories separated like `PATH`, with `:` on
+    /// Unix, and an empty variable searches no directories of its kind. These
+    /// are the search paths used until `set_debug_search_paths` is called.
+    pub fn from_env() -> DebugSearchPaths {
+        let mut paths = DebugSearchPaths::new();
+        if let Some(dirs) = env::var_os(GLOBAL_DIRS_VAR) {
+            paths = paths.global_dirs(split_dirs(&dirs));
+        }
+        if let Some(dirs) = env::var_os(RELATIVE_DIRS_VAR) {
+            paths = paths.relative_dirs(split_dirs(&dirs));
+        }
+        paths
+    }
+
+    /// Searches the global debug directories `dirs`, in order, instead of the
+    /// current ones.
+    ///
+    /// These hold the debug files of all binaries, by build ID and by the
+    /// absolute paths of the binaries, like `/usr/lib/debug` or the
+    /// directories of `NIX_DEBUG_INFO_DIRS` on NixOS.
+    pub fn global_dirs<I>(mut self, dirs: I) -> DebugSearchPaths
+    where
+        I: IntoIterator,
+        I::Item: AsRef<Path>,
+    {
+        self.global_dirs = dirs.into_iter().map(|d| d.as_ref().into()).collect();
+        self
+    }
+
+    /// Searches the directories `dirs`, in order and relative to the
+    /// directory of each binary, instead of the current ones.
+    ///
+    /// These hold the debug files of the binaries next to them, like the
+    /// `.debug` directories some build systems put them in.
+    pub fn relative_dirs<I>(mut self, dirs: I) -> DebugSearchPaths
+    where
+        I: IntoIterator,
+        I::Item: AsRef<Path>,
+    {
+        self.relative_dirs = dirs.into_iter().map(|d| d.as_ref().into()).collect();
+        self
+    }
+}
+
+/// Splits a list of directories from an environment variable, leaving out
+/// empty entries.
+fn split_dirs(dirs: &OsStr) -> Vec<PathBuf> {
+    env::split_paths(dirs)
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .collect()
+}
+
+/// Sets where separate debug information files are searched for from now on.
+///
+/// Without this, debug files are searched for in the directories of
+/// `DebugSearchPaths::from_env`, which are those of most Linux distributions
+/// unless the environment says otherwise. Systems which keep debug files
+/// elsewhere, like NixOS, or build systems which put them next to the
+/// binaries they build can point this at their layout.
+///
+/// Debug files already loaded for the symbol cache aren't looked for again,
+/// so this is best called before resolving any symbols, or followed by
+/// `clear_symbol_cache`. This only has an effect for ELF binaries, on
+/// platforms where symbols are resolved with the `gimli` crate.
+///
+/// # Example
+///
+/// ```
+/// extern crate backtrace;
+///
+/// use backtrace::DebugSearchPaths;
+///
+/// fn main() {
+///     backtrace::set_debug_search_paths(
+///         DebugSearchPaths::from_env()
+///             .global_dirs(["/opt/debug", "/usr/lib/debug"])
+///             .relative_dirs([".debug", "../debug"]),
+///     );
+/// }
+/// ```
+///
+/// # Required features
+///
+/// This function requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default.
+pub fn set_debug_search_paths(paths: DebugSearchPaths) {
+    *PATHS.lock().unwrap_or_else(|e| e.into_inner()) = Some(paths);
+}
+
+/// Returns the global and relative debug directories to search, see
+/// `set_debug_search_paths`.
+#[allow(dead_code)] // unused on platforms where symbols aren't resolved with gimli
+pub(crate) fn debug_dirs() -> (Vec<PathBuf>, Vec<PathBuf>) {
+    let paths = PATHS.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    let paths = paths.unwrap_or_else(DebugSearchPaths::from_env);
+    (paths.global_dirs, paths.relative_dirs)
+}
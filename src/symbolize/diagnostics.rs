@@ -0,0 +1,56 @@
+use core::fmt;
+use core::mem;
+use core::ptr;
+use core::sync::atomic::{AtomicPtr, Ordering};
+
+static HOOK: AtomicPtr<()> = AtomicPtr::new(ptr::null_mut());
+
+/// Sets a function to be called with messages about non-fatal problems
+/// encountered while resolving symbols, or removes it with `None`.
+///
+/// Resolving symbols silently moves on when it runs into problems like a
+/// separate debug info file which can't be opened, a supplementary debug info
+/// file with a mismatched build ID or a compressed section in an unsupported
+/// format, which just results in less information about symbols. Reporting
+/// those through this hook helps to find out why symbols are missing, like by
+/// forwarding them to the logging framework of an application. The messages
+/// are meant to be read by humans, and their wording isn't stable.
+///
+/// The hook may be called from any thread resolving symbols while internal
+/// locks are held, so it must not capture backtraces or resolve symbols
+/// itself.
+///
+/// Which problems are reported depends on the platform, and on platforms where
+/// symbols aren't resolved at all nothing is ever reported.
+///
+/// # Example
+///
+/// ```
+/// extern crate backtrace;
+///
+/// fn main() {
+///     backtrace::set_diagnostics_hook(Some(|message| {
+///         eprintln!("backtrace: {}", message);
+///     }));
+/// }
+/// ```
+pub fn set_diagnostics_hook(hook: Option<fn(fmt::Arguments<'_>)>) {
+    let hook = match hook {
+        Some(hook) => hook as *mut (),
+        None => ptr::null_mut(),
+    };
+    HOOK.store(hook, Ordering::Release);
+}
+
+/// Reports a non-fatal problem to the hook set with `set_diagnostics_hook`,
+/// if any.
+#[allow(dead_code)] // unused on platforms where symbols aren't resolved
+pub(crate) fn report(message: fmt::Arguments<'_>) {
+    let hook = HOOK.load(Ordering::Acquire);
+    if !hook.is_null() {
+        // SAFETY: non-null values of `HOOK` are only ever stored by
+        // `set_diagnostics_hook`, from a function pointer of this type.
+        let hook = unsafe { mem::transmute::<*mut (), fn(fmt::Arguments<'_>)>(hook) };
+        hook(message);
+    }
+}
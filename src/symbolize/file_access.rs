@@ -0,0 +1,126 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(feature = "std")]
+use core::{mem, ptr, sync::atomic::AtomicPtr};
+#[cfg(feature = "std")]
+use std::path::Path;
+
+/// How object files and debug information are read while resolving symbols,
+/// as set with `set_file_access`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FileAccess {
+    /// Map files into memory, only reading the parts which are actually used.
+    ///
+    /// This is the default. If a mapped file is truncated while it's in use,
+    /// like when it's replaced by a package upgrade or a network filesystem
+    /// misbehaves, touching the missing part kills the process with `SIGBUS`
+    /// on Unix platforms.
+    Map,
+    /// Copy the complete contents of files into memory with regular reads.
+    ///
+    /// This takes up as much memory as the files are large, but resolving
+    /// symbols can't crash the process when files change underneath it.
+    Copy,
+    /// Read only the parts of files needed to resolve symbols with regular
+    /// reads, like the headers, symbol tables and debug information of ELF
+    /// files.
+    ///
+    /// Like `Copy`, this can't crash the process when files change, while not
+    /// taking up memory for the code and data of the files. It also avoids
+    /// mapping files in environments where that's slow or unreliable, like
+    /// for large files on network shares. Files in formats other than ELF are
+    /// copied completely.
+    Read,
+}
+
+static FILE_ACCESS: AtomicUsize = AtomicUsize::new(FileAccess::Map as usize);
+
+/// Sets how object files and debug information are read while resolving
+/// symbols from now on.
+///
+/// Files which were already read for the symbol cache aren't affected, so
+/// this is best called before resolving any symbols, or followed by
+/// `clear_symbol_cache`.
+///
+/// This only has an effect on platforms where symbols are resolved with the
+/// `gimli` crate.
+pub fn set_file_access(access: FileAccess) {
+    FILE_ACCESS.store(access as usize, Ordering::Relaxed);
+}
+
+/// Returns the setting last passed to `set_file_access`.
+#[allow(dead_code)] // unused on platforms where symbols aren't resolved with gimli
+pub(crate) fn file_access() -> FileAccess {
+    match FILE_ACCESS.load(Ordering::Relaxed) {
+        n if n == FileAccess::Copy as usize => FileAccess::Copy,
+        n if n == FileAccess::Read as usize => FileAccess::Read,
+        _ => FileAccess::Map,
+    }
+}
+
+#[cfg(feature = "std")]
+static SELECT: AtomicPtr<()> = AtomicPtr::new(ptr::null_mut());
+
+/// The file access of the `Symbolizer` currently resolving symbols, if it has
+/// one, as one more than the `FileAccess` value, or zero.
+#[cfg(feature = "std")]
+static OVERRIDE: AtomicUsize = AtomicUsize::new(0);
+
+/// Runs `f` with files read according to `access` instead of the global
+/// settings, if it's not `None`.
+///
+/// This must be called with the global lock held, so no other thread reads
+/// files in the meantime.
+#[cfg(feature = "std")]
+pub(crate) fn with_file_access<R>(access: Option<FileAccess>, f: impl FnOnce() -> R) -> R {
+    // Restored even if `f` unwinds.
+    struct Restore(usize);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            OVERRIDE.store(self.0, Ordering::Relaxed);
+        }
+    }
+    let access = access.map_or(0, |access| access as usize + 1);
+    let _restore = Restore(OVERRIDE.swap(access, Ordering::Relaxed));
+    f()
+}
+
+/// Sets a function choosing how each file is read while resolving symbols,
+/// given its path, overriding `set_file_access`, or removes it with `None`.
+///
+/// This allows reading only some files differently, like those on a network
+/// share. The function is called while internal locks are held, so it must
+/// not capture backtraces or resolve symbols itself.
+///
+/// # Required features
+///
+/// This function requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default.
+#[cfg(feature = "std")]
+pub fn set_file_access_with(select: Option<fn(&Path) -> FileAccess>) {
+    let select = match select {
+        Some(select) => select as *mut (),
+        None => ptr::null_mut(),
+    };
+    SELECT.store(select, Ordering::Release);
+}
+
+/// Returns how the file at `path` is to be read.
+#[cfg(feature = "std")]
+#[allow(dead_code)] // unused on platforms where symbols aren't resolved with gimli
+pub(crate) fn file_access_for(path: &Path) -> FileAccess {
+    match OVERRIDE.load(Ordering::Relaxed) {
+        0 => {}
+        n if n == FileAccess::Copy as usize + 1 => return FileAccess::Copy,
+        n if n == FileAccess::Read as usize + 1 => return FileAccess::Read,
+        _ => return FileAccess::Map,
+    }
+    let select = SELECT.load(Ordering::Acquire);
+    if select.is_null() {
+        return file_access();
+    }
+    // SAFETY: non-null values of `SELECT` are only ever stored by
+    // `set_file_access_with`, from a function pointer of this type.
+    let select = unsafe { mem::transmute::<*mut (), fn(&Path) -> FileAccess>(select) };
+    select(path)
+}
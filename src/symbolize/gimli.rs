@@ -3,17 +3,24 @@
 //! This is the default symbolication implementation for Rust.
 
 use self::gimli::read::EndianSlice;
-use self::gimli::NativeEndian as Endian;
+use self::gimli::RunTimeEndian as Endian;
 use self::mmap::Mmap;
+use self::scratch::Scratch;
 use self::stash::Stash;
+use super::diagnostics;
+use super::sources::{self, ResolveMode, SymbolSource};
 use super::BytesOrWideString;
+use super::FileAccess;
 use super::ResolveWhat;
 use super::SymbolName;
+use super::{SymbolBinding, SymbolVersion, SymbolVisibility};
 use addr2line::gimli;
 use core::convert::TryInto;
 use core::mem;
+use core::sync::atomic::{AtomicBool, Ordering};
 use core::u32;
 use libc::c_void;
+use mystd::borrow::Cow;
 use mystd::ffi::OsString;
 use mystd::fs::File;
 use mystd::path::Path;
@@ -53,14 +60,46 @@ cfg_if::cfg_if! {
     }
 }
 
+#[cfg(not(feature = "minimal-symbolication"))]
+mod names;
+mod scratch;
 mod stash;
 
 const MAPPINGS_CACHE_SIZE: usize = 4;
 
+/// How many unloaded libraries are remembered, see `Cache::refresh`.
+const MAX_UNLOADED_LIBRARIES: usize = 64;
+
+// A very small, very simple LRU cache for debug info mappings.
+//
+// The hit rate should be very high, since the typical stack doesn't cross
+// between many shared libraries.
+//
+// The `addr2line::Context` structures are pretty expensive to create. Its
+// cost is expected to be amortized by subsequent `locate` queries, which
+// leverage the structures built when constructing `addr2line::Context`s to
+// get nice speedups. If we didn't have this cache, that amortization would
+// never happen, and symbolicating backtraces would be ssssllllooooowwww.
+static mut MAPPINGS_CACHE: Option<Cache> = None;
+
+/// Whether `MAPPINGS_CACHE` is borrowed by a call further up the stack, see
+/// `Cache::with_global`.
+static GLOBAL_IN_USE: AtomicBool = AtomicBool::new(false);
+
+/// Whether the mappings of all libraries of the global cache were created up
+/// front by `preopen`, in which case no more files may be opened for it until
+/// it's cleared.
+static PREOPENED: AtomicBool = AtomicBool::new(false);
+
+/// Paths of all files opened by `mmap` while `files` is running.
+#[cfg(feature = "std")]
+static OPENED_FILES: mystd::sync::Mutex<Option<Vec<mystd::path::PathBuf>>> =
+    mystd::sync::Mutex::new(None);
+
 struct Mapping {
     // 'static lifetime is a lie to hack around lack of support for self-referential structs.
     cx: Context<'static>,
-    _map: Mmap,
+    map: Mmap,
     stash: Stash,
 }
 
@@ -84,6 +123,28 @@ impl Mapping {
         })
     }
 
+    /// Creates a `Mapping` from `data` in any format the `object` crate can
+    /// read, without looking for separate debug information.
+    #[cfg(feature = "std")]
+    fn new_generic(data: Mmap) -> Option<Mapping> {
+        Mapping::mk(data, |data, stash| {
+            let object = generic::Object::parse(data)?;
+            Context::new(stash, AnyObject::Generic(Box::new(object)), None, None)
+        })
+    }
+
+    /// Returns the number of bytes of object files and debug information
+    /// held by this mapping.
+    fn size(&self) -> usize {
+        self.map.len() + self.stash.size()
+    }
+
+    fn has_debuginfo(&self) -> bool {
+        // Like in `Cache::mapping_for_lib`, don't leak the `'static` lifetime.
+        let cx = unsafe { mem::transmute::<&Context<'static>, &Context<'_>>(&self.cx) };
+        cx.has_debuginfo(&self.stash)
+    }
+
     /// Creates a `Mapping` from `data`, or if the closure decides to, returns a
     /// different mapping.
     fn mk_or_other<F>(data: Mmap, mk: F) -> Option<Mapping>
@@ -99,7 +160,7 @@ impl Mapping {
             // Convert to 'static lifetimes since the symbols should
             // only borrow `map` and `stash` and we're preserving them below.
             cx: unsafe { core::mem::transmute::<Context<'_>, Context<'static>>(cx) },
-            _map: data,
+            map: data,
             stash: stash,
         })
     }
@@ -107,54 +168,282 @@ impl Mapping {
 
 struct Context<'a> {
     dwarf: addr2line::Context<EndianSlice<'a, Endian>>,
-    object: Object<'a>,
+    #[cfg(not(feature = "minimal-symbolication"))]
+    names: names::Names<'a>,
+    object: AnyObject<'a>,
+    #[cfg(not(feature = "minimal-symbolication"))]
     package: Option<gimli::DwarfPackage<EndianSlice<'a, Endian>>>,
 }
 
+/// Finds the symbol named `name` by going through all symbols passed by
+/// `for_each_symbol`, for object files without a faster way to look up names.
+#[cfg_attr(not(feature = "std"), allow(dead_code))] // unused for ELF without `std`
+fn scan_symbols<'a>(
+    name: &[u8],
+    for_each_symbol: impl FnOnce(&mut dyn FnMut(&'a [u8], u64, Option<u64>)),
+) -> Option<(u64, Option<u64>)> {
+    let mut found = None;
+    for_each_symbol(&mut |sym, addr, size| {
+        if found.is_none() && sym == name {
+            found = Some((addr, size));
+        }
+    });
+    found
+}
+
+/// Merges the symbols `dynamic_syms` of a dynamic symbol table into `syms` of
+/// the full symbol table, adding those at addresses which `syms` has nothing
+/// for, and sorts the result by `address`.
+#[cfg(any(
+    feature = "std",
+    not(any(windows, target_vendor = "apple", target_os = "aix"))
+))]
+fn merge_dynamic_syms<T>(syms: &mut Vec<T>, dynamic_syms: Vec<T>, address: impl Fn(&T) -> u64) {
+    if syms.is_empty() {
+        *syms = dynamic_syms;
+        return;
+    }
+    let len = syms.len();
+    for sym in dynamic_syms {
+        if syms[..len]
+            .binary_search_by_key(&address(&sym), &address)
+            .is_err()
+        {
+            syms.push(sym);
+        }
+    }
+    syms.sort_by_key(address);
+}
+
+/// The object file of a `Context`, either in the native format of the current
+/// platform or in any format the `object` crate can read.
+// Native objects are by far the most common, so they aren't boxed.
+#[allow(clippy::large_enum_variant)]
+enum AnyObject<'a> {
+    Native(Object<'a>),
+    #[cfg(feature = "std")]
+    Generic(Box<generic::Object<'a>>),
+    /// A digest of the debug information and symbol table of an object file,
+    /// loaded from the directory set with `set_symbol_cache_dir`.
+    #[cfg(feature = "std")]
+    Digest(digest::Digest<'a>),
+}
+
+impl<'a> AnyObject<'a> {
+    /// Returns the native object, for format-specific lookups.
+    #[cfg_attr(not(target_vendor = "apple"), allow(dead_code))]
+    fn native(&self) -> Option<&Object<'a>> {
+        match self {
+            AnyObject::Native(object) => Some(object),
+            #[cfg(feature = "std")]
+            AnyObject::Generic(_) => None,
+            #[cfg(feature = "std")]
+            AnyObject::Digest(_) => None,
+        }
+    }
+
+    /// Returns the byte order of this object, which native ones share with the
+    /// current platform.
+    fn endian(&self) -> Endian {
+        match self {
+            AnyObject::Native(_) => Endian::default(),
+            #[cfg(feature = "std")]
+            AnyObject::Generic(object) => object.endian(),
+            #[cfg(feature = "std")]
+            AnyObject::Digest(_) => Endian::default(),
+        }
+    }
+
+    /// Tests whether DWARF sections of this object use the names of XCOFF
+    /// rather than the usual ones.
+    fn is_xcoff(&self) -> bool {
+        match self {
+            AnyObject::Native(_) => cfg!(target_os = "aix"),
+            #[cfg(feature = "std")]
+            AnyObject::Generic(object) => object.is_xcoff(),
+            #[cfg(feature = "std")]
+            AnyObject::Digest(_) => false,
+        }
+    }
+
+    fn section(&self, stash: &'a Stash, name: &str) -> Option<&'a [u8]> {
+        match self {
+            AnyObject::Native(object) => object.section(stash, name),
+            #[cfg(feature = "std")]
+            AnyObject::Generic(object) => object.section(stash, name),
+            #[cfg(feature = "std")]
+            AnyObject::Digest(_) => None,
+        }
+    }
+
+    fn for_each_symbol(&self, cb: &mut dyn FnMut(&'a [u8], u64, Option<u64>)) {
+        match self {
+            AnyObject::Native(object) => object.for_each_symbol(cb),
+            #[cfg(feature = "std")]
+            AnyObject::Generic(object) => object.for_each_symbol(cb),
+            #[cfg(feature = "std")]
+            AnyObject::Digest(digest) => digest.for_each_symbol(cb),
+        }
+    }
+
+    fn search_symtab(&self, addr: u64) -> Option<&[u8]> {
+        match self {
+            AnyObject::Native(object) => object.search_symtab(addr),
+            #[cfg(feature = "std")]
+            AnyObject::Generic(object) => object.search_symtab(addr),
+            #[cfg(feature = "std")]
+            AnyObject::Digest(digest) => digest.search_symtab(addr),
+        }
+    }
+
+    fn find_symbol(&self, name: &[u8]) -> Option<(u64, Option<u64>)> {
+        match self {
+            AnyObject::Native(object) => object.find_symbol(name),
+            #[cfg(feature = "std")]
+            AnyObject::Generic(object) => scan_symbols(name, |cb| object.for_each_symbol(cb)),
+            #[cfg(feature = "std")]
+            AnyObject::Digest(digest) => scan_symbols(name, |cb| digest.for_each_symbol(cb)),
+        }
+    }
+
+    fn dynamic_symbol_count(&self) -> Option<usize> {
+        match self {
+            AnyObject::Native(object) => object.dynamic_symbol_count(),
+            #[cfg(feature = "std")]
+            AnyObject::Generic(_) => None,
+            #[cfg(feature = "std")]
+            AnyObject::Digest(_) => None,
+        }
+    }
+
+    fn symtab_info(&self, addr: u64) -> Option<SymtabInfo<'a>> {
+        match self {
+            AnyObject::Native(object) => object.symtab_info(addr),
+            #[cfg(feature = "std")]
+            AnyObject::Generic(_) => None,
+            #[cfg(feature = "std")]
+            AnyObject::Digest(digest) => digest.symtab_info(addr),
+        }
+    }
+
+    fn function_start(&self, addr: u64) -> Option<u64> {
+        match self {
+            AnyObject::Native(object) => object.function_start(addr),
+            #[cfg(feature = "std")]
+            AnyObject::Generic(object) => object.function_start(addr),
+            #[cfg(feature = "std")]
+            AnyObject::Digest(digest) => digest.function_start(addr),
+        }
+    }
+
+    /// Calls `cb` with the start and end of each function found in unwinding
+    /// information, for objects which `function_start` is used for.
+    #[cfg(feature = "std")]
+    fn for_each_function(&self, cb: &mut dyn FnMut(u64, u64)) {
+        match self {
+            AnyObject::Native(object) => object.for_each_function(cb),
+            AnyObject::Generic(object) => object.for_each_function(cb),
+            AnyObject::Digest(digest) => digest.for_each_function(cb),
+        }
+    }
+
+    /// Calls `cb` with the address and size of each symbol whose debug
+    /// information is in an object file, see `search_object_map`.
+    #[cfg(feature = "std")]
+    fn for_each_object_map_symbol(&self, cb: &mut dyn FnMut(u64, u64)) {
+        match self {
+            AnyObject::Native(object) => object.for_each_object_map_symbol(cb),
+            AnyObject::Generic(_) | AnyObject::Digest(_) => {}
+        }
+    }
+
+    fn search_object_map(&mut self, addr: u64, open: bool) -> Option<(&Context<'_>, u64)> {
+        match self {
+            AnyObject::Native(object) => object.search_object_map(addr, open),
+            #[cfg(feature = "std")]
+            AnyObject::Generic(object) => object.search_object_map(addr, open),
+            #[cfg(feature = "std")]
+            AnyObject::Digest(_) => None,
+        }
+    }
+}
+
+impl<'a> From<Object<'a>> for AnyObject<'a> {
+    fn from(object: Object<'a>) -> AnyObject<'a> {
+        AnyObject::Native(object)
+    }
+}
+
 impl<'data> Context<'data> {
     fn new(
         stash: &'data Stash,
-        object: Object<'data>,
+        object: impl Into<AnyObject<'data>>,
         sup: Option<Object<'data>>,
         dwp: Option<Object<'data>>,
     ) -> Option<Context<'data>> {
-        let mut sections = gimli::Dwarf::load(|id| -> Result<_, ()> {
-            if cfg!(not(target_os = "aix")) {
-                let data = object.section(stash, id.name()).unwrap_or(&[]);
-                Ok(EndianSlice::new(data, Endian))
+        let object = object.into();
+        let endian = object.endian();
+        // Don't bother loading DWARF sections, which may have to be
+        // decompressed, if they won't be used anyway.
+        let debuginfo = sources::uses_source(SymbolSource::DebugInfo);
+        // Sections over the limit are left out as if they were missing.
+        let limit = |name: &str, data: Option<&'data [u8]>| {
+            data.filter(|data| section_size_allowed(name, data.len()))
+                .unwrap_or(&[])
+        };
+        let sections = gimli::DwarfSections::load(|id| -> Result<_, ()> {
+            if !debuginfo {
+                Ok(EndianSlice::new(&[], endian))
+            } else if !object.is_xcoff() {
+                let data = limit(id.name(), object.section(stash, id.name()));
+                Ok(EndianSlice::new(data, endian))
             } else {
                 if let Some(name) = id.xcoff_name() {
-                    let data = object.section(stash, name).unwrap_or(&[]);
-                    Ok(EndianSlice::new(data, Endian))
+                    let data = limit(name, object.section(stash, name));
+                    Ok(EndianSlice::new(data, endian))
                 } else {
-                    Ok(EndianSlice::new(&[], Endian))
+                    Ok(EndianSlice::new(&[], endian))
                 }
             }
         })
         .ok()?;
-
-        if let Some(sup) = sup {
-            sections
-                .load_sup(|id| -> Result<_, ()> {
-                    let data = sup.section(stash, id.name()).unwrap_or(&[]);
-                    Ok(EndianSlice::new(data, Endian))
+        let sup_sections = match sup.filter(|_| debuginfo) {
+            Some(sup) => Some(
+                gimli::DwarfSections::load(|id| -> Result<_, ()> {
+                    let data = limit(id.name(), sup.section(stash, id.name()));
+                    Ok(EndianSlice::new(data, endian))
                 })
-                .ok()?;
-        }
-        let dwarf = addr2line::Context::from_dwarf(sections).ok()?;
+                .ok()?,
+            ),
+            None => None,
+        };
+        // Both `addr2line` and the lookup of names only need their own view of
+        // the same sections.
+        let borrow = || match &sup_sections {
+            Some(sup_sections) => sections.borrow_with_sup(sup_sections, |section| *section),
+            None => sections.borrow(|section| *section),
+        };
+        let dwarf = addr2line::Context::from_dwarf(borrow()).ok()?;
+        #[cfg(not(feature = "minimal-symbolication"))]
+        let names = names::Names::new(borrow());
 
+        // DWARF packages are only needed to look up functions.
+        #[cfg(feature = "minimal-symbolication")]
+        let _ = dwp;
+        #[cfg(not(feature = "minimal-symbolication"))]
         let mut package = None;
-        if let Some(dwp) = dwp {
+        #[cfg(not(feature = "minimal-symbolication"))]
+        if let Some(dwp) = dwp.filter(|_| debuginfo) {
             package = Some(
                 gimli::DwarfPackage::load(
                     |id| -> Result<_, gimli::Error> {
-                        let data = id
-                            .dwo_name()
-                            .and_then(|name| dwp.section(stash, name))
-                            .unwrap_or(&[]);
-                        Ok(EndianSlice::new(data, Endian))
+                        let data = match id.dwo_name() {
+                            Some(name) => limit(name, dwp.section(stash, name)),
+                            None => &[],
+                        };
+                        Ok(EndianSlice::new(data, endian))
                     },
-                    EndianSlice::new(&[], Endian),
+                    EndianSlice::new(&[], endian),
                 )
                 .ok()?,
             );
@@ -162,52 +451,297 @@ impl<'data> Context<'data> {
 
         Some(Context {
             dwarf,
+            #[cfg(not(feature = "minimal-symbolication"))]
+            names,
             object,
+            #[cfg(not(feature = "minimal-symbolication"))]
             package,
         })
     }
 
-    fn find_frames(
-        &'_ self,
+    /// Finds the frames of the function containing `probe` and of the
+    /// functions inlined into it there, passing the name and location of each
+    /// to `cb` from the innermost one outwards.
+    ///
+    /// With the `minimal-symbolication` feature only the line table is used,
+    /// which finds a single frame without a name.
+    fn find_frames<'s>(
+        &'s self,
         stash: &'data Stash,
         probe: u64,
-    ) -> gimli::Result<addr2line::FrameIter<'_, EndianSlice<'data, Endian>>> {
-        use addr2line::{LookupContinuation, LookupResult};
-
-        let mut l = self.dwarf.find_frames(probe);
-        loop {
-            let (load, continuation) = match l {
-                LookupResult::Output(output) => break output,
-                LookupResult::Load { load, continuation } => (load, continuation),
+        cb: &mut dyn FnMut(Option<&'data [u8]>, Option<addr2line::Location<'s>>),
+    ) {
+        #[cfg(feature = "minimal-symbolication")]
+        {
+            let _ = stash;
+            if let Ok(Some(location)) = self.dwarf.find_location(probe) {
+                cb(None, Some(location));
+            }
+        }
+        #[cfg(not(feature = "minimal-symbolication"))]
+        {
+            use addr2line::{LookupContinuation, LookupResult};
+
+            let mut l = self.dwarf.find_frames(probe);
+            let mut frames = loop {
+                let (load, continuation) = match l {
+                    LookupResult::Output(Ok(output)) => break output,
+                    LookupResult::Output(Err(_)) => return,
+                    LookupResult::Load { load, continuation } => (load, continuation),
+                };
+
+                // Split DWARF is loaded lazily, which isn't allowed anymore
+                // after `preopen`.
+                let dwarf = match stash.is_sealed() {
+                    true => None,
+                    false => handle_split_dwarf(self.package.as_ref(), stash, load),
+                };
+                l = continuation.resume(dwarf);
             };
+            while let Ok(Some(frame)) = frames.next() {
+                cb(frame.function.map(|f| f.name.slice()), frame.location);
+            }
+        }
+    }
 
-            l = continuation.resume(handle_split_dwarf(self.package.as_ref(), stash, load));
+    /// Tests whether the object has DWARF debug information, as opposed to
+    /// just a symbol table.
+    fn has_debuginfo(&self, stash: &'data Stash) -> bool {
+        #[cfg(feature = "std")]
+        if let AnyObject::Digest(digest) = &self.object {
+            return digest.has_debuginfo();
         }
+        self.object.section(stash, ".debug_info").is_some()
     }
 }
 
 fn mmap(path: &Path) -> Option<Mmap> {
-    let file = File::open(path).ok()?;
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) => {
+            // Places which are merely probed for debug info check whether
+            // files exist first, so anything else is worth reporting.
+            diagnostics::report(format_args!("couldn't open `{}`: {}", path.display(), e));
+            return None;
+        }
+    };
+    #[cfg(feature = "std")]
+    if let Ok(mut opened) = OPENED_FILES.lock() {
+        if let Some(opened) = opened.as_mut() {
+            opened.push(path.to_path_buf());
+        }
+    }
     let len = file.metadata().ok()?.len().try_into().ok()?;
-    unsafe { Mmap::map(&file, len) }
+    match file_access(path) {
+        FileAccess::Map => unsafe { Mmap::map(&file, len) },
+        FileAccess::Copy => unsafe { Mmap::read(&file, len) },
+        FileAccess::Read => read_needed(&file, len),
+    }
+}
+
+/// Returns how the file at `path` is accessed, see `set_file_access`.
+fn file_access(path: &Path) -> FileAccess {
+    #[cfg(feature = "std")]
+    return super::file_access::file_access_for(path);
+    #[cfg(not(feature = "std"))]
+    {
+        let _ = path;
+        super::file_access::file_access()
+    }
+}
+
+/// Reads the parts of `file`, which is `len` bytes large, needed to resolve
+/// symbols into an otherwise zeroed buffer of the same size.
+fn read_needed(file: &File, len: usize) -> Option<Mmap> {
+    let mut map = Mmap::zeroed(len)?;
+    #[cfg(not(any(windows, target_vendor = "apple", target_os = "aix")))]
+    if elf::read_needed(file, &mut map).is_some() {
+        return Some(map);
+    }
+    map.read_at(file, 0, len)?;
+    Some(map)
+}
+
+/// Decompresses the zlib stream `input` of the section `name` into memory
+/// allocated in `stash`, where `size` is the size of the decompressed data.
+#[cfg(any(
+    feature = "std",
+    not(any(windows, target_vendor = "apple", target_os = "aix"))
+))]
+fn decompress_section<'a>(
+    stash: &'a Stash,
+    name: &str,
+    input: &[u8],
+    size: usize,
+) -> Option<&'a [u8]> {
+    // The size comes from the file, so check it before allocating.
+    if !section_size_allowed(name, size) {
+        return None;
+    }
+    let output = stash.allocate(size);
+    if decompress_zlib(input, output).is_none() {
+        diagnostics::report(format_args!("couldn't decompress section `{}`", name));
+        return None;
+    }
+    Some(output)
+}
+
+/// Tests whether a section of `size` bytes is within the limit set with
+/// `set_parse_limits`, reporting the section `name` if it isn't.
+fn section_size_allowed(name: &str, size: usize) -> bool {
+    let max = super::parse_limits::max_section_size();
+    if size > max {
+        diagnostics::report(format_args!(
+            "section `{}` is larger than the limit of {} bytes",
+            name, max
+        ));
+        return false;
+    }
+    true
+}
+
+#[cfg(any(
+    feature = "std",
+    not(any(windows, target_vendor = "apple", target_os = "aix"))
+))]
+fn decompress_zlib(input: &[u8], output: &mut [u8]) -> Option<()> {
+    use miniz_oxide::inflate::core::inflate_flags::{
+        TINFL_FLAG_PARSE_ZLIB_HEADER, TINFL_FLAG_USING_NON_WRAPPING_OUTPUT_BUF,
+    };
+    use miniz_oxide::inflate::core::{decompress, DecompressorOxide};
+    use miniz_oxide::inflate::TINFLStatus;
+
+    let (status, in_read, out_read) = decompress(
+        &mut DecompressorOxide::new(),
+        input,
+        output,
+        0,
+        TINFL_FLAG_USING_NON_WRAPPING_OUTPUT_BUF | TINFL_FLAG_PARSE_ZLIB_HEADER,
+    );
+    if status == TINFLStatus::Done && in_read == input.len() && out_read == output.len() {
+        Some(())
+    } else {
+        None
+    }
+}
+
+/// A section of an object file along with its stated virtual memory address.
+#[cfg(any(
+    feature = "std",
+    not(any(windows, target_vendor = "apple", target_os = "aix"))
+))]
+struct LoadedSection<'a> {
+    data: &'a [u8],
+    address: u64,
+}
+
+/// Finds the start of the function containing the stated virtual memory
+/// address `addr` in the binary search table of `.eh_frame_hdr`, which is
+/// there for unwinding even in binaries stripped of all symbols.
+#[cfg(any(
+    feature = "std",
+    not(any(windows, target_vendor = "apple", target_os = "aix"))
+))]
+fn eh_frame_function_start(
+    endian: Endian,
+    address_size: u8,
+    eh_frame_hdr: LoadedSection<'_>,
+    eh_frame: LoadedSection<'_>,
+    addr: u64,
+) -> Option<u64> {
+    use gimli::{BaseAddresses, EhFrame, EhFrameHdr, UnwindSection};
+
+    let bases = BaseAddresses::default()
+        .set_eh_frame_hdr(eh_frame_hdr.address)
+        .set_eh_frame(eh_frame.address);
+    let eh_frame_hdr = EhFrameHdr::new(eh_frame_hdr.data, endian)
+        .parse(&bases, address_size)
+        .ok()?;
+    let mut eh_frame = EhFrame::new(eh_frame.data, endian);
+    eh_frame.set_address_size(address_size);
+    // The table only records where functions start, so check that `addr` is
+    // actually covered by the entry found rather than in a gap after it.
+    let fde = eh_frame_hdr
+        .table()?
+        .fde_for_address(&eh_frame, &bases, addr, EhFrame::cie_from_offset)
+        .ok()?;
+    Some(fde.initial_address())
+}
+
+/// Calls `cb` with the start and end of each function in the binary search
+/// table of `.eh_frame_hdr`, in order of increasing address, see
+/// `eh_frame_function_start`.
+#[cfg(feature = "std")]
+fn eh_frame_functions(
+    endian: Endian,
+    address_size: u8,
+    eh_frame_hdr: LoadedSection<'_>,
+    eh_frame: LoadedSection<'_>,
+    cb: &mut dyn FnMut(u64, u64),
+) {
+    use gimli::{BaseAddresses, EhFrame, EhFrameHdr, UnwindSection};
+
+    let bases = BaseAddresses::default()
+        .set_eh_frame_hdr(eh_frame_hdr.address)
+        .set_eh_frame(eh_frame.address);
+    let eh_frame_hdr = match EhFrameHdr::new(eh_frame_hdr.data, endian).parse(&bases, address_size)
+    {
+        Ok(eh_frame_hdr) => eh_frame_hdr,
+        Err(_) => return,
+    };
+    let table = match eh_frame_hdr.table() {
+        Some(table) => table,
+        None => return,
+    };
+    let mut eh_frame = EhFrame::new(eh_frame.data, endian);
+    eh_frame.set_address_size(address_size);
+    let mut entries = table.iter(&bases);
+    while let Ok(Some((_, fde))) = entries.next() {
+        let fde = table
+            .pointer_to_offset(fde)
+            .and_then(|offset| eh_frame.fde_from_offset(&bases, offset, EhFrame::cie_from_offset));
+        if let Ok(fde) = fde {
+            if fde.len() != 0 {
+                cb(
+                    fde.initial_address(),
+                    fde.initial_address().saturating_add(fde.len()),
+                );
+            }
+        }
+    }
 }
 
 cfg_if::cfg_if! {
     if #[cfg(windows)] {
         mod coff;
-        use self::coff::{handle_split_dwarf, Object};
+        use self::coff::Object;
+        #[cfg(not(feature = "minimal-symbolication"))]
+        use self::coff::handle_split_dwarf;
     } else if #[cfg(any(target_vendor = "apple"))] {
         mod macho;
-        use self::macho::{handle_split_dwarf, Object};
+        use self::macho::Object;
+        #[cfg(not(feature = "minimal-symbolication"))]
+        use self::macho::handle_split_dwarf;
     } else if #[cfg(target_os = "aix")] {
         mod xcoff;
-        use self::xcoff::{handle_split_dwarf, Object};
+        use self::xcoff::Object;
+        #[cfg(not(feature = "minimal-symbolication"))]
+        use self::xcoff::handle_split_dwarf;
     } else {
         mod elf;
-        use self::elf::{handle_split_dwarf, Object};
+        use self::elf::Object;
+        #[cfg(not(feature = "minimal-symbolication"))]
+        use self::elf::handle_split_dwarf;
     }
 }
 
+#[cfg(feature = "std")]
+mod digest;
+#[cfg(feature = "std")]
+mod generic;
+#[cfg(feature = "std")]
+mod symbol_map;
+
 cfg_if::cfg_if! {
     if #[cfg(windows)] {
         mod libs_windows;
@@ -256,16 +790,41 @@ struct Cache {
     /// All known shared libraries that have been loaded.
     libraries: Vec<Library>,
 
+    /// The segments of all `libraries`, sorted by where they start so that
+    /// the library containing an address can be found with a binary search.
+    segments: Vec<SegmentRange>,
+
+    /// The index of the library of the last address looked up with a hint by
+    /// `avma_to_svma_hinted`.
+    hint: Option<usize>,
+
     /// Mappings cache where we retain parsed dwarf information.
     ///
-    /// This list has a fixed capacity for its entire lifetime which never
-    /// increases. The `usize` element of each pair is an index into `libraries`
-    /// above where `usize::max_value()` represents the current executable. The
-    /// `Mapping` is corresponding parsed dwarf information.
+    /// This list never grows beyond `capacity`, except that `preopen` adds
+    /// the mappings of all libraries. The `usize` element of each pair is an
+    /// index into `libraries` above where `usize::max_value()` represents the
+    /// current executable. The `Mapping` is corresponding parsed dwarf
+    /// information.
     ///
     /// Note that this is basically an LRU cache and we'll be shifting things
     /// around in here as we symbolize addresses.
     mappings: Vec<(usize, Mapping)>,
+
+    /// The maximum number of entries in `mappings`.
+    capacity: usize,
+
+    /// Whether `preopen` created the mappings of all libraries, in which case
+    /// no more files may be opened.
+    preopened: bool,
+
+    /// Statistics about the use of `mappings`, see `stats`.
+    hits: u64,
+    misses: u64,
+    evictions: u64,
+
+    /// Indices into `libraries` of all libraries a mapping was created for,
+    /// along with whether they had DWARF debug information.
+    debuginfo: Vec<(usize, bool)>,
 }
 
 struct Library {
@@ -285,6 +844,25 @@ struct Library {
     /// this bias is subtracted from real virtual memory addresses to index into
     /// debuginfo and the symbol table.
     bias: usize,
+    /// An identifier of the exact build of this library, like the GNU build
+    /// ID note of ELF objects or the `LC_UUID` of Mach-O objects, if known.
+    build_id: Option<Vec<u8>>,
+    /// Whether this library was unloaded since, as found by
+    /// `Cache::refresh`, in which case only its file may be looked at, as
+    /// its memory may be unmapped or reused.
+    unloaded: bool,
+}
+
+/// Where a segment of a library is loaded, see `Cache::segments`.
+struct SegmentRange {
+    start: usize,
+    end: usize,
+    /// The greatest `end` of this and all previous ranges, bounding how far
+    /// back ranges which overlap a later one can start.
+    max_end: usize,
+    /// The index of the library in `Cache::libraries`.
+    lib: usize,
+    executable: bool,
 }
 
 struct LibrarySegment {
@@ -294,83 +872,559 @@ struct LibrarySegment {
     stated_virtual_memory_address: usize,
     /// The size of this segment in memory.
     len: usize,
+    /// Whether this segment may contain code, as opposed to only data which
+    /// addresses are usually not resolved in, see `set_resolve_data_addresses`.
+    executable: bool,
 }
 
-#[cfg(target_os = "aix")]
+impl Library {
+    /// Returns the lowest address this library is loaded at.
+    fn base(&self) -> usize {
+        self.segments
+            .iter()
+            .map(|s| s.stated_virtual_memory_address.wrapping_add(self.bias))
+            .min()
+            .unwrap_or(self.bias)
+    }
+
+    /// Tests whether any segment of this library contains the actual virtual
+    /// memory address `addr`.
+    fn contains(&self, addr: usize) -> bool {
+        self.segments.iter().any(|s| self.segment_contains(s, addr))
+    }
+
+    /// Like `contains`, but only looks at the segments addresses are resolved
+    /// in, see `set_resolve_data_addresses`.
+    fn contains_resolvable(&self, addr: usize) -> bool {
+        let data = sources::resolves_data_addresses();
+        self.segments
+            .iter()
+            .any(|s| (s.executable || data) && self.segment_contains(s, addr))
+    }
+
+    fn segment_contains(&self, segment: &LibrarySegment, addr: usize) -> bool {
+        // Note that we're using `wrapping_add` here to avoid overflow
+        // checks. It's been seen in the wild that the SVMA + bias
+        // computation overflows. It seems a bit odd that would happen
+        // but there's not a huge amount we can do about it other than
+        // probably just ignore those segments since they're likely
+        // pointing off into space. This originally came up in
+        // rust-lang/backtrace-rs#329.
+        let svma = segment.stated_virtual_memory_address;
+        let start = svma.wrapping_add(self.bias);
+        let end = start.wrapping_add(segment.len);
+        start <= addr && addr < end
+    }
+}
+
+/// Creates the mapping of `lib`, from its cached digest if there is one, or
+/// digesting it otherwise, see `set_symbol_cache_dir` and
+/// `set_compact_symbols`.
 fn create_mapping(lib: &Library) -> Option<Mapping> {
-    let name = &lib.name;
+    #[cfg(feature = "std")]
+    if sources::uses_source(SymbolSource::DebugInfo) {
+        if let Some(mapping) = digest::load(lib) {
+            return Some(mapping);
+        }
+        return open_mapping(lib).map(|mapping| digest::store(lib, mapping));
+    }
+    open_mapping(lib)
+}
+
+/// Tests whether `lib` is still loaded, so its memory may be read, even if
+/// it was unloaded without `Cache::refresh` noticing.
+#[cfg(all(
+    any(target_os = "linux", target_os = "android"),
+    not(target_env = "uclibc")
+))]
+fn still_loaded(lib: &Library) -> bool {
+    native_libraries()
+        .iter()
+        .any(|l| l.name == lib.name && l.bias == lib.bias)
+}
+
+/// Returns the path to open the file of `lib` at, see
+/// `set_module_path_prefixes`.
+fn module_path(lib: &Library) -> Cow<'_, Path> {
+    #[cfg(feature = "std")]
+    return super::module_paths::module_path(Path::new(&lib.name));
+    #[cfg(not(feature = "std"))]
+    Cow::Borrowed(Path::new(&lib.name))
+}
+
+#[cfg(target_os = "aix")]
+fn open_mapping(lib: &Library) -> Option<Mapping> {
     let member_name = &lib.member_name;
-    Mapping::new(name.as_ref(), member_name)
+    Mapping::new(&module_path(lib), member_name)
 }
 
 #[cfg(not(target_os = "aix"))]
-fn create_mapping(lib: &Library) -> Option<Mapping> {
-    let name = &lib.name;
-    Mapping::new(name.as_ref())
+fn open_mapping(lib: &Library) -> Option<Mapping> {
+    // The vDSO isn't backed by a file, but the kernel maps its complete ELF
+    // image into memory, so read it from there instead.
+    #[cfg(all(
+        any(target_os = "linux", target_os = "android"),
+        not(target_env = "uclibc")
+    ))]
+    if let Some(vdso) = elf::vdso() {
+        if !lib.unloaded && lib.contains(vdso.as_ptr() as usize) {
+            return Mapping::from_bytes(vdso);
+        }
+    }
+    // Opening a binary or library by name yields the wrong file, if any, when
+    // it was deleted or replaced since it was loaded.
+    #[cfg(all(target_os = "linux", not(target_env = "uclibc")))]
+    if let Some(path) = libs_dl_iterate_phdr::mapped_image_path(lib).filter(|_| !lib.unloaded) {
+        let mapping = Mapping::new(&path, lib.build_id.as_deref());
+        #[cfg(feature = "std")]
+        if mapping.is_none() {
+            return symbol_map::mapping(lib);
+        }
+        return mapping;
+    }
+    let mapping = Mapping::new(&module_path(lib), lib.build_id.as_deref());
+    // Files which are gone may have been dumped into a symbol map before.
+    #[cfg(feature = "std")]
+    if mapping.is_none() {
+        if let Some(mapping) = symbol_map::mapping(lib) {
+            return Some(mapping);
+        }
+    }
+    // Libraries which can't be opened still provide their dynamic symbol
+    // table in memory.
+    #[cfg(all(
+        any(target_os = "linux", target_os = "android"),
+        not(target_env = "uclibc")
+    ))]
+    if mapping.is_none() && !lib.unloaded && still_loaded(lib) {
+        return Mapping::new_loaded(lib);
+    }
+    mapping
+}
+
+/// Tests whether the object file at `path`, with the build ID `found`, is the
+/// same build as the library loaded into memory with the build ID `expected`.
+///
+/// A binary may be replaced in place while the process is running, after
+/// which its symbols would be actively misleading, so a mismatch is reported
+/// and the file shouldn't be used. Missing build IDs can't be compared, and
+/// are trusted to match.
+#[cfg(not(any(windows, target_os = "aix")))]
+fn same_build(path: &Path, found: Option<&[u8]>, expected: Option<&[u8]>) -> bool {
+    match (found, expected) {
+        (Some(found), Some(expected)) if found != expected => {
+            diagnostics::report(format_args!(
+                "ignoring `{}` as its build ID doesn't match the one loaded into memory",
+                path.display()
+            ));
+            false
+        }
+        _ => true,
+    }
+}
+
+#[cfg(not(any(windows, target_os = "aix")))]
+#[test]
+fn compares_build_ids() {
+    let path = Path::new("lib.so");
+    assert!(same_build(path, Some(b"abc"), Some(b"abc")));
+    assert!(!same_build(path, Some(b"abc"), Some(b"abd")));
+    assert!(same_build(path, None, Some(b"abc")));
+    assert!(same_build(path, Some(b"abc"), None));
+}
+
+#[test]
+fn finds_libraries_of_addresses() {
+    fn library(bias: usize, segments: &[(usize, usize, bool)]) -> Library {
+        Library {
+            name: OsString::new(),
+            #[cfg(target_os = "aix")]
+            member_name: OsString::new(),
+            segments: segments
+                .iter()
+                .map(|&(svma, len, executable)| LibrarySegment {
+                    stated_virtual_memory_address: svma,
+                    len,
+                    executable,
+                })
+                .collect(),
+            bias,
+            build_id: None,
+            unloaded: false,
+        }
+    }
+
+    let mut unloaded = library(0x30000, &[(0, 0x10, true)]);
+    unloaded.unloaded = true;
+    let cache = Cache::with_libraries(vec![
+        library(0x1000, &[(0, 0x100, true), (0x200, 0x100, true)]),
+        library(0x5000, &[(0, 0x1000, true)]),
+        // Overlapping segments, where the tightest one wins.
+        library(0x5800, &[(0, 0x100, true)]),
+        library(0x10100, &[(0, 0x10, true)]),
+        library(0x10000, &[(0, 0x1000, true)]),
+        // A segment whose end overflows.
+        library(usize::MAX - 0x10, &[(0x8, 0x20, true)]),
+        // A data segment, which is skipped by default.
+        library(0x20000, &[(0, 0x100, true), (0x100, 0x100, false)]),
+        // Libraries which are still loaded win over unloaded ones, and ties
+        // go to the first library.
+        library(0x30000, &[(0, 0x100, true)]),
+        unloaded,
+        library(0x30000, &[(0, 0x100, true)]),
+        // Empty segments contain nothing.
+        library(0x40000, &[(0, 0, true)]),
+    ]);
+    let lookup = |addr: usize| {
+        let (lib, svma) = cache.avma_to_svma(addr as *const u8)?;
+        Some((lib, svma as usize))
+    };
+    assert_eq!(lookup(0x1000), Some((0, 0)));
+    assert_eq!(lookup(0x10ff), Some((0, 0xff)));
+    assert_eq!(lookup(0x1100), None);
+    assert_eq!(lookup(0x1250), Some((0, 0x250)));
+    assert_eq!(lookup(0x5900), Some((1, 0x900)));
+    assert_eq!(lookup(0x6000), None);
+    assert_eq!(lookup(0x10105), Some((3, 0x5)));
+    assert_eq!(lookup(0x10500), Some((4, 0x500)));
+    assert_eq!(lookup(0), None);
+    assert_eq!(lookup(0x10), None);
+    assert_eq!(lookup(usize::MAX - 0x4), None);
+    assert_eq!(lookup(0x200ff), Some((6, 0xff)));
+    assert_eq!(lookup(0x20100), None);
+    assert_eq!(lookup(0x5850), Some((2, 0x50)));
+    assert_eq!(lookup(0x30008), Some((7, 0x8)));
+    assert_eq!(lookup(0x40000), None);
+
+    // Hints are trusted once they're known to belong to a library, but not
+    // for addresses outside of it.
+    let mut cache = cache;
+    let hint = || Some(0x1000 as *mut c_void);
+    let mut lookup = |addr: usize| {
+        let (lib, svma) = cache.avma_to_svma_hinted(addr as *const u8, hint)?;
+        Some((lib, svma as usize))
+    };
+    assert_eq!(lookup(0x1050), Some((0, 0x50)));
+    assert_eq!(lookup(0x1250), Some((0, 0x250)));
+    assert_eq!(lookup(0x5900), Some((1, 0x900)));
+    assert_eq!(lookup(0x1100), None);
 }
 
 // unsafe because this is required to be externally synchronized
 pub unsafe fn clear_symbol_cache() {
-    Cache::with_global(|cache| cache.mappings.clear());
+    Cache::with_global(|cache| {
+        cache.mappings.clear();
+        cache.preopened = false;
+        PREOPENED.store(false, Ordering::Relaxed);
+    });
+}
+
+/// Resets the symbol cache in the child process after `fork`.
+///
+/// If the cache is `stale`, another thread was using it when the process
+/// forked and may have been halfway through changing it, so it's leaked
+/// rather than dropped.
+// unsafe because this is required to be externally synchronized
+#[cfg(all(feature = "std", unix))]
+pub unsafe fn after_fork(stale: bool) {
+    let cache = (*core::ptr::addr_of_mut!(MAPPINGS_CACHE)).take();
+    if stale {
+        mem::forget(cache);
+        GLOBAL_IN_USE.store(false, Ordering::Relaxed);
+    }
+    PREOPENED.store(false, Ordering::Relaxed);
+}
+
+/// Updates the list of libraries after some were loaded or unloaded, see
+/// `notify_modules_changed`.
+// unsafe because this is required to be externally synchronized
+pub unsafe fn refresh_libraries() {
+    Cache::with_global(|cache| cache.refresh(native_libraries()));
+}
+
+/// Creates the mappings of all libraries up front and keeps them around, so
+/// resolving symbols afterwards doesn't need to open any files.
+// unsafe because this is required to be externally synchronized
+pub unsafe fn preopen() {
+    // Nested calls would only fill a temporary cache.
+    if GLOBAL_IN_USE.load(Ordering::Relaxed) {
+        return;
+    }
+    Cache::with_global(|cache| {
+        if cache.preopened {
+            return;
+        }
+        for lib in 0..cache.libraries.len() {
+            if cache.mappings.iter().any(|(idx, _)| *idx == lib) {
+                continue;
+            }
+            if let Some(mapping) = cache.create_mapping(lib) {
+                cache.mappings.push((lib, mapping));
+            }
+        }
+        for (_, mapping) in cache.mappings.iter() {
+            mapping.stash.seal();
+        }
+        cache.preopened = true;
+        PREOPENED.store(true, Ordering::Relaxed);
+    });
+}
+
+/// Returns the paths of all files which are opened to load the debug
+/// information of the libraries currently loaded.
+///
+/// This creates a mapping for each library without caching it, recording the
+/// files `mmap` opens in the meantime.
+// unsafe because this is required to be externally synchronized
+#[cfg(feature = "std")]
+pub unsafe fn files() -> Vec<mystd::path::PathBuf> {
+    if PREOPENED.load(Ordering::Relaxed) {
+        return Vec::new();
+    }
+    if let Ok(mut opened) = OPENED_FILES.lock() {
+        *opened = Some(Vec::new());
+    }
+    for lib in native_libraries().iter() {
+        drop(create_mapping(lib));
+    }
+    let mut files = match OPENED_FILES.lock() {
+        Ok(mut opened) => opened.take().unwrap_or_default(),
+        Err(_) => Vec::new(),
+    };
+    files.sort();
+    files.dedup();
+    files
+}
+
+/// Dumps the symbol tables of all libraries currently loaded, see
+/// `dump_symbol_map`.
+// unsafe because this is required to be externally synchronized
+#[cfg(feature = "std")]
+pub unsafe fn dump_symbol_map() -> Option<Vec<u8>> {
+    Some(symbol_map::dump())
+}
+
+/// Loads a symbol map dumped by `dump_symbol_map`, returning whether it's
+/// valid.
+#[cfg(feature = "std")]
+pub fn load_symbol_map(data: &[u8]) -> Option<bool> {
+    Some(symbol_map::load(data).is_some())
 }
 
 impl Cache {
     fn new() -> Cache {
         Cache {
             mappings: Vec::with_capacity(MAPPINGS_CACHE_SIZE),
-            libraries: native_libraries(),
+            capacity: MAPPINGS_CACHE_SIZE,
+            ..Cache::with_libraries(native_libraries())
+        }
+    }
+
+    /// Creates a cache without any mappings for `libraries`.
+    fn with_libraries(libraries: Vec<Library>) -> Cache {
+        let mut segments = Vec::new();
+        for (lib, library) in libraries.iter().enumerate() {
+            for segment in library.segments.iter() {
+                // See `Library::contains` for why this wraps around, segments
+                // for which it does can't contain any address anyway.
+                let start = segment
+                    .stated_virtual_memory_address
+                    .wrapping_add(library.bias);
+                let end = start.wrapping_add(segment.len);
+                if start < end {
+                    segments.push(SegmentRange {
+                        start,
+                        end,
+                        max_end: end,
+                        lib,
+                        executable: segment.executable,
+                    });
+                }
+            }
+        }
+        segments.sort_by_key(|s| s.start);
+        // Along the way, report segments of different libraries which overlap,
+        // apart from those of unloaded libraries whose memory may well have
+        // been reused.
+        let loaded = |s: &SegmentRange| !libraries[s.lib].unloaded;
+        let mut widest: Option<usize> = None;
+        for i in 0..segments.len() {
+            let segment = &segments[i];
+            if let Some(other) = widest.map(|w| &segments[w]) {
+                if segment.start < other.end
+                    && segment.lib != other.lib
+                    && loaded(segment)
+                    && loaded(other)
+                {
+                    diagnostics::report(format_args!(
+                        "segments of `{}` and `{}` overlap at {:#x}..{:#x}",
+                        Path::new(&libraries[other.lib].name).display(),
+                        Path::new(&libraries[segment.lib].name).display(),
+                        segment.start,
+                        segment.end.min(other.end),
+                    ));
+                }
+            }
+            if widest.map_or(true, |w| segments[w].end < segment.end) {
+                widest = Some(i);
+            }
+            segments[i].max_end = widest.map_or(0, |w| segments[w].end);
+        }
+        Cache {
+            libraries,
+            segments,
+            ..Default::default()
         }
     }
 
+    /// Replaces the known libraries with `libraries`, keeping the mappings
+    /// and statistics of those which are still loaded at the same address.
+    ///
+    /// Libraries which were unloaded are remembered after the loaded ones,
+    /// most recently unloaded first, so that addresses captured while they
+    /// were loaded still resolve from their files. Addresses in the range of
+    /// a loaded library are always attributed to it, as `avma_to_svma`
+    /// prefers libraries which are still loaded.
+    fn refresh(&mut self, mut libraries: Vec<Library>) {
+        let old = mem::take(self);
+        let same = |a: &Library, b: &Library| {
+            a.name == b.name && a.bias == b.bias && a.build_id == b.build_id
+        };
+        let mut index = old
+            .libraries
+            .iter()
+            .map(|old| libraries.iter().position(|new| same(new, old)))
+            .collect::<Vec<_>>();
+        let gone = (0..index.len()).filter(|&i| index[i].is_none());
+        let (newly, before): (Vec<usize>, Vec<usize>) =
+            gone.partition(|&i| !old.libraries[i].unloaded);
+        let mut old_libraries = old.libraries.into_iter().map(Some).collect::<Vec<_>>();
+        for i in newly.into_iter().chain(before).take(MAX_UNLOADED_LIBRARIES) {
+            if let Some(mut lib) = old_libraries[i].take() {
+                lib.unloaded = true;
+                index[i] = Some(libraries.len());
+                libraries.push(lib);
+            }
+        }
+
+        *self = Cache::with_libraries(libraries);
+        self.mappings = old
+            .mappings
+            .into_iter()
+            .filter_map(|(lib, mapping)| Some((index[lib]?, mapping)))
+            .collect();
+        self.debuginfo = old
+            .debuginfo
+            .iter()
+            .filter_map(|&(lib, debuginfo)| Some((index[lib]?, debuginfo)))
+            .collect();
+        self.capacity = old.capacity;
+        self.hits = old.hits;
+        self.misses = old.misses;
+        self.evictions = old.evictions;
+    }
+
     // unsafe because this is required to be externally synchronized
     unsafe fn with_global(f: impl FnOnce(&mut Self)) {
-        // A very small, very simple LRU cache for debug info mappings.
-        //
-        // The hit rate should be very high, since the typical stack doesn't cross
-        // between many shared libraries.
-        //
-        // The `addr2line::Context` structures are pretty expensive to create. Its
-        // cost is expected to be amortized by subsequent `locate` queries, which
-        // leverage the structures built when constructing `addr2line::Context`s to
-        // get nice speedups. If we didn't have this cache, that amortization would
-        // never happen, and symbolicating backtraces would be ssssllllooooowwww.
-        static mut MAPPINGS_CACHE: Option<Cache> = None;
+        // The global lock is reentrant, so this is called again while `f` runs
+        // if a callback passed to `resolve` resolves symbols itself, like when
+        // it captures a backtrace or panics. The global cache is already
+        // borrowed then, so the nested call gets a temporary one instead. That
+        // has to load everything it needs from scratch, but it's only used
+        // when the outer call is itself in trouble or debugging something.
+        if GLOBAL_IN_USE.swap(true, Ordering::Relaxed) {
+            let mut cache = Cache::new();
+            cache.preopened = PREOPENED.load(Ordering::Relaxed);
+            return f(&mut cache);
+        }
 
-        f(MAPPINGS_CACHE.get_or_insert_with(|| Cache::new()))
+        // Released even if `f` unwinds.
+        struct Release;
+        impl Drop for Release {
+            fn drop(&mut self) {
+                GLOBAL_IN_USE.store(false, Ordering::Relaxed);
+            }
+        }
+        let _release = Release;
+        f((*core::ptr::addr_of_mut!(MAPPINGS_CACHE)).get_or_insert_with(|| Cache::new()))
     }
 
     fn avma_to_svma(&self, addr: *const u8) -> Option<(usize, *const u8)> {
-        self.libraries
-            .iter()
-            .enumerate()
-            .filter_map(|(i, lib)| {
-                // First up, test if this `lib` has any segment containing the
-                // `addr` (handling relocation). If this check passes then we
-                // can continue below and actually translate the address.
-                //
-                // Note that we're using `wrapping_add` here to avoid overflow
-                // checks. It's been seen in the wild that the SVMA + bias
-                // computation overflows. It seems a bit odd that would happen
-                // but there's not a huge amount we can do about it other than
-                // probably just ignore those segments since they're likely
-                // pointing off into space. This originally came up in
-                // rust-lang/backtrace-rs#329.
-                if !lib.segments.iter().any(|s| {
-                    let svma = s.stated_virtual_memory_address;
-                    let start = svma.wrapping_add(lib.bias);
-                    let end = start.wrapping_add(s.len);
-                    let address = addr as usize;
-                    start <= address && address < end
-                }) {
-                    return None;
-                }
+        let addr = addr as usize;
+        // First up, find the library with a segment containing `addr`. Only
+        // segments starting at or before `addr` can, and of those only ones
+        // after any which end before it.
+        let data = sources::resolves_data_addresses();
+        let candidates = self.segments.partition_point(|s| s.start <= addr);
+        let containing = || {
+            self.segments[..candidates]
+                .iter()
+                .rev()
+                .take_while(|s| s.max_end > addr)
+                .filter(|s| addr < s.end && (s.executable || data))
+        };
+        // Segments shouldn't overlap, but odd linkers produce ones which do.
+        // Of those containing `addr`, the tightest executable segment of a
+        // library which is still loaded is the most likely to be right, and
+        // the first library is only picked as a last resort.
+        let rank = |s: &SegmentRange| {
+            let unloaded = self.libraries[s.lib].unloaded;
+            (unloaded, !s.executable, s.end - s.start)
+        };
+        let best = containing().min_by_key(|s| (rank(s), s.lib))?;
+        if let Some(other) = containing().find(|s| s.lib != best.lib && rank(s) == rank(best)) {
+            diagnostics::report(format_args!(
+                "{:#x} is in segments of both `{}` and `{}`, attributing it to the former",
+                addr,
+                Path::new(&self.libraries[best.lib].name).display(),
+                Path::new(&self.libraries[other.lib].name).display(),
+            ));
+        }
+        let lib = best.lib;
 
-                // Now that we know `lib` contains `addr`, we can offset with
-                // the bias to find the stated virtual memory address.
-                let svma = (addr as usize).wrapping_sub(lib.bias);
-                Some((i, svma as *const u8))
-            })
-            .next()
+        // Now that we know `lib` contains `addr`, we can offset with the bias
+        // to find the stated virtual memory address.
+        let svma = addr.wrapping_sub(self.libraries[lib].bias);
+        Some((lib, svma as *const u8))
+    }
+
+    /// Like `avma_to_svma`, given a way to ask the unwinder for the base
+    /// address of the module containing `addr`, if it knows it.
+    ///
+    /// Consecutive frames are usually in the same module, so the library the
+    /// last hinted address belonged to is used right away if it contains
+    /// `addr`. Asking for the hint may be costly, like calling `dladdr` on
+    /// macOS, so that's only done otherwise.
+    fn avma_to_svma_hinted(
+        &mut self,
+        addr: *const u8,
+        hint: impl FnOnce() -> Option<*mut c_void>,
+    ) -> Option<(usize, *const u8)> {
+        if let Some(lib) = self.hint {
+            let library = &self.libraries[lib];
+            if library.contains_resolvable(addr as usize) {
+                let svma = (addr as usize).wrapping_sub(library.bias);
+                return Some((lib, svma as *const u8));
+            }
+        }
+        let hinted = hint().is_some();
+        let (lib, svma) = self.avma_to_svma(addr)?;
+        if hinted {
+            self.hint = Some(lib);
+        }
+        Some((lib, svma))
+    }
+
+    /// Creates a mapping for the library at index `lib`, recording it in the
+    /// statistics returned by `stats`.
+    fn create_mapping(&mut self, lib: usize) -> Option<Mapping> {
+        self.misses += 1;
+        let mapping = create_mapping(&self.libraries[lib]);
+        if !self.debuginfo.iter().any(|(idx, _)| *idx == lib) {
+            let debuginfo = mapping.as_ref().map_or(false, Mapping::has_debuginfo);
+            self.debuginfo.push((lib, debuginfo));
+        }
+        mapping
     }
 
     fn mapping_for_lib<'a>(&'a mut self, lib: usize) -> Option<(&'a mut Context<'a>, &'a Stash)> {
@@ -380,6 +1434,7 @@ impl Cache {
         // from an error, the cache entry for this path is at index 0.
 
         if let Some(idx) = idx {
+            self.hits += 1;
             // When the mapping is already in the cache, move it to the front.
             if idx != 0 {
                 let entry = self.mappings.remove(idx);
@@ -389,10 +1444,17 @@ impl Cache {
             // When the mapping is not in the cache, create a new mapping,
             // insert it into the front of the cache, and evict the oldest cache
             // entry if necessary.
-            let mapping = create_mapping(&self.libraries[lib])?;
+            //
+            // After `preopen` all libraries which can be loaded at all already
+            // have a mapping, and no files may be opened anymore.
+            if self.preopened {
+                return None;
+            }
+            let mapping = self.create_mapping(lib)?;
 
-            if self.mappings.len() == MAPPINGS_CACHE_SIZE {
+            if self.mappings.len() >= self.capacity {
                 self.mappings.pop();
+                self.evictions += 1;
             }
 
             self.mappings.insert(0, (lib, mapping));
@@ -410,8 +1472,53 @@ impl Cache {
     }
 }
 
-pub unsafe fn resolve(what: ResolveWhat<'_>, cb: &mut dyn FnMut(&super::Symbol)) {
+// unsafe because this is required to be externally synchronized
+#[cfg(feature = "std")]
+pub unsafe fn stats(stats: &mut super::SymbolizerStats) {
+    Cache::with_global(|cache| {
+        stats.cache_hits = cache.hits;
+        stats.cache_misses = cache.misses;
+        stats.cache_evictions = cache.evictions;
+        stats.cached_bytes = cache.mappings.iter().map(|(_, m)| m.size()).sum();
+        for &(_, debuginfo) in cache.debuginfo.iter() {
+            if debuginfo {
+                stats.modules_with_debuginfo += 1;
+            } else {
+                stats.modules_without_debuginfo += 1;
+            }
+        }
+    });
+}
+
+/// Finds out why no symbols were found for `what`.
+#[cfg(feature = "std")]
+pub unsafe fn diagnose(what: ResolveWhat<'_>) -> Option<super::ResolveError> {
+    use super::ResolveError;
+    use mystd::path::PathBuf;
+
     let addr = what.address_or_ip();
+    let mut ret = None;
+    Cache::with_global(|cache| {
+        let lib = match cache.avma_to_svma(addr.cast_const().cast::<u8>()) {
+            Some((lib, _)) => lib,
+            None => {
+                ret = Some(ResolveError::NoModule);
+                return;
+            }
+        };
+        let path = PathBuf::from(&cache.libraries[lib].name);
+        let preopened = cache.preopened;
+        ret = Some(match cache.mapping_for_lib(lib) {
+            Some((cx, stash)) if cx.has_debuginfo(stash) => ResolveError::NoSymbol,
+            Some(_) => ResolveError::NoDebugInfo,
+            None if !preopened && File::open(&path).is_err() => ResolveError::Unreadable(path),
+            None => ResolveError::ParseError(path),
+        });
+    });
+    ret
+}
+
+pub unsafe fn resolve(what: ResolveWhat<'_>, cb: &mut dyn FnMut(&super::Symbol)) {
     let mut call = |sym: Symbol<'_>| {
         // Extend the lifetime of `sym` to `'static` since we are unfortunately
         // required to here, but it's only ever going out as a reference so no
@@ -420,55 +1527,580 @@ pub unsafe fn resolve(what: ResolveWhat<'_>, cb: &mut dyn FnMut(&super::Symbol))
         (cb)(&super::Symbol { inner: sym });
     };
 
-    Cache::with_global(|cache| {
-        let (lib, addr) = match cache.avma_to_svma(addr.cast_const().cast::<u8>()) {
-            Some(pair) => pair,
-            None => return,
-        };
+    Cache::with_global(|cache| resolve_in(cache, &what, &mut call));
+}
 
-        // Finally, get a cached mapping or create a new mapping for this file, and
-        // evaluate the DWARF info to find the file/line/name for this address.
-        let (cx, stash) = match cache.mapping_for_lib(lib) {
-            Some((cx, stash)) => (cx, stash),
-            None => return,
+unsafe fn resolve_in(cache: &mut Cache, what: &ResolveWhat<'_>, call: &mut dyn FnMut(Symbol<'_>)) {
+    let addr = what.address_or_ip();
+    let lib = cache.avma_to_svma_hinted(addr.cast_const().cast::<u8>(), || what.module_hint());
+
+    // Get a cached mapping or create a new mapping for this file once it's
+    // needed, and evaluate the DWARF info to find the file/line/name for this
+    // address.
+    let load = move || {
+        let (lib, svma) = lib?;
+        let cache = cache;
+        let (cx, stash) = cache.mapping_for_lib(lib)?;
+        Some((cx, stash, svma as u64))
+    };
+    resolve_sources(load, Some(addr), call);
+}
+
+/// A list of libraries and cache of their mappings separate from the global
+/// one, see `super::Symbolizer`.
+#[cfg(feature = "std")]
+pub struct Symbolizer {
+    cache: Cache,
+}
+
+#[cfg(feature = "std")]
+impl Symbolizer {
+    pub fn new() -> Symbolizer {
+        Symbolizer {
+            cache: Cache::new(),
+        }
+    }
+
+    pub fn set_cache_size(&mut self, size: usize) {
+        self.cache.capacity = size.max(1);
+        self.cache.mappings.truncate(self.cache.capacity);
+    }
+
+    pub fn clear_cache(&mut self) {
+        self.cache.mappings.clear();
+    }
+
+    // unsafe because this is required to be externally synchronized
+    pub unsafe fn resolve(&mut self, what: ResolveWhat<'_>, cb: &mut dyn FnMut(&super::Symbol)) {
+        resolve_in(&mut self.cache, &what, &mut |sym| {
+            // See `resolve` for why this is fine.
+            let sym = mem::transmute::<Symbol<'_>, Symbol<'static>>(sym);
+            cb(&super::Symbol { inner: sym });
+        });
+    }
+}
+
+/// Debug information of an object file provided by the caller, see
+/// `super::ObjectSymbolizer`.
+#[cfg(feature = "std")]
+pub struct ObjectSymbolizer {
+    mapping: Mapping,
+}
+
+#[cfg(feature = "std")]
+impl ObjectSymbolizer {
+    pub fn new(data: &[u8]) -> Option<ObjectSymbolizer> {
+        // Object files of other platforms are only understood generically.
+        let mapping =
+            Mapping::from_bytes(data).or_else(|| Mapping::new_generic(Mmap::copy_from(data)?))?;
+        Some(ObjectSymbolizer { mapping })
+    }
+
+    pub fn open(path: &Path) -> Option<ObjectSymbolizer> {
+        #[cfg(not(target_os = "aix"))]
+        let mapping = Mapping::new(path, None);
+        #[cfg(target_os = "aix")]
+        let mapping = Mapping::new(path, &OsString::new());
+        let mapping = mapping.or_else(|| Mapping::new_generic(mmap(path)?))?;
+        Some(ObjectSymbolizer { mapping })
+    }
+
+    pub fn from_process(pid: u32, base: u64, size: u64) -> Option<(ObjectSymbolizer, u64)> {
+        cfg_if::cfg_if! {
+            if #[cfg(all(
+                any(target_os = "linux", target_os = "android"),
+                not(target_env = "uclibc")
+            ))] {
+                let (mapping, image_base) = Mapping::new_remote(pid, base, size)?;
+                Some((ObjectSymbolizer { mapping }, image_base))
+            } else {
+                let _ = (pid, base, size);
+                None
+            }
+        }
+    }
+
+    pub fn image(&self) -> Option<(u64, Option<Vec<u8>>)> {
+        generic::image(&self.mapping.map)
+    }
+
+    pub fn resolve(&mut self, svma: u64, cb: &mut dyn FnMut(&super::Symbol)) {
+        // Like in `Cache::mapping_for_lib`, don't leak the `'static` lifetime.
+        let cx = unsafe {
+            mem::transmute::<&mut Context<'static>, &mut Context<'_>>(&mut self.mapping.cx)
         };
-        let mut any_frames = false;
-        if let Ok(mut frames) = cx.find_frames(stash, addr as u64) {
-            while let Ok(Some(frame)) = frames.next() {
-                any_frames = true;
-                let name = match frame.function {
-                    Some(f) => Some(f.name.slice()),
-                    None => cx.object.search_symtab(addr as u64),
+        let stash = &self.mapping.stash;
+        unsafe {
+            resolve_sources(|| Some((cx, stash, svma)), None, &mut |sym| {
+                // See `resolve` for why this is fine.
+                let sym = mem::transmute::<Symbol<'_>, Symbol<'static>>(sym);
+                cb(&super::Symbol { inner: sym });
+            });
+        }
+    }
+}
+
+/// Resolves `addrs` in bulk, passing each symbol to `cb` along with the index
+/// of the address it was found for.
+///
+/// This goes through the global cache just like `resolve`, but the addresses
+/// are grouped by library and sorted first, so the debug information of each
+/// library is looked up once and walked in address order.
+///
+/// `next` is called with the index of each address before it's resolved, and
+/// nothing more is resolved once it returns `false`. For the first address of
+/// each library that's before its debug information is loaded.
+pub unsafe fn resolve_many(
+    addrs: &[ResolveWhat<'_>],
+    next: &mut dyn FnMut(usize) -> bool,
+    cb: &mut dyn FnMut(usize, &super::Symbol),
+) {
+    let needs_files = needs_files();
+    Cache::with_global(|cache| {
+        for (lib, group) in group_by_library(cache, addrs) {
+            if !next(group[0].0) {
+                return;
+            }
+            let mut mapping = if needs_files {
+                cache.mapping_for_lib(lib)
+            } else {
+                None
+            };
+            for (n, (i, svma)) in group.into_iter().enumerate() {
+                if n > 0 && !next(i) {
+                    return;
+                }
+                let entry = mapping.as_mut().map(|(cx, stash)| (&mut **cx, *stash));
+                let load = move || {
+                    let (cx, stash) = entry?;
+                    Some((cx, stash, svma as u64))
                 };
-                call(Symbol::Frame {
-                    addr: addr as *mut c_void,
-                    location: frame.location,
-                    name,
+                resolve_sources(load, Some(addrs[i].address_or_ip()), &mut |sym| {
+                    let sym = mem::transmute::<Symbol<'_>, Symbol<'static>>(sym);
+                    cb(i, &super::Symbol { inner: sym });
                 });
             }
         }
-        if !any_frames {
-            if let Some((object_cx, object_addr)) = cx.object.search_object_map(addr as u64) {
-                if let Ok(mut frames) = object_cx.find_frames(stash, object_addr) {
-                    while let Ok(Some(frame)) = frames.next() {
-                        any_frames = true;
-                        call(Symbol::Frame {
-                            addr: addr as *mut c_void,
-                            location: frame.location,
-                            name: frame.function.map(|f| f.name.slice()),
-                        });
-                    }
+        resolve_unknown(cache, addrs, next, cb);
+    });
+}
+
+/// Tests whether any of the sources configured with `set_symbol_sources`
+/// needs the mappings of libraries.
+fn needs_files() -> bool {
+    sources::uses_source(SymbolSource::DebugInfo) || sources::uses_source(SymbolSource::SymbolTable)
+}
+
+/// Like `resolve_many`, but `cb` may be called concurrently from multiple
+/// threads.
+///
+/// Rather than going through the global cache this uses a fresh list of
+/// libraries and parses the debug information of each library involved once,
+/// without any state shared between libraries. With the `rayon` feature
+/// enabled the libraries are then processed in parallel.
+#[cfg(feature = "std")]
+pub unsafe fn resolve_batch(
+    addrs: &[ResolveWhat<'_>],
+    cb: &(dyn Fn(usize, &super::Symbol) + Sync),
+) {
+    // Fresh mappings can't be created after `preopen`, so use the ones from
+    // the global cache instead.
+    if PREOPENED.load(Ordering::Relaxed) {
+        return resolve_many(addrs, &mut |_| true, &mut |i, sym| cb(i, sym));
+    }
+
+    let cache = Cache::with_libraries(native_libraries());
+    let groups = group_by_library(&cache, addrs);
+
+    let libraries = &cache.libraries;
+    let needs_files = needs_files();
+    // `ResolveWhat` holds raw pointers, which can't be shared with the
+    // threads resolving the groups.
+    let avmas = addrs
+        .iter()
+        .map(|what| what.address_or_ip() as usize)
+        .collect::<Vec<_>>();
+    let resolve_lib = |(lib, group): &(usize, Vec<(usize, usize)>)| {
+        let mut mapping = if needs_files {
+            create_mapping(&libraries[*lib])
+        } else {
+            None
+        };
+        // Like in `Cache::mapping_for_lib`, don't leak the `'static` lifetime
+        // of the context beyond the mapping it borrows from.
+        let mut cx = mapping.as_mut().map(|mapping| {
+            let cx = mem::transmute::<&mut Context<'static>, &mut Context<'_>>(&mut mapping.cx);
+            (cx, &mapping.stash)
+        });
+        for &(i, svma) in group {
+            let entry = cx.as_mut().map(|(cx, stash)| (&mut **cx, *stash));
+            let load = move || {
+                let (cx, stash) = entry?;
+                Some((cx, stash, svma as u64))
+            };
+            resolve_sources(load, Some(avmas[i] as *mut c_void), &mut |sym| {
+                let sym = mem::transmute::<Symbol<'_>, Symbol<'static>>(sym);
+                cb(i, &super::Symbol { inner: sym });
+            });
+        }
+    };
+
+    #[cfg(feature = "rayon")]
+    {
+        use rayon::prelude::*;
+        groups.par_iter().for_each(resolve_lib);
+    }
+    #[cfg(not(feature = "rayon"))]
+    groups.iter().for_each(resolve_lib);
+    resolve_unknown(&cache, addrs, &mut |_| true, &mut |i, sym| cb(i, sym));
+}
+
+/// Groups `addrs` by the library containing them, as pairs of each address's
+/// index in `addrs` and its stated virtual memory address, sorted by the
+/// latter. Addresses outside of any known library are dropped.
+fn group_by_library(cache: &Cache, addrs: &[ResolveWhat<'_>]) -> Vec<(usize, Vec<(usize, usize)>)> {
+    let mut sorted = addrs
+        .iter()
+        .enumerate()
+        .filter_map(|(i, what)| {
+            let addr = what.address_or_ip();
+            let (lib, svma) = cache.avma_to_svma(addr.cast_const().cast::<u8>())?;
+            Some((lib, svma as usize, i))
+        })
+        .collect::<Vec<_>>();
+    sorted.sort_unstable();
+
+    let mut groups: Vec<(usize, Vec<(usize, usize)>)> = Vec::new();
+    for (lib, svma, i) in sorted {
+        match groups.last_mut() {
+            Some((l, addrs)) if *l == lib => addrs.push((i, svma)),
+            _ => groups.push((lib, vec![(i, svma)])),
+        }
+    }
+    groups
+}
+
+/// Finds the symbols for an address by trying the sources configured with
+/// `set_symbol_sources` in turn, passing them to `call`.
+///
+/// `load` returns the context of the object file containing the address along
+/// with its stated virtual memory address, and is only called once a source
+/// needs it. The address is 64 bits wide even on 32-bit platforms, as object
+/// files provided by callers may be of other, 64-bit platforms. `avma` is the
+/// address in the current process, if it is one.
+unsafe fn resolve_sources<'a: 'b, 'b>(
+    load: impl FnOnce() -> Option<(&'b mut Context<'a>, &'a Stash, u64)>,
+    avma: Option<*mut c_void>,
+    call: &mut dyn FnMut(Symbol<'_>),
+) {
+    let (sources, merge) = sources::symbol_sources();
+    let mut load = Some(load);
+    let mut cx = None;
+    let scratch = Scratch::new();
+    for source in sources {
+        if matches!(source, SymbolSource::DebugInfo | SymbolSource::SymbolTable) {
+            if let Some(load) = load.take() {
+                cx = load();
+            }
+        }
+        let found = match (source, &mut cx) {
+            (SymbolSource::DebugInfo, Some((cx, stash, svma))) => {
+                resolve_debuginfo(cx, stash, *svma, call)
+            }
+            (SymbolSource::SymbolTable, Some((cx, _, svma))) => {
+                if let Some(name) = cx.object.search_symtab(*svma) {
+                    let info = cx.object.symtab_info(*svma);
+                    call(Symbol::Symtab { name, info });
+                    true
+                } else if let Some(start) = cx.object.function_start(*svma) {
+                    // Without any symbols, name functions after their start so
+                    // that they can at least be told apart.
+                    let name = scratch.format(format_args!("fn_{:#x}+{:#x}", start, *svma - start));
+                    call(Symbol::Symtab { name, info: None });
+                    true
+                } else {
+                    false
                 }
             }
+            (SymbolSource::Dladdr, _) => avma.map_or(false, |avma| resolve_dladdr(avma, call)),
+            (SymbolSource::Custom, _) => avma.map_or(false, |avma| {
+                sources::resolve_custom(avma, &mut |name| call(Symbol::Symtab { name, info: None }))
+            }),
+            _ => false,
+        };
+        if found && !merge {
+            break;
         }
-        if !any_frames {
-            if let Some(name) = cx.object.search_symtab(addr as u64) {
-                call(Symbol::Symtab { name });
+    }
+}
+
+/// Evaluates the debug information in `cx` to find the symbols for the stated
+/// virtual memory address `addr`, passing them to `call`, and returns whether
+/// any were found.
+unsafe fn resolve_debuginfo<'a>(
+    cx: &mut Context<'a>,
+    stash: &'a Stash,
+    addr: u64,
+    call: &mut dyn FnMut(Symbol<'_>),
+) -> bool {
+    let mut any_frames = false;
+    #[cfg(feature = "std")]
+    if let AnyObject::Digest(digest) = &cx.object {
+        for frame in digest.frames(addr) {
+            any_frames = true;
+            call(Symbol::frame(addr, Some(frame.location), frame.name));
+        }
+        return any_frames;
+    }
+    // Names alone can be found without evaluating line tables, but as soon
+    // as locations are needed `addr2line` finds both at once. Units nested
+    // too deeply for `addr2line` only get the function containing `addr`,
+    // with the location from the line table.
+    let mode = sources::resolve_mode();
+    #[cfg(not(feature = "minimal-symbolication"))]
+    {
+        let too_deep = cx.names.too_deep(addr);
+        if mode == ResolveMode::NamesOnly || too_deep {
+            let mut names = Vec::new();
+            if cx.names.find_frames(addr, &mut |name| names.push(name)) {
+                let mut location = match mode {
+                    ResolveMode::NamesOnly => None,
+                    _ => cx.dwarf.find_location(addr).ok().flatten(),
+                };
+                for name in names {
+                    call(Symbol::frame(
+                        addr,
+                        location.take(),
+                        name.or_else(|| cx.object.search_symtab(addr)),
+                    ));
+                }
+                return true;
+            }
+            if too_deep {
+                return false;
             }
         }
+    }
+    // Everything else needs the line tables.
+    if mode == ResolveMode::NamesOnly {
+        return false;
+    }
+    cx.find_frames(stash, addr, &mut |name, location| {
+        any_frames = true;
+        call(Symbol::frame(
+            addr,
+            location,
+            name.or_else(|| cx.object.search_symtab(addr)),
+        ));
+    });
+    if !any_frames {
+        let open = !stash.is_sealed();
+        if let Some((object_cx, object_addr)) = cx.object.search_object_map(addr, open) {
+            object_cx.find_frames(stash, object_addr, &mut |name, location| {
+                any_frames = true;
+                call(Symbol::frame(addr, location, name));
+            });
+        }
+    }
+    any_frames
+}
+
+/// Looks up the exported symbol containing `addr` with `dladdr`, passing it to
+/// `call`, and returns whether one was found.
+#[cfg(all(
+    any(
+        target_os = "android",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "illumos",
+        target_os = "linux",
+        target_os = "netbsd",
+        target_os = "openbsd",
+        target_os = "solaris",
+        target_vendor = "apple",
+    ),
+    not(feature = "pure-rust"),
+))]
+unsafe fn resolve_dladdr(addr: *mut c_void, call: &mut dyn FnMut(Symbol<'_>)) -> bool {
+    let mut info: libc::Dl_info = mem::zeroed();
+    if libc::dladdr(addr, &mut info) == 0 || info.dli_sname.is_null() {
+        return false;
+    }
+    let name = core::ffi::CStr::from_ptr(info.dli_sname).to_bytes();
+    let module_path = if info.dli_fname.is_null() {
+        None
+    } else {
+        Some(core::ffi::CStr::from_ptr(info.dli_fname).to_bytes())
+    };
+    call(Symbol::Dladdr {
+        name,
+        addr: info.dli_saddr,
+        offset: (addr as usize).wrapping_sub(info.dli_saddr as usize),
+        module_base: info.dli_fbase,
+        module_path,
+    });
+    true
+}
+
+#[cfg(not(all(
+    any(
+        target_os = "android",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "illumos",
+        target_os = "linux",
+        target_os = "netbsd",
+        target_os = "openbsd",
+        target_os = "solaris",
+        target_vendor = "apple",
+    ),
+    not(feature = "pure-rust"),
+)))]
+unsafe fn resolve_dladdr(_addr: *mut c_void, _call: &mut dyn FnMut(Symbol<'_>)) -> bool {
+    false
+}
+
+/// Passes the symbols of addresses in `addrs` outside of any library known to
+/// `cache` to `cb`, as found by the sources which look at the process itself.
+unsafe fn resolve_unknown(
+    cache: &Cache,
+    addrs: &[ResolveWhat<'_>],
+    next: &mut dyn FnMut(usize) -> bool,
+    cb: &mut dyn FnMut(usize, &super::Symbol),
+) {
+    if !sources::uses_source(SymbolSource::Dladdr) && !sources::uses_source(SymbolSource::Custom) {
+        return;
+    }
+    for (i, what) in addrs.iter().enumerate() {
+        let addr = what.address_or_ip();
+        if cache.avma_to_svma(addr.cast_const().cast::<u8>()).is_some() {
+            continue;
+        }
+        if !next(i) {
+            return;
+        }
+        resolve_sources(|| None, Some(addr), &mut |sym| {
+            let sym = mem::transmute::<Symbol<'_>, Symbol<'static>>(sym);
+            cb(i, &super::Symbol { inner: sym });
+        });
+    }
+}
+
+pub unsafe fn module_symbols(addr: usize, cb: &mut dyn FnMut(&super::ModuleSymbol<'_>)) {
+    Cache::with_global(|cache| {
+        let lib = match cache.avma_to_svma(addr as *const u8) {
+            Some((lib, _)) => lib,
+            None => return,
+        };
+        let bias = cache.libraries[lib].bias;
+        let (cx, _) = match cache.mapping_for_lib(lib) {
+            Some(pair) => pair,
+            None => return,
+        };
+        cx.object.for_each_symbol(&mut |name, svma, size| {
+            cb(&super::ModuleSymbol {
+                name,
+                addr: (svma as usize).wrapping_add(bias) as *mut c_void,
+                size: size.map(|size| size as usize),
+            })
+        });
+    });
+}
+
+pub unsafe fn find_module_symbol<'a>(
+    addr: usize,
+    name: &'a [u8],
+) -> Option<super::ModuleSymbol<'a>> {
+    let mut found = None;
+    Cache::with_global(|cache| {
+        let lib = match cache.avma_to_svma(addr as *const u8) {
+            Some((lib, _)) => lib,
+            None => return,
+        };
+        let bias = cache.libraries[lib].bias;
+        if let Some((cx, _)) = cache.mapping_for_lib(lib) {
+            found = cx
+                .object
+                .find_symbol(name)
+                .map(|(svma, size)| ((svma as usize).wrapping_add(bias), size));
+        }
+    });
+    let (addr, size) = found?;
+    Some(super::ModuleSymbol {
+        name,
+        addr: addr as *mut c_void,
+        size: size.map(|size| size as usize),
+    })
+}
+
+pub unsafe fn dynamic_symbol_count(addr: usize) -> Option<usize> {
+    let mut count = None;
+    Cache::with_global(|cache| {
+        let lib = match cache.avma_to_svma(addr as *const u8) {
+            Some((lib, _)) => lib,
+            None => return,
+        };
+        if let Some((cx, _)) = cache.mapping_for_lib(lib) {
+            count = cx.object.dynamic_symbol_count();
+        }
+    });
+    count
+}
+
+pub unsafe fn find_module(addr: *mut c_void, cb: &mut dyn FnMut(&super::ModuleInfo<'_>)) {
+    Cache::with_global(|cache| {
+        let (lib, svma) = match cache.avma_to_svma(addr.cast_const().cast::<u8>()) {
+            Some(pair) => pair,
+            None => return,
+        };
+        let lib = &cache.libraries[lib];
+        let base = lib.base();
+
+        #[cfg(unix)]
+        let name = {
+            use mystd::os::unix::prelude::*;
+            BytesOrWideString::Bytes(lib.name.as_bytes())
+        };
+        #[cfg(windows)]
+        let wide = {
+            use mystd::os::windows::prelude::*;
+            lib.name.encode_wide().collect::<Vec<u16>>()
+        };
+        #[cfg(windows)]
+        let name = BytesOrWideString::Wide(&wide);
+        #[cfg(not(any(unix, windows)))]
+        let name = BytesOrWideString::Bytes(lib.name.to_str().unwrap_or("").as_bytes());
+
+        cb(&super::ModuleInfo {
+            name,
+            base,
+            svma: svma as usize,
+            build_id: lib.build_id.as_deref(),
+        });
     });
 }
 
+pub unsafe fn find_module_by_build_id(build_id: &[u8]) -> Option<usize> {
+    let mut base = None;
+    Cache::with_global(|cache| {
+        base = cache
+            .libraries
+            .iter()
+            .find(|lib| !lib.unloaded && lib.build_id.as_deref() == Some(build_id))
+            .map(Library::base);
+    });
+    base
+}
+
+/// What a symbol table records about a symbol besides its name, for
+/// `Symbol::Symtab`.
+#[derive(Copy, Clone)]
+pub struct SymtabInfo<'a> {
+    binding: Option<SymbolBinding>,
+    visibility: SymbolVisibility,
+    version: Option<SymbolVersion<'a>>,
+}
+
 pub enum Symbol<'a> {
     /// We were able to locate frame information for this symbol, and
     /// `addr2line`'s frame internally has all the nitty gritty details.
@@ -479,58 +2111,118 @@ pub enum Symbol<'a> {
     },
     /// Couldn't find debug information, but we found it in the symbol table of
     /// the elf executable.
-    Symtab { name: &'a [u8] },
+    Symtab {
+        name: &'a [u8],
+        info: Option<SymtabInfo<'a>>,
+    },
+    /// The dynamic linker found an exported symbol with `dladdr`.
+    #[allow(dead_code)] // unused on platforms without `dladdr`
+    Dladdr {
+        name: &'a [u8],
+        addr: *mut c_void,
+        offset: usize,
+        module_base: *mut c_void,
+        module_path: Option<&'a [u8]>,
+    },
+}
+
+impl<'a> Symbol<'a> {
+    fn frame(
+        addr: u64,
+        location: Option<addr2line::Location<'a>>,
+        name: Option<&'a [u8]>,
+    ) -> Symbol<'a> {
+        Symbol::Frame {
+            addr: addr as usize as *mut c_void,
+            location,
+            name,
+        }
+    }
 }
 
 impl Symbol<'_> {
+    fn location(&self) -> Option<&addr2line::Location<'_>> {
+        match self {
+            Symbol::Frame { location, .. } => location.as_ref(),
+            Symbol::Symtab { .. } | Symbol::Dladdr { .. } => None,
+        }
+    }
+
     pub fn name(&self) -> Option<SymbolName<'_>> {
         match self {
             Symbol::Frame { name, .. } => {
                 let name = name.as_ref()?;
                 Some(SymbolName::new(name))
             }
-            Symbol::Symtab { name, .. } => Some(SymbolName::new(name)),
+            Symbol::Symtab { name, .. } | Symbol::Dladdr { name, .. } => {
+                Some(SymbolName::new(name))
+            }
         }
     }
 
     pub fn addr(&self) -> Option<*mut c_void> {
         match self {
-            Symbol::Frame { addr, .. } => Some(*addr),
+            Symbol::Frame { addr, .. } | Symbol::Dladdr { addr, .. } => Some(*addr),
             Symbol::Symtab { .. } => None,
         }
     }
 
-    pub fn filename_raw(&self) -> Option<BytesOrWideString<'_>> {
+    pub fn offset(&self) -> Option<usize> {
         match self {
-            Symbol::Frame { location, .. } => {
-                let file = location.as_ref()?.file?;
-                Some(BytesOrWideString::Bytes(file.as_bytes()))
-            }
-            Symbol::Symtab { .. } => None,
+            Symbol::Dladdr { offset, .. } => Some(*offset),
+            Symbol::Frame { .. } | Symbol::Symtab { .. } => None,
         }
     }
 
-    pub fn filename(&self) -> Option<&Path> {
+    pub fn module_base(&self) -> Option<*mut c_void> {
         match self {
-            Symbol::Frame { location, .. } => {
-                let file = location.as_ref()?.file?;
-                Some(Path::new(file))
-            }
-            Symbol::Symtab { .. } => None,
+            Symbol::Dladdr { module_base, .. } => Some(*module_base),
+            Symbol::Frame { .. } | Symbol::Symtab { .. } => None,
         }
     }
 
-    pub fn lineno(&self) -> Option<u32> {
+    pub fn module_path(&self) -> Option<BytesOrWideString<'_>> {
         match self {
-            Symbol::Frame { location, .. } => location.as_ref()?.line,
-            Symbol::Symtab { .. } => None,
+            Symbol::Dladdr { module_path, .. } => module_path.map(BytesOrWideString::Bytes),
+            Symbol::Frame { .. } | Symbol::Symtab { .. } => None,
         }
     }
 
-    pub fn colno(&self) -> Option<u32> {
+    pub fn binding(&self) -> Option<SymbolBinding> {
         match self {
-            Symbol::Frame { location, .. } => location.as_ref()?.column,
-            Symbol::Symtab { .. } => None,
+            Symbol::Symtab { info, .. } => info.as_ref()?.binding,
+            Symbol::Frame { .. } | Symbol::Dladdr { .. } => None,
+        }
+    }
+
+    pub fn visibility(&self) -> Option<SymbolVisibility> {
+        match self {
+            Symbol::Symtab { info, .. } => Some(info.as_ref()?.visibility),
+            Symbol::Frame { .. } | Symbol::Dladdr { .. } => None,
+        }
+    }
+
+    pub fn version(&self) -> Option<SymbolVersion<'_>> {
+        match self {
+            Symbol::Symtab { info, .. } => info.as_ref()?.version,
+            Symbol::Frame { .. } | Symbol::Dladdr { .. } => None,
         }
     }
+
+    pub fn filename_raw(&self) -> Option<BytesOrWideString<'_>> {
+        let file = self.location()?.file?;
+        Some(BytesOrWideString::Bytes(file.as_bytes()))
+    }
+
+    pub fn filename(&self) -> Option<&Path> {
+        Some(Path::new(self.location()?.file?))
+    }
+
+    pub fn lineno(&self) -> Option<u32> {
+        self.location()?.line
+    }
+
+    pub fn colno(&self) -> Option<u32> {
+        self.location()?.column
+    }
 }
@@ -1,4 +1,7 @@
-use super::{gimli, Context, Endian, EndianSlice, Mapping, Path, Stash, Vec};
+#[cfg(not(feature = "minimal-symbolication"))]
+use super::{gimli, Endian, EndianSlice};
+use super::{Context, Mapping, Path, Stash, SymtabInfo, Vec};
+#[cfg(not(feature = "minimal-symbolication"))]
 use alloc::sync::Arc;
 use core::convert::TryFrom;
 use object::pe::{ImageDosHeader, ImageSymbol};
@@ -13,12 +16,22 @@ type Pe = object::pe::ImageNtHeaders32;
 type Pe = object::pe::ImageNtHeaders64;
 
 impl Mapping {
-    pub fn new(path: &Path) -> Option<Mapping> {
+    // Build IDs of PE files aren't known, so `_build_id` is always `None`.
+    pub fn new(path: &Path, _build_id: Option<&[u8]>) -> Option<Mapping> {
         let map = super::mmap(path)?;
         Mapping::mk(map, |data, stash| {
             Context::new(stash, Object::parse(data)?, None, None)
         })
     }
+
+    /// Creates a `Mapping` from a copy of the object file `data`.
+    #[cfg(feature = "std")]
+    pub fn from_bytes(data: &[u8]) -> Option<Mapping> {
+        let map = super::Mmap::copy_from(data)?;
+        Mapping::mk(map, |data, stash| {
+            Context::new(stash, Object::parse(data)?, None, None)
+        })
+    }
 }
 
 pub struct Object<'a> {
@@ -86,6 +99,16 @@ impl<'a> Object<'a> {
         )
     }
 
+    /// Passes the name, address and size of each symbol to `cb`, in order of
+    /// increasing address. COFF doesn't record the size of symbols.
+    pub fn for_each_symbol(&self, cb: &mut dyn FnMut(&'a [u8], u64, Option<u64>)) {
+        for &(addr, sym) in self.symbols.iter() {
+            if let Ok(name) = sym.name(self.strings) {
+                cb(name, addr as u64, None);
+            }
+        }
+    }
+
     pub fn search_symtab<'b>(&'b self, addr: u64) -> Option<&'b [u8]> {
         // Note that unlike other formats COFF doesn't embed the size of
         // each symbol. As a last ditch effort search for the *closest*
@@ -104,11 +127,34 @@ impl<'a> Object<'a> {
         self.symbols[i].1.name(self.strings).ok()
     }
 
-    pub(super) fn search_object_map(&self, _addr: u64) -> Option<(&Context<'_>, u64)> {
+    pub fn function_start(&self, _addr: u64) -> Option<u64> {
+        None
+    }
+
+    #[cfg(feature = "std")]
+    pub fn for_each_function(&self, _cb: &mut dyn FnMut(u64, u64)) {}
+
+    pub fn symtab_info(&self, _addr: u64) -> Option<SymtabInfo<'a>> {
+        None
+    }
+
+    pub fn find_symbol(&self, name: &[u8]) -> Option<(u64, Option<u64>)> {
+        super::scan_symbols(name, |cb| self.for_each_symbol(cb))
+    }
+
+    pub fn dynamic_symbol_count(&self) -> Option<usize> {
+        None
+    }
+
+    #[cfg(feature = "std")]
+    pub(super) fn for_each_object_map_symbol(&self, _cb: &mut dyn FnMut(u64, u64)) {}
+
+    pub(super) fn search_object_map(&self, _addr: u64, _open: bool) -> Option<(&Context<'_>, u64)> {
         None
     }
 }
 
+#[cfg(not(feature = "minimal-symbolication"))]
 pub(super) fn handle_split_dwarf<'data>(
     _package: Option<&gimli::DwarfPackage<EndianSlice<'data, Endian>>>,
     _stash: &'data Stash,
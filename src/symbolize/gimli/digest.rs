@@ -0,0 +1,544 @@
+//! Digests of the debug information of modules, which are cached on disk or
+//! kept in memory instead of the modules, see `set_symbol_cache_dir` and
+//! `set_compact_symbols`.
+//!
+//! A digest lists the ranges of addresses covered by the line tables of the
+//! DWARF debug information, including that of object files on macOS, each with
+//! the frames found for it, innermost inlined function first, along with the
+//! symbol table and, for binaries stripped of all symbols, the functions found
+//! in unwinding information. Everything is stored
+//! in little endian at fixed offsets, so digests are looked up right where
+//! they're mapped into memory:
+//!
+//! - header: magic, version, length of the build ID, build ID, and the number
+//!   of ranges, frames, symbols and functions, and the size of the string
+//!   table,
+//! - ranges, sorted by address: start, end, index of the first frame and
+//!   number of frames,
+//! - frames: name, file, line and column,
+//! - symbols, sorted by address: address, size, name, the binding and
+//!   visibility packed as in `pack_info`, and the name of the version,
+//! - functions, sorted by address: start and end,
+//! - the string table, of strings each preceded by their length.
+//!
+//! Names and files are offsets into the string table, and absent values are
+//! stored as all ones.
+
+use super::mystd::collections::HashMap;
+use super::mystd::fs;
+use super::mystd::path::PathBuf;
+use super::mystd::prelude::v1::*;
+use super::mystd::process;
+use super::{AnyObject, Context, Library, Mapping, Mmap, Stash, SymtabInfo};
+use crate::symbolize::symbol_cache::{compact_symbols, symbol_cache_dir};
+use crate::symbolize::{SymbolBinding, SymbolVersion, SymbolVisibility};
+use core::convert::TryInto;
+use core::mem;
+
+const MAGIC: &[u8; 8] = b"BTDIGEST";
+/// Changed whenever the layout changes, so stale digests are ignored.
+const VERSION: u32 = 2;
+const NONE: u32 = u32::MAX;
+const UNKNOWN_SIZE: u64 = u64::MAX;
+
+const RANGE_SIZE: usize = 24;
+const FRAME_SIZE: usize = 16;
+const SYMBOL_SIZE: usize = 28;
+const FUNCTION_SIZE: usize = 16;
+
+/// A digest borrowed from wherever it was read or mapped.
+pub struct Digest<'a> {
+    ranges: &'a [u8],
+    frames: &'a [u8],
+    symbols: &'a [u8],
+    functions: &'a [u8],
+    strings: &'a [u8],
+}
+
+/// A frame of a digest, see `Digest::frames`.
+pub struct DigestFrame<'a> {
+    pub name: Option<&'a [u8]>,
+    pub location: addr2line::Location<'a>,
+}
+
+impl<'a> Digest<'a> {
+    /// Parses the digest in `data`, which has to be for the build `build_id`
+    /// and of the current version.
+    pub fn parse(data: &'a [u8], build_id: &[u8]) -> Option<Digest<'a>> {
+        let mut reader = Reader(data);
+        if reader.bytes(MAGIC.len())? != MAGIC || reader.u32()? != VERSION {
+            return None;
+        }
+        let id_len = reader.u32()? as usize;
+        if reader.bytes(id_len)? != build_id {
+            return None;
+        }
+        let ranges = reader.u32()? as usize;
+        let frames = reader.u32()? as usize;
+        let symbols = reader.u32()? as usize;
+        let functions = reader.u32()? as usize;
+        let strings = reader.u32()? as usize;
+        let digest = Digest {
+            ranges: reader.bytes(ranges.checked_mul(RANGE_SIZE)?)?,
+            frames: reader.bytes(frames.checked_mul(FRAME_SIZE)?)?,
+            symbols: reader.bytes(symbols.checked_mul(SYMBOL_SIZE)?)?,
+            functions: reader.bytes(functions.checked_mul(FUNCTION_SIZE)?)?,
+            strings: reader.bytes(strings)?,
+        };
+        // Anything left over means the file is damaged.
+        if !reader.0.is_empty() {
+            return None;
+        }
+        Some(digest)
+    }
+
+    /// Tests whether the digest has any debug information, as opposed to just
+    /// the symbol table like those in symbol maps.
+    pub fn has_debuginfo(&self) -> bool {
+        !self.ranges.is_empty()
+    }
+
+    /// Returns the frames of the stated virtual memory address `addr`,
+    /// innermost inlined function first.
+    pub fn frames(&self, addr: u64) -> impl Iterator<Item = DigestFrame<'a>> + '_ {
+        let count = self.ranges.len() / RANGE_SIZE;
+        let i = partition_point(count, |i| u64_at(self.ranges, i * RANGE_SIZE) <= addr);
+        let range = i
+            .checked_sub(1)
+            .map(|i| &self.ranges[i * RANGE_SIZE..][..RANGE_SIZE])
+            .filter(|range| addr < u64_at(range, 8));
+        let (first, len) = match range {
+            Some(range) => (u32_at(range, 16) as usize, u32_at(range, 20) as usize),
+            None => (0, 0),
+        };
+        (first..first + len).filter_map(move |i| {
+            let frame = self.frames.get(i * FRAME_SIZE..)?.get(..FRAME_SIZE)?;
+            let line = Some(u32_at(frame, 8)).filter(|&n| n != NONE);
+            let column = Some(u32_at(frame, 12)).filter(|&n| n != NONE);
+            Some(DigestFrame {
+                name: self.string(u32_at(frame, 0)),
+                location: addr2line::Location {
+                    file: self
+                        .string(u32_at(frame, 4))
+                        .and_then(|file| core::str::from_utf8(file).ok()),
+                    line,
+                    column,
+                },
+            })
+        })
+    }
+
+    /// Calls `cb` with the name, address and size, if known, of each symbol.
+    pub fn for_each_symbol(&self, cb: &mut dyn FnMut(&'a [u8], u64, Option<u64>)) {
+        for symbol in self.symbols.chunks_exact(SYMBOL_SIZE) {
+            if let Some(name) = self.string(u32_at(symbol, 16)) {
+                let size = Some(u64_at(symbol, 8)).filter(|&size| size != UNKNOWN_SIZE);
+                cb(name, u64_at(symbol, 0), size);
+            }
+        }
+    }
+
+    pub fn search_symtab(&self, addr: u64) -> Option<&'a [u8]> {
+        self.string(u32_at(self.find_symbol(addr)?, 16))
+    }
+
+    pub fn symtab_info(&self, addr: u64) -> Option<SymtabInfo<'a>> {
+        let symbol = self.find_symbol(addr)?;
+        let version = self.string(u32_at(symbol, 24));
+        unpack_info(u32_at(symbol, 20), version)
+    }
+
+    /// Finds the start of the function containing `addr`, see the ELF
+    /// `Object::function_start`.
+    pub fn function_start(&self, addr: u64) -> Option<u64> {
+        let count = self.functions.len() / FUNCTION_SIZE;
+        let i = partition_point(count, |i| u64_at(self.functions, i * FUNCTION_SIZE) <= addr);
+        let function = &self.functions[i.checked_sub(1)? * FUNCTION_SIZE..][..FUNCTION_SIZE];
+        Some(u64_at(function, 0)).filter(|_| addr < u64_at(function, 8))
+    }
+
+    /// Passes the start and end of each function that `function_start` finds
+    /// to `cb`.
+    pub fn for_each_function(&self, cb: &mut dyn FnMut(u64, u64)) {
+        for function in self.functions.chunks_exact(FUNCTION_SIZE) {
+            cb(u64_at(function, 0), u64_at(function, 8));
+        }
+    }
+
+    /// Finds the entry of the symbol containing `addr`.
+    fn find_symbol(&self, addr: u64) -> Option<&'a [u8]> {
+        let count = self.symbols.len() / SYMBOL_SIZE;
+        let i = partition_point(count, |i| u64_at(self.symbols, i * SYMBOL_SIZE) <= addr);
+        let symbol = &self.symbols[i.checked_sub(1)? * SYMBOL_SIZE..][..SYMBOL_SIZE];
+        let size = u64_at(symbol, 8);
+        if size == UNKNOWN_SIZE || addr - u64_at(symbol, 0) <= size {
+            Some(symbol)
+        } else {
+            None
+        }
+    }
+
+    fn string(&self, offset: u32) -> Option<&'a [u8]> {
+        if offset == NONE {
+            return None;
+        }
+        let mut reader = Reader(self.strings.get(offset as usize..)?);
+        let len = reader.u32()? as usize;
+        reader.bytes(len)
+    }
+}
+
+/// Finds the mapping of the cached digest of `lib`, if there is one.
+pub fn load(lib: &Library) -> Option<Mapping> {
+    let build_id = lib.build_id.as_deref()?;
+    let path = digest_path(build_id)?;
+    if !path.exists() {
+        return None;
+    }
+    let mapping = Mapping::mk(super::mmap(&path)?, |data, stash| {
+        let digest = Digest::parse(data, build_id)?;
+        Context::new(stash, AnyObject::Digest(digest), None, None)
+    });
+    if mapping.is_none() {
+        super::diagnostics::report(format_args!(
+            "ignoring `{}` as it's damaged or of another version",
+            path.display()
+        ));
+    }
+    mapping
+}
+
+/// Digests the debug information of `mapping`, which was created for `lib`,
+/// saving the digest to the cache directory if one is set, and returns the
+/// mapping to keep, which is one of just the digest if `set_compact_symbols`
+/// asks for it.
+pub fn store(lib: &Library, mut mapping: Mapping) -> Mapping {
+    let path = lib.build_id.as_deref().and_then(digest_path);
+    let compact = compact_symbols();
+    // Mappings which didn't come with debug information, like those of the
+    // dynamic symbol table found in memory, aren't worth digesting.
+    if (path.is_none() && !compact) || !mapping.has_debuginfo() {
+        return mapping;
+    }
+    if let AnyObject::Digest(_) = mapping.cx.object {
+        return mapping;
+    }
+    // Like in `Cache::mapping_for_lib`, don't leak the `'static` lifetime.
+    let cx = unsafe { mem::transmute::<&mut Context<'static>, &mut Context<'_>>(&mut mapping.cx) };
+    let build_id = lib.build_id.as_deref().unwrap_or(&[]);
+    let data = digest(cx, &mapping.stash, build_id, true);
+
+    if let Some(path) = path {
+        // Write to a temporary file first, so that other processes never map
+        // a partially written digest.
+        let mut tmp = path.clone().into_os_string();
+        tmp.push(format!(".{}.tmp", process::id()));
+        let tmp = PathBuf::from(tmp);
+        let res = fs::write(&tmp, &data).and_then(|()| fs::rename(&tmp, &path));
+        if let Err(e) = res {
+            let _ = fs::remove_file(&tmp);
+            super::diagnostics::report(format_args!("couldn't save `{}`: {}", path.display(), e));
+        }
+    }
+
+    if !compact {
+        return mapping;
+    }
+    from_bytes(&data, build_id).unwrap_or(mapping)
+}
+
+/// Creates a mapping of a copy of the digest `data`, which has to be for the
+/// build `build_id`.
+pub fn from_bytes(data: &[u8], build_id: &[u8]) -> Option<Mapping> {
+    Mapping::mk(Mmap::copy_from(data)?, |data, stash| {
+        let digest = Digest::parse(data, build_id)?;
+        Context::new(stash, AnyObject::Digest(digest), None, None)
+    })
+}
+
+/// Digests only the symbol table of `mapping` for the build `build_id`, which
+/// is much faster than digesting its debug information too.
+pub fn digest_symbols(mapping: &mut Mapping, build_id: &[u8]) -> Vec<u8> {
+    // Like in `Cache::mapping_for_lib`, don't leak the `'static` lifetime.
+    let cx = unsafe { mem::transmute::<&mut Context<'static>, &mut Context<'_>>(&mut mapping.cx) };
+    digest(cx, &mapping.stash, build_id, false)
+}
+
+fn digest_path(build_id: &[u8]) -> Option<PathBuf> {
+    let dir = symbol_cache_dir()?;
+    let mut name = String::with_capacity(build_id.len() * 2 + 7);
+    for byte in build_id {
+        name.push_str(&format!("{:02x}", byte));
+    }
+    name.push_str(".digest");
+    Some(dir.join(name))
+}
+
+/// Digests the symbol table of `cx`, and its debug information if `debuginfo`
+/// is set, for the build `build_id`.
+fn digest<'a>(cx: &mut Context<'a>, stash: &'a Stash, build_id: &[u8], debuginfo: bool) -> Vec<u8> {
+    let mut strings = Strings::default();
+    let open = !stash.is_sealed();
+
+    let mut rows = Vec::new();
+    if debuginfo {
+        if let Ok(iter) = cx.dwarf.find_location_range(0, u64::MAX) {
+            for (start, len, _) in iter {
+                if len != 0 {
+                    rows.push((start, start.saturating_add(len)));
+                }
+            }
+        }
+        // Symbols whose debug information is in object files, which are
+        // translated back to addresses of the module.
+        let mut object_symbols = Vec::new();
+        cx.object
+            .for_each_object_map_symbol(&mut |addr, size| object_symbols.push((addr, size)));
+        for (addr, size) in object_symbols {
+            let (object_cx, object_addr) = match cx.object.search_object_map(addr, open) {
+                Some(found) => found,
+                None => continue,
+            };
+            let end = object_addr.saturating_add(size);
+            if let Ok(iter) = object_cx.dwarf.find_location_range(object_addr, end) {
+                for (start, len, _) in iter {
+                    let start = start.wrapping_sub(object_addr).wrapping_add(addr);
+                    if len != 0 {
+                        rows.push((start, start.saturating_add(len)));
+                    }
+                }
+            }
+        }
+    }
+    rows.sort_unstable();
+
+    let mut ranges: Vec<(u64, u64, u32, u32)> = Vec::new();
+    let mut frames: Vec<[u32; 4]> = Vec::new();
+    let mut row_frames = Vec::new();
+    for (start, end) in rows {
+        row_frames.clear();
+        let mut add_frame = |name: Option<&[u8]>, location: Option<&addr2line::Location<'_>>| {
+            row_frames.push([
+                strings.add(name),
+                strings.add(location.and_then(|l| l.file).map(str::as_bytes)),
+                location.and_then(|l| l.line).unwrap_or(NONE),
+                location.and_then(|l| l.column).unwrap_or(NONE),
+            ]);
+        };
+        // Like in `resolve_debuginfo`, fall back to the object files.
+        let mut any_frames = false;
+        cx.find_frames(stash, start, &mut |name, location| {
+            any_frames = true;
+            add_frame(
+                name.or_else(|| cx.object.search_symtab(start)),
+                location.as_ref(),
+            );
+        });
+        if !any_frames {
+            if let Some((object_cx, object_addr)) = cx.object.search_object_map(start, open) {
+                object_cx.find_frames(stash, object_addr, &mut |name, location| {
+                    add_frame(name, location.as_ref());
+                });
+            }
+        }
+        if row_frames.is_empty() {
+            continue;
+        }
+        let mut start = start;
+        if let Some(last) = ranges.last_mut() {
+            // Rows of different units may overlap, in which case the first
+            // one wins and the rest of the later one is kept.
+            if last.1 > start {
+                if last.1 >= end {
+                    continue;
+                }
+                start = last.1;
+            }
+            // Merge adjacent rows with the same frames, which only differ in
+            // where statements start and such.
+            let last_frames = &frames[last.2 as usize..][..last.3 as usize];
+            if last.1 == start && last_frames == &row_frames[..] {
+                last.1 = end;
+                continue;
+            }
+        }
+        ranges.push((start, end, frames.len() as u32, row_frames.len() as u32));
+        frames.extend_from_slice(&row_frames);
+    }
+
+    let mut symbols = Vec::new();
+    cx.object.for_each_symbol(&mut |name, addr, size| {
+        symbols.push((addr, size.unwrap_or(UNKNOWN_SIZE), name));
+    });
+    symbols.sort_by_key(|&(addr, _, _)| addr);
+    let symbols = symbols
+        .into_iter()
+        .map(|(addr, size, name)| {
+            let info = cx.object.symtab_info(addr);
+            let version = info
+                .and_then(|info| info.version)
+                .map(|version| version.name);
+            (
+                addr,
+                size,
+                strings.add(Some(name)),
+                info.map_or(NONE, pack_info),
+                strings.add(version),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let mut functions = Vec::new();
+    cx.object
+        .for_each_function(&mut |start, end| functions.push((start, end)));
+    functions.sort_unstable();
+
+    let mut out = Vec::new();
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&VERSION.to_le_bytes());
+    out.extend_from_slice(&(build_id.len() as u32).to_le_bytes());
+    out.extend_from_slice(build_id);
+    out.extend_from_slice(&(ranges.len() as u32).to_le_bytes());
+    out.extend_from_slice(&(frames.len() as u32).to_le_bytes());
+    out.extend_from_slice(&(symbols.len() as u32).to_le_bytes());
+    out.extend_from_slice(&(functions.len() as u32).to_le_bytes());
+    out.extend_from_slice(&(strings.data.len() as u32).to_le_bytes());
+    for (start, end, first, len) in ranges {
+        out.extend_from_slice(&start.to_le_bytes());
+        out.extend_from_slice(&end.to_le_bytes());
+        out.extend_from_slice(&first.to_le_bytes());
+        out.extend_from_slice(&len.to_le_bytes());
+    }
+    for frame in frames {
+        for field in frame {
+            out.extend_from_slice(&field.to_le_bytes());
+        }
+    }
+    for (addr, size, name, info, version) in symbols {
+        out.extend_from_slice(&addr.to_le_bytes());
+        out.extend_from_slice(&size.to_le_bytes());
+        out.extend_from_slice(&name.to_le_bytes());
+        out.extend_from_slice(&info.to_le_bytes());
+        out.extend_from_slice(&version.to_le_bytes());
+    }
+    for (start, end) in functions {
+        out.extend_from_slice(&start.to_le_bytes());
+        out.extend_from_slice(&end.to_le_bytes());
+    }
+    out.extend_from_slice(&strings.data);
+    out
+}
+
+/// Packs what `info` records besides the version name into the binding in the
+/// lowest four bits, with zero for none, the visibility in the next four, and
+/// whether the version is hidden in the bit after.
+fn pack_info(info: SymtabInfo<'_>) -> u32 {
+    let binding = match info.binding {
+        None => 0,
+        Some(SymbolBinding::Local) => 1,
+        Some(SymbolBinding::Global) => 2,
+        Some(SymbolBinding::Weak) => 3,
+        Some(SymbolBinding::Unique) => 4,
+    };
+    let visibility = match info.visibility {
+        SymbolVisibility::Default => 0,
+        SymbolVisibility::Internal => 1,
+        SymbolVisibility::Hidden => 2,
+        SymbolVisibility::Protected => 3,
+    };
+    let hidden = info.version.map_or(false, |version| version.hidden);
+    binding | visibility << 4 | (hidden as u32) << 8
+}
+
+/// Reverses `pack_info`, given the name of the version.
+fn unpack_info(info: u32, version: Option<&[u8]>) -> Option<SymtabInfo<'_>> {
+    if info == NONE {
+        return None;
+    }
+    let binding = match info & 0xf {
+        1 => Some(SymbolBinding::Local),
+        2 => Some(SymbolBinding::Global),
+        3 => Some(SymbolBinding::Weak),
+        4 => Some(SymbolBinding::Unique),
+        _ => None,
+    };
+    let visibility = match (info >> 4) & 0xf {
+        0 => SymbolVisibility::Default,
+        1 => SymbolVisibility::Internal,
+        2 => SymbolVisibility::Hidden,
+        _ => SymbolVisibility::Protected,
+    };
+    let version = version.map(|name| SymbolVersion {
+        name,
+        hidden: info & 0x100 != 0,
+    });
+    Some(SymtabInfo {
+        binding,
+        visibility,
+        version,
+    })
+}
+
+/// The string table of a digest being built, storing each string once.
+#[derive(Default)]
+struct Strings {
+    data: Vec<u8>,
+    offsets: HashMap<Vec<u8>, u32>,
+}
+
+impl Strings {
+    fn add(&mut self, s: Option<&[u8]>) -> u32 {
+        let s = match s {
+            Some(s) => s,
+            None => return NONE,
+        };
+        if let Some(&offset) = self.offsets.get(s) {
+            return offset;
+        }
+        let offset = self.data.len() as u32;
+        self.data.extend_from_slice(&(s.len() as u32).to_le_bytes());
+        self.data.extend_from_slice(s);
+        self.offsets.insert(s.to_vec(), offset);
+        offset
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.0.len() < len {
+            return None;
+        }
+        let (bytes, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Some(bytes)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.bytes(4)?.try_into().ok()?))
+    }
+}
+
+fn u64_at(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
+
+fn u32_at(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+/// Returns the index of the first of `count` entries for which `pred` is
+/// false, given that it's true for all entries before it.
+fn partition_point(count: usize, pred: impl Fn(usize) -> bool) -> usize {
+    let (mut low, mut high) = (0, count);
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if pred(mid) {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+    low
+}
@@ -1,16 +1,27 @@
-use super::mystd::ffi::{OsStr, OsString};
+use super::diagnostics;
+use super::mystd::ffi::OsStr;
 use super::mystd::fs;
-use super::mystd::os::unix::ffi::{OsStrExt, OsStringExt};
+use super::mystd::io::{self, Read};
+use super::mystd::os::unix::ffi::OsStrExt;
 use super::mystd::path::{Path, PathBuf};
+use super::mystd::sync::Mutex;
+use super::mystd::time::SystemTime;
 use super::Either;
-use super::{gimli, Context, Endian, EndianSlice, Mapping, Stash, Vec};
+use super::FileAccess;
+#[cfg(not(feature = "minimal-symbolication"))]
+use super::{gimli, EndianSlice};
+use super::{Context, Endian, LoadedSection, Mapping, Stash, SymtabInfo, Vec};
+use super::{SymbolBinding, SymbolVersion, SymbolVisibility};
+#[cfg(not(feature = "minimal-symbolication"))]
 use alloc::sync::Arc;
 use core::convert::{TryFrom, TryInto};
 use core::str;
 use object::elf::{ELFCOMPRESS_ZLIB, ELF_NOTE_GNU, NT_GNU_BUILD_ID, SHF_COMPRESSED};
-use object::read::elf::{CompressionHeader, FileHeader, SectionHeader, SectionTable, Sym};
-use object::read::StringTable;
-use object::{BigEndian, Bytes, NativeEndian};
+use object::read::elf::{
+    CompressionHeader, FileHeader, SectionHeader, SectionTable, Sym, VersionIndex, VersionTable,
+};
+use object::read::{SectionIndex, StringTable, SymbolIndex};
+use object::{BigEndian, Bytes, NativeEndian, U32Bytes};
 
 #[cfg(target_pointer_width = "32")]
 type Elf = object::elf::FileHeader32<NativeEndian>;
@@ -18,39 +29,137 @@ type Elf = object::elf::FileHeader32<NativeEndian>;
 type Elf = object::elf::FileHeader64<NativeEndian>;
 
 impl Mapping {
-    pub fn new(path: &Path) -> Option<Mapping> {
+    pub fn new(path: &Path, build_id: Option<&[u8]>) -> Option<Mapping> {
         let map = super::mmap(path)?;
         Mapping::mk_or_other(map, |map, stash| {
             let object = Object::parse(&map)?;
+            if !super::same_build(path, object.build_id(), build_id) {
+                return None;
+            }
 
             // Try to locate an external debug file using the build ID.
-            if let Some(path_debug) = object.build_id().and_then(locate_build_id) {
-                if let Some(mapping) = Mapping::new_debug(path, path_debug, None) {
-                    return Some(Either::A(mapping));
+            if let Some(build_id) = object.build_id() {
+                if let Some(path_debug) = locate_build_id(build_id) {
+                    let expected = Expected::BuildId(build_id);
+                    if let Some(mapping) = Mapping::new_debug(path, path_debug, expected) {
+                        return Some(Either::A(mapping));
+                    }
                 }
             }
 
             // Try to locate an external debug file using the GNU debug link section.
             if let Some((path_debug, crc)) = object.gnu_debuglink_path(path) {
-                if let Some(mapping) = Mapping::new_debug(path, path_debug, Some(crc)) {
+                if let Some(mapping) = Mapping::new_debug(path, path_debug, Expected::Crc(crc)) {
                     return Some(Either::A(mapping));
                 }
             }
 
+            // Try to locate an external debug file by the path of the object
+            // file, if it has no debug information of its own.
+            if object.section_header(".debug_info").is_none() {
+                if let Some(path_debug) = locate_by_path(path) {
+                    let expected = match object.build_id() {
+                        Some(build_id) => Expected::BuildId(build_id),
+                        None => Expected::Nothing,
+                    };
+                    if let Some(mapping) = Mapping::new_debug(path, path_debug, expected) {
+                        return Some(Either::A(mapping));
+                    }
+                }
+            }
+
             let dwp = Mapping::load_dwarf_package(path, stash);
 
             Context::new(stash, object, None, dwp).map(Either::B)
         })
     }
 
-    /// Load debuginfo from an external debug file.
-    fn new_debug(original_path: &Path, path: PathBuf, crc: Option<u32>) -> Option<Mapping> {
+    /// Creates a `Mapping` from a copy of the object file `data`, without
+    /// looking for separate debug information anywhere.
+    ///
+    /// This is used for the vDSO, which isn't backed by a file but whose
+    /// complete image is in memory, and for object files provided by callers.
+    #[cfg(any(
+        feature = "std",
+        all(
+            any(target_os = "linux", target_os = "android"),
+            not(target_env = "uclibc")
+        )
+    ))]
+    pub fn from_bytes(data: &[u8]) -> Option<Mapping> {
+        let map = super::Mmap::copy_from(data)?;
+        Mapping::mk(map, |map, stash| {
+            Context::new(stash, Object::parse(map)?, None, None)
+        })
+    }
+
+    /// Creates a `Mapping` from the image of a library loaded into memory, for
+    /// when it isn't backed by a file which can be opened, like libraries
+    /// loaded from a memfd or deleted from a minimal container.
+    ///
+    /// Debug information usually isn't loaded, so only the dynamic symbol
+    /// table is available.
+    #[cfg(all(
+        any(target_os = "linux", target_os = "android"),
+        not(target_env = "uclibc")
+    ))]
+    pub fn new_loaded(lib: &super::Library) -> Option<Mapping> {
+        // SAFETY: `lib` describes a library which is still loaded.
+        let (image, base) = unsafe { copy_loaded_image(lib)? };
+        let map = super::Mmap::copy_from(&image)?;
+        Mapping::mk(map, |image, stash| {
+            let object = Object::parse_loaded(image, base as u64, lib.bias as u64)?;
+            Context::new(stash, object, None, None)
+        })
+    }
+
+    /// Creates a `Mapping` from the image of a module loaded at `base` into
+    /// the process `pid`, for when its object file can't be opened, returning
+    /// it along with the stated virtual memory address the image starts at.
+    ///
+    /// Like for `new_loaded`, only the dynamic symbol table is available.
+    #[cfg(all(
+        feature = "std",
+        any(target_os = "linux", target_os = "android"),
+        not(target_env = "uclibc")
+    ))]
+    pub fn new_remote(pid: u32, base: u64, size: u64) -> Option<(Mapping, u64)> {
+        let (image, svma, bias) = copy_process_image(pid, base, size)?;
+        let map = super::Mmap::copy_from(&image)?;
+        let mapping = Mapping::mk(map, |image, stash| {
+            let object = Object::parse_loaded(image, svma, bias)?;
+            Context::new(stash, object, None, None)
+        })?;
+        Some((mapping, svma))
+    }
+
+    /// Load debuginfo from an external debug file, unless it doesn't match
+    /// `expected`.
+    fn new_debug(original_path: &Path, path: PathBuf, expected: Expected<'_>) -> Option<Mapping> {
         let map = super::mmap(&path)?;
         Mapping::mk(map, |map, stash| {
             let object = Object::parse(&map)?;
 
-            if let Some(_crc) = crc {
-                // TODO: check crc
+            // Debug files left behind by another build would resolve symbols
+            // to wrong lines, so make sure this is the right one.
+            let mismatch = match expected {
+                Expected::BuildId(build_id) => object
+                    .build_id()
+                    .map_or(false, |b| b != build_id)
+                    .then_some("build ID"),
+                Expected::Crc(crc) => {
+                    (file_crc(&path, map)? != crc).then_some("CRC in the debug link")
+                }
+                Expected::Nothing => None,
+            };
+            if let Some(mismatch) = mismatch {
+                diagnostics::report(format_args!(
+                    "ignoring `{}` as it doesn't match the {} of `{}`",
+                    path.display(),
+                    mismatch,
+                    original_path.display()
+                ));
+                return None;
             }
 
             // Try to locate a supplementary object file.
@@ -61,6 +170,12 @@ impl Mapping {
                     if let Some(sup_) = Object::parse(map_sup) {
                         if sup_.build_id() == Some(build_id_sup) {
                             sup = Some(sup_);
+                        } else {
+                            diagnostics::report(format_args!(
+                                "build ID of `{}` doesn't match the one expected by `{}`",
+                                path_sup.display(),
+                                path.display()
+                            ));
                         }
                     }
                 }
@@ -74,6 +189,10 @@ impl Mapping {
 
     /// Try to locate a DWARF package file.
     fn load_dwarf_package<'data>(path: &Path, stash: &'data Stash) -> Option<Object<'data>> {
+        // Packages are only needed to look up functions.
+        if cfg!(feature = "minimal-symbolication") {
+            return None;
+        }
         let mut path_dwp = path.to_path_buf();
         let dwp_extension = path
             .extension()
@@ -84,6 +203,9 @@ impl Mapping {
             })
             .unwrap_or_else(|| "dwp".into());
         path_dwp.set_extension(dwp_extension);
+        if !path_dwp.is_file() {
+            return None;
+        }
         if let Some(map_dwp) = super::mmap(&path_dwp) {
             let map_dwp = stash.cache_mmap(map_dwp);
             if let Some(dwp_) = Object::parse(map_dwp) {
@@ -99,6 +221,14 @@ struct ParsedSym {
     address: u64,
     size: u64,
     name: u32,
+    /// Whether this symbol is from the dynamic symbol table, and so its name
+    /// is in `Object::dynamic_strings`.
+    dynamic: bool,
+    /// The `st_bind` of the symbol in the high and its `st_visibility` in the
+    /// low bits, packed so that symbols don't take more memory.
+    binding: u8,
+    /// The index of the version of a dynamic symbol in `Object::versions`.
+    version: u16,
 }
 
 pub struct Object<'a> {
@@ -110,6 +240,16 @@ pub struct Object<'a> {
     data: &'a [u8],
     sections: SectionTable<'a, Elf>,
     strings: StringTable<'a>,
+    dynamic_strings: StringTable<'a>,
+    /// The versions of the symbols in the dynamic symbol table.
+    versions: VersionTable<'a, Elf>,
+    /// The dynamic symbol table, in its original order.
+    dynamic_syms: &'a [<Elf as FileHeader>::Sym],
+    /// The GNU hash table of `dynamic_syms`, if there's one.
+    gnu_hash: Option<GnuHash<'a>>,
+    /// Whether `syms` contains symbols of the full symbol table, rather than
+    /// only dynamic ones.
+    has_symtab: bool,
     /// List of pre-parsed and sorted symbols by base address.
     syms: Vec<ParsedSym>,
 }
@@ -119,52 +259,122 @@ impl<'a> Object<'a> {
         let elf = Elf::parse(data).ok()?;
         let endian = elf.endian().ok()?;
         let sections = elf.sections(endian, data).ok()?;
-        let mut syms = sections
+        let symtab = sections
             .symbols(endian, data, object::elf::SHT_SYMTAB)
             .ok()?;
-        if syms.is_empty() {
-            syms = sections
-                .symbols(endian, data, object::elf::SHT_DYNSYM)
-                .ok()?;
-        }
-        let strings = syms.strings();
-
-        let mut syms = syms
+        // Stripped binaries usually still have a dynamic symbol table, which
+        // also names exported functions missing from a partial symbol table.
+        let dynsym = sections
+            .symbols(endian, data, object::elf::SHT_DYNSYM)
+            .ok()?;
+        let versions = sections.versions(endian, data).ok().flatten();
+        let versions = versions.unwrap_or_default();
+        let gnu_hash = sections
             .iter()
-            // Only look at function/object symbols. This mirrors what
-            // libbacktrace does and in general we're only symbolicating
-            // function addresses in theory. Object symbols correspond
-            // to data, and maybe someone's crazy enough to have a
-            // function go into static data?
-            .filter(|sym| {
-                let st_type = sym.st_type();
-                st_type == object::elf::STT_FUNC || st_type == object::elf::STT_OBJECT
-            })
-            // skip anything that's in an undefined section header,
-            // since it means it's an imported function and we're only
-            // symbolicating with locally defined functions.
-            .filter(|sym| sym.st_shndx(endian) != object::elf::SHN_UNDEF)
-            .map(|sym| {
-                let address = sym.st_value(endian).into();
-                let size = sym.st_size(endian).into();
-                let name = sym.st_name(endian);
-                ParsedSym {
-                    address,
-                    size,
-                    name,
-                }
-            })
-            .collect::<Vec<_>>();
-        syms.sort_unstable_by_key(|s| s.address);
+            .find(|section| section.sh_type(endian) == object::elf::SHT_GNU_HASH)
+            .and_then(|section| GnuHash::parse(section.data(endian, data).ok()?));
+        let mut syms = parse_syms(endian, symtab.symbols(), false, &VersionTable::default());
+        let has_symtab = !syms.is_empty();
+        let dynamic_syms = parse_syms(endian, dynsym.symbols(), true, &versions);
+        super::merge_dynamic_syms(&mut syms, dynamic_syms, |s| s.address);
         Some(Object {
             endian,
             data,
             sections,
-            strings,
+            strings: symtab.strings(),
+            dynamic_strings: dynsym.strings(),
+            versions,
+            dynamic_syms: dynsym.symbols(),
+            gnu_hash,
+            has_symtab,
             syms,
         })
     }
 
+    /// Parses the dynamic symbol table of an image copied from memory by
+    /// `copy_loaded_image`, which starts at the stated virtual memory address
+    /// `base` and was loaded with the given `bias`.
+    ///
+    /// Without section headers, which usually aren't loaded, the dynamic
+    /// symbol table is found through the `PT_DYNAMIC` segment instead.
+    #[cfg(all(
+        any(target_os = "linux", target_os = "android"),
+        not(target_env = "uclibc")
+    ))]
+    #[allow(clippy::useless_conversion)] // the conversions are needed for 32-bit ELF
+    fn parse_loaded(image: &'a [u8], base: u64, bias: u64) -> Option<Object<'a>> {
+        use object::elf::{
+            DT_GNU_HASH, DT_HASH, DT_NULL, DT_STRSZ, DT_STRTAB, DT_SYMTAB, PT_DYNAMIC,
+        };
+        use object::read::elf::{Dyn, ProgramHeader};
+
+        let elf = Elf::parse(image).ok()?;
+        let endian = elf.endian().ok()?;
+        // The segment containing the headers was loaded from the start of the
+        // file, so their file offsets are also offsets into `image`.
+        let dynamic = elf
+            .program_headers(endian, image)
+            .ok()?
+            .iter()
+            .find(|header| header.p_type(endian) == PT_DYNAMIC)?;
+        let offset = |svma: u64| usize::try_from(svma.checked_sub(base)?).ok();
+        let dynamic = image.get(offset(dynamic.p_vaddr(endian).into())?..)?;
+        let dynamic = Bytes(dynamic)
+            .read_slice::<<Elf as FileHeader>::Dyn>(
+                dynamic.len() / core::mem::size_of::<<Elf as FileHeader>::Dyn>(),
+            )
+            .ok()?;
+
+        // Some dynamic linkers, like glibc's, relocate the addresses in the
+        // dynamic section in place, so translate them back if necessary.
+        let ptr = |value: u64| {
+            if bias != 0 && value >= bias.wrapping_add(base) {
+                offset(value - bias)
+            } else {
+                offset(value)
+            }
+        };
+        let (mut symtab, mut strtab, mut strsz, mut count) = (None, None, None, None);
+        let mut gnu_hash = None;
+        for entry in dynamic {
+            let value = entry.d_val(endian).into();
+            let tag: u64 = entry.d_tag(endian).into();
+            let tag = match u32::try_from(tag) {
+                Ok(tag) => tag,
+                Err(_) => continue,
+            };
+            match tag {
+                DT_NULL => break,
+                DT_SYMTAB => symtab = ptr(value),
+                DT_STRTAB => strtab = ptr(value),
+                DT_STRSZ => strsz = Some(value),
+                // The number of symbols isn't recorded anywhere, except
+                // indirectly in the hash tables.
+                DT_HASH => count = count.or_else(|| sysv_hash_len(image, ptr(value)?)),
+                DT_GNU_HASH => gnu_hash = ptr(value).and_then(|p| GnuHash::parse(image.get(p..)?)),
+                _ => {}
+            }
+        }
+        let count = count.or_else(|| gnu_hash.as_ref()?.symbol_count());
+        let syms = Bytes(image.get(symtab?..)?)
+            .read_slice::<<Elf as FileHeader>::Sym>(count?)
+            .ok()?;
+        let strtab = strtab? as u64;
+        let strings = StringTable::new(image, strtab, strtab.checked_add(strsz?)?);
+        Some(Object {
+            endian,
+            data: image,
+            sections: SectionTable::default(),
+            strings: StringTable::default(),
+            dynamic_strings: strings,
+            versions: VersionTable::default(),
+            dynamic_syms: syms,
+            gnu_hash,
+            has_symtab: false,
+            syms: parse_syms(endian, syms, true, &VersionTable::default()),
+        })
+    }
+
     pub fn section(&self, stash: &'a Stash, name: &str) -> Option<&'a [u8]> {
         if let Some(section) = self.section_header(name) {
             let mut data = Bytes(section.data(self.endian, self.data).ok()?);
@@ -178,14 +388,17 @@ impl<'a> Object<'a> {
             }
 
             let header = data.read::<<Elf as FileHeader>::CompressionHeader>().ok()?;
-            if header.ch_type(self.endian) != ELFCOMPRESS_ZLIB {
+            let ch_type = header.ch_type(self.endian);
+            if ch_type != ELFCOMPRESS_ZLIB {
                 // Zlib compression is the only known type.
+                diagnostics::report(format_args!(
+                    "section `{}` uses unsupported compression type {}",
+                    name, ch_type
+                ));
                 return None;
             }
             let size = usize::try_from(header.ch_size(self.endian)).ok()?;
-            let buf = stash.allocate(size);
-            decompress_zlib(data.0, buf)?;
-            return Some(buf);
+            return super::decompress_section(stash, name, data.0, size);
         }
 
         // Check for the nonstandard GNU compression format, i.e., as generated
@@ -210,12 +423,14 @@ impl<'a> Object<'a> {
             .next()?;
         let mut data = Bytes(compressed_section.data(self.endian, self.data).ok()?);
         if data.read_bytes(8).ok()?.0 != b"ZLIB\0\0\0\0" {
+            diagnostics::report(format_args!(
+                "section `.zdebug_{}` has an unsupported compression header",
+                &name[7..]
+            ));
             return None;
         }
         let size = usize::try_from(data.read::<object::U32Bytes<_>>().ok()?.get(BigEndian)).ok()?;
-        let buf = stash.allocate(size);
-        decompress_zlib(data.0, buf)?;
-        Some(buf)
+        super::decompress_section(stash, name, data.0, size)
     }
 
     fn section_header(&self, name: &str) -> Option<&<Elf as FileHeader>::SectionHeader> {
@@ -224,7 +439,100 @@ impl<'a> Object<'a> {
             .map(|(_index, section)| section)
     }
 
+    /// Passes the name, address and size of each symbol to `cb`, in order of
+    /// increasing address.
+    pub fn for_each_symbol(&self, cb: &mut dyn FnMut(&'a [u8], u64, Option<u64>)) {
+        for sym in self.syms.iter() {
+            if let Some(name) = self.sym_name(sym) {
+                cb(name, sym.address, Some(sym.size));
+            }
+        }
+    }
+
+    /// Finds the symbol named `name`, returning its address and size.
+    ///
+    /// Dynamic symbols are looked up in the GNU hash table if there's one, so
+    /// only symbols of the full symbol table need to be searched one by one.
+    #[allow(clippy::useless_conversion)] // the conversions are needed for 32-bit ELF
+    pub fn find_symbol(&self, name: &[u8]) -> Option<(u64, Option<u64>)> {
+        if let Some(hash) = &self.gnu_hash {
+            let named = |i: usize, hidden: bool| {
+                let sym = match self.dynamic_syms.get(i) {
+                    Some(sym) => sym,
+                    None => return false,
+                };
+                let version = self.versions.version_index(self.endian, SymbolIndex(i));
+                is_defined(self.endian, sym)
+                    && version.is_hidden() == hidden
+                    && self.dynamic_strings.get(sym.st_name(self.endian)) == Ok(name)
+            };
+            // Prefer the default version of symbols with several.
+            let found = hash
+                .find(name, |i| named(i, false))
+                .or_else(|| hash.find(name, |i| named(i, true)));
+            if let Some(i) = found {
+                let sym = &self.dynamic_syms[i];
+                return Some((
+                    sym.st_value(self.endian).into(),
+                    Some(sym.st_size(self.endian).into()),
+                ));
+            }
+            if !self.has_symtab {
+                return None;
+            }
+        }
+        self.syms
+            .iter()
+            .find(|sym| self.sym_name(sym) == Some(name))
+            .map(|sym| (sym.address, Some(sym.size)))
+    }
+
+    /// Returns the number of entries of the dynamic symbol table, including
+    /// the null symbol and undefined ones.
+    ///
+    /// For images copied from memory, this is only known from the hash
+    /// tables.
+    pub fn dynamic_symbol_count(&self) -> Option<usize> {
+        Some(self.dynamic_syms.len()).filter(|&count| count > 0)
+    }
+
     pub fn search_symtab<'b>(&'b self, addr: u64) -> Option<&'b [u8]> {
+        self.sym_name(self.find_sym(addr)?)
+    }
+
+    /// Returns the binding, visibility and version of the symbol containing
+    /// `addr`.
+    pub fn symtab_info(&self, addr: u64) -> Option<SymtabInfo<'a>> {
+        let sym = self.find_sym(addr)?;
+        let binding = match sym.binding >> 4 {
+            object::elf::STB_LOCAL => Some(SymbolBinding::Local),
+            object::elf::STB_GLOBAL => Some(SymbolBinding::Global),
+            object::elf::STB_WEAK => Some(SymbolBinding::Weak),
+            object::elf::STB_GNU_UNIQUE => Some(SymbolBinding::Unique),
+            _ => None,
+        };
+        let visibility = match sym.binding & 0x3 {
+            object::elf::STV_DEFAULT => SymbolVisibility::Default,
+            object::elf::STV_INTERNAL => SymbolVisibility::Internal,
+            object::elf::STV_HIDDEN => SymbolVisibility::Hidden,
+            _ => SymbolVisibility::Protected,
+        };
+        let version = VersionIndex(sym.version);
+        let version = match self.versions.version(version) {
+            Ok(Some(name)) => Some(SymbolVersion {
+                name: name.name(),
+                hidden: version.is_hidden(),
+            }),
+            _ => None,
+        };
+        Some(SymtabInfo {
+            binding,
+            visibility,
+            version,
+        })
+    }
+
+    fn find_sym(&self, addr: u64) -> Option<&ParsedSym> {
         // Same sort of binary search as Windows above
         let i = match self.syms.binary_search_by_key(&addr, |sym| sym.address) {
             Ok(i) => i,
@@ -232,13 +540,72 @@ impl<'a> Object<'a> {
         };
         let sym = self.syms.get(i)?;
         if sym.address <= addr && addr <= sym.address + sym.size {
-            self.strings.get(sym.name).ok()
+            Some(sym)
         } else {
             None
         }
     }
 
-    pub(super) fn search_object_map(&self, _addr: u64) -> Option<(&Context<'_>, u64)> {
+    fn sym_name(&self, sym: &ParsedSym) -> Option<&'a [u8]> {
+        let strings = if sym.dynamic {
+            &self.dynamic_strings
+        } else {
+            &self.strings
+        };
+        strings.get(sym.name).ok()
+    }
+
+    /// Finds the start of the function containing `addr` from unwinding
+    /// information, for binaries stripped of all symbols.
+    pub fn function_start(&self, addr: u64) -> Option<u64> {
+        // Only make up names where nothing else is known, so that functions
+        // merely missing from the symbols of a binary stay unnamed.
+        if !self.syms.is_empty() {
+            return None;
+        }
+        super::eh_frame_function_start(
+            Endian::default(),
+            core::mem::size_of::<usize>() as u8,
+            self.loaded_section(".eh_frame_hdr")?,
+            self.loaded_section(".eh_frame")?,
+            addr,
+        )
+    }
+
+    /// Passes the start and end of each function that `function_start` finds
+    /// to `cb`.
+    #[cfg(feature = "std")]
+    pub fn for_each_function(&self, cb: &mut dyn FnMut(u64, u64)) {
+        if !self.syms.is_empty() {
+            return;
+        }
+        if let (Some(eh_frame_hdr), Some(eh_frame)) = (
+            self.loaded_section(".eh_frame_hdr"),
+            self.loaded_section(".eh_frame"),
+        ) {
+            super::eh_frame_functions(
+                Endian::default(),
+                core::mem::size_of::<usize>() as u8,
+                eh_frame_hdr,
+                eh_frame,
+                cb,
+            );
+        }
+    }
+
+    #[allow(clippy::useless_conversion)] // the conversion is needed for 32-bit ELF
+    fn loaded_section(&self, name: &str) -> Option<LoadedSection<'a>> {
+        let header = self.section_header(name)?;
+        Some(LoadedSection {
+            data: header.data(self.endian, self.data).ok()?,
+            address: header.sh_addr(self.endian).into(),
+        })
+    }
+
+    #[cfg(feature = "std")]
+    pub(super) fn for_each_object_map_symbol(&self, _cb: &mut dyn FnMut(u64, u64)) {}
+
+    pub(super) fn search_object_map(&self, _addr: u64, _open: bool) -> Option<(&Context<'_>, u64)> {
         None
     }
 
@@ -283,48 +650,395 @@ impl<'a> Object<'a> {
     }
 }
 
-fn decompress_zlib(input: &[u8], output: &mut [u8]) -> Option<()> {
-    use miniz_oxide::inflate::core::inflate_flags::{
-        TINFL_FLAG_PARSE_ZLIB_HEADER, TINFL_FLAG_USING_NON_WRAPPING_OUTPUT_BUF,
+/// Returns the number of symbols covered by the SysV hash table at `offset`.
+#[cfg(all(
+    any(target_os = "linux", target_os = "android"),
+    not(target_env = "uclibc")
+))]
+fn sysv_hash_len(image: &[u8], offset: usize) -> Option<usize> {
+    // The header is made of the number of buckets and the number of chains,
+    // which is the number of symbols.
+    let word = image.get(offset + 4..offset + 8)?;
+    usize::try_from(u32::from_ne_bytes(word.try_into().ok()?)).ok()
+}
+
+/// A GNU hash table, which finds the symbols of a dynamic symbol table by
+/// name.
+///
+/// Symbols before `symoffset` aren't hashed. Each bucket holds the first
+/// symbol of its chain, and the chain holds the hashes of its symbols, the
+/// last one marked by the lowest bit.
+struct GnuHash<'a> {
+    symoffset: usize,
+    bloom_shift: u32,
+    /// Made of native words, unlike the rest of the table.
+    bloom: &'a [u8],
+    buckets: &'a [U32Bytes<NativeEndian>],
+    chains: &'a [U32Bytes<NativeEndian>],
+}
+
+impl<'a> GnuHash<'a> {
+    /// Parses the GNU hash table at the start of `data`, which may go on past
+    /// its end, as its length is only known from the symbols in it.
+    fn parse(data: &'a [u8]) -> Option<GnuHash<'a>> {
+        let mut data = Bytes(data);
+        let header = data.read_slice::<U32Bytes<NativeEndian>>(4).ok()?;
+        let word = |i: usize| usize::try_from(header[i].get(NativeEndian)).ok();
+        let (nbuckets, symoffset, bloom_size) = (word(0)?, word(1)?, word(2)?);
+        let bloom_len = bloom_size.checked_mul(core::mem::size_of::<usize>())?;
+        let bloom = data.read_bytes(bloom_len).ok()?.0;
+        let buckets = data.read_slice(nbuckets).ok()?;
+        let chains = data.read_slice(data.len() / 4).ok()?;
+        Some(GnuHash {
+            symoffset,
+            bloom_shift: header[3].get(NativeEndian),
+            bloom,
+            buckets,
+            chains,
+        })
+    }
+
+    /// Returns the number of symbols of the dynamic symbol table, which ends
+    /// with the last symbol of the last chain.
+    fn symbol_count(&self) -> Option<usize> {
+        let last = self
+            .buckets
+            .iter()
+            .map(|bucket| bucket.get(NativeEndian) as usize)
+            .max()?;
+        if last < self.symoffset {
+            return Some(self.symoffset);
+        }
+        let mut sym = last;
+        while self.chains.get(sym - self.symoffset)?.get(NativeEndian) & 1 == 0 {
+            sym += 1;
+        }
+        Some(sym + 1)
+    }
+
+    /// Returns the index of the first symbol which could be named `name` for
+    /// which `matches` returns `true`.
+    fn find(&self, name: &[u8], mut matches: impl FnMut(usize) -> bool) -> Option<usize> {
+        const BITS: u32 = usize::BITS;
+        let hash = object::elf::gnu_hash(name);
+
+        // The bloom filter rules out most symbols which aren't in the table
+        // without looking at the chains.
+        let words = self.bloom.len() / core::mem::size_of::<usize>();
+        let word = (hash / BITS) as usize % words.max(1) * core::mem::size_of::<usize>();
+        let word = self.bloom.get(word..word + core::mem::size_of::<usize>())?;
+        let word = usize::from_ne_bytes(word.try_into().ok()?);
+        if word >> (hash % BITS) & 1 == 0 || word >> ((hash >> self.bloom_shift) % BITS) & 1 == 0 {
+            return None;
+        }
+
+        let bucket = self
+            .buckets
+            .get(hash as usize % self.buckets.len().max(1))?;
+        let mut sym = bucket.get(NativeEndian) as usize;
+        if sym < self.symoffset {
+            return None;
+        }
+        loop {
+            let chain = self.chains.get(sym - self.symoffset)?.get(NativeEndian);
+            if chain | 1 == hash | 1 && matches(sym) {
+                return Some(sym);
+            }
+            if chain & 1 != 0 {
+                return None;
+            }
+            sym += 1;
+        }
+    }
+}
+
+/// Copies the readable `PT_LOAD` segments of the ELF image loaded into memory
+/// for `lib` into a buffer, each at its stated virtual memory address minus
+/// the lowest one of `lib`, which is returned along with the buffer.
+///
+/// This relies on the ELF header being loaded at the lowest address, as the
+/// segment containing it is loaded from the start of the file.
+#[cfg(all(
+    any(target_os = "linux", target_os = "android"),
+    not(target_env = "uclibc")
+))]
+unsafe fn copy_loaded_image(lib: &super::Library) -> Option<(Vec<u8>, usize)> {
+    use core::{mem, slice};
+    use object::elf::{PF_R, PT_LOAD};
+    use object::read::elf::ProgramHeader;
+
+    // Only ever read memory within the segments reported for `lib`.
+    let loaded = |svma: usize, len: usize| {
+        lib.segments.iter().any(|segment| {
+            let start = segment.stated_virtual_memory_address;
+            start <= svma && svma.saturating_add(len) <= start.saturating_add(segment.len)
+        })
     };
-    use miniz_oxide::inflate::core::{decompress, DecompressorOxide};
-    use miniz_oxide::inflate::TINFLStatus;
-
-    let (status, in_read, out_read) = decompress(
-        &mut DecompressorOxide::new(),
-        input,
-        output,
-        0,
-        TINFL_FLAG_USING_NON_WRAPPING_OUTPUT_BUF | TINFL_FLAG_PARSE_ZLIB_HEADER,
-    );
-    if status == TINFLStatus::Done && in_read == input.len() && out_read == output.len() {
-        Some(())
-    } else {
-        None
+    let read = |svma: usize, len: usize| {
+        if !loaded(svma, len) {
+            return None;
+        }
+        Some(slice::from_raw_parts(
+            lib.bias.wrapping_add(svma) as *const u8,
+            len,
+        ))
+    };
+
+    let base = lib
+        .segments
+        .iter()
+        .filter(|segment| segment.len > 0)
+        .map(|segment| segment.stated_virtual_memory_address)
+        .min()?;
+    let elf = Elf::parse(read(base, mem::size_of::<Elf>())?).ok()?;
+    let endian = elf.endian().ok()?;
+    let phoff = usize::try_from(elf.e_phoff(endian)).ok()?;
+    let phnum = usize::from(elf.e_phnum(endian));
+    let headers = read(
+        base.checked_add(phoff)?,
+        phnum * mem::size_of::<<Elf as FileHeader>::ProgramHeader>(),
+    )?;
+    let headers = Bytes(headers)
+        .read_slice::<<Elf as FileHeader>::ProgramHeader>(phnum)
+        .ok()?;
+
+    let mut image = Vec::new();
+    for header in headers {
+        if header.p_type(endian) != PT_LOAD || header.p_flags(endian) & PF_R == 0 {
+            continue;
+        }
+        let svma = usize::try_from(header.p_vaddr(endian)).ok()?;
+        let len = usize::try_from(header.p_memsz(endian)).ok()?;
+        let data = read(svma, len)?;
+        let start = svma.checked_sub(base)?;
+        if image.len() < start + len {
+            image.resize(start + len, 0);
+        }
+        image[start..start + len].copy_from_slice(data);
     }
+    Some((image, base))
 }
 
-const DEBUG_PATH: &[u8] = b"/usr/lib/debug";
+/// Copies the loadable segments of the ELF image loaded at `base` into the
+/// process `pid`, like `copy_loaded_image` does for the current process.
+///
+/// Returns the image along with the stated virtual memory address it starts
+/// at and the bias it was loaded with. Only memory within `size` bytes of
+/// `base` is read, and segments are cut off there.
+#[cfg(all(
+    feature = "std",
+    any(target_os = "linux", target_os = "android"),
+    not(target_env = "uclibc")
+))]
+#[allow(clippy::useless_conversion)] // the conversions are needed for 32-bit ELF
+fn copy_process_image(pid: u32, base: u64, size: u64) -> Option<(Vec<u8>, u64, u64)> {
+    use core::mem;
+    use object::elf::{PF_R, PT_LOAD};
+    use object::read::elf::ProgramHeader;
+
+    let read = |offset: u64, len: usize| {
+        let end = offset.checked_add(u64::try_from(len).ok()?)?;
+        if end > size {
+            return None;
+        }
+        let mut buf = vec![0; len];
+        read_process_memory(pid, base + offset, &mut buf)?;
+        Some(buf)
+    };
 
-fn debug_path_exists() -> bool {
-    cfg_if::cfg_if! {
-        if #[cfg(any(target_os = "freebsd", target_os = "hurd", target_os = "linux"))] {
-            use core::sync::atomic::{AtomicU8, Ordering};
-            static DEBUG_PATH_EXISTS: AtomicU8 = AtomicU8::new(0);
+    // The segment containing the headers was loaded from the start of the
+    // file, which is what `base` points to.
+    let header = read(0, mem::size_of::<Elf>())?;
+    let elf = Elf::parse(&*header).ok()?;
+    let endian = elf.endian().ok()?;
+    let phnum = usize::from(elf.e_phnum(endian));
+    let headers = read(
+        elf.e_phoff(endian).into(),
+        phnum * mem::size_of::<<Elf as FileHeader>::ProgramHeader>(),
+    )?;
+    let headers = Bytes(&headers)
+        .read_slice::<<Elf as FileHeader>::ProgramHeader>(phnum)
+        .ok()?;
 
-            let mut exists = DEBUG_PATH_EXISTS.load(Ordering::Relaxed);
-            if exists == 0 {
-                exists = if Path::new(OsStr::from_bytes(DEBUG_PATH)).is_dir() {
-                    1
-                } else {
-                    2
-                };
-                DEBUG_PATH_EXISTS.store(exists, Ordering::Relaxed);
+    let svma = headers
+        .iter()
+        .filter(|header| header.p_type(endian) == PT_LOAD)
+        .map(|header| header.p_vaddr(endian).into())
+        .min()?;
+    let mut image = Vec::new();
+    for header in headers {
+        if header.p_type(endian) != PT_LOAD || header.p_flags(endian) & PF_R == 0 {
+            continue;
+        }
+        let start = u64::from(header.p_vaddr(endian)) - svma;
+        // Writable segments usually end in zero-initialized memory which isn't
+        // part of the mappings of the module's file, and isn't needed anyway.
+        let len = u64::from(header.p_memsz(endian)).min(size.saturating_sub(start));
+        let data = read(start, usize::try_from(len).ok()?)?;
+        let start = usize::try_from(start).ok()?;
+        if image.len() < start + data.len() {
+            image.resize(start + data.len(), 0);
+        }
+        image[start..start + data.len()].copy_from_slice(&data);
+    }
+    Some((image, svma, base.wrapping_sub(svma)))
+}
+
+/// Reads `buf.len()` bytes at `addr` in the memory of the process `pid`.
+///
+/// `process_vm_readv` is tried first, and `/proc/<pid>/mem` where it's not
+/// permitted, as is common in containers.
+#[cfg(all(
+    feature = "std",
+    any(target_os = "linux", target_os = "android"),
+    not(target_env = "uclibc")
+))]
+fn read_process_memory(pid: u32, addr: u64, buf: &mut [u8]) -> Option<()> {
+    use super::mystd::os::unix::fs::FileExt;
+
+    let local = libc::iovec {
+        iov_base: buf.as_mut_ptr().cast(),
+        iov_len: buf.len(),
+    };
+    let remote = libc::iovec {
+        iov_base: usize::try_from(addr).ok()? as *mut _,
+        iov_len: buf.len(),
+    };
+    let pid = libc::pid_t::try_from(pid).ok()?;
+    // SAFETY: `local` describes `buf`, and the memory of the other process is
+    // only read.
+    let read = unsafe { libc::process_vm_readv(pid, &local, 1, &remote, 1, 0) };
+    if usize::try_from(read).ok() == Some(buf.len()) {
+        return Some(());
+    }
+    let mem = fs::File::open(format!("/proc/{}/mem", pid)).ok()?;
+    mem.read_exact_at(buf, addr).ok()
+}
+
+/// Reads the parts of the ELF file `file` needed to resolve symbols into
+/// `map`, which is as large as the file and zeroed, at the same offsets.
+///
+/// These are the headers along with all sections which aren't loaded into
+/// memory, like symbol tables and debug information, and the few loaded ones
+/// which are used as well, like the unwinding information function starts are
+/// found with in stripped binaries. Returns `None` if `file` isn't an ELF file or can't
+/// be read.
+#[allow(clippy::useless_conversion)] // the conversions are needed for 32-bit ELF
+pub(super) fn read_needed(file: &fs::File, map: &mut super::Mmap) -> Option<()> {
+    use core::mem;
+    use object::elf::{SHF_ALLOC, SHT_DYNSYM, SHT_NOBITS, SHT_NOTE};
+
+    map.read_at(file, 0, mem::size_of::<Elf>())?;
+    let elf = *Elf::parse(&**map).ok()?;
+    let endian = elf.endian().ok()?;
+    let shoff = usize::try_from(elf.e_shoff(endian)).ok()?;
+    if shoff == 0 {
+        return Some(());
+    }
+    let shentsize = usize::from(elf.e_shentsize(endian));
+    // The number of sections may be stored in the first section header.
+    map.read_at(file, shoff, shentsize)?;
+    let shnum = elf.shnum(endian, &**map).ok()?;
+    map.read_at(file, shoff, shnum.checked_mul(shentsize)?)?;
+    // Section names are needed to tell which of the loaded sections are used.
+    let headers = elf.section_headers(endian, &**map).ok()?;
+    let shstrndx = elf.shstrndx(endian, &**map).ok()?;
+    let read = |map: &mut super::Mmap, (offset, size): (u64, u64)| {
+        let offset = usize::try_from(offset).ok()?;
+        map.read_at(file, offset, usize::try_from(size).ok()?)
+    };
+    read(map, headers.get(shstrndx as usize)?.file_range(endian)?)?;
+
+    let mut ranges = Vec::new();
+    let sections = elf.sections(endian, &**map).ok()?;
+    for section in sections.iter() {
+        let kind = section.sh_type(endian);
+        let flags: u64 = section.sh_flags(endian).into();
+        let name = sections.section_name(endian, section).unwrap_or(&[]);
+        let needed = kind == SHT_NOTE
+            || kind == SHT_DYNSYM
+            || flags & u64::from(SHF_ALLOC) == 0
+            || name == b".eh_frame_hdr"
+            || name == b".eh_frame";
+        if !needed || kind == SHT_NOBITS {
+            continue;
+        }
+        ranges.push(section.file_range(endian)?);
+        // The dynamic symbol table refers to a string table which is loaded.
+        if kind == SHT_DYNSYM {
+            let link = SectionIndex(section.sh_link(endian) as usize);
+            ranges.push(sections.section(link).ok()?.file_range(endian)?);
+        }
+    }
+    for range in ranges {
+        read(map, range)?;
+    }
+    Some(())
+}
+
+/// Tests whether `sym` is a function or data defined in its object file.
+fn is_defined(endian: NativeEndian, sym: &<Elf as FileHeader>::Sym) -> bool {
+    // Only look at function/object symbols. This mirrors what
+    // libbacktrace does and in general we're only symbolicating
+    // function addresses in theory. Object symbols correspond
+    // to data, and maybe someone's crazy enough to have a
+    // function go into static data?
+    let st_type = sym.st_type();
+    if st_type != object::elf::STT_FUNC && st_type != object::elf::STT_OBJECT {
+        return false;
+    }
+    // skip anything that's in an undefined section header,
+    // since it means it's an imported function and we're only
+    // symbolicating with locally defined functions.
+    sym.st_shndx(endian) != object::elf::SHN_UNDEF
+}
+
+fn parse_syms(
+    endian: NativeEndian,
+    syms: &[<Elf as FileHeader>::Sym],
+    dynamic: bool,
+    versions: &VersionTable<'_, Elf>,
+) -> Vec<ParsedSym> {
+    let mut syms = syms
+        .iter()
+        .enumerate()
+        .filter(|(_, sym)| is_defined(endian, sym))
+        .map(|(i, sym)| {
+            let address = sym.st_value(endian).into();
+            let size = sym.st_size(endian).into();
+            let name = sym.st_name(endian);
+            ParsedSym {
+                address,
+                size,
+                name,
+                dynamic,
+                binding: sym.st_bind() << 4 | sym.st_visibility(),
+                version: versions.version_index(endian, SymbolIndex(i)).0,
             }
-            exists == 1
-        } else {
-            false
+        })
+        .collect::<Vec<_>>();
+    syms.sort_unstable_by_key(|s| s.address);
+    syms
+}
+
+/// Returns the global debug directories and the directories relative to
+/// binaries which debug files are searched in, see `set_debug_search_paths`.
+fn debug_dirs() -> (Vec<PathBuf>, Vec<PathBuf>) {
+    #[cfg(feature = "std")]
+    return crate::symbolize::debug_dirs::debug_dirs();
+    #[cfg(not(feature = "std"))]
+    {
+        let mut global_dirs = Vec::new();
+        if cfg!(any(
+            target_os = "freebsd",
+            target_os = "hurd",
+            target_os = "linux"
+        )) {
+            global_dirs.push(PathBuf::from("/usr/lib/debug"));
         }
+        let mut relative_dirs = Vec::new();
+        relative_dirs.push(PathBuf::from(".debug"));
+        (global_dirs, relative_dirs)
     }
 }
 
@@ -333,29 +1047,34 @@ fn debug_path_exists() -> bool {
 /// The format of build id paths is documented at:
 /// https://sourceware.org/gdb/onlinedocs/gdb/Separate-Debug-Files.html
 fn locate_build_id(build_id: &[u8]) -> Option<PathBuf> {
-    const BUILD_ID_PATH: &[u8] = b"/usr/lib/debug/.build-id/";
+    const BUILD_ID_DIR: &[u8] = b".build-id/";
     const BUILD_ID_SUFFIX: &[u8] = b".debug";
 
     if build_id.len() < 2 {
         return None;
     }
 
-    if !debug_path_exists() {
+    let (global_dirs, _) = debug_dirs();
+    if global_dirs.is_empty() {
         return None;
     }
 
-    let mut path =
-        Vec::with_capacity(BUILD_ID_PATH.len() + BUILD_ID_SUFFIX.len() + build_id.len() * 2 + 1);
-    path.extend(BUILD_ID_PATH);
-    path.push(hex(build_id[0] >> 4));
-    path.push(hex(build_id[0] & 0xf));
-    path.push(b'/');
+    let mut name =
+        Vec::with_capacity(BUILD_ID_DIR.len() + BUILD_ID_SUFFIX.len() + build_id.len() * 2 + 1);
+    name.extend(BUILD_ID_DIR);
+    name.push(hex(build_id[0] >> 4));
+    name.push(hex(build_id[0] & 0xf));
+    name.push(b'/');
     for byte in &build_id[1..] {
-        path.push(hex(byte >> 4));
-        path.push(hex(byte & 0xf));
+        name.push(hex(byte >> 4));
+        name.push(hex(byte & 0xf));
     }
-    path.extend(BUILD_ID_SUFFIX);
-    Some(PathBuf::from(OsString::from_vec(path)))
+    name.extend(BUILD_ID_SUFFIX);
+    let name = Path::new(OsStr::from_bytes(&name));
+    global_dirs
+        .iter()
+        .map(|dir| dir.join(name))
+        .find(|path| path.is_file())
 }
 
 fn hex(byte: u8) -> u8 {
@@ -366,6 +1085,94 @@ fn hex(byte: u8) -> u8 {
     }
 }
 
+/// What an external debug file has to match to be used, see
+/// `Mapping::new_debug`.
+#[derive(Copy, Clone)]
+enum Expected<'a> {
+    /// The build ID of the object file, which debug files found by it should
+    /// have too.
+    BuildId(&'a [u8]),
+    /// The CRC of the whole debug file, recorded in the `.gnu_debuglink`
+    /// section it was found by.
+    Crc(u32),
+    /// Nothing, for debug files found by the path of an object file without a
+    /// build ID.
+    Nothing,
+}
+
+/// The CRCs computed by `file_crc` so far, along with the paths and times of
+/// the last modification of the files they were computed for.
+static FILE_CRCS: Mutex<Vec<(PathBuf, SystemTime, u32)>> = Mutex::new(Vec::new());
+
+/// Computes the CRC of the file at `path`, which is mapped as `map`, like the
+/// one recorded in `.gnu_debuglink` sections.
+///
+/// This goes through the whole file, so the CRC is only computed again once
+/// the file was modified.
+fn file_crc(path: &Path, map: &[u8]) -> Option<u32> {
+    let modified = fs::metadata(path).and_then(|m| m.modified()).ok();
+    let mut crcs = FILE_CRCS.lock().unwrap_or_else(|e| e.into_inner());
+    let cached = crcs
+        .iter()
+        .find(|(p, m, _)| p == path && Some(*m) == modified);
+    if let Some(&(_, _, crc)) = cached {
+        return Some(crc);
+    }
+    let crc = compute_file_crc(path, map)?;
+    if let Some(modified) = modified {
+        crcs.retain(|(p, _, _)| p != path);
+        crcs.push((path.to_path_buf(), modified, crc));
+    }
+    Some(crc)
+}
+
+fn compute_file_crc(path: &Path, map: &[u8]) -> Option<u32> {
+    // Only the parts needed to resolve symbols are read with
+    // `FileAccess::Read`, so the rest has to be read here.
+    if super::file_access(path) != FileAccess::Read {
+        return Some(!crc32(!0, map));
+    }
+    let mut file = fs::File::open(path).ok()?;
+    let mut buf = [0; 0x4000];
+    let mut crc = !0;
+    loop {
+        match file.read(&mut buf) {
+            Ok(0) => return Some(!crc),
+            Ok(n) => crc = crc32(crc, &buf[..n]),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(_) => return None,
+        }
+    }
+}
+
+/// Updates the CRC-32 `crc` with `data`, as in zlib and gdb but without
+/// inverting the value before and after.
+fn crc32(mut crc: u32, data: &[u8]) -> u32 {
+    const TABLE: [u32; 256] = {
+        let mut table = [0; 256];
+        let mut i = 0;
+        while i < 256 {
+            let mut c = i as u32;
+            let mut k = 0;
+            while k < 8 {
+                c = if c & 1 != 0 {
+                    0xedb8_8320 ^ (c >> 1)
+                } else {
+                    c >> 1
+                };
+                k += 1;
+            }
+            table[i] = c;
+            i += 1;
+        }
+        table
+    };
+    for &byte in data {
+        crc = TABLE[((crc ^ u32::from(byte)) & 0xff) as usize] ^ (crc >> 8);
+    }
+    crc
+}
+
 /// Locate a file specified in a `.gnu_debuglink` section.
 ///
 /// `path` is the file containing the section.
@@ -374,43 +1181,70 @@ fn hex(byte: u8) -> u8 {
 /// Search order is based on gdb, documented at:
 /// https://sourceware.org/gdb/onlinedocs/gdb/Separate-Debug-Files.html
 ///
-/// gdb also allows the user to customize the debug search path, but we don't.
+/// The directories searched are configured with `set_debug_search_paths`,
+/// like gdb's debug search path.
 ///
 /// gdb also supports debuginfod, but we don't yet.
 fn locate_debuglink(path: &Path, filename: &[u8]) -> Option<PathBuf> {
     let path = fs::canonicalize(path).ok()?;
     let parent = path.parent()?;
-    let mut f = PathBuf::from(OsString::with_capacity(
-        DEBUG_PATH.len() + parent.as_os_str().len() + filename.len() + 2,
-    ));
     let filename = Path::new(OsStr::from_bytes(filename));
+    let (global_dirs, relative_dirs) = debug_dirs();
 
     // Try "/parent/filename" if it differs from "path"
-    f.push(parent);
-    f.push(filename);
+    let f = parent.join(filename);
     if f != path && f.is_file() {
         return Some(f);
     }
 
-    // Try "/parent/.debug/filename"
-    let mut s = OsString::from(f);
-    s.clear();
-    f = PathBuf::from(s);
-    f.push(parent);
-    f.push(".debug");
-    f.push(filename);
-    if f.is_file() {
-        return Some(f);
+    // Try "/parent/.debug/filename" and the other relative directories
+    for dir in &relative_dirs {
+        let f = parent.join(dir).join(filename);
+        if f.is_file() {
+            return Some(f);
+        }
     }
 
-    if debug_path_exists() {
-        // Try "/usr/lib/debug/parent/filename"
-        let mut s = OsString::from(f);
-        s.clear();
-        f = PathBuf::from(s);
-        f.push(OsStr::from_bytes(DEBUG_PATH));
-        f.push(parent.strip_prefix("/").unwrap());
-        f.push(filename);
+    // Try "/usr/lib/debug/parent/filename" and the other global directories
+    let parent = parent.strip_prefix("/").ok()?;
+    for dir in &global_dirs {
+        let f = dir.join(parent).join(filename);
+        if f.is_file() {
+            return Some(f);
+        }
+    }
+
+    None
+}
+
+/// Locate a debug file by the path of the object file `path`, for object
+/// files without a build ID or debug link, or whose debug files weren't found
+/// by them.
+///
+/// Search order is based on the conventions of gdb followed by Debian's
+/// debug packages and the sysroots of embedded SDKs like Yocto's:
+/// - "/parent/.debug/filename" in the relative debug directories
+/// - "/usr/lib/debug/parent/filename.debug" in the global debug directories
+fn locate_by_path(path: &Path) -> Option<PathBuf> {
+    let path = fs::canonicalize(path).ok()?;
+    let parent = path.parent()?;
+    let filename = path.file_name()?;
+    let (global_dirs, relative_dirs) = debug_dirs();
+
+    // Try "/parent/.debug/filename" and the other relative directories
+    for dir in &relative_dirs {
+        let f = parent.join(dir).join(filename);
+        if f != path && f.is_file() {
+            return Some(f);
+        }
+    }
+
+    // Try "/usr/lib/debug/parent/filename.debug" and the other global
+    // directories
+    let mut name = path.strip_prefix("/").ok()?.as_os_str().to_os_string();
+    name.push(".debug");
+    for dir in &global_dirs {
+        let f = dir.join(&name);
         if f.is_file() {
             return Some(f);
         }
@@ -426,9 +1260,7 @@ fn locate_debuglink(path: &Path, filename: &[u8]) -> Option<PathBuf> {
 ///
 /// Search order is based on gdb:
 /// - filename, which is either absolute or relative to `path`
-/// - the build ID path under `BUILD_ID_PATH`
-///
-/// gdb also allows the user to customize the debug search path, but we don't.
+/// - the build ID path in the global debug directories
 ///
 /// gdb also supports debuginfod, but we don't yet.
 fn locate_debugaltlink(path: &Path, filename: &[u8], build_id: &[u8]) -> Option<PathBuf> {
@@ -450,11 +1282,80 @@ fn locate_debugaltlink(path: &Path, filename: &[u8], build_id: &[u8]) -> Option<
     locate_build_id(build_id)
 }
 
+#[cfg(not(feature = "minimal-symbolication"))]
 fn convert_path<R: gimli::Reader>(r: &R) -> Result<PathBuf, gimli::Error> {
     let bytes = r.to_slice()?;
     Ok(PathBuf::from(OsStr::from_bytes(&bytes)))
 }
 
+/// Returns the ELF image of the vDSO mapped into the current process by the
+/// kernel, if any.
+#[cfg(all(
+    any(target_os = "linux", target_os = "android"),
+    not(target_env = "uclibc")
+))]
+pub(super) fn vdso() -> Option<&'static [u8]> {
+    use core::slice;
+
+    extern "C" {
+        fn getauxval(kind: libc::c_ulong) -> libc::c_ulong;
+    }
+    const AT_SYSINFO_EHDR: libc::c_ulong = 33;
+
+    let base = unsafe { getauxval(AT_SYSINFO_EHDR) } as *const u8;
+    if base.is_null() {
+        return None;
+    }
+    // SAFETY: the vDSO stays mapped for the lifetime of the process, and the
+    // kernel maps all of its image.
+    unsafe {
+        let len = image_len(base)?;
+        Some(slice::from_raw_parts(base, len))
+    }
+}
+
+/// Returns the length of the ELF image loaded at `base`.
+///
+/// The image is laid out just like the file it was linked to, so it ends with
+/// the last of its header tables.
+///
+/// # Safety
+///
+/// `base` must point to an ELF header, followed by the rest of the image.
+#[cfg(all(
+    any(target_os = "linux", target_os = "android"),
+    not(target_env = "uclibc")
+))]
+unsafe fn image_len(base: *const u8) -> Option<usize> {
+    use core::{mem, slice};
+
+    let header = slice::from_raw_parts(base, mem::size_of::<Elf>());
+    let elf = Elf::parse(header).ok()?;
+    let endian = elf.endian().ok()?;
+
+    fn table_end(offset: impl Into<u64>, count: usize, size: u16) -> Option<usize> {
+        usize::try_from(offset.into())
+            .ok()?
+            .checked_add(count.checked_mul(usize::from(size))?)
+    }
+    let shoff = elf.e_shoff(endian);
+    let shentsize = elf.e_shentsize(endian);
+    // With extended numbering, the number of sections is in the first section
+    // header instead.
+    let first_section = usize::try_from(shoff)
+        .ok()?
+        .checked_add(usize::from(shentsize))?;
+    let headers = slice::from_raw_parts(base, first_section);
+    let phdrs_end = table_end(
+        elf.e_phoff(endian),
+        usize::from(elf.e_phnum(endian)),
+        elf.e_phentsize(endian),
+    )?;
+    let shdrs_end = table_end(shoff, elf.shnum(endian, headers).ok()?, shentsize)?;
+    Some(phdrs_end.max(shdrs_end))
+}
+
+#[cfg(not(feature = "minimal-symbolication"))]
 pub(super) fn handle_split_dwarf<'data>(
     package: Option<&gimli::DwarfPackage<EndianSlice<'data, Endian>>>,
     stash: &'data Stash,
@@ -481,7 +1382,7 @@ pub(super) fn handle_split_dwarf<'data>(
                     .dwo_name()
                     .and_then(|name| dwo.section(stash, name))
                     .unwrap_or(&[]);
-                Ok(EndianSlice::new(data, Endian))
+                Ok(EndianSlice::new(data, Endian::default()))
             })
             .ok()
             .map(|mut dwo_dwarf| {
@@ -493,3 +1394,96 @@ pub(super) fn handle_split_dwarf<'data>(
 
     None
 }
+
+#[cfg(all(
+    any(target_os = "linux", target_os = "android"),
+    not(target_env = "uclibc")
+))]
+#[test]
+fn parse_loaded_dynamic_symbols() {
+    let addr = unsafe { libc::dlsym(libc::RTLD_DEFAULT, b"getpid\0".as_ptr().cast()) } as usize;
+    assert_ne!(addr, 0);
+    let libs = super::native_libraries();
+    let lib = libs.iter().find(|lib| lib.contains(addr)).unwrap();
+
+    let mapping = Mapping::new_loaded(lib).unwrap();
+    let name = mapping
+        .cx
+        .object
+        .search_symtab(addr.wrapping_sub(lib.bias) as u64)
+        .unwrap();
+    assert!(name.ends_with(b"getpid"), "{:?}", str::from_utf8(name));
+}
+
+#[test]
+fn computes_debuglink_crcs() {
+    assert_eq!(!crc32(!0, b""), 0);
+    assert_eq!(!crc32(!0, b"123456789"), 0xcbf4_3926);
+    assert_eq!(!crc32(crc32(!0, b"1234"), b"56789"), 0xcbf4_3926);
+
+    // The CRCs of files are only computed once, unless they're modified.
+    let name = alloc::format!("backtrace-crc-{}", super::mystd::process::id());
+    let path = super::mystd::env::temp_dir().join(name);
+    fs::write(&path, b"123456789").unwrap();
+    assert_eq!(file_crc(&path, b"123456789"), Some(0xcbf4_3926));
+    assert_eq!(file_crc(&path, b""), Some(0xcbf4_3926));
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn search_sorted_symbols() {
+    let data = fs::read(super::mystd::env::current_exe().unwrap()).unwrap();
+    let object = Object::parse(&data).unwrap();
+
+    // Symbols are sorted once when parsing, so that they can be searched with
+    // a binary search for each address afterwards.
+    let mut syms = Vec::new();
+    object.for_each_symbol(&mut |name, addr, size| syms.push((addr, size, name)));
+    assert!(syms.len() > 100);
+    assert!(syms.windows(2).all(|w| w[0].0 <= w[1].0));
+    for &(addr, _, _) in syms.iter().filter(|sym| sym.1 != Some(0)) {
+        let name = object.search_symtab(addr).unwrap();
+        assert!(
+            syms.iter().any(|sym| sym.0 == addr && sym.2 == name),
+            "{:?} isn't at {:#x}",
+            str::from_utf8(name),
+            addr
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(all(
+        any(target_os = "linux", target_os = "android"),
+        not(target_env = "uclibc"),
+        target_arch = "x86_64"
+    ))]
+    #[test]
+    fn image_len_with_extended_section_numbering() {
+        // See `tests/data/extended_sections.c`, its section headers come last.
+        let data = include_bytes!("../../../tests/data/extended_sections.elf");
+        // Copy the image so its headers are aligned.
+        let mut image = alloc::vec![0u64; (data.len() + 7) / 8];
+        unsafe {
+            core::ptr::copy_nonoverlapping(data.as_ptr(), image.as_mut_ptr().cast(), data.len());
+            assert_eq!(image_len(image.as_ptr().cast()), Some(data.len()));
+        }
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    fn reads_unwinding_information() {
+        // See `tests/data/stripped.c`, whose functions are only found through
+        // its unwinding information.
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/stripped.elf");
+        let file = fs::File::open(path).unwrap();
+        let len = usize::try_from(file.metadata().unwrap().len()).unwrap();
+        let mut map = super::super::Mmap::zeroed(len).unwrap();
+        read_needed(&file, &mut map).unwrap();
+        let object = Object::parse(&map).unwrap();
+        assert_eq!(object.function_start(0x1cc), Some(0x1c9));
+    }
+}
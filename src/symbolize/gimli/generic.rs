@@ -0,0 +1,231 @@
+use super::{Context, Endian, LoadedSection, Stash, Vec};
+use core::convert::TryFrom;
+use object::read::{CompressionFormat, File};
+use object::{
+    BinaryFormat, Object as _, ObjectSection as _, ObjectSegment as _, ObjectSymbol as _,
+    SymbolKind,
+};
+
+/// An object file in any format the `object` crate can read, as opposed to the
+/// native `Object` of the current platform.
+///
+/// This is less capable than the native `Object`s, for example it doesn't
+/// follow the debug map of Mach-O executables to their object files, but it
+/// allows resolving symbols of object files from other platforms.
+pub struct Object<'a> {
+    file: File<'a>,
+    syms: Vec<ParsedSym<'a>>,
+}
+
+struct ParsedSym<'a> {
+    address: u64,
+    size: u64,
+    name: &'a [u8],
+}
+
+impl<'a> Object<'a> {
+    pub fn parse(data: &'a [u8]) -> Option<Object<'a>> {
+        let file = File::parse(data).ok()?;
+
+        // Stripped binaries may still have a dynamic symbol table, which also
+        // names exported functions missing from a partial symbol table.
+        let mut syms = parse_syms(file.symbols());
+        super::merge_dynamic_syms(&mut syms, parse_syms(file.dynamic_symbols()), |s| s.address);
+        Some(Object { file, syms })
+    }
+
+    pub fn endian(&self) -> Endian {
+        if self.file.is_little_endian() {
+            Endian::Little
+        } else {
+            Endian::Big
+        }
+    }
+
+    /// Tests whether DWARF sections of this object use the names of XCOFF
+    /// rather than the usual ones.
+    pub fn is_xcoff(&self) -> bool {
+        self.file.format() == BinaryFormat::Xcoff
+    }
+
+    pub fn section(&self, stash: &'a Stash, name: &str) -> Option<&'a [u8]> {
+        let section = match self.file.section_by_name(name) {
+            Some(section) => section,
+            // Look for the nonstandard GNU compression format as well, see the
+            // ELF `Object::section`.
+            None if self.file.format() == BinaryFormat::Elf && name.starts_with(".debug_") => {
+                let name = alloc::format!(".zdebug_{}", &name[7..]);
+                self.file.section_by_name(&name)?
+            }
+            None => return None,
+        };
+        let compressed = section.compressed_data().ok()?;
+        match compressed.format {
+            CompressionFormat::None => Some(compressed.data),
+            CompressionFormat::Zlib => {
+                let size = usize::try_from(compressed.uncompressed_size).ok()?;
+                super::decompress_section(stash, name, compressed.data, size)
+            }
+            _ => {
+                super::diagnostics::report(format_args!(
+                    "section `{}` uses an unsupported compression type",
+                    name
+                ));
+                None
+            }
+        }
+    }
+
+    /// Passes the name, address and size of each symbol to `cb`, in order of
+    /// increasing address.
+    pub fn for_each_symbol(&self, cb: &mut dyn FnMut(&'a [u8], u64, Option<u64>)) {
+        for sym in self.syms.iter() {
+            cb(
+                sym.name,
+                sym.address,
+                Some(sym.size).filter(|&size| size != 0),
+            );
+        }
+    }
+
+    pub fn search_symtab(&self, addr: u64) -> Option<&[u8]> {
+        let i = match self.syms.binary_search_by_key(&addr, |sym| sym.address) {
+            Ok(i) => i,
+            Err(i) => i.checked_sub(1)?,
+        };
+        let sym = self.syms.get(i)?;
+        // Some formats don't record the size of symbols, in which case the
+        // closest symbol is the best guess.
+        if sym.size == 0 || addr - sym.address <= sym.size {
+            Some(sym.name)
+        } else {
+            None
+        }
+    }
+
+    /// Finds the start of the function containing `addr` from unwinding
+    /// information, for binaries stripped of all symbols, see the ELF
+    /// `Object::function_start`.
+    pub fn function_start(&self, addr: u64) -> Option<u64> {
+        if !self.syms.is_empty() {
+            return None;
+        }
+        super::eh_frame_function_start(
+            self.endian(),
+            self.address_size(),
+            self.loaded_section(".eh_frame_hdr")?,
+            self.loaded_section(".eh_frame")?,
+            addr,
+        )
+    }
+
+    /// Passes the start and end of each function that `function_start` finds
+    /// to `cb`.
+    pub fn for_each_function(&self, cb: &mut dyn FnMut(u64, u64)) {
+        if !self.syms.is_empty() {
+            return;
+        }
+        if let (Some(eh_frame_hdr), Some(eh_frame)) = (
+            self.loaded_section(".eh_frame_hdr"),
+            self.loaded_section(".eh_frame"),
+        ) {
+            super::eh_frame_functions(
+                self.endian(),
+                self.address_size(),
+                eh_frame_hdr,
+                eh_frame,
+                cb,
+            );
+        }
+    }
+
+    fn loaded_section(&self, name: &str) -> Option<LoadedSection<'a>> {
+        let section = self.file.section_by_name(name)?;
+        Some(LoadedSection {
+            data: section.data().ok()?,
+            address: section.address(),
+        })
+    }
+
+    fn address_size(&self) -> u8 {
+        if self.file.is_64() {
+            8
+        } else {
+            4
+        }
+    }
+
+    pub(super) fn search_object_map(&self, _addr: u64, _open: bool) -> Option<(&Context<'_>, u64)> {
+        None
+    }
+}
+
+/// Returns the address the object file `data` expects to be loaded at, which
+/// corresponds to the base address of the module loaded from it, along with
+/// its build ID.
+///
+/// Build IDs are the GNU build ID note of ELF files, the `LC_UUID` of Mach-O
+/// files, and the GUID followed by the little endian age of the PDB file
+/// referenced by PE files.
+pub fn image(data: &[u8]) -> Option<(u64, Option<Vec<u8>>)> {
+    let file = File::parse(data).ok()?;
+    let base = match file.format() {
+        BinaryFormat::Pe => file.relative_address_base(),
+        BinaryFormat::MachO => file
+            .segments()
+            .find(|segment| segment.name() == Ok(Some("__TEXT")))
+            .map_or(0, |segment| segment.address()),
+        _ => file
+            .segments()
+            .map(|segment| segment.address())
+            .min()
+            .unwrap_or(0),
+    };
+    let build_id = match file.format() {
+        BinaryFormat::Pe => file.pdb_info().ok().flatten().map(|info| {
+            let mut id = info.guid().to_vec();
+            id.extend_from_slice(&info.age().to_le_bytes());
+            id
+        }),
+        BinaryFormat::MachO => file.mach_uuid().ok().flatten().map(|uuid| uuid.to_vec()),
+        _ => file.build_id().ok().flatten().map(|id| id.to_vec()),
+    };
+    Some((base, build_id))
+}
+
+fn parse_syms<'a: 'file, 'file>(
+    syms: impl Iterator<Item = object::Symbol<'a, 'file>>,
+) -> Vec<ParsedSym<'a>> {
+    let mut syms = syms
+        .filter(|sym| sym.kind() == SymbolKind::Text && sym.is_definition())
+        .filter_map(|sym| {
+            Some(ParsedSym {
+                address: sym.address(),
+                size: sym.size(),
+                name: sym.name_bytes().ok().filter(|name| !name.is_empty())?,
+            })
+        })
+        .collect::<Vec<_>>();
+    syms.sort_unstable_by_key(|s| s.address);
+    syms
+}
+
+#[test]
+fn parse_current_exe() {
+    #[inline(never)]
+    fn target() {}
+
+    let addr = target as *const () as usize;
+    let libs = super::native_libraries();
+    let lib = libs.iter().find(|lib| lib.contains(addr)).unwrap();
+    let data = super::mystd::fs::read(super::mystd::env::current_exe().unwrap()).unwrap();
+
+    let stash = Stash::new();
+    let object = Object::parse(&data).unwrap();
+    assert!(object.section(&stash, ".text").is_some());
+    let name = object
+        .search_symtab(addr.wrapping_sub(lib.bias) as u64)
+        .unwrap();
+    let name = super::mystd::str::from_utf8(name).unwrap();
+    assert!(name.contains("target"), "{name}");
+}
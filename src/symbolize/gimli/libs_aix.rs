@@ -59,8 +59,11 @@ pub(super) fn native_libraries() -> Vec<Library> {
                     segments: vec![LibrarySegment {
                         stated_virtual_memory_address: image.base as usize,
                         len: image.size,
+                        executable: true,
                     }],
                     bias: (text_base + image.offset).wrapping_sub(image.base as usize),
+                    build_id: None,
+                    unloaded: false,
                 });
             }
             if (*current).ldinfo_next == 0 {
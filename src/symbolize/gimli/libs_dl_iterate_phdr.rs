@@ -5,10 +5,34 @@
 use super::mystd::borrow::ToOwned;
 use super::mystd::env;
 use super::mystd::ffi::{CStr, OsStr};
+#[cfg(target_os = "linux")]
+use super::mystd::fs;
 use super::mystd::os::unix::prelude::*;
+#[cfg(target_os = "linux")]
+use super::mystd::path::PathBuf;
 use super::{Library, LibrarySegment, OsString, Vec};
+use core::convert::TryInto;
+#[cfg(all(target_os = "linux", any(target_env = "gnu", target_env = "musl")))]
+use core::mem;
 use core::slice;
 
+#[cfg(all(
+    target_os = "linux",
+    any(target_env = "gnu", target_env = "musl"),
+    target_pointer_width = "32"
+))]
+use libc::{Elf32_Ehdr as Ehdr, Elf32_Phdr as Phdr};
+#[cfg(all(
+    target_os = "linux",
+    any(target_env = "gnu", target_env = "musl"),
+    target_pointer_width = "64"
+))]
+use libc::{Elf64_Ehdr as Ehdr, Elf64_Phdr as Phdr};
+
+const PT_LOAD: u32 = 1;
+#[cfg(all(target_os = "linux", any(target_env = "gnu", target_env = "musl")))]
+const PT_PHDR: u32 = 6;
+
 pub(super) fn native_libraries() -> Vec<Library> {
     let mut ret = Vec::new();
     unsafe {
@@ -17,14 +41,41 @@ pub(super) fn native_libraries() -> Vec<Library> {
     return ret;
 }
 
-fn infer_current_exe(base_addr: usize) -> OsString {
+/// Returns whether `info` describes the main program.
+unsafe fn is_main_program(info: &libc::dl_phdr_info, first: bool) -> bool {
+    // The kernel tells where it loaded the program headers of the main
+    // program, which tells it apart regardless of the order objects are
+    // visited in, or whether the C library names it.
+    #[cfg(all(target_os = "linux", any(target_env = "gnu", target_env = "musl")))]
+    {
+        let phdr = libc::getauxval(libc::AT_PHDR) as usize;
+        if phdr != 0 {
+            return info.dlpi_phdr as usize == phdr;
+        }
+    }
+    // The man page for dl_iterate_phdr says that the first object visited by
+    // callback is the main program, and it's nameless.
+    first && (info.dlpi_name.is_null() || *info.dlpi_name == 0)
+}
+
+/// Infers the path of an object `dl_iterate_phdr` doesn't name from the file
+/// mapped at `addr`, an address in one of its segments.
+///
+/// That's usually the main program, but also any program started through the
+/// dynamic loader explicitly, in which case the loader is the main program as
+/// far as the kernel is concerned. Only the actual main program falls back to
+/// `current_exe`, which would otherwise attribute an unnamed object to the
+/// wrong file.
+fn infer_path(addr: Option<usize>, is_main_prog: bool) -> OsString {
     cfg_if::cfg_if! {
         if #[cfg(not(target_os = "hurd"))] {
-                if let Ok(entries) = super::parse_running_mmaps::parse_maps() {
+            if let (Some(addr), Ok(entries)) = (addr, super::parse_running_mmaps::parse_maps()) {
                 let opt_path = entries
                     .iter()
-                    .find(|e| e.ip_matches(base_addr) && e.pathname().len() > 0)
+                    .find(|e| e.ip_matches(addr) && e.pathname().len() > 0)
                     .map(|e| e.pathname())
+                    // Pseudo-paths like `[vdso]` don't name any file.
+                    .filter(|path| !path.as_bytes().starts_with(b"["))
                     .cloned();
                 if let Some(path) = opt_path {
                     return path;
@@ -32,7 +83,49 @@ fn infer_current_exe(base_addr: usize) -> OsString {
             }
         }
     }
-    env::current_exe().map(|e| e.into()).unwrap_or_default()
+    if is_main_prog {
+        env::current_exe().map(|e| e.into()).unwrap_or_default()
+    } else {
+        OsString::new()
+    }
+}
+
+/// Returns a path through which exactly the image of `lib` mapped into memory
+/// can be opened, if opening `lib` by its name would yield a different file.
+///
+/// Binaries and libraries may be deleted or replaced while the process is
+/// running, like when a long-running service is upgraded, after which their
+/// name refers to a different file or none at all. The kernel still provides
+/// access to the mapped file through `/proc/self/map_files`, or for the main
+/// program through `/proc/self/exe` if the former isn't accessible.
+#[cfg(target_os = "linux")]
+pub(super) fn mapped_image_path(lib: &Library) -> Option<PathBuf> {
+    if lib.name.is_empty() {
+        return None;
+    }
+    let maps = super::parse_running_mmaps::parse_maps().ok()?;
+    let entry = lib
+        .segments
+        .iter()
+        .filter(|segment| segment.len > 0)
+        .find_map(|segment| {
+            let addr = lib.bias.wrapping_add(segment.stated_virtual_memory_address);
+            maps.iter()
+                .find(|e| e.ip_matches(addr) && !e.pathname().is_empty())
+        })?;
+
+    let (start, end) = entry.address();
+    let mut path = PathBuf::from(alloc::format!("/proc/self/map_files/{start:x}-{end:x}"));
+    let mut mapped = fs::metadata(&path);
+    if mapped.is_err() && fs::read_link("/proc/self/exe").ok()? == *entry.pathname() {
+        path = PathBuf::from("/proc/self/exe");
+        mapped = fs::metadata(&path);
+    }
+    let mapped = mapped.ok()?;
+    match fs::metadata(&lib.name) {
+        Ok(named) if named.dev() == mapped.dev() && named.ino() == mapped.ino() => None,
+        _ => Some(path),
+    }
 }
 
 // `info` should be a valid pointers.
@@ -44,32 +137,228 @@ unsafe extern "C" fn callback(
 ) -> libc::c_int {
     let info = &*info;
     let libs = &mut *vec.cast::<Vec<Library>>();
-    let is_main_prog = info.dlpi_name.is_null() || *info.dlpi_name == 0;
-    let name = if is_main_prog {
-        // The man page for dl_iterate_phdr says that the first object visited by
-        // callback is the main program; so the first time we encounter a
-        // nameless entry, we can assume its the main program and try to infer its path.
-        // After that, we cannot continue that assumption, and we use an empty string.
-        if libs.is_empty() {
-            infer_current_exe(info.dlpi_addr as usize)
-        } else {
-            OsString::new()
-        }
+    let is_main_prog = is_main_program(info, libs.is_empty());
+    const PF_X: u32 = 1;
+
+    let headers = slice::from_raw_parts(info.dlpi_phdr, info.dlpi_phnum as usize);
+    let bias = info.dlpi_addr as usize;
+    #[cfg(all(target_os = "linux", any(target_env = "gnu", target_env = "musl")))]
+    let bias = if is_main_prog {
+        main_program_bias(bias, headers)
+    } else {
+        bias
+    };
+    let name = if info.dlpi_name.is_null() || *info.dlpi_name == 0 {
+        let addr = headers
+            .iter()
+            .find(|header| header.p_type == PT_LOAD && header.p_memsz > 0)
+            .map(|header| bias.wrapping_add(header.p_vaddr as usize));
+        infer_path(addr, is_main_prog)
     } else {
         let bytes = CStr::from_ptr(info.dlpi_name).to_bytes();
         OsStr::from_bytes(bytes).to_owned()
     };
-    let headers = slice::from_raw_parts(info.dlpi_phdr, info.dlpi_phnum as usize);
     libs.push(Library {
         name,
+        // Other program headers, like `PT_TLS` or `PT_GNU_RELRO`, describe
+        // parts of loaded segments or nothing loaded at all.
         segments: headers
             .iter()
+            .filter(|header| header.p_type == PT_LOAD)
             .map(|header| LibrarySegment {
                 len: (*header).p_memsz as usize,
                 stated_virtual_memory_address: (*header).p_vaddr as usize,
+                executable: header.p_flags & PF_X != 0,
             })
             .collect(),
-        bias: info.dlpi_addr as usize,
+        bias,
+        build_id: build_id(info, bias),
+        unloaded: false,
     });
     0
 }
+
+/// Returns the bias of the main program, given the one reported by
+/// `dl_iterate_phdr`.
+///
+/// Statically linked programs have no dynamic loader to tell where they were
+/// loaded, so the C library works it out from the address the kernel loaded
+/// the program headers at. musl subtracts the address the `PT_PHDR` header
+/// states for them, and reports a bias of zero if there's no such header,
+/// which older linkers leave out of static PIE binaries.
+#[cfg(all(target_os = "linux", any(target_env = "gnu", target_env = "musl")))]
+unsafe fn main_program_bias(reported: usize, headers: &[Phdr]) -> usize {
+    let phdr = libc::getauxval(libc::AT_PHDR) as usize;
+    let entry = libc::getauxval(libc::AT_ENTRY) as usize;
+    fix_bias(reported, phdr, entry, headers)
+}
+
+/// Returns `reported` if it's the bias of a program whose `headers` were
+/// loaded at `phdr`, and the bias worked out from where they are otherwise.
+///
+/// `entry` is the address of the program's entry point, or zero if unknown.
+#[cfg(all(target_os = "linux", any(target_env = "gnu", target_env = "musl")))]
+unsafe fn fix_bias(reported: usize, phdr: usize, entry: usize, headers: &[Phdr]) -> usize {
+    // The program headers are always loaded as part of a segment.
+    let loaded = |bias: usize| {
+        headers.iter().filter(|h| h.p_type == PT_LOAD).any(|h| {
+            let start = bias.wrapping_add(h.p_vaddr as usize);
+            phdr.wrapping_sub(start) < h.p_memsz as usize
+        })
+    };
+    if phdr == 0 || loaded(reported) {
+        return reported;
+    }
+    if let Some(header) = headers.iter().find(|h| h.p_type == PT_PHDR) {
+        return phdr.wrapping_sub(header.p_vaddr as usize);
+    }
+
+    // Otherwise, linkers put the program headers right after the ELF header,
+    // which is at the start of the segment loaded from the start of the file.
+    // It's only read if it's on the same page as the program headers, which
+    // is surely mapped.
+    let size = mem::size_of::<Ehdr>();
+    if phdr % 4096 < size {
+        return reported;
+    }
+    let ehdr = &*((phdr - size) as *const Ehdr);
+    if ehdr.e_ident[..4] != *b"\x7fELF" || ehdr.e_phoff as usize != size {
+        return reported;
+    }
+    let first = match headers
+        .iter()
+        .find(|h| h.p_type == PT_LOAD && h.p_offset == 0)
+    {
+        Some(header) => header,
+        None => return reported,
+    };
+    let bias = (phdr - size).wrapping_sub(first.p_vaddr as usize);
+    let entry_matches = entry == 0 || (ehdr.e_entry as usize).wrapping_add(bias) == entry;
+    if entry_matches && loaded(bias) {
+        bias
+    } else {
+        reported
+    }
+}
+
+// Finds the GNU build ID note in the loaded segments of an object.
+unsafe fn build_id(info: &libc::dl_phdr_info, bias: usize) -> Option<Vec<u8>> {
+    const PT_NOTE: u32 = 4;
+
+    let headers = slice::from_raw_parts(info.dlpi_phdr, info.dlpi_phnum as usize);
+    for header in headers.iter().filter(|h| h.p_type == PT_NOTE) {
+        // Notes are padded to 4 bytes, except in segments aligned to 8 bytes,
+        // which some linkers emit for 64-bit objects. Anything else isn't
+        // valid.
+        let align = match header.p_align {
+            0..=4 => 4,
+            8 => 8,
+            _ => continue,
+        };
+        let data = slice::from_raw_parts(
+            bias.wrapping_add(header.p_vaddr as usize) as *const u8,
+            header.p_memsz as usize,
+        );
+        if let Some(build_id) = find_build_id_note(data, align) {
+            return Some(build_id.to_vec());
+        }
+    }
+    None
+}
+
+// Finds the GNU build ID in `notes`, whose names and descriptions are padded to
+// `align` bytes.
+fn find_build_id_note(mut notes: &[u8], align: usize) -> Option<&[u8]> {
+    const NT_GNU_BUILD_ID: u32 = 3;
+
+    let pad = |n: usize| (n + align - 1) & !(align - 1);
+    while notes.len() >= 12 {
+        let word = |i: usize| u32::from_ne_bytes(notes[i..i + 4].try_into().unwrap());
+        let (namesz, descsz, kind) = (word(0) as usize, word(4) as usize, word(8));
+        let desc_start = 12 + pad(namesz);
+        let desc_end = desc_start + descsz;
+        if desc_end > notes.len() {
+            break;
+        }
+        if kind == NT_GNU_BUILD_ID && &notes[12..12 + namesz] == b"GNU\0" {
+            return Some(&notes[desc_start..desc_end]);
+        }
+        notes = &notes[(desc_start + pad(descsz)).min(notes.len())..];
+    }
+    None
+}
+
+#[test]
+fn finds_aligned_build_id_notes() {
+    let note = |namesz: u32, descsz: u32, kind: u32, name: &[u8], desc: &[u8]| {
+        let mut note = Vec::new();
+        note.extend_from_slice(&namesz.to_ne_bytes());
+        note.extend_from_slice(&descsz.to_ne_bytes());
+        note.extend_from_slice(&kind.to_ne_bytes());
+        note.extend_from_slice(name);
+        note.extend_from_slice(desc);
+        note
+    };
+    // Names padded to 8 bytes rather than 4 are skipped correctly.
+    let mut notes = note(4, 4, 1, b"GNU\0\0\0\0\0", b"abcd\0\0\0\0");
+    notes.extend(note(4, 3, 3, b"GNU\0\0\0\0\0", b"xyz"));
+    assert_eq!(find_build_id_note(&notes, 8), Some(&b"xyz"[..]));
+
+    let mut notes = note(4, 4, 1, b"GNU\0", b"abcd");
+    notes.extend(note(4, 3, 3, b"GNU\0", b"xyz"));
+    assert_eq!(find_build_id_note(&notes, 4), Some(&b"xyz"[..]));
+    assert_eq!(find_build_id_note(&notes[..notes.len() - 1], 4), None);
+}
+
+#[cfg(all(target_os = "linux", any(target_env = "gnu", target_env = "musl")))]
+#[test]
+fn fix_static_pie_bias() {
+    let header = |p_type, p_offset, p_vaddr, p_memsz| {
+        let mut header: Phdr = unsafe { mem::zeroed() };
+        header.p_type = p_type;
+        header.p_offset = p_offset;
+        header.p_vaddr = p_vaddr;
+        header.p_memsz = p_memsz;
+        header
+    };
+    let phoff = mem::size_of::<Ehdr>();
+
+    // An image loaded at a page boundary, starting with its ELF header.
+    let memory = alloc::vec![0u64; 4096];
+    let page = (memory.as_ptr() as usize + 4095) & !4095;
+    let mut ehdr: Ehdr = unsafe { mem::zeroed() };
+    ehdr.e_ident[..4].copy_from_slice(b"\x7fELF");
+    ehdr.e_phoff = phoff as _;
+    ehdr.e_entry = 0x1234;
+    unsafe { (page as *mut Ehdr).write(ehdr) };
+    let phdr = page + phoff;
+    let entry = page + 0x1234;
+
+    let without_phdr = [header(PT_LOAD, 0, 0, 0x2000)];
+    let with_phdr = [
+        header(PT_PHDR, phoff as _, phoff as _, 0x100),
+        header(PT_LOAD, 0, 0, 0x2000),
+    ];
+    unsafe {
+        // Correct biases are kept.
+        assert_eq!(fix_bias(page, phdr, entry, &without_phdr), page);
+        assert_eq!(fix_bias(page, phdr, 0, &with_phdr), page);
+        // musl reports zero for the main program if there's no `PT_PHDR`.
+        assert_eq!(fix_bias(0, phdr, entry, &without_phdr), page);
+        assert_eq!(fix_bias(0, phdr, 0, &without_phdr), page);
+        assert_eq!(fix_bias(0, phdr, entry, &with_phdr), page);
+        // Nothing is guessed if the entry point doesn't match.
+        assert_eq!(fix_bias(0, phdr, entry + 1, &without_phdr), 0);
+    }
+
+    // The test itself is a static PIE binary without `PT_PHDR` when built for
+    // musl by some linkers.
+    unsafe {
+        let phdr = libc::getauxval(libc::AT_PHDR) as usize;
+        let phnum = libc::getauxval(libc::AT_PHNUM) as usize;
+        let entry = libc::getauxval(libc::AT_ENTRY) as usize;
+        let headers = slice::from_raw_parts(phdr as *const Phdr, phnum);
+        let bias = native_libraries()[0].bias;
+        assert_eq!(fix_bias(0, phdr, entry, headers), bias);
+    }
+}
@@ -30,6 +30,7 @@ pub(super) fn native_libraries() -> Vec<Library> {
             segments.push(LibrarySegment {
                 stated_virtual_memory_address: 0,
                 len: info.text_size as usize,
+                executable: true,
             });
 
             let bytes = CStr::from_ptr(info.name.as_ptr()).to_bytes();
@@ -38,6 +39,8 @@ pub(super) fn native_libraries() -> Vec<Library> {
                 name: name,
                 segments: segments,
                 bias: info.text as usize,
+                build_id: None,
+                unloaded: false,
             });
 
             status = libc::get_next_image_info(libc::B_CURRENT_TEAM, &mut cookie, &mut info);
@@ -73,12 +73,15 @@ pub(super) fn native_libraries() -> Vec<Library> {
             name,
             segments: phdr
                 .iter()
+                .filter(|p| p.p_type.get(NativeEndian) == object::elf::PT_LOAD)
                 .map(|p| {
                     let memsz = p.p_memsz.get(NativeEndian);
                     let vaddr = p.p_vaddr.get(NativeEndian);
+                    let flags = p.p_flags.get(NativeEndian);
                     LibrarySegment {
                         len: memsz as usize,
                         stated_virtual_memory_address: vaddr as usize,
+                        executable: flags & object::elf::PF_X != 0,
                     }
                 })
                 .collect(),
@@ -90,6 +93,8 @@ pub(super) fn native_libraries() -> Vec<Library> {
                 // Other addresses are relative to the object base.
                 addr as usize
             },
+            build_id: None,
+            unloaded: false,
         });
 
         l = unsafe { (*l).l_next };
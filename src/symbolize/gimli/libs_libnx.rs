@@ -14,6 +14,7 @@ pub(super) fn native_libraries() -> Vec<Library> {
     segments.push(LibrarySegment {
         stated_virtual_memory_address: 0,
         len: usize::max_value() - bias,
+        executable: true,
     });
 
     let path = "romfs:/debug_info.elf";
@@ -21,6 +22,8 @@ pub(super) fn native_libraries() -> Vec<Library> {
         name: path.into(),
         segments,
         bias,
+        build_id: None,
+        unloaded: false,
     });
 
     ret
@@ -6,6 +6,7 @@ use super::mystd::prelude::v1::*;
 use super::{Library, LibrarySegment};
 use core::convert::TryInto;
 use core::mem;
+use object::macho::VM_PROT_EXECUTE;
 
 // FIXME: replace with ptr::from_ref once MSRV is high enough
 #[inline(always)]
@@ -75,7 +76,11 @@ fn native_library(i: u32) -> Option<Library> {
     let mut segments = Vec::new();
     let mut first_text = 0;
     let mut text_fileoff_zero = false;
+    let mut build_id = None;
     while let Some(cmd) = load_commands.next().ok()? {
+        if let Some(uuid) = cmd.uuid().ok()? {
+            build_id = Some(uuid.uuid.to_vec());
+        }
         if let Some((seg, _)) = cmd.segment_32().ok()? {
             if seg.name() == b"__TEXT" {
                 first_text = segments.len();
@@ -86,6 +91,7 @@ fn native_library(i: u32) -> Option<Library> {
             segments.push(LibrarySegment {
                 len: seg.vmsize(endian).try_into().ok()?,
                 stated_virtual_memory_address: seg.vmaddr(endian).try_into().ok()?,
+                executable: seg.initprot(endian) & VM_PROT_EXECUTE != 0,
             });
         }
         if let Some((seg, _)) = cmd.segment_64().ok()? {
@@ -98,6 +104,7 @@ fn native_library(i: u32) -> Option<Library> {
             segments.push(LibrarySegment {
                 len: seg.vmsize(endian).try_into().ok()?,
                 stated_virtual_memory_address: seg.vmaddr(endian).try_into().ok()?,
+                executable: seg.initprot(endian) & VM_PROT_EXECUTE != 0,
             });
         }
     }
@@ -149,5 +156,7 @@ fn native_library(i: u32) -> Option<Library> {
         name: OsStr::from_bytes(name.to_bytes()).to_owned(),
         segments,
         bias: slide,
+        build_id,
+        unloaded: false,
     })
 }
@@ -40,12 +40,19 @@ unsafe fn add_loaded_images(ret: &mut Vec<Library>) {
 }
 
 unsafe fn load_library(me: &MODULEENTRY32W) -> Option<Library> {
-    let pos = me
-        .szExePath
-        .iter()
-        .position(|i| *i == 0)
-        .unwrap_or(me.szExePath.len());
-    let name = OsString::from_wide(&me.szExePath[..pos]);
+    // `szExePath` is truncated to `MAX_PATH` characters, so the full path is
+    // asked for separately.
+    let name = match module_file_name(me.hModule) {
+        Some(name) => OsString::from_wide(&name),
+        None => {
+            let pos = me
+                .szExePath
+                .iter()
+                .position(|i| *i == 0)
+                .unwrap_or(me.szExePath.len());
+            OsString::from_wide(&me.szExePath[..pos])
+        }
+    };
 
     // MinGW libraries currently don't support ASLR
     // (rust-lang/rust#16514), but DLLs can still be relocated around in
@@ -84,6 +91,9 @@ unsafe fn load_library(me: &MODULEENTRY32W) -> Option<Library> {
         segments: vec![LibrarySegment {
             stated_virtual_memory_address: image_base,
             len: me.modBaseSize as usize,
+            executable: true,
         }],
+        build_id: None,
+        unloaded: false,
     })
 }
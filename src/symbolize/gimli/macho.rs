@@ -1,4 +1,7 @@
-use super::{gimli, Box, Context, Endian, EndianSlice, Mapping, Path, Stash, Vec};
+#[cfg(not(feature = "minimal-symbolication"))]
+use super::{gimli, Endian, EndianSlice};
+use super::{Box, Context, Mapping, Path, Stash, SymtabInfo, Vec};
+#[cfg(not(feature = "minimal-symbolication"))]
 use alloc::sync::Arc;
 use core::convert::TryInto;
 use object::macho;
@@ -17,13 +20,16 @@ impl Mapping {
     // The loading path for macOS is so different we just have a completely
     // different implementation of the function here. On macOS we need to go
     // probing the filesystem for a bunch of files.
-    pub fn new(path: &Path) -> Option<Mapping> {
+    pub fn new(path: &Path, build_id: Option<&[u8]>) -> Option<Mapping> {
         // First up we need to load the unique UUID which is stored in the macho
         // header of the file we're reading, specified at `path`.
         let map = super::mmap(path)?;
         let (macho, data) = find_header(&map)?;
         let endian = macho.endian().ok()?;
         let uuid = macho.uuid(endian, data, 0).ok()?;
+        if !super::same_build(path, uuid.as_ref().map(|uuid| &uuid[..]), build_id) {
+            return None;
+        }
 
         // Next we need to look for a `*.dSYM` file. For now we just probe the
         // containing directory and look around for something that matches
@@ -50,6 +56,19 @@ impl Mapping {
         })
     }
 
+    /// Creates a `Mapping` from a copy of the object file `data`, without
+    /// looking for a `*.dSYM` bundle.
+    #[cfg(feature = "std")]
+    pub fn from_bytes(data: &[u8]) -> Option<Mapping> {
+        let map = super::Mmap::copy_from(data)?;
+        Mapping::mk(map, |data, stash| {
+            let (macho, data) = find_header(data)?;
+            let endian = macho.endian().ok()?;
+            let obj = Object::parse(macho, endian, data)?;
+            Context::new(stash, obj, None, None)
+        })
+    }
+
     fn load_dsym(dir: &Path, uuid: [u8; 16]) -> Option<Mapping> {
         for entry in dir.read_dir().ok()? {
             let entry = entry.ok()?;
@@ -237,6 +256,15 @@ impl<'a> Object<'a> {
         Some(section.data(self.endian, self.data).ok()?)
     }
 
+    /// Passes the name, address and size of each symbol to `cb`, in order of
+    /// increasing address. Mach-O doesn't record the size of symbols.
+    pub fn for_each_symbol(&self, cb: &mut dyn FnMut(&'a [u8], u64, Option<u64>)) {
+        debug_assert!(!self.syms_sort_by_name);
+        for &(name, addr) in self.syms.iter() {
+            cb(name, addr, None);
+        }
+    }
+
     pub fn search_symtab<'b>(&'b self, addr: u64) -> Option<&'b [u8]> {
         debug_assert!(!self.syms_sort_by_name);
         let i = match self.syms.binary_search_by_key(&addr, |(_, addr)| *addr) {
@@ -247,10 +275,45 @@ impl<'a> Object<'a> {
         Some(sym)
     }
 
+    pub fn function_start(&self, _addr: u64) -> Option<u64> {
+        None
+    }
+
+    #[cfg(feature = "std")]
+    pub fn for_each_function(&self, _cb: &mut dyn FnMut(u64, u64)) {}
+
+    pub fn symtab_info(&self, _addr: u64) -> Option<SymtabInfo<'a>> {
+        None
+    }
+
+    pub fn find_symbol(&self, name: &[u8]) -> Option<(u64, Option<u64>)> {
+        super::scan_symbols(name, |cb| self.for_each_symbol(cb))
+    }
+
+    pub fn dynamic_symbol_count(&self) -> Option<usize> {
+        None
+    }
+
+    /// Passes the address and size of each symbol whose debug information
+    /// `search_object_map` looks up in an object file to `cb`.
+    #[cfg(feature = "std")]
+    pub(super) fn for_each_object_map_symbol(&self, cb: &mut dyn FnMut(u64, u64)) {
+        if let Some(object_map) = &self.object_map {
+            for symbol in object_map.symbols() {
+                cb(symbol.address(), symbol.size());
+            }
+        }
+    }
+
     /// Try to load a context for an object file.
     ///
     /// If dsymutil was not run, then the DWARF may be found in the source object files.
-    pub(super) fn search_object_map<'b>(&'b mut self, addr: u64) -> Option<(&Context<'b>, u64)> {
+    /// Object files which weren't loaded yet are only opened if `open` is set.
+    pub(super) fn search_object_map<'b>(
+        &'b mut self,
+        addr: u64,
+        open: bool,
+    ) -> Option<(&Context<'b>, u64)> {
         // `object_map` contains a map from addresses to symbols and object paths.
         // Look up the address and get a mapping for the object.
         let object_map = self.object_map.as_ref()?;
@@ -258,6 +321,9 @@ impl<'a> Object<'a> {
         let object_index = symbol.object_index();
         let mapping = self.object_mappings.get_mut(object_index)?;
         if mapping.is_none() {
+            if !open {
+                return None;
+            }
             // No cached mapping, so create it.
             *mapping = Some(object_mapping(object_map.objects().get(object_index)?));
         }
@@ -267,13 +333,13 @@ impl<'a> Object<'a> {
 
         // We must translate the address in order to be able to look it up
         // in the DWARF in the object file.
-        debug_assert!(cx.object.syms.is_empty() || cx.object.syms_sort_by_name);
-        let i = cx
-            .object
+        let object = cx.object.native()?;
+        debug_assert!(object.syms.is_empty() || object.syms_sort_by_name);
+        let i = object
             .syms
             .binary_search_by_key(&symbol.name(), |(name, _)| *name)
             .ok()?;
-        let object_symbol = cx.object.syms.get(i)?;
+        let object_symbol = object.syms.get(i)?;
         let object_addr = addr
             .wrapping_sub(symbol.address())
             .wrapping_add(object_symbol.1);
@@ -324,6 +390,7 @@ fn split_archive_path(path: &[u8]) -> Option<(&[u8], &[u8])> {
     Some((archive, &rest[1..]))
 }
 
+#[cfg(not(feature = "minimal-symbolication"))]
 pub(super) fn handle_split_dwarf<'data>(
     _package: Option<&gimli::DwarfPackage<EndianSlice<'data, Endian>>>,
     _stash: &'data Stash,
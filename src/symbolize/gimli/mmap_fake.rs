@@ -1,4 +1,6 @@
-use super::{mystd::io::Read, File};
+use super::mystd::io::{Read, Seek, SeekFrom};
+use super::File;
+use alloc::vec;
 use alloc::vec::Vec;
 use core::ops::Deref;
 
@@ -14,6 +16,25 @@ impl Mmap {
         file.read_to_end(&mut mmap.vec).ok()?;
         Some(mmap)
     }
+
+    pub fn zeroed(len: usize) -> Option<Mmap> {
+        Some(Mmap { vec: vec![0; len] })
+    }
+
+    #[cfg(feature = "std")]
+    pub fn copy_from(data: &[u8]) -> Option<Mmap> {
+        Some(Mmap { vec: data.to_vec() })
+    }
+
+    pub unsafe fn read(file: &File, len: usize) -> Option<Mmap> {
+        Mmap::map(file, len)
+    }
+
+    pub fn read_at(&mut self, mut file: &File, offset: usize, len: usize) -> Option<()> {
+        let buf = self.vec.get_mut(offset..offset.checked_add(len)?)?;
+        file.seek(SeekFrom::Start(offset as u64)).ok()?;
+        file.read_exact(buf).ok()
+    }
 }
 
 impl Deref for Mmap {
@@ -29,8 +29,65 @@ impl Mmap {
         }
         Some(Mmap { ptr, len })
     }
+
+    /// Creates an anonymous mapping of `len` zeroed bytes, which only takes
+    /// up memory once written to.
+    pub fn zeroed(len: usize) -> Option<Mmap> {
+        let ptr = unsafe {
+            mmap64(
+                ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                -1,
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return None;
+        }
+        Some(Mmap { ptr, len })
+    }
+
+    /// Reads the first `len` bytes of `file` into an anonymous mapping, rather
+    /// than mapping the file itself.
+    pub unsafe fn read(file: &File, len: usize) -> Option<Mmap> {
+        let mut mmap = Mmap::zeroed(len)?;
+        mmap.read_at(file, 0, len)?;
+        Some(mmap)
+    }
+
+    /// Reads `len` bytes at `offset` of `file` into the same offset of this
+    /// mapping, which must have been created by `zeroed`.
+    pub fn read_at(&mut self, file: &File, offset: usize, len: usize) -> Option<()> {
+        if offset.checked_add(len)? > self.len {
+            return None;
+        }
+        let buf = unsafe { slice::from_raw_parts_mut(self.ptr.cast::<u8>().add(offset), len) };
+        file.read_exact_at(buf, offset as u64).ok()
+    }
+
+    /// Creates an anonymous mapping holding a copy of `data`.
+    #[cfg(any(
+        feature = "std",
+        all(
+            any(target_os = "linux", target_os = "android"),
+            not(target_env = "uclibc")
+        )
+    ))]
+    pub fn copy_from(data: &[u8]) -> Option<Mmap> {
+        let mmap = Mmap::zeroed(data.len())?;
+        unsafe {
+            ptr::copy_nonoverlapping(data.as_ptr(), mmap.ptr.cast::<u8>(), data.len());
+        }
+        Some(mmap)
+    }
 }
 
+// SAFETY: the mapping is owned exclusively and only ever read through shared
+// references, or written to through exclusive ones.
+unsafe impl Send for Mmap {}
+
 impl Deref for Mmap {
     type Target = [u8];
 
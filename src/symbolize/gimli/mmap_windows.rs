@@ -6,9 +6,9 @@ use core::ptr;
 use core::slice;
 
 pub struct Mmap {
-    // keep the file alive to prevent it from being deleted which would cause
-    // us to read bad data.
-    _file: File,
+    // keep a mapped file alive to prevent it from being deleted which would
+    // cause us to read bad data.
+    _file: Option<File>,
     ptr: *mut c_void,
     len: usize,
 }
@@ -33,12 +33,83 @@ impl Mmap {
             return None;
         }
         Some(Mmap {
-            _file: file,
+            _file: Some(file),
             ptr,
             len,
         })
     }
+
+    /// Creates a mapping of `len` zeroed bytes backed by the paging file.
+    pub fn zeroed(len: usize) -> Option<Mmap> {
+        let size = len as u64;
+        unsafe {
+            let mapping = CreateFileMappingA(
+                INVALID_HANDLE_VALUE,
+                ptr::null_mut(),
+                PAGE_READWRITE,
+                (size >> 32) as DWORD,
+                size as DWORD,
+                ptr::null(),
+            );
+            if mapping.is_null() {
+                return None;
+            }
+            let ptr = MapViewOfFile(mapping, FILE_MAP_WRITE, 0, 0, len);
+            CloseHandle(mapping);
+            if ptr.is_null() {
+                return None;
+            }
+            Some(Mmap {
+                _file: None,
+                ptr,
+                len,
+            })
+        }
+    }
+
+    /// Creates a mapping backed by the paging file holding a copy of `data`.
+    #[cfg(feature = "std")]
+    pub fn copy_from(data: &[u8]) -> Option<Mmap> {
+        let mmap = Mmap::zeroed(data.len())?;
+        unsafe {
+            ptr::copy_nonoverlapping(data.as_ptr(), mmap.ptr.cast::<u8>(), data.len());
+        }
+        Some(mmap)
+    }
+
+    /// Reads the first `len` bytes of `file` into a mapping backed by the
+    /// paging file, rather than mapping the file itself.
+    pub unsafe fn read(file: &File, len: usize) -> Option<Mmap> {
+        let mut mmap = Mmap::zeroed(len)?;
+        mmap.read_at(file, 0, len)?;
+        Some(mmap)
+    }
+
+    /// Reads `len` bytes at `offset` of `file` into the same offset of this
+    /// mapping, which must have been created by `zeroed`.
+    pub fn read_at(&mut self, file: &File, offset: usize, len: usize) -> Option<()> {
+        if offset.checked_add(len)? > self.len {
+            return None;
+        }
+        let mut buf = unsafe { slice::from_raw_parts_mut(self.ptr.cast::<u8>().add(offset), len) };
+        let mut offset = offset as u64;
+        while !buf.is_empty() {
+            match file.seek_read(buf, offset).ok()? {
+                0 => return None,
+                n => {
+                    buf = &mut buf[n..];
+                    offset += n as u64;
+                }
+            }
+        }
+        Some(())
+    }
 }
+
+// SAFETY: the mapping is owned exclusively and only ever read through shared
+// references, or written to through exclusive ones.
+unsafe impl Send for Mmap {}
+
 impl Deref for Mmap {
     type Target = [u8];
 
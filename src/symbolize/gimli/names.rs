@@ -0,0 +1,344 @@
+//! Looking up the functions containing an address in DWARF debug information
+//! by their `DW_AT_name` attributes alone.
+//!
+//! `addr2line` always evaluates the line program of a unit to find the file
+//! and line of an address along with its functions. This finds just the
+//! functions, so that line programs are never evaluated with
+//! `ResolveMode::NamesOnly`.
+
+use super::super::parse_limits::max_die_depth;
+use super::{diagnostics, gimli, Endian, EndianSlice, Vec};
+
+type Reader<'a> = EndianSlice<'a, Endian>;
+
+/// How many `DW_AT_abstract_origin` and `DW_AT_specification` attributes are
+/// followed to find the name of a function, like in `addr2line`.
+const MAX_NAME_DEPTH: usize = 16;
+
+pub(super) struct Names<'a> {
+    dwarf: gimli::Dwarf<Reader<'a>>,
+    /// All compilation units, parsed the first time an address is looked up.
+    units: Option<Vec<Unit<'a>>>,
+    /// The address ranges of `units` along with the index of their unit,
+    /// sorted by where they start.
+    ranges: Vec<(gimli::Range, usize)>,
+}
+
+struct Unit<'a> {
+    dw_unit: gimli::Unit<Reader<'a>>,
+    /// The functions of the unit, parsed the first time an address in the
+    /// unit is looked up.
+    functions: Option<Functions>,
+}
+
+struct Functions {
+    /// The address ranges of the `DW_TAG_subprogram` entries of the unit,
+    /// sorted by where they start.
+    ranges: Vec<(gimli::Range, gimli::UnitOffset)>,
+    /// How deeply entries are nested in the unit, with the unit itself at
+    /// depth 0 and its functions at depth 1.
+    depth: usize,
+}
+
+impl<'a> Names<'a> {
+    pub fn new(dwarf: gimli::Dwarf<Reader<'a>>) -> Names<'a> {
+        Names {
+            dwarf,
+            units: None,
+            ranges: Vec::new(),
+        }
+    }
+
+    /// Finds the function containing `probe` and the functions inlined into
+    /// it there, passing their names to `cb` from the innermost inlined
+    /// function to the function the code was inlined into, in the same order
+    /// as `addr2line`. Returns whether any function was found.
+    ///
+    /// Only the function containing `probe` is found in units nested deeper
+    /// than the limit of `set_parse_limits`, as looking for inlined functions
+    /// recurses for each level. Units of split DWARF are left to `addr2line`,
+    /// as their functions are only in the split DWARF file.
+    pub fn find_frames(&mut self, probe: u64, cb: &mut dyn FnMut(Option<&'a [u8]>)) -> bool {
+        let index = match self.unit_index(probe) {
+            Some(index) => index,
+            None => return false,
+        };
+        let units = match &self.units {
+            Some(units) => units,
+            None => return false,
+        };
+        let unit = &units[index];
+        let functions = match &unit.functions {
+            Some(functions) if unit.dw_unit.dwo_id.is_none() => functions,
+            _ => return false,
+        };
+        let offset = match find_range(&functions.ranges, probe) {
+            Some(index) => functions.ranges[index].1,
+            None => return false,
+        };
+
+        let dw_unit = &unit.dw_unit;
+        let mut tree = match dw_unit.entries_tree(Some(offset)) {
+            Ok(tree) => tree,
+            Err(_) => return false,
+        };
+        let root = match tree.root() {
+            Ok(root) => root,
+            Err(_) => return false,
+        };
+        let mut names = Vec::new();
+        names.push(name(&self.dwarf, units, dw_unit, root.entry()));
+        if functions.depth <= max_die_depth() {
+            find_inlined(&self.dwarf, units, dw_unit, root, probe, &mut names);
+        }
+        for name in names.into_iter().rev() {
+            cb(name);
+        }
+        true
+    }
+
+    /// Tests whether the entries of the unit containing `probe` are nested
+    /// deeper than the limit of `set_parse_limits`, which is reported if so.
+    ///
+    /// `addr2line` recurses for each level of inlined functions when it
+    /// parses a function, so such units must never be passed to it. Units of
+    /// split DWARF can't be checked without loading the split DWARF file, so
+    /// they count as too deep whenever there's a limit.
+    pub fn too_deep(&mut self, probe: u64) -> bool {
+        let limit = max_die_depth();
+        if limit == usize::MAX {
+            return false;
+        }
+        let index = match self.unit_index(probe) {
+            Some(index) => index,
+            None => return false,
+        };
+        let unit = match &self.units {
+            Some(units) => &units[index],
+            None => return false,
+        };
+        if unit.dw_unit.dwo_id.is_some() {
+            diagnostics::report(format_args!(
+                "split DWARF units can't be checked against the limit on nested entries"
+            ));
+            return true;
+        }
+        let depth = unit
+            .functions
+            .as_ref()
+            .map_or(0, |functions| functions.depth);
+        if depth > limit {
+            diagnostics::report(format_args!(
+                "debug information entries are nested deeper than the limit of {}",
+                limit
+            ));
+        }
+        depth > limit
+    }
+
+    /// Finds the unit containing `probe`, parsing the units and the functions
+    /// of the unit first if they haven't been yet, and returns its index.
+    fn unit_index(&mut self, probe: u64) -> Option<usize> {
+        let Names {
+            dwarf,
+            units,
+            ranges,
+        } = self;
+        let units = units.get_or_insert_with(|| parse_units(dwarf, ranges));
+        let index = ranges[find_range(ranges, probe)?].1;
+        let unit = &mut units[index];
+        // The entries of split DWARF units are in the split DWARF file.
+        if unit.dw_unit.dwo_id.is_none() {
+            let dw_unit = &unit.dw_unit;
+            unit.functions
+                .get_or_insert_with(|| parse_functions(dwarf, dw_unit));
+        }
+        Some(index)
+    }
+}
+
+/// Parses all compilation units of `dwarf`, adding their address ranges to
+/// `ranges`.
+fn parse_units<'a>(
+    dwarf: &gimli::Dwarf<Reader<'a>>,
+    ranges: &mut Vec<(gimli::Range, usize)>,
+) -> Vec<Unit<'a>> {
+    let mut units = Vec::new();
+    let mut headers = dwarf.units();
+    while let Ok(Some(header)) = headers.next() {
+        // Type units have no code.
+        if let gimli::UnitType::Type { .. } | gimli::UnitType::SplitType { .. } = header.type_() {
+            continue;
+        }
+        let dw_unit = match dwarf.unit(header) {
+            Ok(dw_unit) => dw_unit,
+            Err(_) => continue,
+        };
+        if let Ok(mut iter) = dwarf.unit_ranges(&dw_unit) {
+            while let Ok(Some(range)) = iter.next() {
+                if range.begin < range.end {
+                    ranges.push((range, units.len()));
+                }
+            }
+        }
+        units.push(Unit {
+            dw_unit,
+            functions: None,
+        });
+    }
+    ranges.sort_unstable_by_key(|(range, _)| range.begin);
+    units
+}
+
+/// Collects the address ranges of all functions in `unit`, and how deeply
+/// its entries are nested.
+fn parse_functions(dwarf: &gimli::Dwarf<Reader<'_>>, unit: &gimli::Unit<Reader<'_>>) -> Functions {
+    let mut ranges = Vec::new();
+    let mut depth = 0isize;
+    let mut max_depth = 0;
+    // This goes through the entries without recursing, however deeply
+    // they're nested.
+    let mut entries = unit.entries();
+    while let Ok(Some((delta, entry))) = entries.next_dfs() {
+        depth += delta;
+        max_depth = max_depth.max(depth);
+        if entry.tag() != gimli::DW_TAG_subprogram {
+            continue;
+        }
+        if let Ok(mut iter) = dwarf.die_ranges(unit, entry) {
+            while let Ok(Some(range)) = iter.next() {
+                if range.begin < range.end {
+                    ranges.push((range, entry.offset()));
+                }
+            }
+        }
+    }
+    ranges.sort_unstable_by_key(|(range, _)| range.begin);
+    Functions {
+        ranges,
+        depth: max_depth as usize,
+    }
+}
+
+/// Finds the index of the range containing `probe` in `ranges`, which are
+/// sorted by where they start and assumed not to overlap.
+fn find_range<T>(ranges: &[(gimli::Range, T)], probe: u64) -> Option<usize> {
+    let index = ranges
+        .partition_point(|(range, _)| range.begin <= probe)
+        .checked_sub(1)?;
+    if probe < ranges[index].0.end {
+        Some(index)
+    } else {
+        None
+    }
+}
+
+/// Goes through the descendants of `node` for the `DW_TAG_inlined_subroutine`
+/// containing `probe`, adding its name to `names` and continuing with its own
+/// descendants.
+fn find_inlined<'a>(
+    dwarf: &gimli::Dwarf<Reader<'a>>,
+    units: &[Unit<'a>],
+    unit: &gimli::Unit<Reader<'a>>,
+    node: gimli::EntriesTreeNode<'_, '_, '_, Reader<'a>>,
+    probe: u64,
+    names: &mut Vec<Option<&'a [u8]>>,
+) {
+    let mut children = node.children();
+    while let Ok(Some(child)) = children.next() {
+        match child.entry().tag() {
+            // Nested functions are separate functions of their own.
+            gimli::DW_TAG_subprogram => {}
+            gimli::DW_TAG_inlined_subroutine => {
+                if contains(dwarf, unit, child.entry(), probe) {
+                    names.push(name(dwarf, units, unit, child.entry()));
+                    return find_inlined(dwarf, units, unit, child, probe, names);
+                }
+            }
+            // Inlined functions may be nested in lexical blocks and such.
+            _ => {
+                let len = names.len();
+                find_inlined(dwarf, units, unit, child, probe, names);
+                if names.len() > len {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Tests whether any address range of `entry` contains `probe`.
+fn contains(
+    dwarf: &gimli::Dwarf<Reader<'_>>,
+    unit: &gimli::Unit<Reader<'_>>,
+    entry: &gimli::DebuggingInformationEntry<'_, '_, Reader<'_>>,
+    probe: u64,
+) -> bool {
+    let mut iter = match dwarf.die_ranges(unit, entry) {
+        Ok(iter) => iter,
+        Err(_) => return false,
+    };
+    while let Ok(Some(range)) = iter.next() {
+        if range.begin <= probe && probe < range.end {
+            return true;
+        }
+    }
+    false
+}
+
+/// Finds the name of the function `entry`, preferring the linkage name.
+fn name<'a>(
+    dwarf: &gimli::Dwarf<Reader<'a>>,
+    units: &[Unit<'a>],
+    unit: &gimli::Unit<Reader<'a>>,
+    entry: &gimli::DebuggingInformationEntry<'_, '_, Reader<'a>>,
+) -> Option<&'a [u8]> {
+    let mut unit = unit;
+    let mut entry = entry.clone();
+    for _ in 0..MAX_NAME_DEPTH {
+        let mut name = None;
+        let mut next = None;
+        let mut attrs = entry.attrs();
+        while let Ok(Some(attr)) = attrs.next() {
+            match attr.name() {
+                gimli::DW_AT_linkage_name | gimli::DW_AT_MIPS_linkage_name => {
+                    if let Ok(val) = dwarf.attr_string(unit, attr.value()) {
+                        return Some(val.slice());
+                    }
+                }
+                gimli::DW_AT_name => {
+                    if let Ok(val) = dwarf.attr_string(unit, attr.value()) {
+                        name = Some(val.slice());
+                    }
+                }
+                gimli::DW_AT_abstract_origin | gimli::DW_AT_specification => {
+                    next = Some(attr.value());
+                }
+                _ => {}
+            }
+        }
+        if name.is_some() {
+            return name;
+        }
+        let (next_unit, offset) = match next? {
+            gimli::AttributeValue::UnitRef(offset) => (unit, offset),
+            gimli::AttributeValue::DebugInfoRef(offset) => find_unit(units, offset)?,
+            // References into supplementary object files aren't followed.
+            _ => return None,
+        };
+        unit = next_unit;
+        entry = unit.entry(offset).ok()?;
+    }
+    None
+}
+
+/// Finds the unit containing the entry at `offset` in `.debug_info`.
+fn find_unit<'u, 'a>(
+    units: &'u [Unit<'a>],
+    offset: gimli::DebugInfoOffset,
+) -> Option<(&'u gimli::Unit<Reader<'a>>, gimli::UnitOffset)> {
+    units.iter().find_map(|unit| {
+        let offset = offset.to_unit_offset(&unit.dw_unit.header)?;
+        Some((&unit.dw_unit, offset))
+    })
+}
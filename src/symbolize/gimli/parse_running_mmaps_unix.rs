@@ -76,6 +76,11 @@ impl MapsEntry {
     pub(super) fn ip_matches(&self, ip: usize) -> bool {
         self.address.0 <= ip && ip < self.address.1
     }
+
+    #[cfg(target_os = "linux")]
+    pub(super) fn address(&self) -> (usize, usize) {
+        self.address
+    }
 }
 
 impl FromStr for MapsEntry {
@@ -0,0 +1,91 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cell::{Cell, UnsafeCell};
+use core::fmt::{self, Write};
+use core::slice;
+
+/// How many bytes a `Scratch` stores without allocating.
+const INLINE: usize = 256;
+
+/// A bump arena for temporaries made up while resolving an address, like the
+/// names of functions without symbols.
+///
+/// Symbols only borrow their names and paths, so these just have to live
+/// until the callback they're passed to returns. The first bytes are stored
+/// inline, so resolving an address usually doesn't allocate for them at all,
+/// and everything is freed at once when the arena is dropped.
+pub struct Scratch {
+    inline: UnsafeCell<[u8; INLINE]>,
+    used: Cell<usize>,
+    overflow: UnsafeCell<Vec<String>>,
+}
+
+impl Scratch {
+    pub fn new() -> Scratch {
+        Scratch {
+            inline: UnsafeCell::new([0; INLINE]),
+            used: Cell::new(0),
+            overflow: UnsafeCell::new(Vec::new()),
+        }
+    }
+
+    /// Formats `args` into the arena, returning the bytes written.
+    pub fn format(&self, args: fmt::Arguments<'_>) -> &[u8] {
+        let start = self.used.get();
+        // SAFETY: only the bytes after `used` are written to, which no
+        // reference returned so far points to.
+        let free = unsafe { self.inline.get().cast::<u8>().add(start) };
+        let mut cursor = Cursor {
+            buf: unsafe { slice::from_raw_parts_mut(free, INLINE - start) },
+            len: 0,
+        };
+        if cursor.write_fmt(args).is_ok() {
+            let len = cursor.len;
+            self.used.set(start + len);
+            return unsafe { slice::from_raw_parts(free, len) };
+        }
+
+        let mut string = String::new();
+        let _ = string.write_fmt(args);
+        // SAFETY: this is the only function that ever constructs a mutable
+        // reference to `self.overflow`, and strings are never removed from
+        // it, so their data lives as long as `self` does.
+        let overflow = unsafe { &mut *self.overflow.get() };
+        overflow.push(string);
+        overflow.last().unwrap().as_bytes()
+    }
+}
+
+/// Writes into a fixed buffer, failing if it's full.
+struct Cursor<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl Write for Cursor<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end = self.len + s.len();
+        if end > self.buf.len() {
+            return Err(fmt::Error);
+        }
+        self.buf[self.len..end].copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_earlier_results() {
+        let scratch = Scratch::new();
+        let short = scratch.format(format_args!("fn_{:#x}", 0x1234));
+        let long = scratch.format(format_args!("{:0300}", 7));
+        let last = scratch.format(format_args!("fn_{:#x}", 0x5678));
+        assert_eq!(short, b"fn_0x1234");
+        assert_eq!(long.len(), 300);
+        assert_eq!(last, b"fn_0x5678");
+    }
+}
@@ -5,12 +5,13 @@
 use super::Mmap;
 use alloc::vec;
 use alloc::vec::Vec;
-use core::cell::UnsafeCell;
+use core::cell::{Cell, UnsafeCell};
 
 /// A simple arena allocator for byte buffers.
 pub struct Stash {
     buffers: UnsafeCell<Vec<Vec<u8>>>,
     mmaps: UnsafeCell<Vec<Mmap>>,
+    sealed: Cell<bool>,
 }
 
 impl Stash {
@@ -18,9 +19,22 @@ impl Stash {
         Stash {
             buffers: UnsafeCell::new(Vec::new()),
             mmaps: UnsafeCell::new(Vec::new()),
+            sealed: Cell::new(false),
         }
     }
 
+    /// Forbids opening any more files for the mapping this `Stash` belongs
+    /// to, like split DWARF or object files which are otherwise loaded once
+    /// they're needed.
+    pub fn seal(&self) {
+        self.sealed.set(true);
+    }
+
+    /// Whether `seal` was called.
+    pub fn is_sealed(&self) -> bool {
+        self.sealed.get()
+    }
+
     /// Allocates a buffer of the specified size and returns a mutable reference
     /// to it.
     pub fn allocate(&self, size: usize) -> &mut [u8] {
@@ -34,6 +48,16 @@ impl Stash {
         &mut buffers[i]
     }
 
+    /// Returns the total size of all buffers and maps in this `Stash`.
+    pub fn size(&self) -> usize {
+        // SAFETY: no mutable references to `buffers` or `mmaps` outlive the
+        // calls to `allocate` and `cache_mmap`.
+        let buffers = unsafe { &*self.buffers.get() };
+        let mmaps = unsafe { &*self.mmaps.get() };
+        buffers.iter().map(|b| b.len()).sum::<usize>()
+            + mmaps.iter().map(|m| m.len()).sum::<usize>()
+    }
+
     /// Stores a `Mmap` for the lifetime of this `Stash`, returning a pointer
     /// which is scoped to just this lifetime.
     pub fn cache_mmap(&self, map: Mmap) -> &[u8] {
@@ -0,0 +1,126 @@
+//! Symbol maps dumped with `dump_symbol_map` and loaded with
+//! `load_symbol_map`.
+//!
+//! A symbol map lists the modules of a process, each with its name, build ID
+//! and a digest of its symbol table, see the `digest` module. Everything is
+//! stored in little endian:
+//!
+//! - header: magic, version and number of modules,
+//! - for each module: length of the name, name, length of the build ID or all
+//!   ones if there's none, build ID, length of the digest and digest.
+
+use super::mystd::prelude::v1::*;
+use super::mystd::sync::Mutex;
+use super::{digest, native_libraries, open_mapping, Library, Mapping};
+use core::convert::TryInto;
+
+const MAGIC: &[u8; 8] = b"BTSYMMAP";
+/// Changed whenever the layout changes, so stale symbol maps are rejected.
+const VERSION: u32 = 2;
+const NONE: u32 = u32::MAX;
+
+/// A module of a loaded symbol map.
+struct Module {
+    name: Vec<u8>,
+    build_id: Option<Vec<u8>>,
+    digest: Vec<u8>,
+}
+
+/// The modules of all symbol maps loaded so far.
+static MODULES: Mutex<Vec<Module>> = Mutex::new(Vec::new());
+
+/// Dumps the symbol tables of all modules currently loaded into the process.
+pub fn dump() -> Vec<u8> {
+    let libraries = native_libraries();
+    let mut out = Vec::new();
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&VERSION.to_le_bytes());
+    let count = out.len();
+    out.extend_from_slice(&0u32.to_le_bytes());
+
+    let mut modules = 0u32;
+    for lib in libraries.iter() {
+        let mut mapping = match open_mapping(lib) {
+            Some(mapping) => mapping,
+            None => continue,
+        };
+        let build_id = lib.build_id.as_deref();
+        let digest = digest::digest_symbols(&mut mapping, build_id.unwrap_or(&[]));
+        let name = name(lib);
+        out.extend_from_slice(&(name.len() as u32).to_le_bytes());
+        out.extend_from_slice(&name);
+        match build_id {
+            Some(build_id) => {
+                out.extend_from_slice(&(build_id.len() as u32).to_le_bytes());
+                out.extend_from_slice(build_id);
+            }
+            None => out.extend_from_slice(&NONE.to_le_bytes()),
+        }
+        out.extend_from_slice(&(digest.len() as u32).to_le_bytes());
+        out.extend_from_slice(&digest);
+        modules += 1;
+    }
+    out[count..count + 4].copy_from_slice(&modules.to_le_bytes());
+    out
+}
+
+/// Loads the symbol map in `data`, returning `None` if it's damaged or of
+/// another version.
+pub fn load(data: &[u8]) -> Option<()> {
+    let mut data = data;
+    if take(&mut data, MAGIC.len())? != MAGIC || take_u32(&mut data)? != VERSION {
+        return None;
+    }
+    let count = take_u32(&mut data)?;
+    let mut modules = Vec::new();
+    for _ in 0..count {
+        let len = take_u32(&mut data)? as usize;
+        let name = take(&mut data, len)?.to_vec();
+        let build_id = match take_u32(&mut data)? {
+            NONE => None,
+            len => Some(take(&mut data, len as usize)?.to_vec()),
+        };
+        let len = take_u32(&mut data)? as usize;
+        let digest = take(&mut data, len)?.to_vec();
+        modules.push(Module {
+            name,
+            build_id,
+            digest,
+        });
+    }
+    if !data.is_empty() {
+        return None;
+    }
+    MODULES.lock().ok()?.extend(modules);
+    Some(())
+}
+
+/// Creates a mapping of `lib` from the loaded symbol maps, if any of them has
+/// a module of the same build, or of the same name if `lib` has no build ID.
+pub fn mapping(lib: &Library) -> Option<Mapping> {
+    let modules = MODULES.lock().ok()?;
+    let name = name(lib);
+    let module = modules.iter().rev().find(|module| match &lib.build_id {
+        Some(build_id) => module.build_id.as_ref() == Some(build_id),
+        None => module.build_id.is_none() && module.name == name,
+    })?;
+    let build_id = module.build_id.as_deref().unwrap_or(&[]);
+    digest::from_bytes(&module.digest, build_id)
+}
+
+fn name(lib: &Library) -> Vec<u8> {
+    lib.name.to_string_lossy().as_bytes().to_vec()
+}
+
+fn take<'a>(data: &mut &'a [u8], len: usize) -> Option<&'a [u8]> {
+    if data.len() < len {
+        return None;
+    }
+    let (bytes, rest) = data.split_at(len);
+    *data = rest;
+    Some(bytes)
+}
+
+fn take_u32(data: &mut &[u8]) -> Option<u32> {
+    Some(u32::from_le_bytes(take(data, 4)?.try_into().ok()?))
+}
@@ -1,7 +1,10 @@
 use super::mystd::ffi::{OsStr, OsString};
 use super::mystd::os::unix::ffi::OsStrExt;
 use super::mystd::str;
-use super::{gimli, Context, Endian, EndianSlice, Mapping, Path, Stash, Vec};
+#[cfg(not(feature = "minimal-symbolication"))]
+use super::{gimli, Endian, EndianSlice};
+use super::{Context, Mapping, Path, Stash, SymtabInfo, Vec};
+#[cfg(not(feature = "minimal-symbolication"))]
 use alloc::sync::Arc;
 use core::ops::Deref;
 use object::read::archive::ArchiveFile;
@@ -38,6 +41,16 @@ impl Mapping {
             }
         })
     }
+
+    /// Creates a `Mapping` from a copy of the object file `data`, which can't
+    /// be an archive.
+    #[cfg(feature = "std")]
+    pub fn from_bytes(data: &[u8]) -> Option<Mapping> {
+        let map = super::Mmap::copy_from(data)?;
+        Mapping::mk(map, |data, stash| {
+            Context::new(stash, Object::parse(data)?, None, None)
+        })
+    }
 }
 
 struct ParsedSym<'a> {
@@ -150,6 +163,19 @@ impl<'a> Object<'a> {
         Some(self.file.section_by_name(name)?.data().ok()?)
     }
 
+    /// Passes the name, address and size of each symbol to `cb`, in order of
+    /// increasing address.
+    pub fn for_each_symbol(&self, cb: &mut dyn FnMut(&'a [u8], u64, Option<u64>)) {
+        for sym in self.syms.iter() {
+            // See `search_symtab` for why the `.` prefix is trimmed.
+            cb(
+                sym.name.trim_start_matches(".").as_bytes(),
+                sym.address,
+                Some(sym.size),
+            );
+        }
+    }
+
     pub fn search_symtab<'b>(&'b self, addr: u64) -> Option<&'b [u8]> {
         // Symbols, except ".text" and ".data", are sorted and are not overlapped each other,
         // so we can just perform a binary search here.
@@ -172,11 +198,34 @@ impl<'a> Object<'a> {
         }
     }
 
-    pub(super) fn search_object_map(&self, _addr: u64) -> Option<(&Context<'_>, u64)> {
+    pub fn function_start(&self, _addr: u64) -> Option<u64> {
+        None
+    }
+
+    #[cfg(feature = "std")]
+    pub fn for_each_function(&self, _cb: &mut dyn FnMut(u64, u64)) {}
+
+    pub fn symtab_info(&self, _addr: u64) -> Option<SymtabInfo<'a>> {
+        None
+    }
+
+    pub fn find_symbol(&self, name: &[u8]) -> Option<(u64, Option<u64>)> {
+        super::scan_symbols(name, |cb| self.for_each_symbol(cb))
+    }
+
+    pub fn dynamic_symbol_count(&self) -> Option<usize> {
+        None
+    }
+
+    #[cfg(feature = "std")]
+    pub(super) fn for_each_object_map_symbol(&self, _cb: &mut dyn FnMut(u64, u64)) {}
+
+    pub(super) fn search_object_map(&self, _addr: u64, _open: bool) -> Option<(&Context<'_>, u64)> {
         None
     }
 }
 
+#[cfg(not(feature = "minimal-symbolication"))]
 pub(super) fn handle_split_dwarf<'data>(
     _package: Option<&gimli::DwarfPackage<EndianSlice<'data, Endian>>>,
     _stash: &'data Stash,
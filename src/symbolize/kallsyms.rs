@@ -0,0 +1,168 @@
+//! Symbolication of Linux kernel addresses using `/proc/kallsyms`.
+//!
+//! Stacks recorded by profilers through `perf_event_open` or eBPF often
+//! contain kernel frames, which can't be resolved through the modules loaded
+//! into the current process. The kernel publishes its own symbol table in
+//! `/proc/kallsyms` instead, which is what this parses.
+
+use super::SymbolName;
+use std::fs;
+use std::io;
+use std::prelude::v1::*;
+
+/// The symbol table of the running Linux kernel, as read from
+/// `/proc/kallsyms`.
+///
+/// This can be used to resolve kernel addresses in stacks recorded by
+/// profiling tools, which `resolve` knows nothing about.
+///
+/// # Required features
+///
+/// This type requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default. It's only available
+/// on Linux and Android.
+#[derive(Clone, Debug)]
+pub struct KernelSymbols {
+    /// Symbols sorted by address.
+    syms: Vec<Sym>,
+    /// All symbol names, followed by the name of their module if any.
+    names: String,
+}
+
+#[derive(Clone, Debug)]
+struct Sym {
+    addr: usize,
+    name: (usize, usize),
+    module: Option<(usize, usize)>,
+}
+
+impl KernelSymbols {
+    /// Reads the symbol table of the running kernel from `/proc/kallsyms`.
+    ///
+    /// Depending on the `kernel.kptr_restrict` sysctl and the privileges of
+    /// the current process, the kernel may hide the addresses of its symbols.
+    /// This isn't worked around in any way, and an error of kind
+    /// `PermissionDenied` is returned instead in that case.
+    pub fn load() -> io::Result<KernelSymbols> {
+        let contents = fs::read_to_string("/proc/kallsyms")?;
+        let syms = KernelSymbols::parse(&contents);
+        if syms.syms.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "kernel symbol addresses are hidden, see kernel.kptr_restrict",
+            ));
+        }
+        Ok(syms)
+    }
+
+    /// Parses a symbol table in the format of `/proc/kallsyms`.
+    ///
+    /// This is useful to resolve kernel addresses recorded on a different
+    /// machine, given a copy of its `/proc/kallsyms`. Only symbols in text
+    /// sections are kept, and malformed lines or symbols with hidden
+    /// addresses are ignored.
+    pub fn parse(contents: &str) -> KernelSymbols {
+        let mut syms = Vec::new();
+        let mut names = String::new();
+        for line in contents.lines() {
+            // Lines look like `ffffffff81000000 T _stext` for symbols of the
+            // kernel itself, with a trailing `\t[module]` for modules.
+            let mut parts = line.split_ascii_whitespace();
+            let (addr, kind, name) = match (parts.next(), parts.next(), parts.next()) {
+                (Some(addr), Some(kind), Some(name)) => (addr, kind, name),
+                _ => continue,
+            };
+            if !matches!(kind, "T" | "t" | "W" | "w") {
+                continue;
+            }
+            let addr = match usize::from_str_radix(addr, 16) {
+                Ok(0) | Err(_) => continue,
+                Ok(addr) => addr,
+            };
+            let module = parts
+                .next()
+                .and_then(|m| m.strip_prefix('[')?.strip_suffix(']'));
+
+            let name = push_str(&mut names, name);
+            let module = module.map(|module| push_str(&mut names, module));
+            syms.push(Sym { addr, name, module });
+        }
+        syms.sort_by_key(|sym| sym.addr);
+        KernelSymbols { syms, names }
+    }
+
+    /// Returns the number of symbols in this table.
+    pub fn len(&self) -> usize {
+        self.syms.len()
+    }
+
+    /// Returns whether this table contains no symbols.
+    pub fn is_empty(&self) -> bool {
+        self.syms.is_empty()
+    }
+
+    /// Finds the symbol containing the kernel address `addr`.
+    ///
+    /// The symbol table doesn't record the size of symbols, so each symbol is
+    /// assumed to extend up to the next one. Addresses past the last symbol
+    /// aren't resolved.
+    pub fn resolve(&self, addr: usize) -> Option<KernelSymbol<'_>> {
+        // Several symbols may share an address, in which case this picks the
+        // last one listed.
+        let i = self
+            .syms
+            .partition_point(|sym| sym.addr <= addr)
+            .checked_sub(1)?;
+        if i + 1 >= self.syms.len() {
+            return None;
+        }
+        let sym = &self.syms[i];
+        Some(KernelSymbol {
+            name: self.str(sym.name),
+            addr: sym.addr,
+            module: sym.module.map(|module| self.str(module)),
+        })
+    }
+
+    fn str(&self, (start, end): (usize, usize)) -> &str {
+        &self.names[start..end]
+    }
+}
+
+fn push_str(names: &mut String, s: &str) -> (usize, usize) {
+    let start = names.len();
+    names.push_str(s);
+    (start, names.len())
+}
+
+/// A symbol of the Linux kernel, as returned by `KernelSymbols::resolve`.
+///
+/// # Required features
+///
+/// This type requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default. It's only available
+/// on Linux and Android.
+#[derive(Copy, Clone, Debug)]
+pub struct KernelSymbol<'a> {
+    name: &'a str,
+    addr: usize,
+    module: Option<&'a str>,
+}
+
+impl<'a> KernelSymbol<'a> {
+    /// Returns the name of this symbol.
+    pub fn name(&self) -> SymbolName<'a> {
+        SymbolName::new(self.name.as_bytes())
+    }
+
+    /// Returns the starting address of this symbol.
+    pub fn addr(&self) -> usize {
+        self.addr
+    }
+
+    /// Returns the name of the kernel module this symbol belongs to, or `None`
+    /// if it's part of the kernel image itself.
+    pub fn module(&self) -> Option<&'a str> {
+        self.module
+    }
+}
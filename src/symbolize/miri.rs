@@ -3,11 +3,13 @@ use core::marker::PhantomData;
 
 use super::super::backtrace::miri::{resolve_addr, Frame};
 use super::BytesOrWideString;
-use super::{ResolveWhat, SymbolName};
+use super::{ResolveWhat, SymbolBinding, SymbolName, SymbolVersion, SymbolVisibility};
 
 pub unsafe fn resolve(what: ResolveWhat<'_>, cb: &mut dyn FnMut(&super::Symbol)) {
     let sym = match what {
-        ResolveWhat::Address(addr) => Symbol {
+        // Miri resolves function pointers rather than instructions, so the
+        // address is never adjusted.
+        ResolveWhat::Address(addr, _) => Symbol {
             inner: resolve_addr(addr),
             _unused: PhantomData,
         },
@@ -33,6 +35,30 @@ impl<'a> Symbol<'a> {
         Some(self.inner.addr)
     }
 
+    pub fn offset(&self) -> Option<usize> {
+        None
+    }
+
+    pub fn module_base(&self) -> Option<*mut c_void> {
+        None
+    }
+
+    pub fn module_path(&self) -> Option<BytesOrWideString<'_>> {
+        None
+    }
+
+    pub fn binding(&self) -> Option<SymbolBinding> {
+        None
+    }
+
+    pub fn visibility(&self) -> Option<SymbolVisibility> {
+        None
+    }
+
+    pub fn version(&self) -> Option<SymbolVersion<'_>> {
+        None
+    }
+
     pub fn filename_raw(&self) -> Option<BytesOrWideString<'_>> {
         Some(BytesOrWideString::Bytes(&self.inner.inner.filename))
     }
@@ -53,4 +79,119 @@ impl<'a> Symbol<'a> {
     }
 }
 
+pub unsafe fn resolve_many(
+    what: &[ResolveWhat<'_>],
+    next: &mut dyn FnMut(usize) -> bool,
+    cb: &mut dyn FnMut(usize, &super::Symbol),
+) {
+    for (i, what) in what.iter().enumerate() {
+        if !next(i) {
+            return;
+        }
+        resolve(*what, &mut |sym| cb(i, sym));
+    }
+}
+
+#[cfg(feature = "std")]
+pub unsafe fn resolve_batch(what: &[ResolveWhat<'_>], cb: &(dyn Fn(usize, &super::Symbol) + Sync)) {
+    for (i, what) in what.iter().enumerate() {
+        resolve(*what, &mut |sym| cb(i, sym));
+    }
+}
+
+#[cfg(feature = "std")]
+pub unsafe fn diagnose(_what: ResolveWhat<'_>) -> Option<super::ResolveError> {
+    None
+}
+
+pub unsafe fn find_module(_addr: *mut c_void, _cb: &mut dyn FnMut(&super::ModuleInfo<'_>)) {}
+
+pub unsafe fn find_module_by_build_id(_build_id: &[u8]) -> Option<usize> {
+    None
+}
+
+pub unsafe fn module_symbols(_addr: usize, _cb: &mut dyn FnMut(&super::ModuleSymbol<'_>)) {}
+
+pub unsafe fn find_module_symbol<'a>(
+    _addr: usize,
+    _name: &'a [u8],
+) -> Option<super::ModuleSymbol<'a>> {
+    None
+}
+
+pub unsafe fn dynamic_symbol_count(_addr: usize) -> Option<usize> {
+    None
+}
+
 pub unsafe fn clear_symbol_cache() {}
+
+#[cfg(all(feature = "std", unix))]
+pub unsafe fn after_fork(_stale: bool) {}
+
+pub unsafe fn refresh_libraries() {}
+
+/// See `super::Symbolizer`, which has nothing of its own to cache here.
+#[cfg(feature = "std")]
+pub struct Symbolizer;
+
+#[cfg(feature = "std")]
+impl Symbolizer {
+    pub fn new() -> Symbolizer {
+        Symbolizer
+    }
+
+    pub fn set_cache_size(&mut self, _size: usize) {}
+
+    pub fn clear_cache(&mut self) {}
+
+    pub unsafe fn resolve(&mut self, what: ResolveWhat<'_>, cb: &mut dyn FnMut(&super::Symbol)) {
+        resolve(what, cb)
+    }
+}
+
+/// See `super::ObjectSymbolizer`, which can't be created here.
+#[cfg(feature = "std")]
+pub enum ObjectSymbolizer {}
+
+#[cfg(feature = "std")]
+impl ObjectSymbolizer {
+    pub fn new(_data: &[u8]) -> Option<ObjectSymbolizer> {
+        None
+    }
+
+    pub fn open(_path: &std::path::Path) -> Option<ObjectSymbolizer> {
+        None
+    }
+
+    pub fn from_process(_pid: u32, _base: u64, _size: u64) -> Option<(ObjectSymbolizer, u64)> {
+        None
+    }
+
+    pub fn image(&self) -> Option<(u64, Option<std::vec::Vec<u8>>)> {
+        match *self {}
+    }
+
+    pub fn resolve(&mut self, _svma: u64, _cb: &mut dyn FnMut(&super::Symbol)) {
+        match *self {}
+    }
+}
+
+pub unsafe fn preopen() {}
+
+#[cfg(feature = "std")]
+pub unsafe fn files() -> std::vec::Vec<std::path::PathBuf> {
+    std::vec::Vec::new()
+}
+
+#[cfg(feature = "std")]
+pub unsafe fn stats(_stats: &mut super::SymbolizerStats) {}
+
+#[cfg(feature = "std")]
+pub unsafe fn dump_symbol_map() -> Option<std::vec::Vec<u8>> {
+    None
+}
+
+#[cfg(feature = "std")]
+pub fn load_symbol_map(_data: &[u8]) -> Option<bool> {
+    None
+}
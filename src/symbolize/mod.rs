@@ -1,8 +1,10 @@
-use core::{fmt, str};
+use core::fmt::{self, Write as _};
+use core::str;
 
 cfg_if::cfg_if! {
     if #[cfg(feature = "std")] {
-        use std::path::Path;
+        use std::io;
+        use std::path::{Path, PathBuf};
         use std::prelude::v1::*;
     }
 }
@@ -28,6 +30,10 @@ use rustc_demangle::{try_demangle, Demangle};
 /// Note that if you have a `Frame` then it's recommended to use the
 /// `resolve_frame` function instead of this one.
 ///
+/// `cb` may capture backtraces and resolve symbols itself. Those nested calls
+/// can't use the symbol cache, which is busy with the outer call, so they load
+/// the debug information they need from scratch and are much slower.
+///
 /// # Required features
 ///
 /// This function requires the `std` feature of the `backtrace` crate to be
@@ -60,7 +66,48 @@ use rustc_demangle::{try_demangle, Demangle};
 #[cfg(feature = "std")]
 pub fn resolve<F: FnMut(&Symbol)>(addr: *mut c_void, cb: F) {
     let _guard = crate::lock::lock();
-    unsafe { resolve_unsynchronized(addr, cb) }
+    stats::time_resolve(|| unsafe { resolve_unsynchronized(addr, cb) })
+}
+
+/// Resolve an address of the given `kind` to a symbol, passing the symbol to
+/// the specified closure.
+///
+/// This is the same as `resolve`, except that addresses which aren't return
+/// addresses can be resolved as well, like the address of the faulting
+/// instruction in a signal handler or the address where a sampling profiler
+/// interrupted a thread. `resolve` treats every address as a return address,
+/// which makes the symbols found for such addresses those of the previous
+/// instruction, possibly on a different line or even in a different function.
+///
+/// # Required features
+///
+/// This function requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default.
+///
+/// # Panics
+///
+/// See information on `resolve` for caveats on `cb` panicking.
+///
+/// # Example
+///
+/// ```
+/// extern crate backtrace;
+///
+/// use backtrace::AddressKind;
+///
+/// fn main() {
+///     // The address of a function is the address of its first instruction.
+///     let addr = main as *const () as *mut std::ffi::c_void;
+///     backtrace::resolve_address(addr, AddressKind::Precise, |symbol| {
+///         println!("{:?}", symbol.name());
+///     });
+/// }
+/// ```
+#[cfg(feature = "std")]
+pub fn resolve_address<F: FnMut(&Symbol)>(addr: *mut c_void, kind: AddressKind, mut cb: F) {
+    let _guard = crate::lock::lock();
+    let what = ResolveWhat::Address(addr, kind);
+    stats::time_resolve(|| unsafe { imp::resolve(what, &mut cb) })
 }
 
 /// Resolve a previously capture frame to a symbol, passing the symbol to the
@@ -102,22 +149,266 @@ pub fn resolve<F: FnMut(&Symbol)>(addr: *mut c_void, cb: F) {
 #[cfg(feature = "std")]
 pub fn resolve_frame<F: FnMut(&Symbol)>(frame: &Frame, cb: F) {
     let _guard = crate::lock::lock();
-    unsafe { resolve_frame_unsynchronized(frame, cb) }
+    stats::time_resolve(|| unsafe { resolve_frame_unsynchronized(frame, cb) })
+}
+
+/// Resolve many addresses to symbols at once, passing each symbol along with
+/// the index in `addrs` of the address it belongs to to the specified closure.
+///
+/// This function performs the same function as calling `resolve` for each
+/// address, but is much faster for large numbers of addresses, like the unique
+/// addresses of a profile. The addresses are sorted and grouped by the module
+/// containing them, so the debug information of each module is only looked up
+/// once and walked in address order. As a result, the closure is called with
+/// addresses in an unspecified order, although all the symbols of one address
+/// are passed one after another in the same order as `resolve` would.
+///
+/// # Required features
+///
+/// This function requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default.
+///
+/// # Panics
+///
+/// This function strives to never panic, but if the `cb` provided panics then
+/// some platforms will force a double panic to abort the process. Some
+/// platforms use a C library which internally uses callbacks which cannot be
+/// unwound through, so panicking from `cb` may trigger a process abort.
+///
+/// # Example
+///
+/// ```
+/// extern crate backtrace;
+///
+/// fn main() {
+///     let mut ips = Vec::new();
+///     backtrace::trace(|frame| {
+///         ips.push(frame.ip() as usize);
+///         true
+///     });
+///
+///     backtrace::resolve_many(&ips, |i, symbol| {
+///         println!("{:#x}: {:?}", ips[i], symbol.name());
+///     });
+/// }
+/// ```
+#[cfg(feature = "std")]
+pub fn resolve_many<F: FnMut(usize, &Symbol)>(addrs: &[usize], mut cb: F) {
+    let what = addrs
+        .iter()
+        .map(|&addr| ResolveWhat::Address(addr as *mut c_void, AddressKind::ReturnAddress))
+        .collect::<Vec<_>>();
+    let _guard = crate::lock::lock();
+    stats::time_resolve(|| unsafe { imp::resolve_many(&what, &mut |_| true, &mut cb) })
 }
 
+/// Like `resolve_many`, but `next` is called with the index of each address
+/// before it's resolved, and nothing more is resolved once it returns `false`.
+///
+/// Addresses are resolved in no particular order, and those in modules which
+/// haven't been looked at yet are checked before loading their debug
+/// information, so this can stop long-running resolution in between.
+#[cfg(feature = "std")]
+pub(crate) fn resolve_many_while(
+    what: &[ResolveWhat<'_>],
+    next: &mut dyn FnMut(usize) -> bool,
+    cb: &mut dyn FnMut(usize, &Symbol),
+) {
+    let _guard = crate::lock::lock();
+    stats::time_resolve(|| unsafe { imp::resolve_many(what, next, cb) })
+}
+
+/// Resolve an address to a symbol like `resolve`, but reporting why no symbols
+/// were found.
+///
+/// The closure is called just like with `resolve`, and `Ok` is returned if it
+/// was called at least once. Otherwise the returned `ResolveError` describes
+/// why the address couldn't be resolved, which helps to diagnose frames
+/// missing from backtraces. How precisely the cause can be determined depends
+/// on the platform.
+///
+/// # Required features
+///
+/// This function requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default.
+///
+/// # Panics
+///
+/// See information on `resolve` for caveats on `cb` panicking.
+///
+/// # Example
+///
+/// ```
+/// extern crate backtrace;
+///
+/// fn main() {
+///     backtrace::trace(|frame| {
+///         let result = backtrace::try_resolve(frame.ip(), |symbol| {
+///             // ...
+///         });
+///         if let Err(e) = result {
+///             println!("no symbols for {:?}: {}", frame.ip(), e);
+///         }
+///
+///         false // only look at the top frame
+///     });
+/// }
+/// ```
+#[cfg(feature = "std")]
+pub fn try_resolve<F: FnMut(&Symbol)>(addr: *mut c_void, cb: F) -> Result<(), ResolveError> {
+    try_resolve_what(ResolveWhat::Address(addr, AddressKind::ReturnAddress), cb)
+}
+
+/// Resolve a previously captured frame to a symbol like `resolve_frame`, but
+/// reporting why no symbols were found.
+///
+/// See `try_resolve` for details.
+///
+/// # Required features
+///
+/// This function requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default.
+///
+/// # Panics
+///
+/// See information on `resolve` for caveats on `cb` panicking.
+#[cfg(feature = "std")]
+pub fn try_resolve_frame<F: FnMut(&Symbol)>(frame: &Frame, cb: F) -> Result<(), ResolveError> {
+    try_resolve_what(ResolveWhat::Frame(frame), cb)
+}
+
+#[cfg(feature = "std")]
+fn try_resolve_what<F: FnMut(&Symbol)>(
+    what: ResolveWhat<'_>,
+    mut cb: F,
+) -> Result<(), ResolveError> {
+    let _guard = crate::lock::lock();
+    let mut found = false;
+    stats::time_resolve(|| unsafe {
+        imp::resolve(what, &mut |symbol| {
+            found = true;
+            cb(symbol)
+        })
+    });
+    if found {
+        Ok(())
+    } else {
+        Err(unsafe { diagnose_unsynchronized(what) })
+    }
+}
+
+/// Finds out why no symbols are found for `what`.
+#[cfg(feature = "std")]
+pub(crate) fn diagnose(what: ResolveWhat<'_>) -> ResolveError {
+    let _guard = crate::lock::lock();
+    unsafe { diagnose_unsynchronized(what) }
+}
+
+#[cfg(feature = "std")]
+unsafe fn diagnose_unsynchronized(what: ResolveWhat<'_>) -> ResolveError {
+    if let Some(error) = imp::diagnose(what) {
+        return error;
+    }
+    // The platform can't tell us more, so at least check whether the address
+    // is in a module.
+    let mut found = false;
+    imp::find_module(what.address_or_ip(), &mut |_| found = true);
+    if found {
+        ResolveError::NoSymbol
+    } else {
+        ResolveError::NoModule
+    }
+}
+
+/// The reason no symbols were found for an address, as returned by
+/// `try_resolve`.
+///
+/// # Required features
+///
+/// This type requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default.
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum ResolveError {
+    /// The address isn't in any module loaded into the current process.
+    NoModule,
+    /// The file of the module containing the address couldn't be read.
+    Unreadable(PathBuf),
+    /// The file of the module containing the address couldn't be parsed.
+    ParseError(PathBuf),
+    /// The module containing the address has no debug information, and its
+    /// symbol table, if any, has no symbol for the address.
+    NoDebugInfo,
+    /// The debug information of the module containing the address has no
+    /// symbol for it.
+    NoSymbol,
+    /// Resolution was stopped because a deadline passed, see
+    /// `Backtrace::try_resolve`.
+    Timeout,
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResolveError::NoModule => f.write_str("address isn't in any loaded module"),
+            ResolveError::Unreadable(path) => write!(f, "couldn't read `{}`", path.display()),
+            ResolveError::ParseError(path) => write!(f, "couldn't parse `{}`", path.display()),
+            ResolveError::NoDebugInfo => f.write_str("module has no debug information"),
+            ResolveError::NoSymbol => f.write_str("no symbol found for address"),
+            ResolveError::Timeout => f.write_str("deadline passed before resolving"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ResolveError {}
+
+/// What kind of address is resolved by `resolve_address`, which determines
+/// the instruction symbols are looked up for.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum AddressKind {
+    /// The address of the instruction after a call, like the instruction
+    /// pointers of all but the innermost frame of a stack. Symbols are looked
+    /// up for the call instruction before it.
+    ///
+    /// This is what `resolve` and the instruction pointers of frames are
+    /// treated as.
+    ReturnAddress,
+    /// The address of an instruction itself, like the instruction pointer of
+    /// a faulting or interrupted thread. Symbols are looked up for exactly
+    /// this address.
+    Precise,
+}
+
+#[derive(Copy, Clone)]
 pub enum ResolveWhat<'a> {
-    Address(*mut c_void),
+    Address(*mut c_void, AddressKind),
     Frame(&'a Frame),
 }
 
 impl<'a> ResolveWhat<'a> {
+    /// Returns the address of the instruction to look up symbols for.
     #[allow(dead_code)]
     fn address_or_ip(&self) -> *mut c_void {
         match self {
-            ResolveWhat::Address(a) => adjust_ip(*a),
+            ResolveWhat::Address(a, AddressKind::ReturnAddress) => adjust_ip(*a),
+            ResolveWhat::Address(a, AddressKind::Precise) => *a,
             ResolveWhat::Frame(f) => adjust_ip(f.ip()),
         }
     }
+
+    /// Returns the base address of the module containing the address, if the
+    /// unwinder reported it along with the frame.
+    #[allow(dead_code)]
+    fn module_hint(&self) -> Option<*mut c_void> {
+        match self {
+            ResolveWhat::Address(..) => None,
+            ResolveWhat::Frame(f) => f.module_base_address(),
+        }
+    }
 }
 
 // IP values from stack frames are typically (always?) the instruction
@@ -136,8 +427,9 @@ impl<'a> ResolveWhat<'a> {
 // or the current.
 //
 // For now though this is a pretty niche concern so we just internally always
-// subtract one. Consumers should keep working and getting pretty good results,
-// so we should be good enough.
+// subtract one, unless an address is explicitly resolved as
+// `AddressKind::Precise`. Consumers should keep working and getting pretty good
+// results, so we should be good enough.
 fn adjust_ip(a: *mut c_void) -> *mut c_void {
     if a.is_null() {
         a
@@ -159,7 +451,30 @@ pub unsafe fn resolve_unsynchronized<F>(addr: *mut c_void, mut cb: F)
 where
     F: FnMut(&Symbol),
 {
-    imp::resolve(ResolveWhat::Address(addr), &mut cb)
+    imp::resolve(
+        ResolveWhat::Address(addr, AddressKind::ReturnAddress),
+        &mut cb,
+    )
+}
+
+/// Same as `resolve_address`, only unsafe as it's unsynchronized.
+///
+/// This function does not have synchronization guarantees but is available
+/// when the `std` feature of this crate isn't compiled in. See the
+/// `resolve_address` function for more documentation and examples.
+///
+/// # Safety
+///
+/// Callers must ensure no other thread resolves symbols at the same time.
+///
+/// # Panics
+///
+/// See information on `resolve` for caveats on `cb` panicking.
+pub unsafe fn resolve_address_unsynchronized<F>(addr: *mut c_void, kind: AddressKind, mut cb: F)
+where
+    F: FnMut(&Symbol),
+{
+    imp::resolve(ResolveWhat::Address(addr, kind), &mut cb)
 }
 
 /// Same as `resolve_frame`, only unsafe as it's unsynchronized.
@@ -178,6 +493,159 @@ where
     imp::resolve(ResolveWhat::Frame(frame), &mut cb)
 }
 
+/// Resolves many addresses or frames at once, passing each symbol to `cb`
+/// along with the index in `what` it was found for.
+///
+/// Backends may group addresses by module and resolve them in parallel, so `cb`
+/// may be called concurrently from multiple threads and in any order.
+#[cfg(feature = "std")]
+pub(crate) fn resolve_batch(what: &[ResolveWhat<'_>], cb: &(dyn Fn(usize, &Symbol) + Sync)) {
+    let _guard = crate::lock::lock();
+    stats::time_resolve(|| unsafe { imp::resolve_batch(what, cb) })
+}
+
+/// Information about the loaded module (executable or shared library)
+/// containing an address, as yielded by `find_module_unsynchronized`.
+pub(crate) struct ModuleInfo<'a> {
+    /// The path of the module, which may be empty if it's unknown.
+    pub(crate) name: BytesOrWideString<'a>,
+    /// The lowest address the module is loaded at.
+    pub(crate) base: usize,
+    /// The stated virtual memory address of the queried address, i.e. the
+    /// address it corresponds to in the object file itself.
+    pub(crate) svma: usize,
+    /// An identifier of the exact build of the module, if known.
+    pub(crate) build_id: Option<&'a [u8]>,
+}
+
+/// Looks up the module containing `addr`, passing it to `cb` if found.
+///
+/// This requires external synchronization just like `resolve_unsynchronized`.
+pub(crate) unsafe fn find_module_unsynchronized(
+    addr: *mut c_void,
+    cb: &mut dyn FnMut(&ModuleInfo<'_>),
+) {
+    imp::find_module(addr, cb)
+}
+
+/// Looks up the module containing `ip` in the current process.
+#[cfg(feature = "std")]
+pub(crate) fn with_module<R>(ip: *mut c_void, f: impl FnOnce(&ModuleInfo<'_>) -> R) -> Option<R> {
+    let _guard = crate::lock::lock();
+    let mut f = Some(f);
+    let mut ret = None;
+    unsafe {
+        find_module_unsynchronized(ip, &mut |module| {
+            if let Some(f) = f.take() {
+                ret = Some(f(module));
+            }
+        });
+    }
+    ret
+}
+
+/// Like `with_module`, but looks up the modules containing all of `ips` while
+/// taking the lock just once, passing the index of each address found to `f`.
+#[cfg(feature = "std")]
+pub(crate) fn with_modules(ips: &[*mut c_void], f: &mut dyn FnMut(usize, &ModuleInfo<'_>)) {
+    let _guard = crate::lock::lock();
+    for (i, &ip) in ips.iter().enumerate() {
+        let mut found = false;
+        unsafe {
+            find_module_unsynchronized(ip, &mut |module| {
+                if !found {
+                    found = true;
+                    f(i, module);
+                }
+            });
+        }
+    }
+}
+
+/// Enumerate all symbols in the symbol table of the module (executable or
+/// shared library) containing `addr`, passing each to the specified closure.
+///
+/// This gives access to the same symbol tables used to name frames when there
+/// is no debug information, so profilers can dump the symbols of a module
+/// ahead of time or tools can build their own lookup structures. Symbols are
+/// yielded in order of increasing address and their addresses are where they
+/// are loaded in memory in the current process.
+///
+/// If no module containing `addr` is found, or its symbol table can't be read,
+/// the closure isn't called. This is currently only implemented for platforms
+/// using the `gimli` crate for symbolication, and so yields nothing on
+/// Windows MSVC for example.
+///
+/// # Required features
+///
+/// This function requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default.
+///
+/// # Example
+///
+/// ```
+/// extern crate backtrace;
+///
+/// fn main() {
+///     let addr = main as *const () as *mut std::ffi::c_void;
+///     backtrace::module_symbols(addr, |symbol| {
+///         println!("{:?} {}", symbol.addr(), symbol.name());
+///     });
+/// }
+/// ```
+#[cfg(feature = "std")]
+pub fn module_symbols<F: FnMut(&ModuleSymbol<'_>)>(addr: *mut c_void, mut cb: F) {
+    let _guard = crate::lock::lock();
+    unsafe { imp::module_symbols(addr as usize, &mut cb) }
+}
+
+/// Finds the symbol named `name` in the symbol table of the module (executable
+/// or shared library) containing `addr`.
+///
+/// This looks at the same symbols as `module_symbols`, and the symbol
+/// returned is the first one `module_symbols` would yield with that name,
+/// except for ELF modules with several versions of the symbol, where the
+/// default version is returned. Exported symbols of ELF modules are found
+/// through the GNU hash table of the dynamic symbol table, so this is fast
+/// even for stripped libraries with many of them, while other symbols are
+/// searched one by one.
+///
+/// Returns `None` if no module containing `addr` is found, or if it has no
+/// symbol named `name`. Like `module_symbols`, this is only implemented for
+/// platforms using the `gimli` crate for symbolication.
+///
+/// # Required features
+///
+/// This function requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default.
+#[cfg(feature = "std")]
+pub fn find_module_symbol<'a>(addr: *mut c_void, name: &'a [u8]) -> Option<ModuleSymbol<'a>> {
+    let _guard = crate::lock::lock();
+    unsafe { imp::find_module_symbol(addr as usize, name) }
+}
+
+/// Returns the number of entries of the dynamic symbol table of the ELF module
+/// containing `addr`.
+///
+/// This includes the undefined symbols a module imports from others, which
+/// `module_symbols` doesn't yield, and can be used to size lookup structures
+/// up front. For modules which are only available from memory, like those
+/// whose file was deleted, the dynamic symbol table has no recorded size, and
+/// this is the count implied by its hash tables instead.
+///
+/// Returns `None` if no module containing `addr` is found, if it isn't an ELF
+/// module, or if it has no dynamic symbol table.
+///
+/// # Required features
+///
+/// This function requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default.
+#[cfg(feature = "std")]
+pub fn dynamic_symbol_count(addr: *mut c_void) -> Option<usize> {
+    let _guard = crate::lock::lock();
+    unsafe { imp::dynamic_symbol_count(addr as usize) }
+}
+
 /// A trait representing the resolution of a symbol in a file.
 ///
 /// This trait is yielded as a trait object to the closure given to the
@@ -187,6 +655,11 @@ where
 /// A symbol can give contextual information about a function, for example the
 /// name, filename, line number, precise address, etc. Not all information is
 /// always available in a symbol, however, so all methods return an `Option`.
+///
+/// The name and filename of a symbol may be made up while resolving it and
+/// stored in temporary memory, which is only valid until the closure the
+/// symbol is passed to returns. Use `BacktraceSymbol::from` to keep an owned
+/// copy of a symbol past that.
 pub struct Symbol {
     // TODO: this lifetime bound needs to be persisted eventually to `Symbol`,
     // but that's currently a breaking change. For now this is safe since
@@ -213,8 +686,66 @@ impl Symbol {
         self.inner.addr()
     }
 
+    /// Returns how far past the start of this function the resolved address
+    /// is, in bytes.
+    ///
+    /// Note that return addresses are resolved as the address one byte before
+    /// them, which this is relative to. This is currently only available for
+    /// symbols found with `dladdr`.
+    pub fn offset(&self) -> Option<usize> {
+        self.inner.offset()
+    }
+
+    /// Returns the address the module containing this function was loaded
+    /// at.
+    ///
+    /// This is currently only available for symbols found with `dladdr` or
+    /// `dbghelp.dll`.
+    pub fn module_base(&self) -> Option<*mut c_void> {
+        self.inner.module_base()
+    }
+
+    /// Returns the path of the module containing this function, as the
+    /// dynamic linker reports it.
+    ///
+    /// This is currently only available for symbols found with `dladdr`.
+    pub fn module_path(&self) -> Option<BytesOrWideString<'_>> {
+        self.inner.module_path()
+    }
+
+    /// Returns how this symbol is bound, i.e. whether it's local to its
+    /// module, global or weak.
+    ///
+    /// This is currently only available for symbols found in ELF symbol
+    /// tables.
+    pub fn binding(&self) -> Option<SymbolBinding> {
+        self.inner.binding()
+    }
+
+    /// Returns the visibility of this symbol to other modules.
+    ///
+    /// This is currently only available for symbols found in ELF symbol
+    /// tables.
+    pub fn visibility(&self) -> Option<SymbolVisibility> {
+        self.inner.visibility()
+    }
+
+    /// Returns the version of this symbol, like `GLIBC_2.17` for
+    /// `memcpy@GLIBC_2.17`.
+    ///
+    /// This is currently only available for symbols found in the dynamic
+    /// symbol table of ELF modules with symbol versioning.
+    pub fn version(&self) -> Option<SymbolVersion<'_>> {
+        self.inner.version()
+    }
+
     /// Returns the raw filename as a slice. This is mainly useful for `no_std`
     /// environments.
+    ///
+    /// Filenames from PDB files are `Wide`. Filenames from DWARF debug
+    /// information are always `Bytes` of UTF-8, even on Windows, and are made
+    /// valid UTF-8 by replacing invalid sequences while the debug information
+    /// is read, so the original bytes of non-UTF-8 paths aren't available.
     pub fn filename_raw(&self) -> Option<BytesOrWideString<'_>> {
         self.inner.filename_raw()
     }
@@ -277,6 +808,116 @@ impl fmt::Debug for Symbol {
     }
 }
 
+/// A symbol in the symbol table of a module, as yielded by `module_symbols` and
+/// returned by `find_module_symbol`.
+pub struct ModuleSymbol<'a> {
+    name: &'a [u8],
+    addr: *mut c_void,
+    size: Option<usize>,
+}
+
+impl<'a> ModuleSymbol<'a> {
+    /// Returns the name of this symbol.
+    pub fn name(&self) -> SymbolName<'a> {
+        SymbolName::new(self.name)
+    }
+
+    /// Returns the address this symbol is loaded at in the current process.
+    pub fn addr(&self) -> *mut c_void {
+        self.addr
+    }
+
+    /// Returns the size of this symbol in bytes.
+    ///
+    /// Only some object file formats, like ELF, record the size of symbols, so
+    /// this returns `None` elsewhere.
+    pub fn size(&self) -> Option<usize> {
+        self.size
+    }
+}
+
+impl fmt::Debug for ModuleSymbol<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut d = f.debug_struct("ModuleSymbol");
+        d.field("name", &self.name());
+        d.field("addr", &self.addr);
+        if let Some(size) = self.size {
+            d.field("size", &size);
+        }
+        d.finish()
+    }
+}
+
+/// How a symbol is bound, as returned by `Symbol::binding`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum SymbolBinding {
+    /// The symbol is only visible within the object file defining it.
+    Local,
+    /// The symbol is visible to all object files being combined.
+    Global,
+    /// Like `Global`, but other definitions take precedence.
+    Weak,
+    /// A GNU extension of `Global`, where the dynamic linker makes sure only
+    /// one definition is used in the whole process.
+    Unique,
+}
+
+/// The visibility of a symbol to other modules, as returned by
+/// `Symbol::visibility`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum SymbolVisibility {
+    /// The visibility is determined by the binding of the symbol.
+    Default,
+    /// Like `Hidden`, with processor-specific meaning.
+    Internal,
+    /// The symbol isn't visible to other modules.
+    Hidden,
+    /// The symbol is visible to other modules, but references from within its
+    /// own module can't be preempted.
+    Protected,
+}
+
+/// The version of a symbol, as returned by `Symbol::version`.
+///
+/// The `Display` implementation prints the version the way it's attached to
+/// symbol names, `@@VERSION` for the default version and `@VERSION` for
+/// others.
+#[derive(Copy, Clone)]
+pub struct SymbolVersion<'a> {
+    name: &'a [u8],
+    hidden: bool,
+}
+
+impl<'a> SymbolVersion<'a> {
+    /// Returns the name of this version, like `GLIBC_2.17`.
+    pub fn name(&self) -> &'a [u8] {
+        self.name
+    }
+
+    /// Returns whether this is the default version of the symbol, which is
+    /// used when linking against it without stating a version.
+    pub fn is_default(&self) -> bool {
+        !self.hidden
+    }
+}
+
+impl fmt::Display for SymbolVersion<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(if self.hidden { "@" } else { "@@" })?;
+        format_symbol_name(fmt::Display::fmt, self.name, f)
+    }
+}
+
+impl fmt::Debug for SymbolVersion<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("SymbolVersion")
+            .field(&format_args!("{}", self))
+            .finish()
+    }
+}
+
 cfg_if::cfg_if! {
     if #[cfg(feature = "cpp_demangle")] {
         // Maybe a parsed C++ symbol, if parsing the mangled symbol as Rust
@@ -302,6 +943,79 @@ pub struct SymbolName<'a> {
     demangled: Option<Demangle<'a>>,
     #[cfg(feature = "cpp_demangle")]
     cpp_demangled: OptionCppSymbol<'a>,
+    options: DemangleOptions,
+}
+
+/// Options controlling how a `SymbolName` is rendered by its `Display`
+/// implementation.
+///
+/// The default options print the full demangled name, which is the same as
+/// what `Display` has always printed. The options here are primarily useful
+/// for normalizing names, for example to group frames of crash reports by
+/// function regardless of the hash or instantiation they came from.
+///
+/// # Example
+///
+/// ```
+/// use backtrace::{DemangleOptions, SymbolName};
+///
+/// let name = SymbolName::new(b"_ZN3foo3bar17h05af221e174051e9E")
+///     .with_options(DemangleOptions::new().strip_hash(true));
+/// assert_eq!(name.to_string(), "foo::bar");
+/// ```
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct DemangleOptions {
+    strip_hash: bool,
+    omit_template_args: bool,
+    omit_params: bool,
+    max_len: Option<usize>,
+}
+
+impl DemangleOptions {
+    /// Creates a new set of options with the default values, printing the
+    /// full demangled name.
+    pub fn new() -> DemangleOptions {
+        DemangleOptions::default()
+    }
+
+    /// Whether the trailing hash of Rust symbols (e.g. `::h05af221e174051e9`)
+    /// is stripped.
+    ///
+    /// Note that the alternate formatting flag (`{:#}`) also strips the hash.
+    pub fn strip_hash(mut self, strip: bool) -> DemangleOptions {
+        self.strip_hash = strip;
+        self
+    }
+
+    /// Whether generic and template arguments (`foo::<T>`, `Vec<u8>`,
+    /// `std::vector<int>`) are omitted from the printed name.
+    pub fn omit_template_args(mut self, omit: bool) -> DemangleOptions {
+        self.omit_template_args = omit;
+        self
+    }
+
+    /// Whether the parameter list of C++ functions is omitted from the
+    /// printed name.
+    ///
+    /// This only has an effect when the `cpp_demangle` feature is enabled, as
+    /// Rust symbol names don't include parameters.
+    pub fn omit_params(mut self, omit: bool) -> DemangleOptions {
+        self.omit_params = omit;
+        self
+    }
+
+    /// The maximum number of characters printed for a name.
+    ///
+    /// Names longer than this are truncated and suffixed with `...`, which is
+    /// included in the limit. `None`, the default, means no limit.
+    pub fn max_len(mut self, max_len: Option<usize>) -> DemangleOptions {
+        self.max_len = max_len;
+        self
+    }
+
+    fn needs_normalization(&self) -> bool {
+        self.omit_template_args || self.max_len.is_some()
+    }
 }
 
 impl<'a> SymbolName<'a> {
@@ -322,9 +1036,22 @@ impl<'a> SymbolName<'a> {
             demangled: demangled,
             #[cfg(feature = "cpp_demangle")]
             cpp_demangled: cpp,
+            options: DemangleOptions::default(),
         }
     }
 
+    /// Configures how this name is printed through its `Display`
+    /// implementation.
+    pub fn with_options(mut self, options: DemangleOptions) -> SymbolName<'a> {
+        self.options = options;
+        self
+    }
+
+    /// Returns the options this name is printed with.
+    pub fn options(&self) -> DemangleOptions {
+        self.options
+    }
+
     /// Returns the raw (mangled) symbol name as a `str` if the symbol is valid utf-8.
     ///
     /// Use the `Display` implementation if you want the demangled version.
@@ -339,6 +1066,90 @@ impl<'a> SymbolName<'a> {
     pub fn as_bytes(&self) -> &'a [u8] {
         self.bytes
     }
+
+    /// Returns the language (or rather, the mangling scheme) this symbol name
+    /// appears to originate from.
+    ///
+    /// This is a heuristic purely based on the raw bytes of the name and
+    /// doesn't depend on which demanglers are compiled in, so it can be used
+    /// to route names to an external demangler.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use backtrace::{SymbolLanguage, SymbolName};
+    ///
+    /// let name = SymbolName::new(b"_RNvCs1234_7mycrate3foo");
+    /// assert_eq!(name.language(), SymbolLanguage::RustV0);
+    /// ```
+    pub fn language(&self) -> SymbolLanguage {
+        SymbolLanguage::detect(self.bytes)
+    }
+}
+
+/// The language a `SymbolName` was mangled by, as returned by
+/// `SymbolName::language`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum SymbolLanguage {
+    /// A Rust symbol using the legacy mangling scheme, which is based on the
+    /// Itanium C++ ABI with a trailing hash (`_ZN...17h<hash>E`).
+    RustLegacy,
+    /// A Rust symbol using the v0 mangling scheme (`_R...`).
+    RustV0,
+    /// A C++ symbol using the Itanium C++ ABI mangling (`_Z...`).
+    Cpp,
+    /// A C++ symbol using the MSVC mangling (`?...`).
+    Msvc,
+    /// A Swift symbol (`$s...`, `_T0...`, ...).
+    Swift,
+    /// The name isn't mangled or uses an unrecognized mangling, e.g. a C
+    /// function.
+    Unknown,
+}
+
+impl SymbolLanguage {
+    fn detect(bytes: &[u8]) -> SymbolLanguage {
+        // Symbols on Apple platforms have an extra leading underscore.
+        let unprefixed = bytes.strip_prefix(b"_").unwrap_or(bytes);
+
+        if bytes.starts_with(b"?") {
+            return SymbolLanguage::Msvc;
+        }
+        for prefix in [&b"$s"[..], b"$S", b"$e", b"_T0"] {
+            if bytes.starts_with(prefix) || unprefixed.starts_with(prefix) {
+                return SymbolLanguage::Swift;
+            }
+        }
+        for name in [bytes, unprefixed] {
+            if let Some(rest) = name.strip_prefix(b"_R") {
+                if rest.first().map_or(false, |b| b.is_ascii_uppercase()) {
+                    return SymbolLanguage::RustV0;
+                }
+            }
+        }
+        for name in [bytes, unprefixed] {
+            if name.starts_with(b"_ZN") && has_legacy_rust_hash(name) {
+                return SymbolLanguage::RustLegacy;
+            }
+        }
+        if bytes.starts_with(b"_Z") || bytes.starts_with(b"__Z") {
+            return SymbolLanguage::Cpp;
+        }
+        SymbolLanguage::Unknown
+    }
+}
+
+// Legacy Rust symbols always end with a path component of the form
+// `17h<16 hex digits>` before the closing `E`, optionally followed by a suffix
+// such as `.llvm.1234` added by LLVM.
+fn has_legacy_rust_hash(name: &[u8]) -> bool {
+    name.windows(20).enumerate().any(|(i, window)| {
+        window.starts_with(b"17h")
+            && window[3..19].iter().all(|b| b.is_ascii_hexdigit())
+            && window[19] == b'E'
+            && name.get(i + 20).map_or(true, |&b| b == b'.')
+    })
 }
 
 fn format_symbol_name(
@@ -365,16 +1176,23 @@ fn format_symbol_name(
     Ok(())
 }
 
-impl<'a> fmt::Display for SymbolName<'a> {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+impl<'a> SymbolName<'a> {
+    fn fmt_demangled(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         if let Some(ref s) = self.demangled {
-            return s.fmt(f);
+            if self.options.strip_hash && !f.alternate() {
+                return write!(f, "{s:#}");
+            }
+            return fmt::Display::fmt(s, f);
         }
 
         #[cfg(feature = "cpp_demangle")]
         {
             if let Some(ref cpp) = self.cpp_demangled.0 {
-                return cpp.fmt(f);
+                if self.options.omit_params {
+                    let options = ::cpp_demangle::DemangleOptions::new().no_params();
+                    return cpp.structured_demangle(f, &options);
+                }
+                return fmt::Display::fmt(cpp, f);
             }
         }
 
@@ -382,6 +1200,135 @@ impl<'a> fmt::Display for SymbolName<'a> {
     }
 }
 
+// Displays a `SymbolName` without the normalizations of `NormalizingWriter`,
+// so the demanglers can be pointed at one.
+struct Demangled<'s, 'a>(&'s SymbolName<'a>);
+
+impl fmt::Display for Demangled<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt_demangled(f)
+    }
+}
+
+/// A `fmt::Write` adapter applying the normalizations of `DemangleOptions`
+/// which can't be expressed through the demanglers themselves.
+struct NormalizingWriter<'a> {
+    out: &'a mut dyn fmt::Write,
+    omit_template_args: bool,
+    // Nesting depth of `<` brackets currently being omitted.
+    depth: usize,
+    // The previously emitted character, used to tell a generic argument list
+    // (`Vec<u8>`) apart from a qualified path (`<T as Trait>::f`).
+    prev: char,
+    // Whether the text emitted so far ends in the `operator` keyword.
+    after_operator: bool,
+    // How many more characters can be written straight through before
+    // running into the room reserved for the `...` suffix.
+    remaining: Option<usize>,
+    // Characters which fit into the room reserved for the suffix, written out
+    // only if the name ends before overflowing it.
+    held: [char; 3],
+    held_len: usize,
+    reserved: usize,
+    truncated: bool,
+}
+
+impl NormalizingWriter<'_> {
+    fn emit(&mut self, c: char) -> fmt::Result {
+        if self.truncated {
+            return Ok(());
+        }
+        if let Some(remaining) = &mut self.remaining {
+            if *remaining == 0 {
+                if self.held_len == self.reserved {
+                    self.truncated = true;
+                } else {
+                    self.held[self.held_len] = c;
+                    self.held_len += 1;
+                    self.prev = c;
+                }
+                return Ok(());
+            }
+            *remaining -= 1;
+        }
+        self.prev = c;
+        self.out.write_char(c)
+    }
+
+    fn finish(self) -> fmt::Result {
+        if self.truncated {
+            // If the limit is smaller than the suffix itself the suffix is
+            // truncated too.
+            return self.out.write_str(&"..."[..self.reserved]);
+        }
+        for &c in &self.held[..self.held_len] {
+            self.out.write_char(c)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Write for NormalizingWriter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            if self.omit_template_args {
+                match c {
+                    '<' if self.depth > 0 => {
+                        self.depth += 1;
+                        continue;
+                    }
+                    '<' if (self.prev.is_alphanumeric() || self.prev == '_')
+                        && !self.after_operator =>
+                    {
+                        self.depth = 1;
+                        continue;
+                    }
+                    '>' if self.depth > 0 => {
+                        self.depth -= 1;
+                        continue;
+                    }
+                    _ if self.depth > 0 => continue,
+                    _ => {}
+                }
+            }
+            self.emit(c)?;
+            // `operator<` and friends are not template argument lists.
+            self.after_operator = false;
+        }
+        self.after_operator = s.ends_with("operator");
+        Ok(())
+    }
+}
+
+impl<'a> fmt::Display for SymbolName<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.options.needs_normalization() {
+            return self.fmt_demangled(f);
+        }
+
+        let alternate = f.alternate();
+        let reserved = self.options.max_len.map_or(0, |max_len| max_len.min(3));
+        let mut writer = NormalizingWriter {
+            out: f,
+            omit_template_args: self.options.omit_template_args,
+            depth: 0,
+            prev: ' ',
+            after_operator: false,
+            remaining: self.options.max_len.map(|max_len| max_len - reserved),
+            held: [' '; 3],
+            held_len: 0,
+            reserved,
+            truncated: false,
+        };
+        if alternate {
+            write!(writer, "{:#}", Demangled(self))?;
+        } else {
+            write!(writer, "{}", Demangled(self))?;
+        }
+        writer.finish()
+    }
+}
+
 impl<'a> fmt::Debug for SymbolName<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         if let Some(ref s) = self.demangled {
@@ -428,6 +1375,335 @@ pub fn clear_symbol_cache() {
     }
 }
 
+#[cfg(all(feature = "std", unix))]
+static CLEAR_AFTER_FORK: core::sync::atomic::AtomicBool =
+    core::sync::atomic::AtomicBool::new(false);
+
+#[cfg(all(feature = "std", unix))]
+static WAIT_BEFORE_FORK: core::sync::atomic::AtomicBool =
+    core::sync::atomic::AtomicBool::new(false);
+
+/// Sets whether the symbol cache is cleared in child processes created with
+/// `fork`, which is disabled by default.
+///
+/// Child processes share the cached debug information with their parent
+/// until either of them changes it, so keeping it makes resolving symbols in
+/// the child as fast as in the parent. Clearing it instead drops the child's
+/// references to the mapped files, which is useful if the child goes on to
+/// run for a long time without resolving any symbols, or if the files might
+/// be replaced before it does.
+///
+/// Regardless of this setting, the cache is cleared if another thread was
+/// tracing or resolving symbols when the process forked, unless `fork` waits
+/// for it to finish, see `set_wait_before_fork`. The cache is cleared the
+/// next time the child uses this crate rather than right after `fork`, so
+/// that nothing is freed while only async-signal-safe functions may be
+/// called.
+///
+/// This only has an effect on Unix platforms.
+///
+/// # Required features
+///
+/// This function requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default.
+#[cfg(feature = "std")]
+pub fn set_clear_symbol_cache_after_fork(enabled: bool) {
+    #[cfg(unix)]
+    CLEAR_AFTER_FORK.store(enabled, core::sync::atomic::Ordering::Relaxed);
+    #[cfg(not(unix))]
+    let _ = enabled;
+}
+
+/// Sets whether `fork` waits for other threads which are tracing or
+/// resolving symbols to finish, which is disabled by default.
+///
+/// Those threads don't exist in the child to finish what they were doing. By
+/// default the child notices that and starts over with a new symbol cache,
+/// which is then created again from scratch the next time it resolves
+/// symbols. Waiting instead lets the child keep the cache of its parent, but
+/// `fork` then blocks until no other thread uses this crate, so callbacks
+/// passed to functions like `resolve` mustn't wait for threads which might
+/// fork.
+///
+/// This only has an effect on Unix platforms.
+///
+/// # Required features
+///
+/// This function requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default.
+#[cfg(feature = "std")]
+pub fn set_wait_before_fork(enabled: bool) {
+    #[cfg(unix)]
+    WAIT_BEFORE_FORK.store(enabled, core::sync::atomic::Ordering::Relaxed);
+    #[cfg(not(unix))]
+    let _ = enabled;
+}
+
+/// Returns whether `fork` waits for the global lock, see
+/// `set_wait_before_fork`.
+#[cfg(all(feature = "std", unix))]
+pub(crate) fn wait_before_fork() -> bool {
+    WAIT_BEFORE_FORK.load(core::sync::atomic::Ordering::Relaxed)
+}
+
+/// Clears the symbol cache in a child process created with `fork` if it's
+/// `stale`, because another thread held the global lock when the process
+/// forked, or if that's enabled with `set_clear_symbol_cache_after_fork`.
+///
+/// This is called with the global lock held the first time it's taken after
+/// `fork`.
+#[cfg(all(feature = "std", unix))]
+pub(crate) unsafe fn after_fork(stale: bool) {
+    if stale || CLEAR_AFTER_FORK.load(core::sync::atomic::Ordering::Relaxed) {
+        imp::after_fork(stale);
+    }
+}
+
+/// Updates the list of modules symbols are resolved in, after modules were
+/// loaded into or unloaded from the process.
+///
+/// The modules of the process are listed the first time symbols are
+/// resolved, and addresses in modules loaded later on, like plugins loaded
+/// with `dlopen` or `LoadLibrary`, aren't resolved until this is called.
+/// Conversely, modules which were unloaded in the meantime may have other
+/// modules loaded in their place. Applications which load and unload modules
+/// at runtime should call this after each `dlopen` and `dlclose`, or after a
+/// batch of them.
+///
+/// The cached debug information of modules which are still loaded at the
+/// same address is kept. Unloaded modules are remembered along with their
+/// path, build ID and where they were loaded, so that addresses captured
+/// while they were loaded, like those of an unresolved `Backtrace`, still
+/// resolve from their files, unless those changed in the meantime. Addresses
+/// within a module which is currently loaded are always attributed to it,
+/// though, even if they were captured while another module was loaded there.
+/// After `preopen_symbols`, symbols of newly loaded modules still aren't
+/// resolved until `clear_symbol_cache` is called.
+///
+/// This only has an effect on platforms where symbols are resolved with the
+/// `gimli` crate, not on Windows with MSVC.
+///
+/// # Required features
+///
+/// This function requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default.
+#[cfg(feature = "std")]
+pub fn notify_modules_changed() {
+    let _guard = crate::lock::lock();
+    unsafe {
+        imp::refresh_libraries();
+    }
+}
+
+/// Opens and loads the debug information of all modules currently loaded into
+/// the process, so that resolving symbols afterwards doesn't access the
+/// filesystem.
+///
+/// This is meant for processes which enter a sandbox (like with seccomp,
+/// `pledge` or Landlock) after startup, in which files can't be opened
+/// anymore, but which still want to print a symbolicated backtrace when
+/// panicking later. Calling this before entering the sandbox loads everything
+/// needed to resolve symbols of the current process up front.
+///
+/// Until `clear_symbol_cache` is called, no files are opened anymore to
+/// resolve symbols, except by `Symbolizer`s, which have caches of their own.
+/// Symbols of modules which were loaded after this call
+/// aren't resolved, and neither is debug information which would have been
+/// loaded lazily from other files, like split DWARF or, on macOS, object
+/// files. All debug information stays in memory in the meantime, which may
+/// take up a significant amount of memory in processes with many modules.
+///
+/// On Windows with MSVC this only loads dbghelp.dll, which loads the symbols
+/// of each module on its own.
+///
+/// # Required features
+///
+/// This function requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default.
+#[cfg(feature = "std")]
+pub fn preopen_symbols() {
+    let _guard = crate::lock::lock();
+    unsafe {
+        imp::preopen();
+    }
+}
+
+/// Returns the paths of the files which are opened to resolve symbols of the
+/// modules currently loaded into the process.
+///
+/// This includes the modules themselves along with any separate debug
+/// information found for them, like files located through a build ID or a
+/// `.gnu_debuglink` section, DWARF packages and dSYM bundles on macOS. It's
+/// meant for generating allowlists for sandboxes or the set of volumes to
+/// mount into a container, so that resolving symbols keeps working there.
+///
+/// The paths are found by loading the debug information of every module once
+/// without caching it, which may take a while. Files which are only loaded
+/// lazily while resolving symbols, like split DWARF or object files on macOS,
+/// aren't included. After `preopen_symbols` no files are opened anymore, so
+/// this returns an empty list until `clear_symbol_cache` is called.
+///
+/// When symbols are resolved with dbghelp on Windows with MSVC, which opens
+/// files on its own, this always returns an empty list.
+///
+/// # Required features
+///
+/// This function requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default.
+#[cfg(feature = "std")]
+pub fn symbol_files() -> Vec<PathBuf> {
+    let _guard = crate::lock::lock();
+    unsafe { imp::files() }
+}
+
+/// Writes the symbol tables of all modules currently loaded into the process
+/// to a self-contained symbol map at `path`, to be loaded with
+/// `load_symbol_map` later on.
+///
+/// Containers and other ephemeral environments often delete or replace
+/// binaries after the process started, or have them on volumes which are
+/// unmounted, so symbols can't be resolved from them anymore when a
+/// backtrace is printed later. Calling this at startup saves, for each
+/// module, its build ID and the addresses, sizes and names of its functions.
+/// Symbol tables are written as they are without looking at any debug
+/// information, so this is quick, but symbols resolved from a symbol map
+/// have no file names and line numbers.
+///
+/// This returns an error of the kind `Unsupported` on platforms where symbols
+/// aren't resolved with the `gimli` crate, like Windows with MSVC.
+///
+/// # Required features
+///
+/// This function requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default.
+#[cfg(feature = "std")]
+pub fn dump_symbol_map(path: &Path) -> io::Result<()> {
+    let data = {
+        let _guard = crate::lock::lock();
+        unsafe { imp::dump_symbol_map() }
+    };
+    match data {
+        Some(data) => std::fs::write(path, data),
+        None => Err(io::ErrorKind::Unsupported.into()),
+    }
+}
+
+/// Loads a symbol map written by `dump_symbol_map`, to resolve symbols of
+/// modules whose files are gone or of another build.
+///
+/// Modules are only resolved from the symbol map if their file can't be
+/// opened or is of a different build, and if the symbol map has a module
+/// with the same build ID, or with the same path for modules without a build
+/// ID. The symbol map may have been written by another process, like a
+/// previous run of the same binary, as long as the modules match. Symbol
+/// maps stay loaded for the rest of the process, and several of them may be
+/// loaded, with the last one taking precedence for modules in more than one.
+///
+/// This returns an error of the kind `InvalidData` if the file isn't a symbol
+/// map written by this version of the crate, and of the kind `Unsupported`
+/// on platforms where symbols aren't resolved with the `gimli` crate.
+///
+/// # Required features
+///
+/// This function requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default.
+#[cfg(feature = "std")]
+pub fn load_symbol_map(path: &Path) -> io::Result<()> {
+    let data = std::fs::read(path)?;
+    match imp::load_symbol_map(&data) {
+        Some(true) => Ok(()),
+        Some(false) => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not a symbol map of this version",
+        )),
+        None => Err(io::ErrorKind::Unsupported.into()),
+    }
+}
+
+/// Returns statistics about symbolication in the current process.
+///
+/// See `SymbolizerStats` for the available statistics.
+///
+/// # Required features
+///
+/// This function requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default.
+#[cfg(feature = "std")]
+pub fn symbolizer_stats() -> SymbolizerStats {
+    let mut stats = SymbolizerStats::default();
+    stats::add_timing(&mut stats);
+    let _guard = crate::lock::lock();
+    unsafe {
+        imp::stats(&mut stats);
+    }
+    stats
+}
+
+mod diagnostics;
+pub use self::diagnostics::set_diagnostics_hook;
+
+mod sources;
+pub use self::sources::{
+    set_custom_symbol_resolver, set_resolve_data_addresses, set_resolve_mode, set_symbol_sources,
+    ResolveMode, SymbolSource, SymbolSourceMode,
+};
+
+mod symbol_server;
+pub use self::symbol_server::{set_symbol_search_options, SymbolSearchOptions};
+
+mod file_access;
+#[cfg(feature = "std")]
+pub use self::file_access::set_file_access_with;
+pub use self::file_access::{set_file_access, FileAccess};
+
+mod parse_limits;
+pub use self::parse_limits::{set_parse_limits, ParseLimits};
+
+#[cfg(feature = "std")]
+mod symbol_cache;
+#[cfg(feature = "std")]
+pub use self::symbol_cache::{set_compact_symbols, set_symbol_cache_dir};
+
+#[cfg(feature = "std")]
+mod debug_dirs;
+#[cfg(feature = "std")]
+pub use self::debug_dirs::{set_debug_search_paths, DebugSearchPaths};
+
+#[cfg(feature = "std")]
+mod module_paths;
+#[cfg(feature = "std")]
+pub use self::module_paths::set_module_path_prefixes;
+
+#[cfg(feature = "std")]
+mod symbolizer;
+#[cfg(feature = "std")]
+pub use self::symbolizer::Symbolizer;
+
+#[cfg(feature = "std")]
+mod object_symbolizer;
+#[cfg(feature = "std")]
+pub use self::object_symbolizer::{resolve_file, symbolize_object, ObjectSymbolizer};
+
+#[cfg(feature = "std")]
+mod module_table;
+#[cfg(feature = "std")]
+pub use self::module_table::{ExternalModule, ModuleTable};
+
+#[cfg(feature = "std")]
+mod module_offset;
+#[cfg(feature = "std")]
+pub use self::module_offset::{resolve_module_offset, ModuleOffset};
+
+#[cfg(feature = "std")]
+mod stats;
+#[cfg(feature = "std")]
+pub use self::stats::SymbolizerStats;
+
+#[cfg(all(feature = "std", any(target_os = "linux", target_os = "android")))]
+mod kallsyms;
+#[cfg(all(feature = "std", any(target_os = "linux", target_os = "android")))]
+pub use self::kallsyms::{KernelSymbol, KernelSymbols};
+
 cfg_if::cfg_if! {
     if #[cfg(miri)] {
         mod miri;
@@ -0,0 +1,133 @@
+use super::{AddressKind, Symbol};
+use core::ffi::c_void;
+use core::fmt;
+use std::prelude::v1::*;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A code location given as an offset into a module identified by its build
+/// ID, independent of where the module was loaded.
+///
+/// Addresses change from one run of a program to the next as modules are
+/// loaded at random addresses, but the build ID of a module and offsets into
+/// it don't, so this can be stored or sent elsewhere and still refer to the
+/// same instruction later. The offset is relative to the lowest address the
+/// module is loaded at, like the offsets printed with
+/// `AddressFmt::ModuleOffset` and the base of an `ExternalModule`.
+///
+/// Build IDs are the GNU build ID note of ELF files and the `LC_UUID` of
+/// Mach-O files. For PE files loaded into the current process, they're the
+/// timestamp of the file header followed by the size of the image, both as
+/// little endian `u32`, which is what symbol servers identify them by.
+///
+/// # Required features
+///
+/// This type requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default.
+///
+/// # Example
+///
+/// ```
+/// extern crate backtrace;
+///
+/// use backtrace::{AddressKind, ModuleOffset};
+///
+/// fn main() {
+///     backtrace::trace(|frame| {
+///         if let Some(offset) = ModuleOffset::from_address(frame.ip()) {
+///             println!("{}", offset);
+///             backtrace::resolve_module_offset(&offset, AddressKind::ReturnAddress, |symbol| {
+///                 println!("    {:?}", symbol.name());
+///             });
+///         }
+///         false
+///     });
+/// }
+/// ```
+#[derive(Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct ModuleOffset {
+    build_id: Vec<u8>,
+    offset: u64,
+}
+
+impl ModuleOffset {
+    /// Refers to `offset` bytes past the start of the module with the build
+    /// ID `build_id`.
+    pub fn new(build_id: &[u8], offset: u64) -> ModuleOffset {
+        ModuleOffset {
+            build_id: build_id.to_vec(),
+            offset,
+        }
+    }
+
+    /// Converts the address `addr` of the current process, like the
+    /// instruction pointer of a frame, with the table of loaded modules.
+    ///
+    /// Returns `None` if `addr` isn't within a loaded module, or if the module
+    /// has no build ID. Modules are only looked up by some backends, so this
+    /// always returns `None` on some platforms.
+    pub fn from_address(addr: *mut c_void) -> Option<ModuleOffset> {
+        super::with_module(addr, |module| {
+            Some(ModuleOffset::new(
+                module.build_id?,
+                (addr as usize).wrapping_sub(module.base) as u64,
+            ))
+        })?
+    }
+
+    /// Returns the build ID of the module.
+    pub fn build_id(&self) -> &[u8] {
+        &self.build_id
+    }
+
+    /// Returns the offset from the start of the module.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Converts back to an address of the current process, if a module with
+    /// the same build ID is loaded into it.
+    pub fn to_address(&self) -> Option<*mut c_void> {
+        let base = {
+            let _guard = crate::lock::lock();
+            unsafe { super::imp::find_module_by_build_id(&self.build_id)? }
+        };
+        Some(base.wrapping_add(self.offset as usize) as *mut c_void)
+    }
+}
+
+/// Prints the build ID in hexadecimal followed by the offset, like
+/// `e0d5edea…+0x1234`.
+impl fmt::Display for ModuleOffset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in &self.build_id {
+            write!(f, "{byte:02x}")?;
+        }
+        write!(f, "+{:#x}", self.offset)
+    }
+}
+
+impl fmt::Debug for ModuleOffset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ModuleOffset({self})")
+    }
+}
+
+/// Resolves a code location given as a `ModuleOffset` to symbols in the
+/// current process, passing each of them to `cb` like `resolve_address`.
+///
+/// The module with the build ID of `offset` has to be loaded into the current
+/// process, nothing is resolved otherwise. Use `ModuleTable` to resolve
+/// offsets into modules of other processes.
+///
+/// # Required features
+///
+/// This function requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default.
+pub fn resolve_module_offset<F: FnMut(&Symbol)>(offset: &ModuleOffset, kind: AddressKind, cb: F) {
+    if let Some(addr) = offset.to_address() {
+        super::resolve_address(addr, kind, cb);
+    }
+}
@@ -0,0 +1,95 @@
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+use std::prelude::v1::*;
+use std::sync::Mutex;
+
+static PREFIXES: Mutex<Vec<(PathBuf, PathBuf)>> = Mutex::new(Vec::new());
+
+/// Sets how the paths of modules are rewritten before their files are opened
+/// from now on, replacing the prefixes set before.
+///
+/// Each pair replaces the prefix in its first path with its second path, so
+/// `("/", "/sysroot/target")` opens a module loaded from `/usr/lib/libfoo.so`
+/// at `/sysroot/target/usr/lib/libfoo.so`. Prefixes are matched by whole
+/// path components, and the longest matching prefix is used. An empty list
+/// opens modules at the paths they were loaded from, which is the default.
+///
+/// This lets host-side tools resolving the addresses of a target system find
+/// the target's files in a copy of its root file system, and processes in a
+/// chroot or container find the files of modules whose paths the system
+/// reports from outside of it. It applies to the modules of the current
+/// process and of a `ModuleTable`, but not to the paths passed to
+/// `ObjectSymbolizer::open` and `resolve_file`, which are opened as they are.
+///
+/// Modules already loaded for the symbol cache aren't opened again, so this
+/// is best called before resolving any symbols, or followed by
+/// `clear_symbol_cache`. This only has an effect on platforms where symbols
+/// are resolved with the `gimli` crate.
+///
+/// # Example
+///
+/// ```
+/// extern crate backtrace;
+///
+/// use std::path::Path;
+///
+/// fn main() {
+///     backtrace::set_module_path_prefixes(&[
+///         (Path::new("/"), Path::new("/sysroot/target")),
+///         (Path::new("/opt/app"), Path::new("/build/app/out")),
+///     ]);
+/// }
+/// ```
+///
+/// # Required features
+///
+/// This function requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default.
+pub fn set_module_path_prefixes(prefixes: &[(&Path, &Path)]) {
+    let mut current = PREFIXES.lock().unwrap_or_else(|e| e.into_inner());
+    *current = prefixes
+        .iter()
+        .map(|(from, to)| (from.to_path_buf(), to.to_path_buf()))
+        .collect();
+}
+
+/// Returns the path to open the module loaded from `path` at, see
+/// `set_module_path_prefixes`.
+pub(crate) fn module_path(path: &Path) -> Cow<'_, Path> {
+    rewrite(&PREFIXES.lock().unwrap_or_else(|e| e.into_inner()), path)
+}
+
+/// Replaces the longest prefix of `path` found in `prefixes`.
+fn rewrite<'a>(prefixes: &[(PathBuf, PathBuf)], path: &'a Path) -> Cow<'a, Path> {
+    prefixes
+        .iter()
+        .filter_map(|(from, to)| Some((from, to, path.strip_prefix(from).ok()?)))
+        .max_by_key(|(from, _, _)| from.components().count())
+        .map_or(Cow::Borrowed(path), |(_, to, rest)| {
+            Cow::Owned(to.join(rest))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rewrites_longest_prefix() {
+        let prefixes = [
+            (PathBuf::from("/"), PathBuf::from("/sysroot")),
+            (PathBuf::from("/opt/app"), PathBuf::from("/build/out")),
+        ];
+        let rewrite = |path: &str| rewrite(&prefixes, Path::new(path)).into_owned();
+        assert_eq!(
+            rewrite("/usr/lib/libc.so.6"),
+            Path::new("/sysroot/usr/lib/libc.so.6")
+        );
+        assert_eq!(rewrite("/opt/app/bin/app"), Path::new("/build/out/bin/app"));
+        assert_eq!(
+            rewrite("/opt/application"),
+            Path::new("/sysroot/opt/application")
+        );
+        assert_eq!(rewrite("relative/lib.so"), Path::new("relative/lib.so"));
+    }
+}
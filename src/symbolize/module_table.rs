@@ -0,0 +1,361 @@
+use super::module_paths::module_path;
+use super::{diagnostics, AddressKind, ModuleOffset, ObjectSymbolizer, Symbol};
+use core::fmt;
+use std::path::{Path, PathBuf};
+use std::prelude::v1::*;
+
+/// A module loaded into some process, as recorded by a profiler or tracing
+/// tool, see `ModuleTable`.
+///
+/// # Required features
+///
+/// This type requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExternalModule {
+    path: PathBuf,
+    base: u64,
+    size: u64,
+    build_id: Option<Vec<u8>>,
+}
+
+impl ExternalModule {
+    /// Describes the module loaded from the object file at `path`, whose image
+    /// occupies `size` bytes of memory starting at `base`.
+    ///
+    /// `base` is where the start of the image was loaded, i.e. the address
+    /// corresponding to the image base of PE files, the first loadable segment
+    /// of ELF files or the `__TEXT` segment of Mach-O files.
+    pub fn new<P: Into<PathBuf>>(path: P, base: u64, size: u64) -> ExternalModule {
+        ExternalModule {
+            path: path.into(),
+            base,
+            size,
+            build_id: None,
+        }
+    }
+
+    /// Sets the build ID recorded for the module, so that symbols aren't
+    /// resolved in an object file of a different build found at its path.
+    ///
+    /// Build IDs are the GNU build ID note of ELF files, the `LC_UUID` of
+    /// Mach-O files, and the GUID of the PDB file referenced by PE files
+    /// followed by its age as a little endian `u32`.
+    pub fn build_id(mut self, build_id: &[u8]) -> ExternalModule {
+        self.build_id = Some(build_id.to_vec());
+        self
+    }
+
+    /// Returns the path of the module's object file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the address the module was loaded at.
+    pub fn base(&self) -> u64 {
+        self.base
+    }
+
+    /// Returns the number of bytes of memory the module occupies.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    fn contains(&self, addr: u64) -> bool {
+        self.base <= addr && addr - self.base < self.size
+    }
+}
+
+/// Resolves addresses of another process, given the table of modules which
+/// were loaded into it.
+///
+/// Profilers and tracing tools, like ETW or `perf`, record the modules loaded
+/// into the processes they observe along with addresses within them. Unlike
+/// `resolve`, which looks at the modules of the current process, a
+/// `ModuleTable` finds the module containing each address in the given table,
+/// and resolves it with an `ObjectSymbolizer` opened from the module's path.
+/// Object files are opened the first time an address in them is resolved, and
+/// kept open until the table is dropped.
+///
+/// Symbols report the address of their function as a stated virtual memory
+/// address of the object file, as `ObjectSymbolizer` does, not as an address
+/// of the process. This is only supported on platforms where
+/// `ObjectSymbolizer` is, which excludes Windows with MSVC.
+///
+/// # Required features
+///
+/// This type requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default.
+///
+/// # Example
+///
+/// ```no_run
+/// extern crate backtrace;
+///
+/// use backtrace::{AddressKind, ExternalModule, ModuleTable};
+///
+/// fn main() {
+///     let mut table = ModuleTable::new(vec![
+///         ExternalModule::new("/usr/bin/app", 0x5555_5555_4000, 0x20000),
+///         ExternalModule::new("/usr/lib/libc.so.6", 0x7fff_f7dc_0000, 0x1c8000),
+///     ]);
+///     let addrs = [0x5555_5555_5123, 0x7fff_f7de_4567];
+///     table.resolve_many(&addrs, AddressKind::ReturnAddress, |i, symbol| {
+///         println!("{:#x}: {:?}", addrs[i], symbol.name());
+///     });
+/// }
+/// ```
+pub struct ModuleTable {
+    // Sorted by base address.
+    modules: Vec<ExternalModule>,
+    opened: Vec<Opened>,
+    pid: Option<u32>,
+}
+
+enum Opened {
+    NotYet,
+    Failed,
+    Open {
+        symbolizer: Box<ObjectSymbolizer>,
+        image_base: u64,
+    },
+}
+
+impl ModuleTable {
+    /// Creates a table of the given modules, without opening any of them yet.
+    ///
+    /// Modules shouldn't overlap, addresses in overlapping modules are only
+    /// resolved in one of them.
+    pub fn new(mut modules: Vec<ExternalModule>) -> ModuleTable {
+        modules.sort_by_key(|module| module.base);
+        let opened = modules.iter().map(|_| Opened::NotYet).collect();
+        ModuleTable {
+            modules,
+            opened,
+            pid: None,
+        }
+    }
+
+    /// Reads modules whose object files can't be opened from the memory of
+    /// the process `pid`, which the table's modules are loaded into.
+    ///
+    /// The files of a process running in a container or chroot usually aren't
+    /// at the paths it sees them at. Its memory still holds the headers and
+    /// the dynamic symbol table of each ELF module, so at least the names of
+    /// exported functions can be found there. They're read with
+    /// `process_vm_readv`, or through `/proc/<pid>/mem` where that's not
+    /// permitted, which both require permission to trace the process.
+    ///
+    /// Only the memory described by each `ExternalModule` is read, which
+    /// should cover all mappings of the module's file.
+    ///
+    /// # Required features
+    ///
+    /// This function is only available on Linux and Android.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn read_process_memory(mut self, pid: u32) -> ModuleTable {
+        self.pid = Some(pid);
+        self
+    }
+
+    /// Returns the modules of this table, sorted by their base address.
+    pub fn modules(&self) -> &[ExternalModule] {
+        &self.modules
+    }
+
+    /// Resolves the address `addr` of the given `kind` to symbols, passing
+    /// each of them to `cb`, innermost inlined function first.
+    ///
+    /// Nothing is resolved if `addr` isn't within any module of the table, or
+    /// if the module's object file can't be opened or is of a different build.
+    pub fn resolve<F: FnMut(&Symbol)>(&mut self, addr: u64, kind: AddressKind, mut cb: F) {
+        self.resolve_many(&[addr], kind, |_, symbol| cb(symbol))
+    }
+
+    /// Resolves all addresses in `addrs` of the given `kind`, passing each
+    /// symbol to `cb` along with the index of the address it was found for.
+    ///
+    /// Addresses are grouped by module and sorted first, so each object file
+    /// is looked at once and its debug information is walked in address
+    /// order, which makes this much faster than resolving the addresses one
+    /// by one for large batches. Symbols are passed to `cb` in that order too.
+    pub fn resolve_many<F: FnMut(usize, &Symbol)>(
+        &mut self,
+        addrs: &[u64],
+        kind: AddressKind,
+        mut cb: F,
+    ) {
+        let mut sorted = addrs
+            .iter()
+            .enumerate()
+            .filter_map(|(i, &addr)| {
+                let addr = match kind {
+                    AddressKind::ReturnAddress => addr.checked_sub(1)?,
+                    AddressKind::Precise => addr,
+                };
+                Some((self.module_index(addr)?, addr, i))
+            })
+            .collect::<Vec<_>>();
+        sorted.sort_unstable();
+
+        for (module, addr, i) in sorted {
+            let offset = addr - self.modules[module].base;
+            self.resolve_in(
+                module,
+                |image_base| offset.wrapping_add(image_base),
+                |symbol| cb(i, symbol),
+            );
+        }
+    }
+
+    /// Resolves the stated virtual memory address `svma` of the given `kind`
+    /// in the object file of the module at index `module` of `modules`,
+    /// passing each symbol to `cb` like `resolve` does.
+    ///
+    /// This is for addresses which are already relative to the object file,
+    /// like those in breakpad symbol files or in logs of tools which
+    /// symbolize offline, rather than addresses in the memory of a process.
+    /// `svma` is used as is, without looking at where the module was loaded.
+    ///
+    /// Nothing is resolved if `module` is out of bounds, or if the module's
+    /// object file can't be opened or is of a different build.
+    pub fn resolve_svma<F: FnMut(&Symbol)>(
+        &mut self,
+        module: usize,
+        svma: u64,
+        kind: AddressKind,
+        cb: F,
+    ) {
+        let svma = match kind {
+            AddressKind::ReturnAddress => match svma.checked_sub(1) {
+                Some(svma) => svma,
+                None => return,
+            },
+            AddressKind::Precise => svma,
+        };
+        if module < self.modules.len() {
+            self.resolve_in(module, |_| svma, cb);
+        }
+    }
+
+    /// Opens the object file of `module` if needed and resolves the SVMA
+    /// `svma` computes from its image base in it.
+    fn resolve_in<F: FnMut(&Symbol)>(
+        &mut self,
+        module: usize,
+        svma: impl FnOnce(u64) -> u64,
+        cb: F,
+    ) {
+        if let Opened::NotYet = self.opened[module] {
+            self.opened[module] = open(&self.modules[module], self.pid);
+        }
+        if let Opened::Open {
+            symbolizer,
+            image_base,
+        } = &mut self.opened[module]
+        {
+            symbolizer.resolve(svma(*image_base), cb);
+        }
+    }
+
+    /// Converts the address `addr` to a `ModuleOffset`, if it's within a
+    /// module of the table whose build ID is known.
+    pub fn module_offset(&self, addr: u64) -> Option<ModuleOffset> {
+        let module = &self.modules[self.module_index(addr)?];
+        Some(ModuleOffset::new(
+            module.build_id.as_deref()?,
+            addr - module.base,
+        ))
+    }
+
+    /// Resolves a code location given as a `ModuleOffset` like `resolve`
+    /// does, in the module of the table with the same build ID.
+    ///
+    /// Nothing is resolved if no module of the table has that build ID.
+    pub fn resolve_module_offset<F: FnMut(&Symbol)>(
+        &mut self,
+        offset: &ModuleOffset,
+        kind: AddressKind,
+        cb: F,
+    ) {
+        let module = self
+            .modules
+            .iter()
+            .find(|module| module.build_id.as_deref() == Some(offset.build_id()));
+        if let Some(module) = module {
+            if offset.offset() < module.size {
+                let addr = module.base + offset.offset();
+                self.resolve(addr, kind, cb);
+            }
+        }
+    }
+
+    fn module_index(&self, addr: u64) -> Option<usize> {
+        let after = self.modules.partition_point(|module| module.base <= addr);
+        let index = after.checked_sub(1)?;
+        if self.modules[index].contains(addr) {
+            Some(index)
+        } else {
+            None
+        }
+    }
+}
+
+fn open(module: &ExternalModule, pid: Option<u32>) -> Opened {
+    match open_file(module) {
+        Opened::Failed => match pid {
+            Some(pid) => open_memory(module, pid),
+            None => Opened::Failed,
+        },
+        opened => opened,
+    }
+}
+
+fn open_file(module: &ExternalModule) -> Opened {
+    let symbolizer = match ObjectSymbolizer::open(&module_path(&module.path)) {
+        Some(symbolizer) => symbolizer,
+        None => return Opened::Failed,
+    };
+    let (image_base, build_id) = match symbolizer.image() {
+        Some(image) => image,
+        None => return Opened::Failed,
+    };
+    if let (Some(expected), Some(found)) = (&module.build_id, &build_id) {
+        if expected != found {
+            diagnostics::report(format_args!(
+                "ignoring `{}` as its build ID doesn't match the one of the module",
+                module.path.display()
+            ));
+            return Opened::Failed;
+        }
+    }
+    Opened::Open {
+        symbolizer: Box::new(symbolizer),
+        image_base,
+    }
+}
+
+fn open_memory(module: &ExternalModule, pid: u32) -> Opened {
+    match ObjectSymbolizer::from_process(pid, module.base, module.size) {
+        Some((symbolizer, image_base)) => Opened::Open {
+            symbolizer: Box::new(symbolizer),
+            image_base,
+        },
+        None => {
+            diagnostics::report(format_args!(
+                "couldn't read `{}` from the memory of process {}",
+                module.path.display(),
+                pid
+            ));
+            Opened::Failed
+        }
+    }
+}
+
+impl fmt::Debug for ModuleTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ModuleTable")
+            .field("modules", &self.modules)
+            .finish()
+    }
+}
@@ -0,0 +1,179 @@
+use super::{imp, Symbol};
+use crate::BacktraceSymbol;
+use core::fmt;
+use std::path::Path;
+use std::prelude::v1::*;
+
+/// Resolves addresses within an object file given as bytes, independent of
+/// the current process.
+///
+/// Symbols are normally resolved for addresses in the modules loaded into the
+/// current process. Tools which already have a copy of a binary and an
+/// address within it, like crash report processors, can use this instead to
+/// look up the symbols at a stated virtual memory address (SVMA) of the
+/// binary, that is an address as it's written in the object file itself,
+/// before it was relocated when loaded into memory.
+///
+/// Nothing about the current process is inspected, and the object file
+/// doesn't need to be in the native format of the current platform: ELF,
+/// Mach-O, PE and XCOFF files can be read everywhere, so that for example a
+/// server on Linux can resolve addresses in binaries built for Windows. Files
+/// in the native format get the most complete support though, like following
+/// the debug map of Mach-O executables to their object files. Neither the
+/// byte order nor the pointer width of the object file has to match the
+/// current platform. This is only supported on platforms where symbols are
+/// resolved with the `gimli` crate, which excludes Windows with MSVC.
+///
+/// # Required features
+///
+/// This type requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default.
+///
+/// # Example
+///
+/// ```no_run
+/// extern crate backtrace;
+///
+/// fn main() {
+///     let data = std::fs::read("path/to/binary").unwrap();
+///     let mut symbolizer = backtrace::ObjectSymbolizer::new(&data).unwrap();
+///     symbolizer.resolve(0x1234, |symbol| {
+///         println!("{:?}", symbol.name());
+///     });
+/// }
+/// ```
+pub struct ObjectSymbolizer {
+    inner: imp::ObjectSymbolizer,
+}
+
+impl ObjectSymbolizer {
+    /// Parses the object file `data`, which is copied.
+    ///
+    /// Only `data` itself is used, no separate debug information is looked up.
+    ///
+    /// Returns `None` if `data` isn't a supported object file, or if this isn't
+    /// supported on the current platform.
+    pub fn new(data: &[u8]) -> Option<ObjectSymbolizer> {
+        let inner = imp::ObjectSymbolizer::new(data)?;
+        Some(ObjectSymbolizer { inner })
+    }
+
+    /// Opens the object file at `path`, the same way as the files of modules
+    /// loaded into the current process.
+    ///
+    /// This means that for object files in the native format, separate debug
+    /// information is looked up like it is for loaded modules, for example
+    /// through `.gnu_debuglink` sections or in `*.dSYM` bundles next to the
+    /// file. Files are also read as configured with `set_file_access`.
+    ///
+    /// Returns `None` if the file can't be opened or parsed, or if this isn't
+    /// supported on the current platform.
+    pub fn open(path: &Path) -> Option<ObjectSymbolizer> {
+        let inner = imp::ObjectSymbolizer::open(path)?;
+        Some(ObjectSymbolizer { inner })
+    }
+
+    /// Copies the image of the module loaded at `base` into the process `pid`
+    /// out of its memory, returning it along with the address the image
+    /// expects to be loaded at.
+    ///
+    /// Only the dynamic symbol table of ELF modules is available this way,
+    /// and only on Linux and Android.
+    pub(super) fn from_process(pid: u32, base: u64, size: u64) -> Option<(ObjectSymbolizer, u64)> {
+        let (inner, image_base) = imp::ObjectSymbolizer::from_process(pid, base, size)?;
+        Some((ObjectSymbolizer { inner }, image_base))
+    }
+
+    /// Returns the address the object file expects to be loaded at and its
+    /// build ID, if it can be parsed generically.
+    pub(super) fn image(&self) -> Option<(u64, Option<Vec<u8>>)> {
+        self.inner.image()
+    }
+
+    /// Resolves the stated virtual memory address `svma` of the object file to
+    /// symbols, passing each of them to `cb` like `resolve` does, innermost
+    /// inlined function first.
+    ///
+    /// Symbols found through debug information report `svma` as their `addr`,
+    /// which is truncated on 32-bit platforms if it doesn't fit.
+    pub fn resolve<F: FnMut(&Symbol)>(&mut self, svma: u64, mut cb: F) {
+        self.inner.resolve(svma, &mut cb)
+    }
+}
+
+impl fmt::Debug for ObjectSymbolizer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ObjectSymbolizer").finish()
+    }
+}
+
+/// Resolves the stated virtual memory address `svma` of the object file
+/// `data` to symbols, innermost inlined function first.
+///
+/// This is a shorthand for resolving a single address with an
+/// `ObjectSymbolizer`, see there for details. Use one directly to resolve
+/// several addresses without parsing the object file again each time.
+///
+/// Nothing is returned if `data` can't be parsed or if this isn't supported
+/// on the current platform.
+///
+/// # Required features
+///
+/// This function requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default.
+pub fn symbolize_object(data: &[u8], svma: u64) -> Vec<BacktraceSymbol> {
+    let mut symbols = Vec::new();
+    if let Some(mut symbolizer) = ObjectSymbolizer::new(data) {
+        symbolizer.resolve(svma, |symbol| symbols.push(BacktraceSymbol::from(symbol)));
+    }
+    symbols
+}
+
+/// Resolves addresses of the module loaded from the object file at `path`
+/// with the load bias `bias`, passing each symbol to `cb` along with the index
+/// of the address it was found for.
+///
+/// This is meant for addresses recorded somewhere else, like on another
+/// machine, for which the module they belong to and its bias, the difference
+/// between addresses in memory and SVMAs of the object file, are known. Each
+/// address is converted to an SVMA by subtracting `bias`, wrapping around on
+/// overflow so that negative biases can be given as well, and then resolved
+/// with an `ObjectSymbolizer` opened from `path`.
+///
+/// For a "module+offset" pair where the offset is relative to where the
+/// module was loaded, the bias is the base address of the module as given in
+/// its object file negated, which is zero for most shared libraries and
+/// position independent executables.
+///
+/// Nothing is resolved if the file can't be opened or parsed, or if this
+/// isn't supported on the current platform.
+///
+/// # Required features
+///
+/// This function requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default.
+///
+/// # Example
+///
+/// ```no_run
+/// extern crate backtrace;
+///
+/// use std::path::Path;
+///
+/// fn main() {
+///     let path = Path::new("path/to/libfoo.so");
+///     let offsets = [0x1234, 0x5678];
+///     backtrace::resolve_file(path, 0, &offsets, |i, symbol| {
+///         println!("{:#x}: {:?}", offsets[i], symbol.name());
+///     });
+/// }
+/// ```
+pub fn resolve_file<F: FnMut(usize, &Symbol)>(path: &Path, bias: u64, addrs: &[u64], mut cb: F) {
+    let mut symbolizer = match ObjectSymbolizer::open(path) {
+        Some(symbolizer) => symbolizer,
+        None => return,
+    };
+    for (i, addr) in addrs.iter().enumerate() {
+        symbolizer.resolve(addr.wrapping_sub(bias), |symbol| cb(i, symbol));
+    }
+}
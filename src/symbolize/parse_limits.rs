@@ -0,0 +1,118 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+static MAX_SECTION_SIZE: AtomicUsize = AtomicUsize::new(usize::MAX);
+static MAX_DIE_DEPTH: AtomicUsize = AtomicUsize::new(usize::MAX);
+
+/// Limits on the resources spent parsing object files and their debug
+/// information, as configured with `set_parse_limits`.
+///
+/// Object files of the current process are usually trusted, but files
+/// symbolized on behalf of others, like the modules of a minidump or files
+/// uploaded to a symbolication service, can be malformed in ways which make
+/// parsing them allocate huge amounts of memory or recurse until the stack
+/// overflows. Parsing stops gracefully at these limits: sections exceeding
+/// them are treated as missing, and units exceeding them are only partially
+/// looked at, which is reported through the hook of `set_diagnostics_hook`.
+///
+/// DWARF expressions, like the locations of variables, are never evaluated to
+/// resolve symbols, so there's no limit on them.
+///
+/// No limits are applied by default.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ParseLimits {
+    max_section_size: Option<usize>,
+    max_die_depth: Option<usize>,
+}
+
+impl Default for ParseLimits {
+    fn default() -> ParseLimits {
+        ParseLimits::new()
+    }
+}
+
+impl ParseLimits {
+    /// Creates a new set of limits which doesn't limit anything.
+    pub fn new() -> ParseLimits {
+        ParseLimits {
+            max_section_size: None,
+            max_die_depth: None,
+        }
+    }
+
+    /// The maximum size in bytes of a section of an object file, after
+    /// decompressing it if it's compressed.
+    ///
+    /// This is checked before memory for a decompressed section is
+    /// allocated, so a compressed section can't claim to be arbitrarily
+    /// large. It also bounds the work done for the tables parsed from DWARF
+    /// sections, like the abbreviations of `.debug_abbrev` and the line
+    /// programs of `.debug_line`.
+    pub fn max_section_size(mut self, size: Option<usize>) -> ParseLimits {
+        self.max_section_size = size;
+        self
+    }
+
+    /// The maximum depth of nested debug information entries in a
+    /// compilation unit, where its functions are at depth 1.
+    ///
+    /// Looking for the functions inlined at an address recurses for each
+    /// level of entries, both here and in the `addr2line` crate, so the limit
+    /// keeps malformed debug information from overflowing the stack. Units
+    /// nested deeper than this only report the function containing an
+    /// address, along with the location from the line table, and never get
+    /// to `addr2line`. Units of split DWARF are left to the symbol table
+    /// whenever there's a limit, as their entries can't be checked up front.
+    ///
+    /// With a limit, all entries of a unit are gone through once before
+    /// `addr2line` gets to look at it. This has no effect with the
+    /// `minimal-symbolication` feature, which never looks at these entries.
+    pub fn max_die_depth(mut self, depth: Option<usize>) -> ParseLimits {
+        self.max_die_depth = depth;
+        self
+    }
+}
+
+/// Sets the limits on parsing object files and debug information from now
+/// on.
+///
+/// Files already parsed for the symbol cache aren't parsed again, so this is
+/// best called before resolving any symbols, or followed by
+/// `clear_symbol_cache`. This only has an effect on platforms where symbols
+/// are resolved with the `gimli` crate.
+///
+/// # Example
+///
+/// ```
+/// extern crate backtrace;
+///
+/// use backtrace::ParseLimits;
+///
+/// fn main() {
+///     backtrace::set_parse_limits(
+///         ParseLimits::new()
+///             .max_section_size(Some(256 << 20))
+///             .max_die_depth(Some(64)),
+///     );
+/// }
+/// ```
+pub fn set_parse_limits(limits: ParseLimits) {
+    let store = |limit: &AtomicUsize, value: Option<usize>| {
+        limit.store(value.unwrap_or(usize::MAX), Ordering::Relaxed)
+    };
+    store(&MAX_SECTION_SIZE, limits.max_section_size);
+    store(&MAX_DIE_DEPTH, limits.max_die_depth);
+}
+
+/// Returns the maximum size of a section, see
+/// `ParseLimits::max_section_size`.
+#[allow(dead_code)] // unused on platforms where symbols aren't resolved with gimli
+pub(crate) fn max_section_size() -> usize {
+    MAX_SECTION_SIZE.load(Ordering::Relaxed)
+}
+
+/// Returns the maximum depth of nested entries, see
+/// `ParseLimits::max_die_depth`.
+#[allow(dead_code)] // unused on platforms where symbols aren't resolved with gimli
+pub(crate) fn max_die_depth() -> usize {
+    MAX_DIE_DEPTH.load(Ordering::Relaxed)
+}
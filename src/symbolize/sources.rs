@@ -0,0 +1,290 @@
+use core::ffi::c_void;
+use core::mem;
+use core::ptr;
+use core::sync::atomic::{AtomicBool, AtomicPtr, AtomicU32, Ordering};
+
+/// A source of information about symbols, as configured with
+/// `set_symbol_sources`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SymbolSource {
+    /// DWARF debug information, including separate debug info files and the
+    /// object files referenced by the debug maps of Mach-O executables.
+    ///
+    /// This is the only source of file names, line numbers and inlined
+    /// functions, and the most expensive one to load.
+    DebugInfo,
+    /// The symbol table of the object file a module was loaded from.
+    ///
+    /// Functions of ELF binaries stripped of all symbols are named after
+    /// where they start according to the unwinding information in
+    /// `.eh_frame_hdr` instead, as in `fn_0x1234+0x10` for an address 0x10
+    /// bytes into the function at the stated virtual memory address 0x1234.
+    SymbolTable,
+    /// The `dladdr` function of the dynamic linker, which only knows about
+    /// exported symbols but doesn't need to read any files.
+    ///
+    /// This only finds symbols in the current process, and only on platforms
+    /// which have `dladdr`, like Linux and macOS. It's never used with the
+    /// `pure-rust` feature of this crate.
+    Dladdr,
+    /// The function set with `set_custom_symbol_resolver`.
+    ///
+    /// This only finds symbols in the current process.
+    Custom,
+}
+
+/// How the results of several sources of symbols are combined, as configured
+/// with `set_symbol_sources`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SymbolSourceMode {
+    /// Stop at the first source which finds any symbols for an address.
+    ///
+    /// This is the default.
+    First,
+    /// Report the symbols found by all sources, in the order of the sources.
+    ///
+    /// This may report the same function several times.
+    Merge,
+}
+
+/// How much of the debug information is evaluated to resolve symbols, as
+/// configured with `set_resolve_mode`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ResolveMode {
+    /// Find the file names and line numbers of symbols along with their
+    /// names.
+    ///
+    /// Names and locations are found together by evaluating the line tables
+    /// of debug information. This is the default.
+    Full,
+    /// Only find the names of functions, from the `DW_AT_name` attributes of
+    /// debug information or from the symbol table, without ever evaluating
+    /// line tables.
+    ///
+    /// Symbols then have no file names, line numbers or columns. Functions
+    /// of split DWARF units are left to the sources after debug information.
+    NamesOnly,
+}
+
+const ALL: [SymbolSource; 4] = [
+    SymbolSource::DebugInfo,
+    SymbolSource::SymbolTable,
+    SymbolSource::Dladdr,
+    SymbolSource::Custom,
+];
+
+/// The configured sources, each as one more than its index into `ALL` in 4
+/// bits, starting at the least significant ones and ending at the first zero.
+static SOURCES: AtomicU32 = AtomicU32::new(DEFAULT_SOURCES);
+const DEFAULT_SOURCES: u32 = 1 | 2 << 4;
+
+static MERGE: AtomicBool = AtomicBool::new(false);
+
+static CUSTOM: AtomicPtr<()> = AtomicPtr::new(ptr::null_mut());
+
+static DATA: AtomicBool = AtomicBool::new(false);
+
+static NAMES_ONLY: AtomicBool = AtomicBool::new(false);
+
+type CustomResolver = fn(*mut c_void, &mut dyn FnMut(&[u8]));
+
+/// Sets which sources of information are used to resolve symbols from now on,
+/// in which order, and how their results are combined.
+///
+/// By default symbols are first looked up in debug information, and only then
+/// in the symbol table, stopping at the first source which finds anything.
+/// Leaving out debug information, for example, trades file names and line
+/// numbers for not having to load and parse it, while putting `dladdr` first
+/// avoids reading files at all for exported functions. Sources are tried in
+/// the order given, and sources given more than once are only tried the first
+/// time. An empty list of sources restores the default.
+///
+/// Mappings of modules which were already cached aren't affected, so this is
+/// best called before resolving any symbols, or followed by
+/// `clear_symbol_cache`.
+///
+/// This applies to `resolve` and related functions, to `Backtrace`, and to an
+/// `ObjectSymbolizer`, which only uses the sources which don't look at the
+/// current process. It only has an effect on platforms where symbols are
+/// resolved with the `gimli` crate.
+///
+/// # Example
+///
+/// ```
+/// extern crate backtrace;
+///
+/// use backtrace::{SymbolSource, SymbolSourceMode};
+///
+/// fn main() {
+///     // Only names are needed, so don't bother with debug information.
+///     backtrace::set_symbol_sources(
+///         &[SymbolSource::SymbolTable, SymbolSource::Dladdr],
+///         SymbolSourceMode::First,
+///     );
+/// }
+/// ```
+pub fn set_symbol_sources(sources: &[SymbolSource], mode: SymbolSourceMode) {
+    let mut bits = 0;
+    let mut len = 0;
+    for source in sources {
+        let n = ALL.iter().position(|s| s == source).unwrap() as u32 + 1;
+        if (0..len).any(|i| bits >> (i * 4) & 0xf == n) {
+            continue;
+        }
+        bits |= n << (len * 4);
+        len += 1;
+    }
+    if bits == 0 {
+        bits = DEFAULT_SOURCES;
+    }
+    SOURCES.store(bits, Ordering::Relaxed);
+    MERGE.store(mode == SymbolSourceMode::Merge, Ordering::Relaxed);
+}
+
+/// Sets a function to resolve symbols for the `Custom` source of
+/// `set_symbol_sources`, or removes it with `None`.
+///
+/// The function is called with an address in the current process, and passes
+/// the names of any symbols it finds there to its callback. This allows
+/// resolving symbols of code the platform doesn't know about, like code
+/// generated at runtime by a JIT compiler. Symbols found this way only have a
+/// name. As for the other sources, the address may be one less than the one
+/// being resolved, so that return addresses point into the call instruction.
+///
+/// The function may be called from any thread resolving symbols while internal
+/// locks are held, so it must not capture backtraces or resolve symbols
+/// itself.
+///
+/// # Example
+///
+/// ```
+/// extern crate backtrace;
+///
+/// use backtrace::{SymbolSource, SymbolSourceMode};
+///
+/// fn main() {
+///     backtrace::set_custom_symbol_resolver(Some(|addr, cb| {
+///         // Look up `addr` in the code generated by the application here.
+///         if addr.is_null() {
+///             cb(b"null");
+///         }
+///     }));
+///     backtrace::set_symbol_sources(
+///         &[SymbolSource::DebugInfo, SymbolSource::SymbolTable, SymbolSource::Custom],
+///         SymbolSourceMode::First,
+///     );
+/// }
+/// ```
+pub fn set_custom_symbol_resolver(resolver: Option<CustomResolver>) {
+    let resolver = match resolver {
+        Some(resolver) => resolver as *mut (),
+        None => ptr::null_mut(),
+    };
+    CUSTOM.store(resolver, Ordering::Release);
+}
+
+/// Sets whether addresses in the data segments of modules are resolved.
+///
+/// By default only addresses in segments of a module which may contain code
+/// are attributed to the module, so that pointers to its data, which show up
+/// when scanning stacks for return addresses for example, aren't mistaken for
+/// code. Enabling this also looks up addresses in other segments, which finds
+/// the symbols of static variables.
+///
+/// This only has an effect on platforms where symbols are resolved with the
+/// `gimli` crate, and which know the permissions of the segments of modules,
+/// like Linux and macOS.
+pub fn set_resolve_data_addresses(enabled: bool) {
+    DATA.store(enabled, Ordering::Relaxed);
+}
+
+/// Sets how much of the debug information is evaluated to resolve symbols
+/// from now on.
+///
+/// Looking up just names is much cheaper for callers which don't print file
+/// names and line numbers, like profilers, as the line tables of debug
+/// information are by far the largest part of it. Unlike leaving out the
+/// `DebugInfo` source with `set_symbol_sources`, this still finds the names
+/// of inlined functions.
+///
+/// This applies to the same functions as `set_symbol_sources`, and only has
+/// an effect on platforms where symbols are resolved with the `gimli` crate.
+///
+/// # Example
+///
+/// ```
+/// extern crate backtrace;
+///
+/// use backtrace::ResolveMode;
+///
+/// fn main() {
+///     // Only names are printed, so don't bother with line tables.
+///     backtrace::set_resolve_mode(ResolveMode::NamesOnly);
+///     #[cfg(feature = "std")]
+///     backtrace::trace(|frame| {
+///         backtrace::resolve_frame(frame, |symbol| println!("{:?}", symbol.name()));
+///         true
+///     });
+/// }
+/// ```
+pub fn set_resolve_mode(mode: ResolveMode) {
+    NAMES_ONLY.store(mode == ResolveMode::NamesOnly, Ordering::Relaxed);
+}
+
+/// Returns the mode last configured with `set_resolve_mode`.
+#[allow(dead_code)] // unused on platforms where symbols aren't resolved with gimli
+pub(crate) fn resolve_mode() -> ResolveMode {
+    if NAMES_ONLY.load(Ordering::Relaxed) {
+        ResolveMode::NamesOnly
+    } else {
+        ResolveMode::Full
+    }
+}
+
+/// Returns whether addresses in data segments are resolved, as configured with
+/// `set_resolve_data_addresses`.
+#[allow(dead_code)] // unused on platforms where symbols aren't resolved with gimli
+pub(crate) fn resolves_data_addresses() -> bool {
+    DATA.load(Ordering::Relaxed)
+}
+
+/// Returns the sources last configured with `set_symbol_sources` in order,
+/// and whether their results are merged.
+#[allow(dead_code)] // unused on platforms where symbols aren't resolved with gimli
+pub(crate) fn symbol_sources() -> (impl Iterator<Item = SymbolSource>, bool) {
+    let mut bits = SOURCES.load(Ordering::Relaxed);
+    let sources = core::iter::from_fn(move || {
+        let n = bits & 0xf;
+        bits >>= 4;
+        ALL.get((n as usize).checked_sub(1)?).copied()
+    });
+    (sources, MERGE.load(Ordering::Relaxed))
+}
+
+/// Tests whether symbols are looked up in `source` at all.
+#[allow(dead_code)] // unused on platforms where symbols aren't resolved with gimli
+pub(crate) fn uses_source(source: SymbolSource) -> bool {
+    symbol_sources().0.any(|s| s == source)
+}
+
+/// Calls the resolver set with `set_custom_symbol_resolver` for `addr`, if
+/// any, returning whether it found any symbols.
+#[allow(dead_code)] // unused on platforms where symbols aren't resolved with gimli
+pub(crate) fn resolve_custom(addr: *mut c_void, cb: &mut dyn FnMut(&[u8])) -> bool {
+    let resolver = CUSTOM.load(Ordering::Acquire);
+    if resolver.is_null() {
+        return false;
+    }
+    // SAFETY: non-null values of `CUSTOM` are only ever stored by
+    // `set_custom_symbol_resolver`, from a function pointer of this type.
+    let resolver = unsafe { mem::transmute::<*mut (), CustomResolver>(resolver) };
+    let mut found = false;
+    resolver(addr, &mut |name| {
+        found = true;
+        cb(name);
+    });
+    found
+}
@@ -0,0 +1,117 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Statistics about symbolication in the current process, as returned by
+/// `symbolizer_stats`.
+///
+/// These help to understand why symbolication is slow or uses a lot of memory
+/// in long-running processes. The statistics about the cache of debug
+/// information are only collected on platforms where symbols are resolved with
+/// the `gimli` crate, and are zero elsewhere.
+///
+/// # Required features
+///
+/// This type requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default.
+#[derive(Clone, Debug, Default)]
+pub struct SymbolizerStats {
+    pub(super) cache_hits: u64,
+    pub(super) cache_misses: u64,
+    pub(super) cache_evictions: u64,
+    pub(super) cached_bytes: usize,
+    pub(super) modules_with_debuginfo: usize,
+    pub(super) modules_without_debuginfo: usize,
+    resolves: u64,
+    resolve_time: Duration,
+    max_resolve_time: Duration,
+}
+
+impl SymbolizerStats {
+    /// Returns how often the debug information of a module was found in the
+    /// cache.
+    pub fn cache_hits(&self) -> u64 {
+        self.cache_hits
+    }
+
+    /// Returns how often the debug information of a module had to be loaded
+    /// because it wasn't in the cache.
+    pub fn cache_misses(&self) -> u64 {
+        self.cache_misses
+    }
+
+    /// Returns how often the debug information of a module was evicted from
+    /// the cache to make room for another one.
+    pub fn cache_evictions(&self) -> u64 {
+        self.cache_evictions
+    }
+
+    /// Returns the number of bytes of object files and debug information
+    /// currently held by the cache, either mapped into memory or decompressed.
+    pub fn cached_bytes(&self) -> usize {
+        self.cached_bytes
+    }
+
+    /// Returns the number of modules that were loaded and had DWARF debug
+    /// information.
+    pub fn modules_with_debuginfo(&self) -> usize {
+        self.modules_with_debuginfo
+    }
+
+    /// Returns the number of modules that were loaded but had no DWARF debug
+    /// information, or couldn't be loaded at all, so only their symbol table,
+    /// if any, is used.
+    pub fn modules_without_debuginfo(&self) -> usize {
+        self.modules_without_debuginfo
+    }
+
+    /// Returns the number of times symbols were resolved through `resolve`,
+    /// `resolve_frame` or `resolve_many`, including when resolving the frames
+    /// of a `Backtrace`.
+    pub fn resolves(&self) -> u64 {
+        self.resolves
+    }
+
+    /// Returns the total time spent in the calls counted by `resolves`,
+    /// excluding time spent waiting for other threads to finish resolving.
+    pub fn resolve_time(&self) -> Duration {
+        self.resolve_time
+    }
+
+    /// Returns the time spent in the slowest call counted by `resolves`.
+    pub fn max_resolve_time(&self) -> Duration {
+        self.max_resolve_time
+    }
+}
+
+struct Timing {
+    resolves: u64,
+    total: Duration,
+    max: Duration,
+}
+
+static TIMING: Mutex<Timing> = Mutex::new(Timing {
+    resolves: 0,
+    total: Duration::from_secs(0),
+    max: Duration::from_secs(0),
+});
+
+/// Runs `f`, recording the time it takes as a call to resolve symbols.
+pub(super) fn time_resolve<R>(f: impl FnOnce() -> R) -> R {
+    let start = Instant::now();
+    let ret = f();
+    let elapsed = start.elapsed();
+    if let Ok(mut timing) = TIMING.lock() {
+        timing.resolves += 1;
+        timing.total += elapsed;
+        timing.max = timing.max.max(elapsed);
+    }
+    ret
+}
+
+pub(super) fn add_timing(stats: &mut SymbolizerStats) {
+    if let Ok(timing) = TIMING.lock() {
+        stats.resolves = timing.resolves;
+        stats.resolve_time = timing.total;
+        stats.max_resolve_time = timing.max;
+    }
+}
@@ -0,0 +1,92 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+static DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
+static COMPACT: AtomicBool = AtomicBool::new(false);
+
+/// Sets a directory to cache the debug information of modules in, or stops
+/// caching it with `None`, which is the default.
+///
+/// Finding the debug information of a large binary in its DWARF sections is
+/// expensive, and short-lived programs like command line tools pay for it
+/// again on every run which prints a backtrace. With a cache directory set,
+/// the function names, file names and line numbers of each module with a
+/// build ID are digested into a compact lookup table the first time its
+/// debug information is loaded. The table is saved to the directory, named
+/// after the build ID, and later runs map it into memory instead of parsing
+/// DWARF at all.
+///
+/// Cached tables record the build ID they were made for and the version of
+/// their format, and are ignored and replaced if either doesn't match, so
+/// the directory may be shared by different versions of a program or of
+/// this crate. Tables are never removed, though, so the directory grows
+/// with every build until it's cleaned up by other means.
+///
+/// Modules already loaded for the symbol cache aren't affected, so this is
+/// best called before resolving any symbols, or followed by
+/// `clear_symbol_cache`. This only has an effect on platforms where symbols
+/// are resolved with the `gimli` crate, and only while
+/// `SymbolSource::DebugInfo` is used.
+///
+/// # Example
+///
+/// ```no_run
+/// extern crate backtrace;
+///
+/// fn main() {
+///     let dir = std::env::temp_dir().join("my-tool-symbols");
+///     let _ = std::fs::create_dir_all(&dir);
+///     backtrace::set_symbol_cache_dir(Some(&dir));
+/// }
+/// ```
+///
+/// # Required features
+///
+/// This function requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default.
+pub fn set_symbol_cache_dir(dir: Option<&Path>) {
+    let mut current = DIR.lock().unwrap_or_else(|e| e.into_inner());
+    *current = dir.map(Path::to_path_buf);
+}
+
+/// Returns the directory last passed to `set_symbol_cache_dir`.
+#[allow(dead_code)] // unused on platforms where symbols aren't resolved with gimli
+pub(crate) fn symbol_cache_dir() -> Option<PathBuf> {
+    DIR.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Sets whether the debug information of modules is digested into compact
+/// lookup tables, which are kept in memory instead of the modules.
+///
+/// By default, the symbol cache keeps each module's object file and debug
+/// information mapped, along with the state of the `addr2line` crate, which
+/// grows with every address looked up and may take up hundreds of megabytes
+/// for large binaries. With this enabled, the first time a module's debug
+/// information is loaded, the function names, file names and line numbers
+/// of all its addresses are digested into a table a fraction of that size,
+/// and everything else is released right away. This makes the first lookup
+/// in each module slower, as all of its debug information is walked, so it
+/// pays off for long-running programs resolving addresses in many modules,
+/// like profilers.
+///
+/// Digests are the same ones saved to the directory set with
+/// `set_symbol_cache_dir`, and the two settings combine. Modules already
+/// loaded for the symbol cache aren't affected, so this is best called
+/// before resolving any symbols, or followed by `clear_symbol_cache`. This
+/// only has an effect on platforms where symbols are resolved with the
+/// `gimli` crate, and only while `SymbolSource::DebugInfo` is used.
+///
+/// # Required features
+///
+/// This function requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default.
+pub fn set_compact_symbols(enabled: bool) {
+    COMPACT.store(enabled, Ordering::Relaxed);
+}
+
+/// Returns the setting last passed to `set_compact_symbols`.
+#[allow(dead_code)] // unused on platforms where symbols aren't resolved with gimli
+pub(crate) fn compact_symbols() -> bool {
+    COMPACT.load(Ordering::Relaxed)
+}
@@ -0,0 +1,205 @@
+use alloc::string::String;
+
+/// The symbol server Microsoft publishes the symbol files of Windows on.
+const MICROSOFT_SYMBOL_SERVER: &str = "https://msdl.microsoft.com/download/symbols";
+
+/// Options for where symbol files are searched for on Windows, as configured
+/// with `set_symbol_search_options`.
+///
+/// By default `dbghelp.dll` searches the current directory and the paths in
+/// the `_NT_SYMBOL_PATH` and `_NT_ALT_SYMBOL_PATH` environment variables,
+/// followed by the directories of all loaded modules.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SymbolSearchOptions {
+    search_path: Option<String>,
+    cache_dir: Option<String>,
+    microsoft_symbol_server: bool,
+    offline: bool,
+}
+
+impl SymbolSearchOptions {
+    /// Creates a new set of options with the default values, which search
+    /// where `dbghelp.dll` searches by default.
+    pub fn new() -> SymbolSearchOptions {
+        SymbolSearchOptions::default()
+    }
+
+    /// Searches `path` instead of the paths of `_NT_SYMBOL_PATH` and
+    /// `_NT_ALT_SYMBOL_PATH`.
+    ///
+    /// The path is in the same syntax as `_NT_SYMBOL_PATH`: a list of
+    /// directories and symbol stores separated by `;`, where symbol servers
+    /// are given as `srv*C:\local\cache*https://symbols.example.com`. The
+    /// directories of loaded modules are still searched after it.
+    pub fn search_path(mut self, path: &str) -> SymbolSearchOptions {
+        self.search_path = Some(path.into());
+        self
+    }
+
+    /// Stores the files downloaded from symbol servers in the local directory
+    /// `dir`, and looks for them there before downloading them again.
+    pub fn cache_dir(mut self, dir: &str) -> SymbolSearchOptions {
+        self.cache_dir = Some(dir.into());
+        self
+    }
+
+    /// Whether the symbol files of Windows and other Microsoft software are
+    /// downloaded from Microsoft's public symbol server.
+    ///
+    /// Downloading symbols may take a long time on the first backtrace which
+    /// needs them, so this is disabled by default.
+    pub fn microsoft_symbol_server(mut self, enabled: bool) -> SymbolSearchOptions {
+        self.microsoft_symbol_server = enabled;
+        self
+    }
+
+    /// Whether symbol servers are only used for the files they already
+    /// cached locally, without accessing the network.
+    ///
+    /// This also applies to servers listed in `_NT_SYMBOL_PATH`.
+    pub fn offline(mut self, offline: bool) -> SymbolSearchOptions {
+        self.offline = offline;
+        self
+    }
+
+    /// Returns the search path to use instead of `default`, the search path
+    /// `dbghelp.dll` came up with from the environment.
+    #[allow(dead_code)] // unused on platforms where symbols aren't resolved with dbghelp
+    pub(crate) fn apply(&self, default: &str) -> String {
+        let mut path = String::new();
+        let mut add = |entry: &str| {
+            if !entry.is_empty() {
+                if !path.is_empty() {
+                    path.push(';');
+                }
+                path.push_str(entry);
+            }
+        };
+
+        let mut server = String::new();
+        if self.microsoft_symbol_server {
+            server.push_str("srv*");
+            if let Some(dir) = &self.cache_dir {
+                server.push_str(dir);
+                server.push('*');
+            }
+            server.push_str(MICROSOFT_SYMBOL_SERVER);
+        } else if let Some(dir) = &self.cache_dir {
+            // Caches the files of all symbol servers after it in the path.
+            server.push_str("cache*");
+            server.push_str(dir);
+        }
+        let entries = self.search_path.as_deref().unwrap_or(default).split(';');
+        for entry in core::iter::once(&server[..]).chain(entries) {
+            if self.offline && is_symbol_server(entry) {
+                add(&without_remote_stores(entry));
+            } else {
+                add(entry);
+            }
+        }
+        path
+    }
+}
+
+/// Returns whether `entry` of a search path is a chain of symbol stores.
+fn is_symbol_server(entry: &str) -> bool {
+    entry.split('*').next().map_or(false, |kind| {
+        ["srv", "symsrv", "cache"]
+            .iter()
+            .any(|k| kind.eq_ignore_ascii_case(k))
+    })
+}
+
+/// Removes the stores which are accessed over the network from a chain of
+/// symbol stores, leaving nothing if no local store is left.
+fn without_remote_stores(entry: &str) -> String {
+    let mut parts = entry.split('*');
+    let mut chain = String::from(parts.next().unwrap_or(""));
+    if chain.eq_ignore_ascii_case("symsrv") {
+        // `symsrv` chains name the DLL implementing them before the stores.
+        chain.push('*');
+        chain.push_str(parts.next().unwrap_or(""));
+    }
+    let mut local = false;
+    for store in parts {
+        let remote = ["http://", "https://"].iter().any(|scheme| {
+            store
+                .get(..scheme.len())
+                .map_or(false, |s| s.eq_ignore_ascii_case(scheme))
+        });
+        if !remote {
+            chain.push('*');
+            chain.push_str(store);
+            local = true;
+        }
+    }
+    if local {
+        chain
+    } else {
+        String::new()
+    }
+}
+
+/// Sets where symbol files are searched for from now on.
+///
+/// This allows applications to opt into downloading symbols from symbol
+/// servers, or to keep symbol servers configured in the environment from
+/// being accessed, without changing environment variables of the process.
+/// Symbols already loaded for a module aren't loaded again, so this is best
+/// called before capturing the first backtrace.
+///
+/// This only has an effect on Windows with MSVC, where symbols are resolved
+/// with `dbghelp.dll`.
+///
+/// # Example
+///
+/// ```
+/// extern crate backtrace;
+///
+/// use backtrace::SymbolSearchOptions;
+///
+/// fn main() {
+///     backtrace::set_symbol_search_options(
+///         SymbolSearchOptions::new()
+///             .microsoft_symbol_server(true)
+///             .cache_dir(r"C:\symbols"),
+///     );
+/// }
+/// ```
+pub fn set_symbol_search_options(options: SymbolSearchOptions) {
+    #[cfg(all(windows, target_env = "msvc", not(target_vendor = "uwp")))]
+    crate::dbghelp::set_search_options(options);
+    #[cfg(not(all(windows, target_env = "msvc", not(target_vendor = "uwp"))))]
+    drop(options);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn composes_search_paths() {
+        let default = r".;srv*C:\sym*https://example.com/symbols;D:\pdbs";
+        assert_eq!(SymbolSearchOptions::new().apply(default), default);
+
+        let options = SymbolSearchOptions::new()
+            .microsoft_symbol_server(true)
+            .cache_dir(r"C:\cache");
+        assert_eq!(
+            options.clone().apply(default),
+            r"srv*C:\cache*https://msdl.microsoft.com/download/symbols;.;srv*C:\sym*https://example.com/symbols;D:\pdbs",
+        );
+        assert_eq!(
+            options.offline(true).search_path(r"E:\pdbs").apply(default),
+            r"srv*C:\cache;E:\pdbs",
+        );
+
+        let options = SymbolSearchOptions::new().cache_dir(r"C:\cache");
+        assert_eq!(
+            options
+                .offline(true)
+                .apply(r"SRV*HTTPS://example.com;symsrv*symsrv.dll*C:\sym*http://example.com;."),
+            r"cache*C:\cache;symsrv*symsrv.dll*C:\sym;.",
+        );
+    }
+}
@@ -0,0 +1,123 @@
+use super::{file_access, imp, AddressKind, FileAccess, ResolveWhat, Symbol};
+use crate::backtrace::Frame;
+use core::ffi::c_void;
+use core::fmt;
+
+/// A symbolizer with its own state, separate from the global state used by
+/// functions like `resolve` and by `Backtrace`.
+///
+/// Resolving symbols normally goes through a single cache of debug
+/// information shared by the whole process, and global settings like
+/// `set_file_access`. A `Symbolizer` instead owns its own list of modules,
+/// cache of debug information and configuration, so that tests or libraries
+/// embedded into larger applications can resolve symbols without affecting
+/// or being affected by the rest of the process. Everything it cached is
+/// freed when it's dropped.
+///
+/// The list of modules is taken when the symbolizer is created, so modules
+/// loaded later aren't known to it. Symbolizers still take the same lock as
+/// the global functions while resolving symbols, as the platform facilities
+/// they may use aren't necessarily thread-safe.
+///
+/// On platforms where symbols aren't resolved with the `gimli` crate, like
+/// Windows with MSVC, this simply resolves symbols like `resolve`.
+///
+/// # Required features
+///
+/// This type requires the `std` feature of the `backtrace` crate to be
+/// enabled, and the `std` feature is enabled by default.
+///
+/// # Example
+///
+/// ```
+/// extern crate backtrace;
+///
+/// fn main() {
+///     let mut symbolizer = backtrace::Symbolizer::new();
+///     backtrace::trace(|frame| {
+///         symbolizer.resolve_frame(frame, |symbol| {
+///             // ...
+///         });
+///
+///         false // only look at the top frame
+///     });
+/// }
+/// ```
+pub struct Symbolizer {
+    inner: imp::Symbolizer,
+    file_access: Option<FileAccess>,
+}
+
+impl Symbolizer {
+    /// Creates a symbolizer knowing about the modules currently loaded into
+    /// the process, with nothing cached yet.
+    pub fn new() -> Symbolizer {
+        Symbolizer {
+            inner: imp::Symbolizer::new(),
+            file_access: None,
+        }
+    }
+
+    /// Sets the number of modules whose debug information is kept in memory
+    /// at most, which is 4 by default and at least 1.
+    pub fn set_cache_size(&mut self, size: usize) {
+        self.inner.set_cache_size(size);
+    }
+
+    /// Sets how object files and debug information are read by this
+    /// symbolizer, overriding `set_file_access` and `set_file_access_with`.
+    pub fn set_file_access(&mut self, access: FileAccess) {
+        self.file_access = Some(access);
+    }
+
+    /// Frees all debug information cached by this symbolizer.
+    pub fn clear_cache(&mut self) {
+        self.inner.clear_cache();
+    }
+
+    /// Resolves an address to symbols like `resolve`, using the state of this
+    /// symbolizer.
+    pub fn resolve<F: FnMut(&Symbol)>(&mut self, addr: *mut c_void, mut cb: F) {
+        self.resolve_what(
+            ResolveWhat::Address(addr, AddressKind::ReturnAddress),
+            &mut cb,
+        )
+    }
+
+    /// Resolves an address of the given `kind` to symbols like
+    /// `resolve_address`, using the state of this symbolizer.
+    pub fn resolve_address<F: FnMut(&Symbol)>(
+        &mut self,
+        addr: *mut c_void,
+        kind: AddressKind,
+        mut cb: F,
+    ) {
+        self.resolve_what(ResolveWhat::Address(addr, kind), &mut cb)
+    }
+
+    /// Resolves a frame to symbols like `resolve_frame`, using the state of
+    /// this symbolizer.
+    pub fn resolve_frame<F: FnMut(&Symbol)>(&mut self, frame: &Frame, mut cb: F) {
+        self.resolve_what(ResolveWhat::Frame(frame), &mut cb)
+    }
+
+    fn resolve_what(&mut self, what: ResolveWhat<'_>, cb: &mut dyn FnMut(&Symbol)) {
+        let _guard = crate::lock::lock();
+        let inner = &mut self.inner;
+        file_access::with_file_access(self.file_access, || unsafe { inner.resolve(what, cb) })
+    }
+}
+
+impl Default for Symbolizer {
+    fn default() -> Symbolizer {
+        Symbolizer::new()
+    }
+}
+
+impl fmt::Debug for Symbolizer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Symbolizer")
+            .field("file_access", &self.file_access)
+            .finish()
+    }
+}
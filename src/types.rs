@@ -3,6 +3,7 @@
 cfg_if::cfg_if! {
     if #[cfg(feature = "std")] {
         use std::borrow::Cow;
+        use std::ffi::OsString;
         use std::fmt;
         use std::path::PathBuf;
         use std::prelude::v1::*;
@@ -39,39 +40,68 @@ impl<'a> BytesOrWideString<'a> {
         }
     }
 
-    /// Provides a `Path` representation of `BytesOrWideString`.
+    /// Converts to a `Cow<str>` without losing any information, or returns
+    /// `None` if `Bytes` isn't valid UTF-8 or `Wide` isn't valid UTF-16.
     ///
     /// # Required features
     ///
     /// This function requires the `std` feature of the `backtrace` crate to be
     /// enabled, and the `std` feature is enabled by default.
-    pub fn into_path_buf(self) -> PathBuf {
+    pub fn to_str(&self) -> Option<Cow<'a, str>> {
+        match *self {
+            BytesOrWideString::Bytes(slice) => str::from_utf8(slice).ok().map(Cow::Borrowed),
+            BytesOrWideString::Wide(wide) => String::from_utf16(wide).ok().map(Cow::Owned),
+        }
+    }
+
+    /// Converts to an `OsString` without losing any information, or returns
+    /// `None` if that's not possible on the current platform.
+    ///
+    /// Any `Bytes` can be converted on Unix, and any `Wide` on Windows, which
+    /// are the native representations of these platforms. Otherwise, the
+    /// string has to be valid UTF-8 or UTF-16.
+    ///
+    /// # Required features
+    ///
+    /// This function requires the `std` feature of the `backtrace` crate to be
+    /// enabled, and the `std` feature is enabled by default.
+    pub fn to_os_string(&self) -> Option<OsString> {
         #[cfg(unix)]
         {
             use std::ffi::OsStr;
             use std::os::unix::ffi::OsStrExt;
 
             if let BytesOrWideString::Bytes(slice) = self {
-                return PathBuf::from(OsStr::from_bytes(slice));
+                return Some(OsStr::from_bytes(slice).to_os_string());
             }
         }
 
         #[cfg(windows)]
         {
-            use std::ffi::OsString;
             use std::os::windows::ffi::OsStringExt;
 
             if let BytesOrWideString::Wide(slice) = self {
-                return PathBuf::from(OsString::from_wide(slice));
+                return Some(OsString::from_wide(slice));
             }
         }
 
-        if let BytesOrWideString::Bytes(b) = self {
-            if let Ok(s) = str::from_utf8(b) {
-                return PathBuf::from(s);
-            }
+        self.to_str().map(|s| OsString::from(s.into_owned()))
+    }
+
+    /// Provides a `Path` representation of `BytesOrWideString`.
+    ///
+    /// This is lossless whenever `to_os_string` is, and otherwise replaces
+    /// invalid parts of the string like `to_str_lossy`.
+    ///
+    /// # Required features
+    ///
+    /// This function requires the `std` feature of the `backtrace` crate to be
+    /// enabled, and the `std` feature is enabled by default.
+    pub fn into_path_buf(self) -> PathBuf {
+        match self.to_os_string() {
+            Some(s) => PathBuf::from(s),
+            None => PathBuf::from(self.to_str_lossy().into_owned()),
         }
-        unreachable!()
     }
 }
 
@@ -81,3 +111,33 @@ impl<'a> fmt::Display for BytesOrWideString<'a> {
         self.to_str_lossy().fmt(f)
     }
 }
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_losslessly() {
+        let wide = "dir/file.rs".encode_utf16().collect::<Vec<_>>();
+        let wide = BytesOrWideString::Wide(&wide);
+        assert_eq!(wide.to_str().as_deref(), Some("dir/file.rs"));
+        assert_eq!(wide.to_os_string(), Some("dir/file.rs".into()));
+        assert_eq!(wide.into_path_buf(), PathBuf::from("dir/file.rs"));
+
+        // An unpaired surrogate is only representable on Windows.
+        let wide = BytesOrWideString::Wide(&[0x61, 0xd800]);
+        assert_eq!(wide.to_str(), None);
+        assert_eq!(wide.to_os_string().is_some(), cfg!(windows));
+        if cfg!(not(windows)) {
+            assert_eq!(wide.into_path_buf(), PathBuf::from("a\u{fffd}"));
+        }
+
+        // Invalid UTF-8 is only representable on Unix.
+        let bytes = BytesOrWideString::Bytes(b"a\xff");
+        assert_eq!(bytes.to_str(), None);
+        assert_eq!(bytes.to_os_string().is_some(), cfg!(unix));
+        if cfg!(not(unix)) {
+            assert_eq!(bytes.into_path_buf(), PathBuf::from("a\u{fffd}"));
+        }
+    }
+}
@@ -27,12 +27,14 @@ cfg_if::cfg_if! {
             pub use winapi::ctypes::*;
             pub use winapi::shared::basetsd::*;
             pub use winapi::shared::minwindef::*;
+            pub use winapi::um::consoleapi::*;
             pub use winapi::um::dbghelp::*;
             pub use winapi::um::fileapi::*;
             pub use winapi::um::handleapi::*;
             pub use winapi::um::libloaderapi::*;
             pub use winapi::um::memoryapi::*;
             pub use winapi::um::minwinbase::*;
+            pub use winapi::um::processenv::*;
             pub use winapi::um::processthreadsapi::*;
             pub use winapi::um::synchapi::*;
             pub use winapi::um::tlhelp32::*;
@@ -362,6 +364,7 @@ ffi! {
     }
 
     pub const MAX_SYM_NAME: usize = 2000;
+    pub const INLINE_FRAME_CONTEXT_IGNORE: DWORD = 0xffffffff;
     pub const AddrModeFlat: ADDRESS_MODE = 3;
     pub const TRUE: BOOL = 1;
     pub const FALSE: BOOL = 0;
@@ -376,6 +379,8 @@ ffi! {
     pub const GENERIC_READ: DWORD = 0x80000000;
     pub const INFINITE: DWORD = !0;
     pub const PAGE_READONLY: DWORD = 2;
+    pub const PAGE_READWRITE: DWORD = 4;
+    pub const FILE_MAP_WRITE: DWORD = 2;
     pub const FILE_MAP_READ: DWORD = 4;
     pub const TH32CS_SNAPMODULE: DWORD = 0x00000008;
     pub const INVALID_HANDLE_VALUE: HANDLE = -1isize as HANDLE;
@@ -394,6 +399,7 @@ ffi! {
     pub type LPSTR = *mut i8;
     pub type LPCSTR = *const i8;
     pub type PWSTR = *mut u16;
+    pub type LPWSTR = *mut u16;
     pub type WORD = u16;
     pub type USHORT = u16;
     pub type ULONG = u32;
@@ -458,6 +464,13 @@ ffi! {
             hSnapshot: HANDLE,
             lpme: LPMODULEENTRY32W,
         ) -> BOOL;
+        pub fn GetStdHandle(nStdHandle: DWORD) -> HANDLE;
+        pub fn GetConsoleMode(hConsoleHandle: HANDLE, lpMode: LPDWORD) -> BOOL;
+        pub fn GetModuleFileNameW(
+            hModule: HMODULE,
+            lpFilename: LPWSTR,
+            nSize: DWORD,
+        ) -> DWORD;
         pub fn lstrlenW(lpstring: PCWSTR) -> i32;
         pub fn WideCharToMultiByte(
             codepage: u32,
@@ -747,3 +760,25 @@ ffi! {
         pub Padding2: [DWORD; 2],
     }
 } // IFDEF(arm)
+
+/// Returns the path of the file `module` of the current process was loaded
+/// from, as recorded by the loader.
+///
+/// Unlike the paths in `MODULEENTRY32W` or a buffer of `MAX_PATH` characters,
+/// this isn't truncated for long paths.
+pub unsafe fn module_file_name(module: HMODULE) -> Option<alloc::vec::Vec<u16>> {
+    // Paths are limited to 32767 characters, including a `\\?\` prefix.
+    let mut name = alloc::vec![0_u16; MAX_PATH];
+    loop {
+        let len = GetModuleFileNameW(module, name.as_mut_ptr(), name.len() as DWORD) as usize;
+        if len == 0 {
+            return None;
+        }
+        // A truncated path fills the whole buffer.
+        if len < name.len() || name.len() > 0x7fff {
+            name.truncate(len);
+            return Some(name);
+        }
+        name.resize(name.len() * 2, 0);
+    }
+}
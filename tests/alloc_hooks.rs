@@ -0,0 +1,72 @@
+use backtrace::{AllocatorHooks, Backtrace, SymbolizerAllocator};
+use std::alloc::{Layout, System};
+use std::cell::UnsafeCell;
+use std::ptr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+#[global_allocator]
+static ALLOCATOR: SymbolizerAllocator<System> = SymbolizerAllocator(System);
+
+const SIZE: usize = 64 << 20;
+struct Arena(UnsafeCell<[u8; SIZE]>);
+unsafe impl Sync for Arena {}
+static ARENA: Arena = Arena(UnsafeCell::new([0; SIZE]));
+static USED: AtomicUsize = AtomicUsize::new(0);
+static FREED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe fn alloc(layout: Layout) -> *mut u8 {
+    let size = layout.size() + layout.align();
+    let offset = USED.fetch_add(size, Ordering::Relaxed);
+    if offset + size > SIZE {
+        return ptr::null_mut();
+    }
+    let addr = ARENA.0.get() as usize + offset;
+    ((addr + layout.align() - 1) & !(layout.align() - 1)) as *mut u8
+}
+
+unsafe fn dealloc(_: *mut u8, _: Layout) {
+    FREED.fetch_add(1, Ordering::Relaxed);
+}
+
+fn owns(ptr: *mut u8) -> bool {
+    let start = ARENA.0.get() as usize;
+    (start..start + SIZE).contains(&(ptr as usize))
+}
+
+static POOL: AllocatorHooks = AllocatorHooks {
+    alloc,
+    dealloc,
+    owns,
+};
+
+#[test]
+fn routes_allocations_of_the_symbolizer() {
+    backtrace::set_allocator_hooks(Some(&POOL));
+
+    let outside = Box::new([0u8; 16]);
+    assert!(!owns(outside.as_ptr() as *mut u8));
+    assert_eq!(USED.load(Ordering::Relaxed), 0);
+
+    let bt = Backtrace::new();
+    assert!(USED.load(Ordering::Relaxed) > 0);
+    // Symbols are copied while they're resolved, with the lock held.
+    let name = bt
+        .frames()
+        .iter()
+        .flat_map(|frame| frame.symbols())
+        .find_map(|symbol| symbol.name());
+    if let Some(name) = name {
+        assert!(owns(name.as_bytes().as_ptr() as *mut u8));
+    }
+
+    // Memory from the pool is returned to it, wherever it's freed, even once
+    // the hooks are removed.
+    backtrace::set_allocator_hooks(None);
+    drop(bt);
+    assert!(FREED.load(Ordering::Relaxed) > 0);
+
+    let used = USED.load(Ordering::Relaxed);
+    let bt = Backtrace::new();
+    assert_eq!(USED.load(Ordering::Relaxed), used);
+    assert!(!owns(bt.frames().as_ptr() as *mut u8));
+}
@@ -0,0 +1,56 @@
+use backtrace::Backtrace;
+
+fn names(bt: &Backtrace) -> Vec<String> {
+    bt.frames()
+        .iter()
+        .map(|f| {
+            f.symbols()
+                .first()
+                .and_then(|s| s.name())
+                .map_or(String::new(), |n| n.to_string())
+        })
+        .collect()
+}
+
+#[inline(never)]
+fn capture(skip: usize, max_frames: usize, resolve: bool) -> Backtrace {
+    Backtrace::builder()
+        .skip(skip)
+        .max_frames(max_frames)
+        .resolve(resolve)
+        .capture()
+}
+
+#[inline(never)]
+fn outer(skip: usize, max_frames: usize, resolve: bool) -> Backtrace {
+    let trace = capture(skip, max_frames, resolve);
+    unsafe { std::ptr::read_volatile(&&trace) };
+    trace
+}
+
+#[test]
+fn captures_with_options() {
+    let full = outer(0, usize::MAX, true);
+    assert!(full.frames().len() > 3);
+    assert!(full.frames().iter().all(|f| f.is_resolved()));
+
+    let limited = outer(1, 2, false);
+    assert_eq!(limited.frames().len(), 2);
+    assert!(limited.frames().iter().all(|f| !f.is_resolved()));
+    assert!(outer(0, 0, true).frames().is_empty());
+    assert!(outer(usize::MAX, 8, true).frames().is_empty());
+
+    // The frames of this crate are only left out where `symbol_address` finds
+    // the start of functions, see `skip_inner_frames.rs`.
+    if cfg!(any(target_vendor = "apple", target_arch = "arm")) {
+        return;
+    }
+    // Both start in `outer`, which calls `capture` from the same place.
+    assert_eq!(limited.frames()[0].ip(), full.frames()[1].ip());
+
+    if cfg!(debug_assertions) {
+        let names = names(&full);
+        assert!(names[0].contains("capture"), "{names:?}");
+        assert!(names[1].contains("outer"), "{names:?}");
+    }
+}
@@ -0,0 +1,48 @@
+use backtrace::CaptureHandle;
+use std::sync::atomic::AtomicUsize;
+
+#[inline(never)]
+fn capture_from_here(handle: &CaptureHandle<'_>) -> bool {
+    let captured = unsafe { handle.capture() };
+    unsafe { std::ptr::read_volatile(&captured) }
+}
+
+#[test]
+fn captures_and_resolves_later() {
+    let storage = (0..4 * 65).map(|_| AtomicUsize::new(0)).collect::<Vec<_>>();
+    let handle = CaptureHandle::new(&storage, 64);
+    assert!(capture_from_here(&handle));
+    assert!(capture_from_here(&handle));
+
+    let traces = handle.resolve();
+    assert_eq!(traces.len(), 2);
+    // The frames of `capture` are only left out where `symbol_address` finds
+    // the start of functions, see `skip_inner_frames.rs`.
+    if cfg!(not(any(target_vendor = "apple", target_arch = "arm"))) {
+        for trace in &traces {
+            let first = trace.frames()[0].symbols()[0].name().unwrap().to_string();
+            assert!(first.contains("capture_from_here"), "{trace:?}");
+        }
+    }
+
+    // All records have been drained.
+    let mut drained = 0;
+    handle.drain(|_| drained += 1);
+    assert_eq!(drained, 0);
+}
+
+#[test]
+fn drops_backtraces_when_full() {
+    let storage = (0..2 * 9).map(|_| AtomicUsize::new(0)).collect::<Vec<_>>();
+    let handle = CaptureHandle::new(&storage, 8);
+    assert!(capture_from_here(&handle));
+    assert!(capture_from_here(&handle));
+    assert!(!capture_from_here(&handle));
+    assert_eq!(handle.dropped(), 1);
+
+    let mut depths = Vec::new();
+    handle.drain(|ips| depths.push(ips.len()));
+    assert_eq!(depths.len(), 2);
+    assert!(depths.iter().all(|&depth| depth > 0 && depth <= 8));
+    assert!(capture_from_here(&handle));
+}
@@ -0,0 +1,25 @@
+use backtrace::{Backtrace, BacktraceStatus};
+
+// The environment is only looked at once, so this is done in a single test.
+#[test]
+fn reports_status() {
+    std::env::set_var("RUST_BACKTRACE", "1");
+    std::env::set_var("RUST_LIB_BACKTRACE", "0");
+    if Backtrace::capture_status() == BacktraceStatus::Unsupported {
+        assert_eq!(
+            Backtrace::new_unresolved().status(),
+            BacktraceStatus::Unsupported
+        );
+        return;
+    }
+    assert_eq!(Backtrace::capture_status(), BacktraceStatus::Disabled);
+    assert!(Backtrace::capture().is_none());
+    assert_eq!(
+        Backtrace::new_unresolved().status(),
+        BacktraceStatus::Captured
+    );
+    assert_eq!(
+        Backtrace::from_ips(&[]).status(),
+        BacktraceStatus::CaptureFailed
+    );
+}
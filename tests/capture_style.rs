@@ -0,0 +1,12 @@
+use backtrace::{Backtrace, PrintFmt};
+
+// The environment is only looked at once, so this is done in a single test.
+#[test]
+fn captures_full_backtraces() {
+    std::env::remove_var("RUST_LIB_BACKTRACE");
+    std::env::set_var("RUST_BACKTRACE", "full");
+    let (bt, style) = Backtrace::capture_with_style().unwrap();
+    assert!(style == PrintFmt::Full);
+    assert!(!bt.frames().is_empty());
+    assert!(Backtrace::capture().is_some());
+}
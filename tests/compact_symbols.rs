@@ -0,0 +1,47 @@
+// Symbols are only digested where they're resolved with gimli.
+#![cfg(not(all(windows, target_env = "msvc")))]
+
+use std::ffi::c_void;
+
+#[inline(never)]
+fn target() -> u32 {
+    unsafe { std::ptr::read_volatile(&42) }
+}
+
+fn symbols() -> Vec<(String, Option<String>, Option<u32>)> {
+    let mut symbols = Vec::new();
+    backtrace::resolve((target as *const () as usize + 1) as *mut c_void, |sym| {
+        symbols.push((
+            sym.name().unwrap().to_string(),
+            sym.filename().map(|f| f.display().to_string()),
+            sym.lineno(),
+        ));
+    });
+    symbols
+}
+
+#[test]
+fn compact_symbols_match() {
+    let full = symbols();
+    assert!(full[0].0.contains("target"), "{full:?}");
+    let full_bytes = backtrace::symbolizer_stats().cached_bytes();
+
+    backtrace::set_compact_symbols(true);
+    backtrace::clear_symbol_cache();
+    let compact = symbols();
+    let compact_bytes = backtrace::symbolizer_stats().cached_bytes();
+    backtrace::set_compact_symbols(false);
+
+    assert_eq!(compact, full);
+    if cfg!(target_os = "linux") {
+        assert!(compact[0]
+            .1
+            .as_ref()
+            .unwrap()
+            .ends_with("compact_symbols.rs"));
+        assert!(
+            compact_bytes < full_bytes,
+            "{compact_bytes} >= {full_bytes}"
+        );
+    }
+}
@@ -0,0 +1,100 @@
+use backtrace::{Backtrace, BacktraceFrame, FrameDiff};
+
+#[inline(never)]
+fn capture() -> Backtrace {
+    Backtrace::new()
+}
+
+#[inline(never)]
+fn first() -> Backtrace {
+    // Avoid tail calls and identical functions being merged.
+    let bt = capture();
+    unsafe { std::ptr::read_volatile(&1) };
+    bt
+}
+
+#[inline(never)]
+fn second() -> Backtrace {
+    // Avoid tail calls and identical functions being merged.
+    let bt = capture();
+    unsafe { std::ptr::read_volatile(&2) };
+    bt
+}
+
+#[test]
+fn same_place() {
+    let mut captures = Vec::new();
+    for _ in 0..unsafe { std::ptr::read_volatile(&2) } {
+        captures.push(first());
+    }
+    let (a, b) = (&captures[0], &captures[1]);
+    assert!(a.eq_ignoring_addresses(b));
+    assert_eq!(a.common_prefix_len(b), a.frames().len());
+    assert_eq!(a.common_suffix_len(b), a.frames().len());
+    assert!(a.diff(b).iter().all(|d| matches!(d, FrameDiff::Both(..))));
+}
+
+#[test]
+fn different_places() {
+    let (a, b) = (first(), second());
+    assert!(!a.eq_ignoring_addresses(&b));
+    assert!(a.common_suffix_len(&b) > 0);
+
+    let diff = a.diff(&b);
+    assert!(diff.iter().any(|d| matches!(d, FrameDiff::Left(..))));
+    assert!(diff.iter().any(|d| matches!(d, FrameDiff::Right(..))));
+    let left = diff
+        .iter()
+        .filter(|d| !matches!(d, FrameDiff::Right(..)))
+        .count();
+    assert_eq!(left, a.frames().len());
+}
+
+#[test]
+fn removed_frame() {
+    let a = first();
+    let mut frames: Vec<BacktraceFrame> = a.clone().into();
+    let removed = frames.len() / 2;
+    frames.remove(removed);
+    let b = Backtrace::from(frames);
+
+    let diff = a.diff(&b);
+    assert_eq!(diff.len(), a.frames().len());
+    for (i, d) in diff.iter().enumerate() {
+        match d {
+            FrameDiff::Left(_) => assert_eq!(i, removed),
+            FrameDiff::Both(..) => assert_ne!(i, removed),
+            FrameDiff::Right(_) => panic!("unexpected frame {d:?}"),
+        }
+    }
+}
+
+#[test]
+fn long_differences() {
+    // Frames in common at both ends surround thousands of others, too many
+    // to pair up even though some of them are equal.
+    let ips = |middle: usize| {
+        let mut ips = vec![1, 2];
+        ips.extend(middle..middle + 5000);
+        ips.extend([3, 4]);
+        ips
+    };
+    let a = Backtrace::from_ips(&ips(0x1000));
+    let b = Backtrace::from_ips(&ips(0x2000));
+
+    let diff = a.diff(&b);
+    assert_eq!(diff.len(), 2 + 2 * 5000 + 2);
+    assert!(diff[..2].iter().all(|d| matches!(d, FrameDiff::Both(..))));
+    assert!(diff[diff.len() - 2..]
+        .iter()
+        .all(|d| matches!(d, FrameDiff::Both(..))));
+    let left = diff
+        .iter()
+        .filter_map(|d| match d {
+            FrameDiff::Both(a, _) | FrameDiff::Left(a) => Some(a.ip()),
+            FrameDiff::Right(_) => None,
+        })
+        .collect::<Vec<_>>();
+    let frames = a.frames().iter().map(|f| f.ip()).collect::<Vec<_>>();
+    assert_eq!(left, frames);
+}
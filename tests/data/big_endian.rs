@@ -0,0 +1,28 @@
+// Source of `big_endian.elf`, a 32-bit big-endian PowerPC executable with
+// DWARF debug information used to test resolving symbols in object files of
+// other platforms. It was built with a nightly compiler and `rust-lld`:
+//
+//     rustc --target powerpc-unknown-linux-gnu --emit obj -C debuginfo=2 \
+//         --remap-path-prefix=$PWD=. big_endian.rs -o big_endian.o
+//     rust-lld -flavor gnu -e big_endian_function -o big_endian.elf big_endian.o
+//
+// This places `big_endian_function` at 0x10010100.
+
+#![feature(no_core, lang_items)]
+#![no_core]
+#![crate_type = "lib"]
+
+#[lang = "pointee_sized"]
+pub trait PointeeSized {}
+#[lang = "meta_sized"]
+pub trait MetaSized: PointeeSized {}
+#[lang = "sized"]
+pub trait Sized: MetaSized {}
+#[lang = "copy"]
+pub trait Copy {}
+impl Copy for u32 {}
+
+#[no_mangle]
+pub fn big_endian_function(x: u32) -> u32 {
+    x
+}
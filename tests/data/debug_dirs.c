@@ -0,0 +1,17 @@
+// Source of `debug_dirs.elf`, an x86-64 ELF shared library with a build ID
+// whose debug information was moved to `debug_dirs.debug`, which is found
+// through the build ID or the `.gnu_debuglink` section. It was built with:
+//
+//     gcc -shared -nostdlib -fPIC -O1 -g -fdebug-prefix-map=$PWD=. \
+//         -fno-asynchronous-unwind-tables \
+//         -Wl,--build-id=sha1,-z,noseparate-code,--no-eh-frame-hdr \
+//         -o debug_dirs.elf debug_dirs.c
+//     objcopy --remove-section=.comment debug_dirs.elf
+//     objcopy --only-keep-debug debug_dirs.elf debug_dirs.debug
+//     objcopy --strip-debug --add-gnu-debuglink=debug_dirs.debug debug_dirs.elf
+//
+// This places the code of `separate_function` at 0x223, on line 16.
+
+int separate_function(int x) {
+    return x * 3;
+}
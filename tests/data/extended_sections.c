@@ -0,0 +1,18 @@
+// Source of `extended_sections.elf`, an x86-64 ELF shared library with debug
+// information whose section headers use extended numbering, like those of
+// binaries with more than 65,279 sections: its `e_shnum` is zero and its
+// `e_shstrndx` is `SHN_XINDEX`, with the actual values in the `sh_size` and
+// `sh_link` of the first section header. It was built with:
+//
+//     gcc -shared -nostdlib -fPIC -O1 -g -fdebug-prefix-map=$PWD=. \
+//         -fno-asynchronous-unwind-tables \
+//         -Wl,--build-id=none,-z,noseparate-code,--no-eh-frame-hdr \
+//         -o extended_sections.elf extended_sections.c
+//     objcopy --remove-section=.comment extended_sections.elf
+//
+// after which the counts were moved to the first section header with a hex
+// editor. This places the code of `extended_function` at 0x1c3, on line 17.
+
+int extended_function(int x) {
+    return x + 1;
+}
@@ -0,0 +1,28 @@
+// Source of `parse_limits.elf`, an x86-64 ELF shared library whose debug
+// sections are compressed, with functions inlined into each other. It was
+// built with:
+//
+//     gcc -shared -nostdlib -fPIC -O1 -g -gz=zlib -fdebug-prefix-map=$PWD=. \
+//         -fno-asynchronous-unwind-tables \
+//         -Wl,--build-id=none,-z,noseparate-code,--no-eh-frame-hdr \
+//         -o parse_limits.elf parse_limits.c
+//     objcopy --remove-section=.comment parse_limits.elf
+//
+// This places the call to `sink` in `inner` at 0x217, on line 17, inlined
+// into `middle` on line 21, inlined into `outer` on line 26.
+
+extern void sink(int x);
+
+static inline __attribute__((always_inline)) void inner(int x) {
+    sink(x + 1);
+}
+
+static inline __attribute__((always_inline)) void middle(int x) {
+    inner(x * 2);
+    sink(x);
+}
+
+void outer(int x) {
+    middle(x);
+    sink(0);
+}
@@ -0,0 +1,19 @@
+// Source of `partial_symtab.elf`, an x86-64 ELF shared library whose symbol
+// table lacks `exported_function`, which is only named by its dynamic symbol
+// table, like in binaries stripped of some of their symbols. It was built with:
+//
+//     gcc -shared -nostdlib -fPIC -O1 -fno-asynchronous-unwind-tables \
+//         -Wl,--build-id=none,-z,noseparate-code,--no-eh-frame-hdr \
+//         -o partial_symtab.elf partial_symtab.c
+//     objcopy --strip-symbol=exported_function --remove-section=.comment \
+//         partial_symtab.elf
+//
+// This places `exported_function` at 0x1c3 and `local_function` at 0x1c7.
+
+int exported_function(int x) {
+    return x + 1;
+}
+
+__attribute__((used)) static int local_function(int x) {
+    return x * 2;
+}
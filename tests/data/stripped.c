@@ -0,0 +1,17 @@
+// Source of `stripped.elf`, an x86-64 ELF shared library stripped of all
+// symbols but the dynamic ones, of which there are none as all functions are
+// hidden. It was built with:
+//
+//     gcc -shared -nostdlib -fPIC -O1 -fvisibility=hidden \
+//         -Wl,--build-id=none,-z,noseparate-code -o stripped.elf stripped.c
+//     strip --strip-all --remove-section=.comment stripped.elf
+//
+// This places `first_function` at 0x1c9 and `second_function` at 0x1cd.
+
+int first_function(int x) {
+    return x + 1;
+}
+
+int second_function(int x) {
+    return x * 2;
+}
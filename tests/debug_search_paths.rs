@@ -0,0 +1,101 @@
+// Separate debug files are only searched for ELF binaries.
+#![cfg(target_os = "linux")]
+
+use backtrace::DebugSearchPaths;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The build ID of `tests/data/debug_dirs.elf`.
+const BUILD_ID: &str = "01da3152cfc1199f7124c2070bdefe2732a30078";
+
+/// The address of `separate_function` in `tests/data/debug_dirs.elf`.
+const ADDR: u64 = 0x223;
+
+fn data(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/data")
+        .join(name)
+}
+
+/// Resolves `ADDR` in `lib`, returning the name of the function and its line
+/// number, which is only known with the debug file.
+fn resolve(lib: &Path) -> Vec<(String, Option<u32>)> {
+    let mut symbols = Vec::new();
+    backtrace::resolve_file(lib, 0, &[ADDR], |_, sym| {
+        symbols.push((sym.name().unwrap().to_string(), sym.lineno()));
+    });
+    symbols
+}
+
+fn place_debug_file(path: &Path) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::copy(data("debug_dirs.debug"), path).unwrap();
+}
+
+// All of this changes global settings, so it's done in a single test.
+#[test]
+fn searches_configured_dirs() {
+    let dir = std::env::temp_dir().join(format!("backtrace-debug-dirs-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let lib_dir = dir.join("lib");
+    fs::create_dir_all(&lib_dir).unwrap();
+    let lib = lib_dir.join("debug_dirs.elf");
+    fs::copy(data("debug_dirs.elf"), &lib).unwrap();
+    let with_line = vec![("separate_function".to_string(), Some(16))];
+    let without_line = vec![("separate_function".to_string(), None)];
+
+    // By build ID, in a global directory from the environment.
+    let global = dir.join("global");
+    let by_build_id = global
+        .join(".build-id")
+        .join(&BUILD_ID[..2])
+        .join(format!("{}.debug", &BUILD_ID[2..]));
+    place_debug_file(&by_build_id);
+    std::env::set_var("BACKTRACE_DEBUG_DIRS", &global);
+    assert_eq!(resolve(&lib), with_line);
+    fs::remove_file(&by_build_id).unwrap();
+    assert_eq!(resolve(&lib), without_line);
+
+    // By debug link, in a directory relative to the binary.
+    let relative = lib_dir.join("debug").join("debug_dirs.debug");
+    place_debug_file(&relative);
+    assert_eq!(resolve(&lib), without_line);
+    backtrace::set_debug_search_paths(DebugSearchPaths::new().relative_dirs(["debug"]));
+    assert_eq!(resolve(&lib), with_line);
+    fs::remove_file(&relative).unwrap();
+
+    // By debug link, under the path of the binary in a global directory,
+    // where the later directories are searched too.
+    let canonical = fs::canonicalize(&lib_dir).unwrap();
+    let by_path = global
+        .join(canonical.strip_prefix("/").unwrap())
+        .join("debug_dirs.debug");
+    place_debug_file(&by_path);
+    let paths = DebugSearchPaths::new().global_dirs([dir.join("missing"), global.clone()]);
+    backtrace::set_debug_search_paths(paths.clone());
+    assert_eq!(resolve(&lib), with_line);
+
+    // Nothing is found without any directories to search.
+    backtrace::set_debug_search_paths(paths.global_dirs(Vec::<PathBuf>::new()));
+    assert_eq!(resolve(&lib), without_line);
+
+    // By the path of a binary without a debug link, under the same name in a
+    // relative directory, or with `.debug` appended in a global directory.
+    // `debug_dirs_nolink.elf` is `debug_dirs.elf` with its `.gnu_debuglink`
+    // section removed by `objcopy`.
+    let lib = lib_dir.join("nolink.so");
+    fs::copy(data("debug_dirs_nolink.elf"), &lib).unwrap();
+    backtrace::set_debug_search_paths(DebugSearchPaths::new().global_dirs([&global]));
+    assert_eq!(resolve(&lib), without_line);
+    let relative = lib_dir.join(".debug").join("nolink.so");
+    place_debug_file(&relative);
+    assert_eq!(resolve(&lib), with_line);
+    fs::remove_file(&relative).unwrap();
+    let by_path = global
+        .join(canonical.strip_prefix("/").unwrap())
+        .join("nolink.so.debug");
+    place_debug_file(&by_path);
+    assert_eq!(resolve(&lib), with_line);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
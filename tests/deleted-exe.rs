@@ -0,0 +1,60 @@
+// Long-running processes keep running from the original image of their binary
+// after it was deleted or replaced, e.g. by an upgrade, and symbols should
+// still be resolved from that image rather than whatever the path refers to
+// now.
+
+use std::env;
+use std::fs;
+use std::process::Command;
+
+mod common;
+
+const VAR: &str = "__BACKTRACE_DELETED_EXE_CHILD";
+
+fn main() {
+    if !cfg!(target_os = "linux") || common::cannot_reexec_the_test() {
+        println!("test result: ignored");
+        return;
+    }
+    if env::var(VAR).is_ok() {
+        child();
+    } else {
+        parent();
+    }
+}
+
+fn parent() {
+    let me = env::current_exe().unwrap();
+    let copy = env::temp_dir().join(format!("backtrace-deleted-exe-{}", std::process::id()));
+    fs::copy(&me, &copy).unwrap();
+
+    let result = Command::new(&copy).env(VAR, "1").output().unwrap();
+    let _ = fs::remove_file(&copy);
+    if result.status.success() {
+        println!("test result: ok");
+        return;
+    }
+    println!("stdout:\n{}", String::from_utf8_lossy(&result.stdout));
+    println!("stderr:\n{}", String::from_utf8_lossy(&result.stderr));
+    println!("code: {}", result.status);
+    panic!();
+}
+
+fn child() {
+    // Replace the binary with garbage before any symbols are resolved.
+    let me = env::current_exe().unwrap();
+    fs::remove_file(&me).unwrap();
+    fs::write(&me, b"not an executable").unwrap();
+
+    let bt = backtrace::Backtrace::new();
+    let _ = fs::remove_file(&me);
+    println!("{bt:?}");
+
+    let found = bt
+        .frames()
+        .iter()
+        .flat_map(|frame| frame.symbols())
+        .filter_map(|sym| sym.filename())
+        .any(|filename| filename.ends_with(file!()));
+    assert!(found);
+}
@@ -0,0 +1,37 @@
+use std::path::Path;
+use std::sync::Mutex;
+
+static MESSAGES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+fn record(message: std::fmt::Arguments<'_>) {
+    MESSAGES.lock().unwrap().push(message.to_string());
+}
+
+// All of this changes global settings, so it's done in a single test.
+#[test]
+fn resolves_with_hook_set() {
+    backtrace::set_diagnostics_hook(Some(record));
+    backtrace::clear_symbol_cache();
+    let bt = backtrace::Backtrace::new();
+
+    assert!(bt.frames().iter().any(|f| !f.symbols().is_empty()));
+    for message in MESSAGES.lock().unwrap().iter() {
+        assert!(!message.is_empty());
+    }
+
+    // Modules whose files are missing are reported.
+    if cfg!(unix) {
+        backtrace::set_module_path_prefixes(&[(Path::new("/"), Path::new("/nonexistent"))]);
+        backtrace::clear_symbol_cache();
+        let _ = backtrace::Backtrace::new();
+        backtrace::set_module_path_prefixes(&[]);
+        let messages = MESSAGES.lock().unwrap();
+        assert!(
+            messages
+                .iter()
+                .any(|m| m.starts_with("couldn't open `/nonexistent/")),
+            "{messages:?}"
+        );
+    }
+    backtrace::set_diagnostics_hook(None);
+}
@@ -0,0 +1,48 @@
+use backtrace::{Backtrace, FileAccess};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+#[inline(never)]
+fn resolves_self() {
+    backtrace::clear_symbol_cache();
+    let bt = Backtrace::new();
+    let found = bt
+        .frames()
+        .iter()
+        .flat_map(|frame| frame.symbols())
+        .filter_map(|sym| sym.name())
+        .any(|sym| sym.to_string().contains("resolves_self"));
+    assert!(found, "{bt:?}");
+}
+
+#[test]
+fn resolves_with_copied_files() {
+    backtrace::set_file_access(FileAccess::Copy);
+    resolves_self();
+    backtrace::set_file_access(FileAccess::Map);
+}
+
+#[test]
+fn resolves_with_read_files() {
+    backtrace::set_file_access(FileAccess::Read);
+    resolves_self();
+    backtrace::set_file_access(FileAccess::Map);
+}
+
+static SELECTED: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+fn select(path: &Path) -> FileAccess {
+    SELECTED.lock().unwrap().push(path.to_path_buf());
+    FileAccess::Read
+}
+
+#[test]
+fn selects_per_file() {
+    backtrace::set_file_access_with(Some(select));
+    resolves_self();
+    backtrace::set_file_access_with(None);
+
+    if cfg!(not(all(windows, target_env = "msvc"))) {
+        assert!(!SELECTED.lock().unwrap().is_empty());
+    }
+}
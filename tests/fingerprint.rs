@@ -0,0 +1,34 @@
+use backtrace::Backtrace;
+
+#[inline(never)]
+fn capture(resolve: bool) -> Backtrace {
+    if resolve {
+        Backtrace::new()
+    } else {
+        Backtrace::new_unresolved()
+    }
+}
+
+#[test]
+fn same_stack_same_fingerprint() {
+    // Make sure both captures happen from the same call site.
+    let mut fingerprints = Vec::new();
+    for _ in 0..unsafe { std::ptr::read_volatile(&2) } {
+        fingerprints.push(capture(false).fingerprint());
+    }
+    assert_eq!(fingerprints[0], fingerprints[1]);
+
+    // Resolving symbols doesn't matter either.
+    let mut bt = capture(false);
+    let unresolved = bt.fingerprint();
+    bt.resolve();
+    assert_eq!(unresolved, bt.fingerprint());
+}
+
+#[test]
+fn different_stack_different_fingerprint() {
+    let a = capture(false);
+    let b = capture(false);
+    // The two calls are made from different lines, so return addresses differ.
+    assert_ne!(a.fingerprint(), b.fingerprint());
+}
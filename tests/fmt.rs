@@ -0,0 +1,329 @@
+use backtrace::{
+    AddressFmt, Backtrace, BacktraceFmt, BytesOrWideString, ColorMode, FrameKind, PrintFmt,
+};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Prints `bt` with a `BacktraceFmt` configured by `configure`.
+struct Configured<'a, F>(&'a Backtrace, F);
+
+impl<F: Fn(&mut BacktraceFmt<'_, '_>)> fmt::Display for Configured<'_, F> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut print_path = print_path;
+        let mut f = BacktraceFmt::new(fmt, PrintFmt::Short, &mut print_path);
+        (self.1)(&mut f);
+        print_frames(self.0, &mut f)
+    }
+}
+
+fn format(bt: &Backtrace, configure: impl Fn(&mut BacktraceFmt<'_, '_>)) -> String {
+    Configured(bt, configure).to_string()
+}
+
+#[test]
+fn symbol_offsets() {
+    let bt = Backtrace::new();
+    if bt.frames().iter().all(|f| f.symbol_address() == f.ip()) {
+        // This unwinder doesn't know where functions start.
+        return;
+    }
+
+    let plain = format(&bt, |_| {});
+    assert!(!plain.contains("+0x"), "{plain}");
+
+    let with_offsets = format(&bt, |f| f.set_symbol_offsets(true));
+    assert!(with_offsets.contains("+0x"), "{with_offsets}");
+}
+
+#[test]
+fn address_fmt() {
+    let bt = Backtrace::new();
+
+    let hidden = format(&bt, |f| f.set_address_fmt(AddressFmt::Hidden));
+    assert!(!hidden.contains("0x"), "{hidden}");
+
+    let absolute = format(&bt, |f| f.set_address_fmt(AddressFmt::Absolute));
+    let ip = format!("{:?}", bt.frames()[0].ip());
+    assert!(absolute.contains(&ip), "{absolute}");
+
+    // Module lookups are only implemented by some backends.
+    if cfg!(all(target_os = "linux", not(miri))) {
+        let exe = std::env::current_exe().unwrap();
+        let exe = exe.file_name().unwrap().to_str().unwrap();
+        let module = format(&bt, |f| f.set_address_fmt(AddressFmt::ModuleOffset));
+        assert!(module.contains(&format!("{exe}+0x")), "{module}");
+    }
+}
+
+#[test]
+fn color() {
+    let bt = Backtrace::new();
+
+    let plain = format(&bt, |f| f.set_color(ColorMode::Never));
+    assert!(!plain.contains('\x1b'), "{plain}");
+
+    let colored = format(&bt, |f| f.set_color(ColorMode::Always));
+    assert!(colored.contains("\x1b[0m"), "{colored}");
+    // Frames of the standard library are highlighted differently than the
+    // frames of this test.
+    if cfg!(debug_assertions) {
+        assert!(colored.contains("\x1b[1;32mfmt::color"), "{colored}");
+    }
+}
+
+#[test]
+fn source_snippets() {
+    let bt = Backtrace::new();
+
+    // Snippets need line information pointing at this file.
+    let has_lines = bt
+        .frames()
+        .iter()
+        .flat_map(|f| f.symbols())
+        .any(|s| s.filename().map_or(false, |f| f.ends_with("tests/fmt.rs")));
+    if !has_lines {
+        return;
+    }
+
+    let without = format(&bt, |_| {});
+    assert!(!without.contains("let bt = Backtrace::new();"), "{without}");
+
+    let with = format(&bt, |f| f.set_source_snippets(Some(1)));
+    assert!(
+        with.contains("> ") && with.contains("| fn source_snippets() {"),
+        "{with}"
+    );
+    assert!(with.contains("|     let bt = Backtrace::new();"), "{with}");
+}
+
+#[test]
+fn source_locator() {
+    let bt = Backtrace::new();
+
+    let has_lines = bt
+        .frames()
+        .iter()
+        .flat_map(|f| f.symbols())
+        .any(|s| s.filename().map_or(false, |f| f.ends_with("tests/fmt.rs")));
+    if !has_lines {
+        return;
+    }
+
+    // Every line of every file is the same, so the snippet shows it no
+    // matter where the frames point.
+    let located = Arc::new(Mutex::new(Vec::<PathBuf>::new()));
+    let paths = located.clone();
+    let locator = move |path: &Path| {
+        paths.lock().unwrap().push(path.to_path_buf());
+        if path.ends_with("tests/fmt.rs") {
+            Some("// located\n".repeat(10_000))
+        } else {
+            None
+        }
+    };
+    let with = format(&bt, |f| {
+        f.set_source_snippets(Some(1));
+        f.set_source_locator(Some(Arc::new(locator.clone())));
+    });
+    assert!(with.contains("| // located"), "{with}");
+    assert!(!with.contains("| fn source_locator() {"), "{with}");
+
+    // Each file is only looked up once.
+    let mut located = located.lock().unwrap().clone();
+    let count = located.len();
+    located.sort();
+    located.dedup();
+    assert_eq!(located.len(), count, "{located:?}");
+}
+
+fn print_frames(bt: &Backtrace, f: &mut BacktraceFmt<'_, '_>) -> fmt::Result {
+    f.add_context()?;
+    for frame in bt.frames() {
+        f.frame().backtrace_frame(frame)?;
+    }
+    f.finish()
+}
+
+fn print_path(fmt: &mut fmt::Formatter<'_>, path: BytesOrWideString<'_>) -> fmt::Result {
+    fmt::Display::fmt(&path, fmt)
+}
+
+#[test]
+fn write_to() {
+    let bt = Backtrace::new();
+    let expected = format(&bt, |_| {});
+
+    let mut out = String::new();
+    BacktraceFmt::write_to(&mut out, PrintFmt::Short, &mut print_path, |f| {
+        print_frames(&bt, f)
+    })
+    .unwrap();
+    assert_eq!(out, expected);
+
+    let mut out = Vec::new();
+    BacktraceFmt::write_io(&mut out, PrintFmt::Short, &mut print_path, |f| {
+        print_frames(&bt, f)
+    })
+    .unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), expected);
+}
+
+#[test]
+#[cfg(unix)]
+fn fd_writer() {
+    use backtrace::FdWriter;
+    use std::os::unix::io::AsRawFd;
+
+    let bt = Backtrace::new();
+    let path = std::env::temp_dir().join(format!("backtrace-fd-writer-{}", std::process::id()));
+    let file = std::fs::File::create(&path).unwrap();
+
+    let mut out = FdWriter::new(file.as_raw_fd());
+    BacktraceFmt::write_to(&mut out, PrintFmt::Short, &mut print_path, |f| {
+        print_frames(&bt, f)
+    })
+    .unwrap();
+    drop(file);
+
+    let written = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(written, format(&bt, |_| {}));
+}
+
+#[test]
+fn collapse_recursion() {
+    #[inline(never)]
+    fn recurse(n: u32) -> Backtrace {
+        if n == 0 {
+            return Backtrace::new();
+        }
+        let bt = recurse(unsafe { std::ptr::read_volatile(&(n - 1)) });
+        unsafe { std::ptr::read_volatile(&&bt) };
+        bt
+    }
+
+    let bt = recurse(50);
+    let print = |collapse| {
+        let mut out = String::new();
+        BacktraceFmt::write_to(&mut out, PrintFmt::Short, &mut print_path, |f| {
+            f.set_collapse_recursion(collapse);
+            f.backtrace_frames(bt.frames())
+        })
+        .unwrap();
+        out
+    };
+
+    let full = print(None);
+    assert!(!full.contains("repeated"), "{full}");
+    assert_eq!(full, format(&bt, |_| {}));
+
+    let collapsed = print(Some(10));
+    assert!(collapsed.contains("more times]"), "{collapsed}");
+    assert!(collapsed.lines().count() < full.lines().count() - 40);
+}
+
+#[test]
+fn hidden_frames() {
+    let bt = Backtrace::new();
+
+    let all = format(&bt, |_| {});
+    let hidden = format(&bt, |f| {
+        f.set_hidden_frames(&[FrameKind::Std, FrameKind::Backtrace, FrameKind::Unknown])
+    });
+    assert!(hidden.lines().count() < all.lines().count(), "{hidden}");
+    assert!(!hidden.contains("backtrace::capture"), "{hidden}");
+    if cfg!(debug_assertions) {
+        assert!(hidden.contains("fmt::hidden_frames"), "{hidden}");
+    }
+
+    let mut out = String::new();
+    BacktraceFmt::write_to(&mut out, PrintFmt::Short, &mut print_path, |f| {
+        f.set_frame_classifier(|_, _| Some(FrameKind::Unknown));
+        f.set_hidden_frames(&[FrameKind::Unknown]);
+        print_frames(&bt, f)
+    })
+    .unwrap();
+    assert_eq!(out, "");
+}
+
+#[test]
+fn classify() {
+    use backtrace::SymbolName;
+
+    let classify = |name: &str, path: Option<&str>| {
+        FrameKind::classify(
+            Some(&SymbolName::new(name.as_bytes())),
+            path.map(|p| BytesOrWideString::Bytes(p.as_bytes()))
+                .as_ref(),
+        )
+    };
+
+    let rust_start = "_ZN3std2rt10lang_start17h0123456789abcdefE";
+    assert_eq!(classify(rust_start, None), FrameKind::Std);
+    let capture = "_ZN9backtrace7capture9Backtrace3new17h0123456789abcdefE";
+    assert_eq!(classify(capture, None), FrameKind::Backtrace);
+    let user = "_ZN3foo3bar17h0123456789abcdefE";
+    assert_eq!(
+        classify(user, Some("/src/foo/src/main.rs")),
+        FrameKind::User
+    );
+    assert_eq!(classify(user, None), FrameKind::User);
+    let registry =
+        "/home/me/.cargo/registry/src/index.crates.io-6f17d22bba15001f/foo-0.1.0/src/lib.rs";
+    assert_eq!(classify(user, Some(registry)), FrameKind::Dependency);
+    assert_eq!(classify("__libc_start_main", None), FrameKind::Unknown);
+    assert_eq!(FrameKind::classify(None, None), FrameKind::Unknown);
+}
+
+#[test]
+fn metadata() {
+    let mut bt = Backtrace::from_ips(&[0x1000, 0x2000]);
+    bt.resolve();
+    bt.metadata_mut().insert("task", 3);
+    bt.frames_mut()[1].metadata_mut().insert("size", 128);
+    bt.frames_mut()[1].metadata_mut().insert("lock", "a");
+    assert_eq!(bt.frames()[1].metadata().get("size"), Some("128"));
+
+    let printed = format!("{bt:?}");
+    let lines: Vec<_> = printed.lines().collect();
+    assert!(!lines[0].contains('['), "{printed}");
+    assert!(lines[1].ends_with(" [size=128, lock=a]"), "{printed}");
+    assert_eq!(lines.last().unwrap().trim(), "[task=3]", "{printed}");
+
+    let without = format(&bt, |f| f.set_metadata_suffix(false));
+    assert!(!without.contains("size=128"), "{without}");
+}
+
+#[test]
+fn deterministic() {
+    // Capture from the same place twice, so both backtraces have the same
+    // frames.
+    let bts: Vec<_> = (0..2).map(|_| Backtrace::new()).collect();
+    let print = |bt| {
+        let mut out = String::new();
+        BacktraceFmt::write_to(&mut out, PrintFmt::Full, &mut print_path, |f| {
+            f.set_address_fmt(AddressFmt::Absolute);
+            f.set_symbol_offsets(true);
+            f.set_deterministic(true);
+            print_frames(bt, f)
+        })
+        .unwrap();
+        out
+    };
+
+    let printed = print(&bts[0]);
+    assert_eq!(printed, print(&bts[1]));
+    assert!(!printed.contains("0x"), "{printed}");
+    assert!(!printed.contains("::h"), "{printed}");
+    assert!(!printed.contains('\\'), "{printed}");
+
+    let has_lines = bts[0]
+        .frames()
+        .iter()
+        .flat_map(|f| f.symbols())
+        .any(|s| s.filename().map_or(false, |p| p.ends_with("tests/fmt.rs")));
+    if has_lines {
+        assert!(printed.contains(" at tests/fmt.rs:"), "{printed}");
+    }
+}
@@ -0,0 +1,89 @@
+// Only Linux is tested, to keep the declarations of `fork` and friends below
+// simple.
+#![cfg(target_os = "linux")]
+
+use std::ffi::c_void;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::Duration;
+
+extern "C" {
+    fn fork() -> i32;
+    fn waitpid(pid: i32, status: *mut i32, options: i32) -> i32;
+    fn alarm(seconds: u32) -> u32;
+    fn _exit(status: i32) -> !;
+}
+
+fn addr() -> *mut c_void {
+    // Resolving looks up the instruction before an address, as if it was a
+    // return address.
+    (resolves_own_name as *const () as usize + 1) as *mut c_void
+}
+
+#[inline(never)]
+fn resolves_own_name() -> bool {
+    let mut found = false;
+    backtrace::resolve(addr(), |sym| {
+        if let Some(name) = sym.name() {
+            found |= name.to_string().contains("resolves_own_name");
+        }
+    });
+    found
+}
+
+/// Forks while another thread is in the middle of resolving a symbol, and
+/// returns whether that thread was done by the time `fork` returned.
+///
+/// The child must still be able to resolve symbols instead of deadlocking on
+/// the lock the other thread held.
+fn fork_while_resolving() -> bool {
+    let done = Arc::new(AtomicBool::new(false));
+    let (locked_tx, locked_rx) = mpsc::channel();
+    let holder = thread::spawn({
+        let done = done.clone();
+        move || {
+            backtrace::resolve(addr(), |_| {
+                locked_tx.send(()).unwrap();
+                thread::sleep(Duration::from_millis(100));
+                done.store(true, Ordering::SeqCst);
+            });
+        }
+    });
+    locked_rx.recv().unwrap();
+
+    let pid = unsafe { fork() };
+    assert!(pid >= 0);
+    if pid == 0 {
+        unsafe {
+            // Kill the child if it deadlocks.
+            alarm(10);
+            _exit(if resolves_own_name() { 0 } else { 1 });
+        }
+    }
+    let waited = done.load(Ordering::SeqCst);
+
+    let mut status = 0;
+    assert_eq!(unsafe { waitpid(pid, &mut status, 0) }, pid);
+    holder.join().unwrap();
+    assert_eq!(status, 0, "child exited with status {status:#x}");
+    assert!(resolves_own_name());
+    waited
+}
+
+#[test]
+fn resolves_after_fork() {
+    // All of this changes global settings, so it's done in a single test.
+    assert!(resolves_own_name());
+
+    // By default `fork` doesn't wait, and the child starts over.
+    assert!(!fork_while_resolving());
+
+    backtrace::set_wait_before_fork(true);
+    assert!(fork_while_resolving());
+    backtrace::set_wait_before_fork(false);
+
+    backtrace::set_clear_symbol_cache_after_fork(true);
+    assert!(!fork_while_resolving());
+    backtrace::set_clear_symbol_cache_after_fork(false);
+}
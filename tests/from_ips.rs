@@ -0,0 +1,39 @@
+use backtrace::{Backtrace, BacktraceFrame};
+
+#[test]
+fn resolves_like_captured() {
+    let mut captured = Backtrace::new();
+    let ips: Vec<usize> = captured.frames().iter().map(|f| f.ip() as usize).collect();
+    let mut rebuilt = Backtrace::from_ips(&ips);
+    assert_eq!(rebuilt.frames().len(), ips.len());
+    assert!(rebuilt.frames().iter().all(|f| f.symbols().is_empty()));
+
+    rebuilt.resolve();
+    captured.resolve();
+    for (a, b) in rebuilt.frames().iter().zip(captured.frames()) {
+        assert_eq!(a.ip(), b.ip());
+        // Only inline frames of the outermost function might differ, since
+        // captured frames may carry extra unwinder information.
+        let name = |f: &BacktraceFrame| {
+            f.symbols()
+                .last()
+                .and_then(|s| s.name().map(|n| n.to_string()))
+        };
+        assert_eq!(name(a), name(b));
+    }
+}
+
+#[test]
+fn single_frame() {
+    let ip = single_frame as *const () as usize + 1;
+    let mut bt = Backtrace::from(vec![BacktraceFrame::from_ip(ip)]);
+    bt.resolve();
+    let frame = &bt.frames()[0];
+    assert_eq!(frame.ip() as usize, ip);
+    assert_eq!(frame.symbol_address() as usize, ip);
+    assert_eq!(frame.module_base_address(), None);
+    if cfg!(debug_assertions) {
+        let name = frame.symbols()[0].name().unwrap().to_string();
+        assert!(name.contains("single_frame"), "{name}");
+    }
+}
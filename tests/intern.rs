@@ -0,0 +1,53 @@
+use backtrace::{Backtrace, TraceStore};
+
+#[inline(never)]
+fn capture() -> Backtrace {
+    Backtrace::new_unresolved()
+}
+
+#[test]
+fn dedups_frames_and_traces() {
+    let mut store = TraceStore::new();
+    let mut ids = Vec::new();
+    let mut backtraces = Vec::new();
+    for _ in 0..unsafe { std::ptr::read_volatile(&3) } {
+        let bt = capture();
+        ids.push(store.intern(&bt));
+        backtraces.push(bt);
+    }
+    assert_eq!(ids[0], ids[1]);
+    assert_eq!(ids[1], ids[2]);
+    assert_eq!(store.trace_count(), 1);
+    assert_eq!(store.frame_count(), backtraces[0].frames().len());
+
+    let other = store.intern(&Backtrace::new_unresolved());
+    assert_ne!(other, ids[0]);
+    assert_eq!(store.trace_count(), 2);
+    // Most frames are shared with the first backtrace.
+    assert!(store.frame_count() < 2 * backtraces[0].frames().len());
+}
+
+#[test]
+fn materialize_and_resolve() {
+    let mut store = TraceStore::new();
+    let bt = capture();
+    let id = store.intern(&bt);
+
+    let frames = store.trace(id).to_vec();
+    assert_eq!(frames.len(), bt.frames().len());
+    for (frame, original) in frames.iter().zip(bt.frames()) {
+        assert_eq!(store.ip(*frame), original.ip());
+    }
+
+    let mut resolved = bt.clone();
+    resolved.resolve();
+    for (frame, original) in frames.iter().zip(resolved.frames()) {
+        let names = |symbols: &[backtrace::BacktraceSymbol]| {
+            symbols
+                .iter()
+                .map(|s| s.name().map(|n| n.to_string()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(store.symbols(*frame)), names(original.symbols()));
+    }
+}
@@ -0,0 +1,54 @@
+#![cfg(any(target_os = "linux", target_os = "android"))]
+
+use backtrace::KernelSymbols;
+use std::io;
+
+const KALLSYMS: &str = "\
+ffffffff81000000 T _stext
+ffffffff81000000 T _text
+ffffffff81000040 D some_data
+ffffffff81000080 T entry_SYSCALL_64
+ffffffff81000100 t do_syscall_64
+0000000000000000 T hidden
+ffffffffc0a01000 t ext4_fill_super\t[ext4]
+ffffffffc0a02000 T ext4_mount\t[ext4]
+";
+
+#[test]
+fn parse() {
+    let syms = KernelSymbols::parse(KALLSYMS);
+    assert_eq!(syms.len(), 6);
+
+    let sym = syms.resolve(0xffffffff81000090).unwrap();
+    assert_eq!(sym.name().as_str(), Some("entry_SYSCALL_64"));
+    assert_eq!(sym.addr(), 0xffffffff81000080);
+    assert_eq!(sym.module(), None);
+
+    // Data symbols are skipped.
+    let sym = syms.resolve(0xffffffff81000040).unwrap();
+    assert_eq!(sym.name().as_str(), Some("_text"));
+
+    let sym = syms.resolve(0xffffffffc0a01234).unwrap();
+    assert_eq!(sym.name().as_str(), Some("ext4_fill_super"));
+    assert_eq!(sym.module(), Some("ext4"));
+
+    assert!(syms.resolve(0x1000).is_none());
+    assert!(syms.resolve(0xffffffffc0a02010).is_none());
+}
+
+#[test]
+fn hidden_addresses() {
+    let syms = KernelSymbols::parse("0000000000000000 T _stext\n0000000000000000 T _text\n");
+    assert!(syms.is_empty());
+}
+
+#[test]
+fn load() {
+    match KernelSymbols::load() {
+        Ok(syms) => assert!(!syms.is_empty()),
+        Err(e) => assert!(matches!(
+            e.kind(),
+            io::ErrorKind::PermissionDenied | io::ErrorKind::NotFound
+        )),
+    }
+}
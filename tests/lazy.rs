@@ -0,0 +1,36 @@
+use backtrace::LazyBacktrace;
+use std::time::{Duration, Instant};
+
+#[test]
+fn resolves_on_first_use() {
+    let bt = LazyBacktrace::new();
+    assert!(!bt.is_resolved());
+    let printed = format!("{bt:?}");
+    assert!(bt.is_resolved());
+    if cfg!(debug_assertions) {
+        assert!(printed.contains("resolves_on_first_use"), "{printed}");
+    }
+    assert_eq!(
+        bt.to_backtrace().frames().len(),
+        bt.clone().to_backtrace().frames().len()
+    );
+}
+
+#[test]
+fn resolves_in_background() {
+    let bt = LazyBacktrace::new_background();
+    let deadline = Instant::now() + Duration::from_secs(60);
+    while !bt.is_resolved() {
+        assert!(Instant::now() < deadline, "not resolved in the background");
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    if cfg!(debug_assertions) {
+        let printed = format!("{bt:?}");
+        assert!(printed.contains("resolves_in_background"), "{printed}");
+    }
+
+    // Dropping backtraces before they're resolved is fine too.
+    for _ in 0..10 {
+        drop(LazyBacktrace::new_background());
+    }
+}
@@ -0,0 +1,111 @@
+// The dynamic loader of glibc can be run explicitly to start programs.
+#![cfg(all(target_os = "linux", target_env = "gnu"))]
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Mutex;
+
+mod common;
+
+type Pos = (&'static str, u32);
+
+const VAR: &str = "__BACKTRACE_MODULE_ATTRIBUTION_EXE";
+
+/// Returns the module of the frame of `function` in a backtrace captured now.
+fn module_of(function: &str) -> Option<PathBuf> {
+    let backtrace = backtrace::Backtrace::new();
+    let symbol = backtrace
+        .frames()
+        .iter()
+        .flat_map(|frame| frame.symbols())
+        .find(|sym| {
+            sym.name()
+                .map_or(false, |name| name.to_string().contains(function))
+        })?;
+    symbol.module().map(Path::to_path_buf)
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap()
+}
+
+#[test]
+fn host_and_library() {
+    let exe = env::current_exe().unwrap();
+    let module = module_of("host_and_library").unwrap();
+    assert_eq!(canonical(&module), canonical(&exe));
+
+    let lib_path = exe.parent().unwrap().join("libdylib_dep.so");
+    unsafe {
+        let lib = libloading::Library::new(&lib_path).unwrap();
+        backtrace::notify_modules_changed();
+        let foo = lib.get::<extern "C" fn(Pos, fn(Pos, Pos))>(b"foo").unwrap();
+        foo(("", 0), library_callback);
+    }
+    let module = LIBRARY_MODULE.lock().unwrap().take().unwrap();
+    assert_eq!(canonical(&module), canonical(&lib_path));
+}
+
+static LIBRARY_MODULE: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+// Records the module of the library function calling this.
+#[inline(never)]
+fn library_callback(_: Pos, _: Pos) {
+    let backtrace = backtrace::Backtrace::new();
+    let frames = backtrace.frames();
+    let callback = frames
+        .iter()
+        .position(|frame| {
+            frame.symbols().iter().any(|sym| {
+                sym.name()
+                    .map_or(false, |name| name.to_string().contains("library_callback"))
+            })
+        })
+        .unwrap();
+    let module = frames[callback + 1]
+        .symbols()
+        .first()
+        .and_then(|sym| sym.module())
+        .map(Path::to_path_buf);
+    *LIBRARY_MODULE.lock().unwrap() = module;
+}
+
+// When a program is started through the dynamic loader, the kernel considers
+// the loader the main program.
+#[test]
+fn started_by_dynamic_loader() {
+    if common::cannot_reexec_the_test() {
+        return;
+    }
+    if let Some(exe) = env::var_os(VAR) {
+        let module = module_of("started_by_dynamic_loader").unwrap();
+        assert_eq!(canonical(&module), canonical(Path::new(&exe)));
+        return;
+    }
+
+    let maps = fs::read_to_string("/proc/self/maps").unwrap();
+    let loader = maps
+        .lines()
+        .filter_map(|line| line.split_whitespace().nth(5))
+        .find(|path| {
+            Path::new(path)
+                .file_name()
+                .map_or(false, |name| name.to_string_lossy().starts_with("ld-linux"))
+        })
+        .unwrap();
+    let exe = env::current_exe().unwrap();
+    let output = Command::new(loader)
+        .arg(&exe)
+        .args(["--exact", "started_by_dynamic_loader", "--test-threads=1"])
+        .env(VAR, &exe)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
@@ -0,0 +1,59 @@
+use backtrace::{AddressKind, ExternalModule, ModuleOffset, ModuleTable};
+
+#[inline(never)]
+fn target() -> u32 {
+    unsafe { std::ptr::read_volatile(&42) }
+}
+
+fn names(resolve: impl FnOnce(&mut dyn FnMut(&backtrace::Symbol))) -> Vec<String> {
+    let mut names = Vec::new();
+    resolve(&mut |sym| names.extend(sym.name().map(|name| name.to_string())));
+    names
+}
+
+#[test]
+fn round_trips_through_the_current_process() {
+    let addr = (target as *const () as usize + 1) as *mut std::ffi::c_void;
+    let offset = match ModuleOffset::from_address(addr) {
+        Some(offset) => offset,
+        // Modules or their build IDs aren't known on all platforms.
+        None => return,
+    };
+    assert!(!offset.build_id().is_empty());
+    assert_eq!(offset.to_address(), Some(addr));
+    assert_eq!(
+        ModuleOffset::new(offset.build_id(), offset.offset()),
+        offset
+    );
+
+    let found =
+        names(|cb| backtrace::resolve_module_offset(&offset, AddressKind::ReturnAddress, cb));
+    assert!(found.iter().any(|n| n.contains("target")), "{found:?}");
+
+    let other = ModuleOffset::new(b"not a build id", offset.offset());
+    assert_eq!(other.to_address(), None);
+}
+
+#[cfg(not(all(windows, target_env = "msvc")))]
+#[test]
+fn resolves_in_module_tables() {
+    let addr = target as *const () as usize + 1;
+    let offset = match ModuleOffset::from_address(addr as *mut std::ffi::c_void) {
+        Some(offset) => offset,
+        None => return,
+    };
+
+    // The same offset refers to the same code wherever the module is loaded.
+    let exe = std::env::current_exe().unwrap();
+    let module = ExternalModule::new(exe, 0x10_0000, 1 << 32).build_id(offset.build_id());
+    let mut table = ModuleTable::new(vec![module]);
+    let converted = table.module_offset(0x10_0000 + offset.offset()).unwrap();
+    assert_eq!(converted, offset);
+
+    let found = names(|cb| table.resolve_module_offset(&offset, AddressKind::ReturnAddress, cb));
+    assert!(found.iter().any(|n| n.contains("target")), "{found:?}");
+
+    let unknown = ModuleOffset::new(b"not a build id", offset.offset());
+    let found = names(|cb| table.resolve_module_offset(&unknown, AddressKind::ReturnAddress, cb));
+    assert!(found.is_empty(), "{found:?}");
+}
@@ -0,0 +1,35 @@
+// Symbols are only resolved in configurable ways where gimli is used.
+#![cfg(not(all(windows, target_env = "msvc")))]
+
+use backtrace::{AddressKind, ExternalModule, ModuleTable};
+use std::path::Path;
+
+/// Resolves the address of `extended_function` in `extended_sections.elf`,
+/// as loaded from `path` at 0x10000.
+fn resolve(path: &str) -> Vec<String> {
+    let module = ExternalModule::new(path, 0x10000, 0x1000);
+    let mut table = ModuleTable::new(vec![module]);
+    let mut names = Vec::new();
+    table.resolve(0x101c3, AddressKind::Precise, |sym| {
+        names.push(sym.name().unwrap().to_string())
+    });
+    names
+}
+
+// All of this changes global settings, so it's done in a single test.
+#[test]
+fn rewrites_module_paths() {
+    let data = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data");
+    let path = "/target/lib/extended_sections.elf";
+    assert!(resolve(path).is_empty());
+
+    backtrace::set_module_path_prefixes(&[
+        (Path::new("/"), Path::new("/nonexistent")),
+        (Path::new("/target/lib"), &data),
+    ]);
+    assert_eq!(resolve(path), ["extended_function"]);
+    assert!(resolve("/target/libextended_sections.elf").is_empty());
+
+    backtrace::set_module_path_prefixes(&[]);
+    assert!(resolve(path).is_empty());
+}
@@ -0,0 +1,80 @@
+use std::ffi::c_void;
+
+#[inline(never)]
+fn module_symbols_marker() -> u32 {
+    unsafe { std::ptr::read_volatile(&42) }
+}
+
+#[test]
+fn finds_own_symbols() {
+    // Symbol tables are only read on platforms using gimli.
+    if cfg!(all(windows, target_env = "msvc")) {
+        return;
+    }
+
+    let marker = module_symbols_marker as *const () as *mut c_void;
+    let mut found = None;
+    let mut count = 0;
+    let mut last = 0;
+    backtrace::module_symbols(marker, |symbol| {
+        let addr = symbol.addr() as usize;
+        assert!(addr >= last, "symbols aren't sorted by address");
+        last = addr;
+        count += 1;
+        if symbol.name().to_string().contains("module_symbols_marker") {
+            found = Some((symbol.addr(), symbol.size()));
+        }
+    });
+    assert!(count > 1);
+
+    let (addr, size) = found.expect("marker function not found");
+    assert_eq!(addr, marker);
+    if let Some(size) = size {
+        assert!(size > 0);
+    }
+    assert_eq!(module_symbols_marker(), 42);
+}
+
+#[test]
+fn unknown_module() {
+    backtrace::module_symbols(std::ptr::null_mut(), |_| panic!());
+}
+
+#[test]
+fn finds_symbols_by_name() {
+    if cfg!(all(windows, target_env = "msvc")) {
+        return;
+    }
+
+    let marker = module_symbols_marker as *const () as *mut c_void;
+    let mut name = None;
+    backtrace::module_symbols(marker, |symbol| {
+        if symbol.name().to_string().contains("module_symbols_marker") {
+            name = Some(symbol.name().as_bytes().to_vec());
+        }
+    });
+    let name = name.expect("marker function not found");
+    let symbol = backtrace::find_module_symbol(marker, &name).unwrap();
+    assert_eq!(symbol.addr(), marker);
+    assert!(backtrace::find_module_symbol(marker, b"no_such_symbol").is_none());
+    assert!(backtrace::find_module_symbol(std::ptr::null_mut(), &name).is_none());
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn finds_dynamic_symbols_by_name() {
+    extern "C" {
+        fn getpid() -> i32;
+    }
+
+    // The C library is usually stripped of its full symbol table, so this
+    // goes through its GNU hash table.
+    let getpid = getpid as *const () as *mut c_void;
+    let symbol = backtrace::find_module_symbol(getpid, b"getpid").unwrap();
+    assert_eq!(symbol.addr(), getpid);
+    assert!(backtrace::find_module_symbol(getpid, b"no_such_symbol").is_none());
+
+    let count = backtrace::dynamic_symbol_count(getpid).unwrap();
+    assert!(count > 100, "{count}");
+    assert!(backtrace::dynamic_symbol_count(std::ptr::null_mut()).is_none());
+}
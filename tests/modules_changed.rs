@@ -0,0 +1,47 @@
+// Other platforms either don't have a library which surely isn't loaded yet,
+// or track modules on their own.
+#![cfg(all(target_os = "linux", target_env = "gnu"))]
+
+use libloading::Library;
+use std::ffi::c_void;
+
+fn names(addr: usize) -> Vec<String> {
+    let mut names = Vec::new();
+    backtrace::resolve(addr as *mut c_void, |sym| {
+        names.push(sym.name().unwrap().to_string());
+    });
+    names
+}
+
+#[test]
+fn resolves_after_loading_and_unloading() {
+    // List the modules before loading any more.
+    let _ = backtrace::Backtrace::new();
+
+    let lib = unsafe { Library::new("libm.so.6").unwrap() };
+    let frexp = unsafe { *lib.get::<*const c_void>(b"frexp\0").unwrap() } as usize;
+    assert!(names(frexp + 1).is_empty());
+
+    backtrace::notify_modules_changed();
+    let frexp_names = names(frexp + 1);
+    assert!(
+        frexp_names.iter().any(|name| name.contains("frexp")),
+        "{frexp_names:?}"
+    );
+
+    // Symbols of the executable are still resolved afterwards.
+    let bt = backtrace::Backtrace::new();
+    assert!(bt
+        .frames()
+        .iter()
+        .flat_map(|frame| frame.symbols())
+        .any(|sym| sym
+            .name()
+            .map_or(false, |name| name.to_string().contains("resolves_after"))));
+
+    // Addresses captured before unloading still resolve from the file.
+    drop(lib);
+    backtrace::notify_modules_changed();
+    backtrace::clear_symbol_cache();
+    assert_eq!(names(frexp + 1), frexp_names);
+}
@@ -0,0 +1,246 @@
+use backtrace::{AddressKind, ExternalModule, ModuleTable, ObjectSymbolizer};
+
+/// Returns the address the current executable was loaded at, which is what
+/// addresses in it are relocated by as it's position independent.
+#[cfg(target_os = "linux")]
+fn exe_base() -> Option<usize> {
+    let exe = std::env::current_exe().ok()?;
+    let maps = std::fs::read_to_string("/proc/self/maps").ok()?;
+    let line = maps.lines().find(|line| {
+        let fields = line.split_ascii_whitespace().collect::<Vec<_>>();
+        fields.len() >= 6 && fields[2] == "00000000" && exe.as_os_str() == fields[5]
+    })?;
+    let start = line.split('-').next()?;
+    usize::from_str_radix(start, 16).ok()
+}
+
+#[cfg(target_os = "linux")]
+#[inline(never)]
+fn target() -> u32 {
+    unsafe { std::ptr::read_volatile(&42) }
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn resolves_address_in_bytes() {
+    let base = match exe_base() {
+        Some(base) => base,
+        None => return,
+    };
+    let data = std::fs::read(std::env::current_exe().unwrap()).unwrap();
+    let svma = (target as *const () as usize - base) as u64;
+
+    let mut symbolizer = ObjectSymbolizer::new(&data).unwrap();
+    let mut names = Vec::new();
+    symbolizer.resolve(svma, |sym| names.push(sym.name().unwrap().to_string()));
+    assert!(names.iter().any(|n| n.contains("target")), "{names:?}");
+
+    let symbols = backtrace::symbolize_object(&data, svma);
+    let names = symbols
+        .iter()
+        .filter_map(|s| Some(s.name()?.to_string()))
+        .collect::<Vec<_>>();
+    assert!(names.iter().any(|n| n.contains("target")), "{names:?}");
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn resolves_addresses_in_file() {
+    let base = match exe_base() {
+        Some(base) => base,
+        None => return,
+    };
+    let exe = std::env::current_exe().unwrap();
+    let addrs = [0, target as *const () as u64];
+
+    let mut names = Vec::new();
+    backtrace::resolve_file(&exe, base as u64, &addrs, |i, sym| {
+        assert_eq!(i, 1);
+        names.push(sym.name().unwrap().to_string());
+    });
+    assert!(names.iter().any(|n| n.contains("target")), "{names:?}");
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn resolves_addresses_of_module_tables() {
+    let base = match exe_base() {
+        Some(base) => base as u64,
+        None => return,
+    };
+    let exe = std::env::current_exe().unwrap();
+    let mut table = ModuleTable::new(vec![ExternalModule::new(exe, base, 1 << 32)]);
+    let mut names = Vec::new();
+    let addr = target as *const () as u64 + 1;
+    table.resolve(addr, AddressKind::ReturnAddress, |sym| {
+        names.push(sym.name().unwrap().to_string())
+    });
+    assert!(names.iter().any(|n| n.contains("target")), "{names:?}");
+}
+
+#[cfg(target_os = "linux")]
+extern "C" {
+    fn getpid() -> i32;
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn reads_module_tables_from_process_memory() {
+    // Find where the C library is mapped, with the lowest and highest address
+    // of all of its mappings.
+    let getpid = getpid as *const () as u64;
+    let maps = std::fs::read_to_string("/proc/self/maps").unwrap();
+    let ranges = maps
+        .lines()
+        .filter_map(|line| {
+            let fields = line.split_ascii_whitespace().collect::<Vec<_>>();
+            let (start, end) = fields[0].split_once('-')?;
+            let start = u64::from_str_radix(start, 16).ok()?;
+            let end = u64::from_str_radix(end, 16).ok()?;
+            Some((start, end, *fields.get(5)?))
+        })
+        .collect::<Vec<_>>();
+    let libc = match ranges.iter().find(|r| r.0 <= getpid && getpid < r.1) {
+        Some(range) => range.2,
+        None => return,
+    };
+    let libc = ranges.iter().filter(|r| r.2 == libc);
+    let base = libc.clone().map(|r| r.0).min().unwrap();
+    let end = libc.map(|r| r.1).max().unwrap();
+
+    // The file can't be opened, as in another mount namespace.
+    let module = ExternalModule::new("/nonexistent/libc.so.6", base, end - base);
+    let mut table = ModuleTable::new(vec![module.clone()]);
+    table.resolve(getpid, AddressKind::Precise, |_| panic!());
+
+    let mut table = ModuleTable::new(vec![module]).read_process_memory(std::process::id());
+    let mut names = Vec::new();
+    table.resolve(getpid, AddressKind::Precise, |sym| {
+        names.push(sym.name().unwrap().to_string())
+    });
+    assert!(names.iter().any(|n| n.contains("getpid")), "{names:?}");
+}
+
+#[cfg(not(all(windows, target_env = "msvc")))]
+#[test]
+fn resolves_module_tables_in_batches() {
+    // `big_endian.elf` expects to be loaded at 0x10000000.
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/big_endian.elf");
+    let mut table = ModuleTable::new(vec![
+        ExternalModule::new(path, 0x7000_0000, 0x20000),
+        ExternalModule::new("/nonexistent", 0x1000, 0x1000),
+    ]);
+    assert_eq!(table.modules()[0].base(), 0x1000);
+
+    let addrs = [0x7001_0104, 0x1500, 0x10, 0x7001_0104, 0x7002_0000];
+    let mut found = Vec::new();
+    table.resolve_many(&addrs, AddressKind::Precise, |i, sym| {
+        assert_eq!(sym.name().unwrap().as_bytes(), b"big_endian_function");
+        found.push(i);
+    });
+    assert_eq!(found, [0, 3]);
+}
+
+#[cfg(not(all(windows, target_env = "msvc")))]
+#[test]
+fn resolves_svmas_in_module_tables() {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/big_endian.elf");
+    let mut table = ModuleTable::new(vec![
+        ExternalModule::new(path, 0x7000_0000, 0x20000),
+        ExternalModule::new("/nonexistent", 0x1000, 0x1000),
+    ]);
+
+    // SVMAs are used as is, whatever the module was loaded at.
+    let mut names = Vec::new();
+    table.resolve_svma(1, 0x1001_0104, AddressKind::Precise, |sym| {
+        names.push(sym.name().unwrap().to_string())
+    });
+    table.resolve_svma(1, 0x1001_0105, AddressKind::ReturnAddress, |sym| {
+        names.push(sym.name().unwrap().to_string())
+    });
+    assert_eq!(names, ["big_endian_function", "big_endian_function"]);
+
+    let mut found = false;
+    table.resolve_svma(0, 0x1001_0104, AddressKind::Precise, |_| found = true);
+    table.resolve_svma(2, 0x1001_0104, AddressKind::Precise, |_| found = true);
+    table.resolve_svma(1, 0x7001_0104, AddressKind::Precise, |_| found = true);
+    assert!(!found);
+}
+
+#[test]
+fn rejects_garbage() {
+    assert!(ObjectSymbolizer::new(b"not an object file").is_none());
+    assert!(backtrace::symbolize_object(&[], 0).is_empty());
+    backtrace::resolve_file("/nonexistent".as_ref(), 0, &[0], |_, _| panic!());
+}
+
+#[cfg(not(all(windows, target_env = "msvc")))]
+#[test]
+fn resolves_other_platforms() {
+    // See `data/big_endian.rs` for how this was built.
+    let data = include_bytes!("data/big_endian.elf");
+    let mut symbolizer = ObjectSymbolizer::new(data).unwrap();
+    let mut found = false;
+    symbolizer.resolve(0x10010104, |sym| {
+        found = true;
+        assert_eq!(sym.name().unwrap().as_bytes(), b"big_endian_function");
+        assert_eq!(
+            sym.filename().unwrap().file_name().unwrap(),
+            "big_endian.rs"
+        );
+        assert_eq!(sym.lineno(), Some(26));
+    });
+    assert!(found);
+}
+
+#[cfg(not(all(windows, target_env = "msvc")))]
+#[test]
+fn resolves_dynamic_symbols() {
+    // See `data/partial_symtab.c` for how this was built.
+    let data = include_bytes!("data/partial_symtab.elf");
+    let mut symbolizer = ObjectSymbolizer::new(data).unwrap();
+    for (svma, expected) in [(0x1c4, "exported_function"), (0x1c8, "local_function")] {
+        let mut names = Vec::new();
+        symbolizer.resolve(svma, |sym| names.push(sym.name().unwrap().to_string()));
+        assert_eq!(names, [expected]);
+    }
+}
+
+#[cfg(not(all(windows, target_env = "msvc")))]
+#[test]
+fn names_functions_without_symbols() {
+    // See `data/stripped.c` for how this was built.
+    let data = include_bytes!("data/stripped.elf");
+    let mut symbolizer = ObjectSymbolizer::new(data).unwrap();
+    for (svma, expected) in [
+        (0x1c9, "fn_0x1c9+0x0"),
+        (0x1cc, "fn_0x1c9+0x3"),
+        (0x1ce, "fn_0x1cd+0x1"),
+    ] {
+        let mut names = Vec::new();
+        symbolizer.resolve(svma, |sym| names.push(sym.name().unwrap().to_string()));
+        assert_eq!(names, [expected]);
+    }
+
+    // Addresses outside of any function aren't named after the previous one.
+    symbolizer.resolve(0x1d4, |_| panic!());
+}
+
+#[cfg(not(all(windows, target_env = "msvc")))]
+#[test]
+fn resolves_extended_section_numbering() {
+    // See `data/extended_sections.c` for how this was built.
+    let data = include_bytes!("data/extended_sections.elf");
+    let mut symbolizer = ObjectSymbolizer::new(data).unwrap();
+    let mut found = false;
+    symbolizer.resolve(0x1c3, |sym| {
+        found = true;
+        assert_eq!(sym.name().unwrap().as_bytes(), b"extended_function");
+        assert_eq!(
+            sym.filename().unwrap().file_name().unwrap(),
+            "extended_sections.c"
+        );
+        assert_eq!(sym.lineno(), Some(17));
+    });
+    assert!(found);
+}
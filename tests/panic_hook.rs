@@ -0,0 +1,59 @@
+use backtrace::{ColorMode, PanicHookOptions};
+use std::env;
+use std::process::Command;
+
+const VAR: &str = "__BACKTRACE_PANIC_HOOK_CHILD";
+
+mod common;
+
+fn main() {
+    // If we cannot re-exec this test, there's no point in trying to do it.
+    if common::cannot_reexec_the_test() {
+        println!("test result: ok");
+        return;
+    }
+
+    if env::var(VAR).is_err() {
+        parent();
+    } else {
+        child();
+    }
+}
+
+fn parent() {
+    let me = env::current_exe().unwrap();
+    let result = Command::new(&me)
+        .env_remove("RUST_BACKTRACE")
+        .env(VAR, "1")
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&result.stderr);
+    assert!(!result.status.success());
+    assert!(
+        stderr.contains("thread 'main' panicked at tests/panic_hook.rs:"),
+        "{stderr}"
+    );
+    assert!(
+        stderr.contains("\nthe message 42\nstack backtrace:\n"),
+        "{stderr}"
+    );
+    if cfg!(debug_assertions) {
+        // The backtrace starts at the function which panicked.
+        let first = stderr.lines().find(|line| line.contains("  0: "));
+        let first = first.unwrap_or_else(|| panic!("{stderr}"));
+        assert!(first.contains("panic_hook::panics"), "{stderr}");
+        assert!(stderr.contains("panic_hook::main"), "{stderr}");
+        assert!(!stderr.contains("lang_start"), "{stderr}");
+    }
+    println!("test result: ok");
+}
+
+fn child() {
+    backtrace::install_panic_hook(PanicHookOptions::new().color(ColorMode::Never));
+    panics(42);
+}
+
+#[inline(never)]
+fn panics(n: u32) {
+    panic!("the message {n}");
+}
@@ -0,0 +1,77 @@
+// The test binary is an ELF file, whose compressed sections are decompressed
+// by the ELF parser of the gimli backend.
+#![cfg(target_os = "linux")]
+
+use backtrace::ParseLimits;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// The address of the call to `sink` in `inner` in
+/// `tests/data/parse_limits.elf`, inlined into `middle` and `outer`.
+const ADDR: u64 = 0x217;
+
+static MESSAGES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+fn record(message: std::fmt::Arguments<'_>) {
+    MESSAGES.lock().unwrap().push(message.to_string());
+}
+
+/// Resolves `ADDR`, returning the names of the functions and their line
+/// numbers, along with the diagnostics reported meanwhile.
+fn resolve() -> (Vec<(String, Option<u32>)>, Vec<String>) {
+    let lib = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/parse_limits.elf");
+    let mut symbols = Vec::new();
+    backtrace::resolve_file(&lib, 0, &[ADDR], |_, sym| {
+        symbols.push((sym.name().unwrap().to_string(), sym.lineno()));
+    });
+    (symbols, std::mem::take(&mut *MESSAGES.lock().unwrap()))
+}
+
+// All of this changes global settings, so it's done in a single test.
+#[test]
+fn enforces_limits() {
+    backtrace::set_diagnostics_hook(Some(record));
+    // Only the line table is used with `minimal-symbolication`, which doesn't
+    // know about inlined functions.
+    #[cfg(not(feature = "minimal-symbolication"))]
+    let inlined = vec![
+        ("inner".to_string(), Some(17)),
+        ("middle".to_string(), Some(21)),
+        ("outer".to_string(), Some(26)),
+    ];
+    #[cfg(feature = "minimal-symbolication")]
+    let inlined = vec![("outer".to_string(), Some(17))];
+    let (symbols, messages) = resolve();
+    assert_eq!(symbols, inlined);
+    assert_eq!(messages, Vec::<String>::new());
+
+    // The compressed debug sections are too large once decompressed, so only
+    // the symbol table is left.
+    backtrace::set_parse_limits(ParseLimits::new().max_section_size(Some(64)));
+    let (symbols, messages) = resolve();
+    assert_eq!(symbols, vec![("outer".to_string(), None)]);
+    assert!(
+        messages.iter().any(|m| m.contains("larger than the limit")),
+        "{messages:?}"
+    );
+
+    // Only the function the others were inlined into is found in units
+    // nested too deeply, with the line from the line table.
+    #[cfg(not(feature = "minimal-symbolication"))]
+    {
+        backtrace::set_parse_limits(ParseLimits::new().max_die_depth(Some(2)));
+        let (symbols, messages) = resolve();
+        assert_eq!(symbols, vec![("outer".to_string(), Some(17))]);
+        assert!(
+            messages.iter().any(|m| m.contains("nested deeper")),
+            "{messages:?}"
+        );
+
+        backtrace::set_parse_limits(ParseLimits::new().max_die_depth(Some(16)));
+        assert_eq!(resolve(), (inlined.clone(), Vec::new()));
+    }
+
+    backtrace::set_parse_limits(ParseLimits::new());
+    assert_eq!(resolve(), (inlined, Vec::new()));
+    backtrace::set_diagnostics_hook(None);
+}
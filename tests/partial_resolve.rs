@@ -0,0 +1,36 @@
+use backtrace::{Backtrace, CancellationToken, ResolveStatus};
+use std::time::{Duration, Instant};
+
+#[test]
+fn expired_deadline() {
+    let mut bt = Backtrace::new_unresolved();
+    let status = bt.resolve_with_deadline(Instant::now());
+    assert_eq!(status, ResolveStatus::TimedOut);
+    assert!(bt.frames().iter().all(|f| !f.is_resolved()));
+
+    // The backtrace can still be resolved afterwards.
+    bt.resolve();
+    assert!(bt.frames().iter().all(|f| f.is_resolved()));
+}
+
+#[test]
+fn generous_deadline() {
+    let mut bt = Backtrace::new_unresolved();
+    let deadline = Instant::now() + Duration::from_secs(3600);
+    assert_eq!(bt.resolve_with_deadline(deadline), ResolveStatus::Complete);
+    assert!(bt.frames().iter().all(|f| f.is_resolved()));
+}
+
+#[test]
+fn cancelled() {
+    let token = CancellationToken::new();
+    let mut bt = Backtrace::new_unresolved();
+    assert_eq!(bt.resolve_cancellable(&token), ResolveStatus::Complete);
+
+    let mut bt = Backtrace::new_unresolved();
+    let clone = token.clone();
+    std::thread::spawn(move || clone.cancel()).join().unwrap();
+    assert!(token.is_cancelled());
+    assert_eq!(bt.resolve_cancellable(&token), ResolveStatus::Cancelled);
+    assert!(bt.frames().iter().all(|f| !f.is_resolved()));
+}
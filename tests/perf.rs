@@ -0,0 +1,54 @@
+#![cfg(target_os = "linux")]
+
+use backtrace::{PerfOptions, PerfSampler};
+use std::time::{Duration, Instant};
+
+extern "C" {
+    fn gettid() -> i32;
+}
+
+#[inline(never)]
+fn busy(duration: Duration) -> u64 {
+    let start = Instant::now();
+    let mut n = 0u64;
+    while start.elapsed() < duration {
+        n = unsafe { std::ptr::read_volatile(&n.wrapping_mul(31).wrapping_add(7)) };
+    }
+    n
+}
+
+#[test]
+fn samples_this_thread() {
+    let tid = unsafe { gettid() };
+    let options = PerfOptions::new().thread(tid).kernel(false).frequency(1000);
+    let mut sampler = match PerfSampler::open(options) {
+        Ok(sampler) => sampler,
+        // Sampling isn't allowed everywhere, like in most containers.
+        Err(err) => {
+            eprintln!("skipping: {err}");
+            return;
+        }
+    };
+    busy(Duration::from_millis(200));
+
+    let mut samples = 0;
+    let mut in_busy = 0;
+    sampler.drain(|sample| {
+        samples += 1;
+        assert_eq!(sample.tid(), tid as u32);
+        assert!(sample.kernel_ips().is_empty());
+        let ip = match sample.user_ips().first() {
+            Some(&ip) => ip,
+            None => return,
+        };
+        backtrace::resolve_address(ip as *mut _, backtrace::AddressKind::Precise, |symbol| {
+            if let Some(name) = symbol.name() {
+                if name.to_string().contains("busy") {
+                    in_busy += 1;
+                }
+            }
+        });
+    });
+    assert!(samples > 0);
+    assert!(in_busy > 0, "{in_busy} of {samples} samples in `busy`");
+}
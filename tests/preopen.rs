@@ -0,0 +1,33 @@
+use backtrace::{Backtrace, Symbolizer};
+
+#[test]
+fn resolves_without_loading() {
+    backtrace::preopen_symbols();
+    let before = backtrace::symbolizer_stats();
+    let bt = Backtrace::new();
+    let after = backtrace::symbolizer_stats();
+    assert!(bt.frames().iter().any(|f| !f.symbols().is_empty()));
+
+    // Everything was loaded up front, so nothing is loaded on demand.
+    assert_eq!(after.cache_misses(), before.cache_misses());
+    assert_eq!(after.cache_evictions(), before.cache_evictions());
+
+    // Symbolizers have caches of their own, which still load files.
+    let mut symbolizer = Symbolizer::new();
+    let mut named = false;
+    backtrace::trace(|frame| {
+        symbolizer.resolve_frame(frame, |sym| named |= sym.name().is_some());
+        true
+    });
+    if cfg!(not(all(windows, target_env = "msvc"))) {
+        assert!(named);
+    }
+
+    // Clearing the cache goes back to loading debug information on demand.
+    backtrace::clear_symbol_cache();
+    Backtrace::new();
+    let cleared = backtrace::symbolizer_stats();
+    if cfg!(not(all(windows, target_env = "msvc"))) {
+        assert!(cleared.cache_misses() > after.cache_misses());
+    }
+}
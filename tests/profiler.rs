@@ -0,0 +1,61 @@
+#![cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd"
+))]
+
+use backtrace::{Backtrace, ProfilerOptions, SamplingProfiler};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+static CALLBACK_SAMPLES: AtomicUsize = AtomicUsize::new(0);
+
+#[inline(never)]
+fn busy(duration: Duration) -> u64 {
+    let start = Instant::now();
+    let mut n = 0u64;
+    while start.elapsed() < duration {
+        n = unsafe { std::ptr::read_volatile(&n.wrapping_mul(31).wrapping_add(7)) };
+    }
+    n
+}
+
+fn count(ips: &[usize]) {
+    if !ips.is_empty() {
+        CALLBACK_SAMPLES.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+// Profilers take over the process-wide `SIGPROF` handler, so everything is
+// done in a single test.
+#[test]
+fn samples_busy_code() {
+    let options = ProfilerOptions::new()
+        .interval(Duration::from_millis(1))
+        .capacity(10_000);
+    let profiler = SamplingProfiler::start(options.clone()).unwrap();
+    assert!(SamplingProfiler::start(options.clone()).is_err());
+    busy(Duration::from_millis(200));
+
+    let mut samples = Vec::new();
+    profiler.drain(|ips| samples.push(ips.to_vec()));
+    drop(profiler);
+    assert!(!samples.is_empty());
+    assert!(samples.iter().all(|ips| ips.len() <= 64));
+    if cfg!(debug_assertions) {
+        let in_busy = samples.iter().take(20).any(|ips| {
+            let mut bt = Backtrace::from_ips(ips);
+            bt.resolve();
+            let names = format!("{bt:?}");
+            !names.contains("on_sigprof") && names.contains("profiler::busy")
+        });
+        assert!(in_busy, "{samples:x?}");
+    }
+
+    let profiler = unsafe { SamplingProfiler::start_with_callback(options, count) }.unwrap();
+    busy(Duration::from_millis(100));
+    drop(profiler);
+    assert!(CALLBACK_SAMPLES.load(Ordering::SeqCst) > 0);
+}
@@ -0,0 +1,52 @@
+use std::ffi::c_void;
+use std::panic;
+
+fn addr() -> *mut c_void {
+    // Resolving looks up the instruction before an address, as if it was a
+    // return address.
+    (resolve_names as *const () as usize + 1) as *mut c_void
+}
+
+#[inline(never)]
+fn resolve_names() -> Vec<String> {
+    let mut names = Vec::new();
+    backtrace::resolve(addr(), |sym| {
+        names.extend(sym.name().map(|name| name.to_string()));
+    });
+    names
+}
+
+#[test]
+fn resolve_from_callback() {
+    let outer = resolve_names();
+    let mut inner = Vec::new();
+    let mut backtrace = None;
+    backtrace::resolve(addr(), |_| {
+        inner = resolve_names();
+        backtrace = Some(backtrace::Backtrace::new());
+    });
+    assert_eq!(inner, outer);
+
+    // Symbols resolved by the nested calls are as good as any others.
+    let backtrace = backtrace.unwrap();
+    assert!(backtrace
+        .frames()
+        .iter()
+        .flat_map(|frame| frame.symbols())
+        .any(|sym| sym.name().map_or(false, |name| name
+            .to_string()
+            .contains("resolve_from_callback"))));
+
+    // And the outer call didn't lose its cache.
+    assert_eq!(resolve_names(), outer);
+}
+
+#[test]
+fn panic_from_callback() {
+    let outer = resolve_names();
+    let result = panic::catch_unwind(|| {
+        backtrace::resolve(addr(), |_| panic!("from the callback"));
+    });
+    assert!(result.is_err());
+    assert_eq!(resolve_names(), outer);
+}
@@ -0,0 +1,37 @@
+type Symbols = Vec<(Option<String>, Option<u32>)>;
+
+fn resolve_one(ip: usize) -> Symbols {
+    let mut symbols = Vec::new();
+    backtrace::resolve(ip as *mut _, |s| {
+        symbols.push((s.name().map(|n| n.to_string()), s.lineno()));
+    });
+    symbols
+}
+
+#[test]
+fn matches_resolve() {
+    let mut ips = Vec::new();
+    backtrace::trace(|frame| {
+        ips.push(frame.ip() as usize);
+        true
+    });
+    assert!(ips.len() > 1);
+    // Duplicates are resolved once per occurrence.
+    ips.push(ips[0]);
+    ips.reverse();
+
+    let mut symbols = vec![Symbols::new(); ips.len()];
+    backtrace::resolve_many(&ips, |i, s| {
+        symbols[i].push((s.name().map(|n| n.to_string()), s.lineno()));
+    });
+
+    for (ip, symbols) in ips.iter().zip(&symbols) {
+        assert_eq!(*symbols, resolve_one(*ip));
+    }
+    assert!(symbols.iter().flatten().any(|(name, _)| name.is_some()));
+}
+
+#[test]
+fn empty() {
+    backtrace::resolve_many(&[], |_, _| panic!());
+}
@@ -0,0 +1,58 @@
+// Symbols are only resolved in configurable ways where gimli is used.
+#![cfg(not(all(windows, target_env = "msvc")))]
+
+use backtrace::{ObjectSymbolizer, ResolveMode};
+use std::ffi::c_void;
+
+#[inline(never)]
+fn target() -> u32 {
+    unsafe { std::ptr::read_volatile(&42) }
+}
+
+fn resolve(addr: *mut c_void) -> Vec<(String, Option<u32>)> {
+    let mut symbols = Vec::new();
+    backtrace::resolve(addr, |sym| {
+        symbols.push((sym.name().unwrap().to_string(), sym.lineno()));
+    });
+    symbols
+}
+
+// All of this changes global settings, so it's done in a single test.
+#[test]
+fn names_only() {
+    // Addresses are resolved as return addresses, one byte back.
+    let target = (target as *const () as usize + 1) as *mut c_void;
+    let full = resolve(target);
+    assert!(
+        full.iter().any(|(name, _)| name.contains("target")),
+        "{full:?}"
+    );
+    let has_lines = full.iter().any(|(_, line)| line.is_some());
+
+    // The same functions are found, just without their lines.
+    backtrace::set_resolve_mode(ResolveMode::NamesOnly);
+    let names_only = resolve(target);
+    assert_eq!(
+        names_only,
+        full.iter()
+            .map(|(name, _)| (name.clone(), None))
+            .collect::<Vec<_>>()
+    );
+
+    // See `data/extended_sections.c` for how this was built.
+    let data = include_bytes!("data/extended_sections.elf");
+    let mut symbolizer = ObjectSymbolizer::new(data).unwrap();
+    let mut found = Vec::new();
+    symbolizer.resolve(0x1c3, |sym| {
+        found.push((sym.name().unwrap().to_string(), sym.filename().is_some()));
+    });
+    assert_eq!(found, [("extended_function".to_string(), false)]);
+
+    backtrace::set_resolve_mode(ResolveMode::Full);
+    assert_eq!(
+        resolve(target).iter().any(|(_, line)| line.is_some()),
+        has_lines
+    );
+    let mut symbolizer = ObjectSymbolizer::new(data).unwrap();
+    symbolizer.resolve(0x1c3, |sym| assert_eq!(sym.lineno(), Some(17)));
+}
@@ -0,0 +1,36 @@
+use backtrace::{resolve_owned, Backtrace, BacktraceSymbol};
+use std::thread;
+
+#[test]
+fn resolve_on_other_thread() {
+    let ip = resolve_on_other_thread as *const () as usize + 1;
+    let symbols: Vec<BacktraceSymbol> = thread::spawn(move || resolve_owned(ip as *mut _))
+        .join()
+        .unwrap();
+    if cfg!(debug_assertions) {
+        let name = symbols[0].name().unwrap().to_string();
+        assert!(name.contains("resolve_on_other_thread"), "{name}");
+    }
+
+    if cfg!(target_os = "linux") {
+        let exe = std::env::current_exe().unwrap();
+        assert_eq!(symbols[0].module(), Some(exe.as_path()));
+    }
+}
+
+#[test]
+fn backtrace_symbols_have_modules() {
+    let bt = Backtrace::new();
+    if cfg!(target_os = "linux") {
+        let exe = std::env::current_exe().unwrap();
+        let symbol = &bt.frames()[0].symbols()[0];
+        assert_eq!(symbol.module(), Some(exe.as_path()));
+
+        // Frames of the same module share its path.
+        let caller = &bt.frames()[1].symbols()[0];
+        assert!(std::ptr::eq(
+            symbol.module().unwrap(),
+            caller.module().unwrap()
+        ));
+    }
+}
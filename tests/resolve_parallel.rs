@@ -0,0 +1,49 @@
+use backtrace::{Backtrace, BacktraceFrame};
+
+fn symbols(frame: &BacktraceFrame) -> Vec<(Option<String>, Option<u32>, bool)> {
+    frame
+        .symbols()
+        .iter()
+        .map(|s| {
+            let name = s.name().map(|n| n.to_string());
+            (name, s.lineno(), s.module().is_some())
+        })
+        .collect()
+}
+
+#[test]
+fn matches_resolve() {
+    let mut traces = vec![Backtrace::new_unresolved(), Backtrace::new_unresolved()];
+    let mut expected = traces.clone();
+    expected.iter_mut().for_each(Backtrace::resolve);
+
+    Backtrace::resolve_all_parallel(&mut traces);
+    for (trace, expected) in traces.iter().zip(&expected) {
+        assert_eq!(trace.frames().len(), expected.frames().len());
+        for (frame, expected) in trace.frames().iter().zip(expected.frames()) {
+            assert!(frame.is_resolved());
+            assert_eq!(symbols(frame), symbols(expected));
+        }
+    }
+    assert!(traces
+        .iter()
+        .flat_map(|t| t.frames())
+        .any(|f| f.symbols().iter().any(|s| s.name().is_some())));
+}
+
+#[test]
+fn partially_resolved() {
+    let mut bt = Backtrace::new_unresolved();
+    let mut expected = bt.clone();
+    expected.resolve();
+
+    assert!(bt.frames().len() > 1);
+    let mut frames: Vec<BacktraceFrame> = bt.clone().into();
+    frames[0].resolve();
+    bt = frames.into();
+    bt.resolve_parallel();
+    for (frame, expected) in bt.frames().iter().zip(expected.frames()) {
+        assert!(frame.is_resolved());
+        assert_eq!(symbols(frame), symbols(expected));
+    }
+}
@@ -321,3 +321,67 @@ fn sp_smoke_test() {
         }
     }
 }
+
+#[inline(never)]
+fn precise_target() -> u32 {
+    unsafe { std::ptr::read_volatile(&42) }
+}
+
+#[test]
+fn resolves_precise_addresses() {
+    use backtrace::AddressKind;
+    use std::ffi::c_void;
+
+    let addr = get_actual_fn_pointer(precise_target as *const () as usize);
+    let names = |addr: usize, kind| {
+        let mut names = Vec::new();
+        backtrace::resolve_address(addr as *mut c_void, kind, |sym| {
+            names.push(sym.name().map(|name| name.to_string()));
+        });
+        names
+    };
+
+    // The first instruction of a function is only found when it isn't taken
+    // for a return address, which is moved back into the previous function.
+    let precise = names(addr, AddressKind::Precise);
+    assert!(
+        precise.iter().any(|name| name
+            .as_deref()
+            .map_or(false, |n| n.contains("precise_target"))),
+        "{precise:?}"
+    );
+    assert_eq!(names(addr + 1, AddressKind::ReturnAddress), precise);
+}
+
+#[test]
+fn trace_ips() {
+    let mut traced = Vec::new();
+    backtrace::trace(|frame| {
+        traced.push(frame.ip() as usize);
+        true
+    });
+    let mut ips = [0; 256];
+    let len = backtrace::trace_ips(&mut ips);
+    let ips = &ips[..len];
+    assert!(len > 0 && len < 256);
+
+    // Only the frames of the test itself and its callers are the same.
+    let callers = |ips: &[usize]| {
+        let index = ips.iter().position(|&ip| {
+            let mut found = false;
+            backtrace::resolve(ip as *mut _, |sym| {
+                found |= sym
+                    .name()
+                    .map_or(false, |name| name.to_string().contains("smoke::trace_ips"));
+            });
+            found
+        });
+        index.map(|index| ips[index + 1..].to_vec())
+    };
+    if let (Some(expected), Some(actual)) = (callers(&traced), callers(ips)) {
+        assert_eq!(expected, actual);
+    }
+
+    assert_eq!(backtrace::trace_ips(&mut [0; 2]), 2);
+    assert_eq!(backtrace::trace_ips(&mut []), 0);
+}
@@ -0,0 +1,64 @@
+// A function without call frame information is only easily written in
+// assembly, which is only done for x86_64.
+#![cfg(all(target_os = "linux", target_arch = "x86_64"))]
+
+use backtrace::Backtrace;
+use std::sync::atomic::{compiler_fence, Ordering};
+use std::sync::Mutex;
+
+std::arch::global_asm!(
+    ".globl no_cfi_call",
+    "no_cfi_call:",
+    "    sub rsp, 8",
+    "    call rdi",
+    "    add rsp, 8",
+    "    ret",
+);
+
+extern "C" {
+    // Calls `f` without any call frame information, which stops unwinders.
+    fn no_cfi_call(f: extern "C" fn());
+}
+
+static TRACE: Mutex<Option<Backtrace>> = Mutex::new(None);
+
+extern "C" fn capture() {
+    *TRACE.lock().unwrap() = Some(Backtrace::new());
+}
+
+#[inline(never)]
+fn call_through_gap() -> Backtrace {
+    unsafe { no_cfi_call(capture) };
+    compiler_fence(Ordering::SeqCst);
+    TRACE.lock().unwrap().take().unwrap()
+}
+
+fn names(trace: &Backtrace, scanned: bool) -> Vec<String> {
+    trace
+        .frames()
+        .iter()
+        .filter(|frame| frame.is_scanned() == scanned)
+        .flat_map(|frame| frame.symbols())
+        .filter_map(|symbol| Some(symbol.name()?.to_string()))
+        .collect()
+}
+
+#[test]
+fn scans_past_frames_without_cfi() {
+    let trace = call_through_gap();
+    assert!(names(&trace, false).iter().any(|n| n.contains("capture")));
+    assert!(!names(&trace, false)
+        .iter()
+        .any(|n| n.contains("call_through_gap")));
+    assert!(names(&trace, true).is_empty());
+
+    backtrace::set_stack_scanning(true);
+    let trace = call_through_gap();
+    backtrace::set_stack_scanning(false);
+    let scanned = names(&trace, true);
+    assert!(
+        scanned.iter().any(|n| n.contains("call_through_gap")),
+        "{scanned:?}"
+    );
+    assert!(format!("{trace:?}").contains("[scanned]"));
+}
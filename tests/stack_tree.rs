@@ -0,0 +1,74 @@
+use backtrace::{Backtrace, StackTree};
+
+#[test]
+fn merges_stacks() {
+    let mut tree = StackTree::new();
+    tree.add_ips(&[3, 2, 1], 1);
+    tree.add_ips(&[4, 2, 1], 2);
+    tree.add_ips(&[3, 2, 1], 5);
+    tree.add_ips(&[2, 1], 1);
+
+    let root = tree.root();
+    assert_eq!(tree.node_count(), 5);
+    assert_eq!(tree.total_weight(root), 9);
+    assert_eq!(tree.self_weight(root), 0);
+    let one = tree.children(root)[0];
+    assert_eq!(tree.ip(one) as usize, 1);
+    let two = tree.children(one)[0];
+    assert_eq!(tree.parent(two), Some(one));
+    assert_eq!((tree.self_weight(two), tree.total_weight(two)), (1, 9));
+    let leaves = tree.children(two).to_vec();
+    assert_eq!(leaves.len(), 2);
+    assert_eq!(tree.ip(leaves[0]) as usize, 3);
+    assert_eq!(
+        (tree.self_weight(leaves[0]), tree.total_weight(leaves[0])),
+        (6, 6)
+    );
+    assert_eq!(tree.ip(leaves[1]) as usize, 4);
+    assert_eq!(tree.self_weight(leaves[1]), 2);
+
+    // There are no symbols at these addresses.
+    let mut folded = Vec::new();
+    tree.write_folded(&mut folded).unwrap();
+    assert_eq!(
+        String::from_utf8(folded).unwrap(),
+        "0x1;0x2 1\n0x1;0x2;0x3 6\n0x1;0x2;0x4 2\n"
+    );
+}
+
+#[inline(never)]
+fn capture() -> Backtrace {
+    Backtrace::new_unresolved()
+}
+
+#[test]
+fn exports_resolved_stacks() {
+    let mut tree = StackTree::new();
+    for _ in 0..unsafe { std::ptr::read_volatile(&3) } {
+        tree.add(&capture(), 10);
+    }
+    assert_eq!(tree.total_weight(tree.root()), 30);
+
+    let mut folded = Vec::new();
+    tree.write_folded(&mut folded).unwrap();
+    let folded = String::from_utf8(folded).unwrap();
+    assert_eq!(folded.lines().count(), 1, "{folded}");
+    assert!(folded.ends_with(" 30\n"), "{folded}");
+    if cfg!(debug_assertions) {
+        assert!(
+            folded.contains(";stack_tree::exports_resolved_stacks;stack_tree::capture"),
+            "{folded}"
+        );
+    }
+
+    let mut pprof = Vec::new();
+    tree.write_pprof(&mut pprof, "samples", "count").unwrap();
+    // The profile starts with the type of the samples, whose names are in the
+    // string table at the end.
+    assert_eq!(pprof[0], 1 << 3 | 2);
+    let contains = |s: &[u8]| pprof.windows(s.len()).any(|w| w == s);
+    assert!(contains(b"\x32\x07samples\x32\x05count"));
+    if cfg!(debug_assertions) {
+        assert!(contains(b"stack_tree::capture"));
+    }
+}
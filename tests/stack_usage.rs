@@ -0,0 +1,123 @@
+// Stack usage is measured by painting the unused stack below the current
+// frame, which is only done on Linux where thread stacks are mapped upfront.
+#![cfg(target_os = "linux")]
+
+use backtrace::{CaptureHandle, MAX_TRACE_STACK};
+use std::ptr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+const PAINTED: usize = 64 * 1024;
+// Not painted, as the locals of `stack_used` may live below `marker`.
+const GAP: usize = 512;
+const PATTERN: u64 = 0xaaaa_5555_aaaa_5555;
+
+/// Returns roughly how many bytes of stack below its own frame `f` used.
+#[inline(never)]
+fn stack_used(f: &mut dyn FnMut()) -> usize {
+    let marker = 0u8;
+    let top = ptr::addr_of!(marker) as usize & !7;
+    let bottom = top - PAINTED;
+    unsafe {
+        for addr in (bottom..top - GAP).step_by(8) {
+            ptr::write_volatile(addr as *mut u64, PATTERN);
+        }
+    }
+    f();
+    let lowest = (bottom..top)
+        .step_by(8)
+        .find(|&addr| unsafe { ptr::read_volatile(addr as *const u64) } != PATTERN)
+        .unwrap();
+    top - lowest
+}
+
+#[inline(never)]
+fn recurse(depth: usize, f: &mut dyn FnMut()) {
+    if depth == 0 {
+        f();
+    } else {
+        recurse(depth - 1, f);
+    }
+    unsafe { ptr::read_volatile(&depth) };
+}
+
+#[test]
+fn tracing_stays_within_bound() {
+    thread::Builder::new()
+        .stack_size(4 << 20)
+        .spawn(|| {
+            let baseline = stack_used(&mut || {});
+            let mut trace = || unsafe {
+                backtrace::trace_unsynchronized(|frame| {
+                    ptr::read_volatile(&frame.ip());
+                    true
+                });
+            };
+            // The first trace of the process sets up the unwinder and uses the
+            // most stack.
+            let first = stack_used(&mut trace) - baseline;
+            assert!(first <= MAX_TRACE_STACK, "{first} bytes used");
+
+            // Deeper stacks don't need more.
+            let mut deep = || recurse(200, &mut trace);
+            let deep = stack_used(&mut deep);
+            let mut shallow = || recurse(200, &mut || {});
+            let shallow = stack_used(&mut shallow);
+            assert!(
+                deep - shallow <= MAX_TRACE_STACK,
+                "{} bytes used",
+                deep - shallow
+            );
+
+            let storage = (0..65).map(|_| AtomicUsize::new(0)).collect::<Vec<_>>();
+            let handle = CaptureHandle::new(&storage, 64);
+            let used = stack_used(&mut || unsafe {
+                assert!(handle.capture());
+            }) - baseline;
+            assert!(used <= MAX_TRACE_STACK, "{used} bytes used");
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+}
+
+static TRACED: AtomicUsize = AtomicUsize::new(0);
+
+extern "C" fn trace_on_signal(_signum: libc::c_int) {
+    let mut ips = [0; 16];
+    let len = unsafe { backtrace::trace_ips_unsynchronized(&mut ips) };
+    TRACED.store(len, Ordering::Relaxed);
+}
+
+/// Returns how many frames are traced from a signal handler running on an
+/// alternate signal stack of `size` bytes.
+fn traced_on_altstack(size: usize) -> usize {
+    thread::spawn(move || unsafe {
+        let mut altstack = vec![0u8; size];
+        let mut stack: libc::stack_t = std::mem::zeroed();
+        stack.ss_sp = altstack.as_mut_ptr().cast();
+        stack.ss_size = size;
+        assert_eq!(libc::sigaltstack(&stack, ptr::null_mut()), 0);
+
+        let mut action: libc::sigaction = std::mem::zeroed();
+        action.sa_sigaction = trace_on_signal as *const () as libc::sighandler_t;
+        action.sa_flags = libc::SA_ONSTACK;
+        assert_eq!(libc::sigaction(libc::SIGUSR1, &action, ptr::null_mut()), 0);
+        TRACED.store(usize::MAX, Ordering::Relaxed);
+        assert_eq!(libc::raise(libc::SIGUSR1), 0);
+
+        stack.ss_flags = libc::SS_DISABLE;
+        assert_eq!(libc::sigaltstack(&stack, ptr::null_mut()), 0);
+        drop(altstack);
+        TRACED.load(Ordering::Relaxed)
+    })
+    .join()
+    .unwrap()
+}
+
+#[test]
+fn small_altstacks_are_not_overflowed() {
+    assert!(traced_on_altstack(64 * 1024) > 0);
+    // Too small for `MAX_TRACE_STACK` once the signal frame is on it.
+    assert_eq!(traced_on_altstack(MAX_TRACE_STACK - 2048), 0);
+}
@@ -0,0 +1,25 @@
+use backtrace::Backtrace;
+
+#[test]
+fn counts_resolves() {
+    let before = backtrace::symbolizer_stats();
+    let bt = Backtrace::new();
+    let after = backtrace::symbolizer_stats();
+
+    assert!(after.resolves() >= before.resolves() + bt.frames().len() as u64);
+    assert!(after.resolve_time() >= before.resolve_time());
+    assert!(after.max_resolve_time() <= after.resolve_time());
+
+    // The cache of debug information is only used with gimli.
+    if cfg!(all(windows, target_env = "msvc")) {
+        return;
+    }
+    let lookups = |s: &backtrace::SymbolizerStats| s.cache_hits() + s.cache_misses();
+    assert!(lookups(&after) > lookups(&before));
+    assert!(after.cache_hits() > 0);
+    assert!(after.modules_with_debuginfo() + after.modules_without_debuginfo() > 0);
+    assert!(after.cached_bytes() > 0);
+
+    backtrace::clear_symbol_cache();
+    assert_eq!(backtrace::symbolizer_stats().cached_bytes(), 0);
+}
@@ -0,0 +1,89 @@
+use backtrace::{Backtrace, PrintFmt};
+
+#[inline(never)]
+fn std_capture() -> std::backtrace::Backtrace {
+    std::backtrace::Backtrace::force_capture()
+}
+
+fn converts_std_backtraces() {
+    let std_bt = std_capture();
+    let std_debug = format!("{std_bt:?}");
+    let bt = Backtrace::from(&std_bt);
+    let names: Vec<String> = bt
+        .frames()
+        .iter()
+        .map(|frame| {
+            assert!(frame.ip().is_null());
+            assert!(frame.is_resolved());
+            let symbol = &frame.symbols()[0];
+            match symbol.name() {
+                Some(name) => name.to_string(),
+                None => "<unknown>".into(),
+            }
+        })
+        .collect();
+    assert_eq!(names.len(), std_debug.matches("{ fn: ").count());
+    for name in &names {
+        assert!(std_debug.contains(name.as_str()), "{name}");
+    }
+
+    if cfg!(debug_assertions) {
+        let frame = bt
+            .frames()
+            .iter()
+            .find(|frame| names_frame(frame, "std_capture"))
+            .expect("no frame of the capturing function");
+        let symbol = &frame.symbols()[0];
+        let file = symbol.filename().unwrap().to_str().unwrap();
+        assert!(file.ends_with("std_interop.rs"), "{file}");
+        assert_eq!(symbol.lineno(), Some(5));
+
+        // Frames without addresses are still printed.
+        let printed = format!("{bt:?}");
+        assert!(printed.contains("std_capture"), "{printed}");
+    }
+
+    assert!(Backtrace::from(std::backtrace::Backtrace::disabled())
+        .frames()
+        .is_empty());
+}
+
+fn names_frame(frame: &backtrace::BacktraceFrame, name: &str) -> bool {
+    frame.symbols()[0]
+        .name()
+        .map_or(false, |n| n.to_string().ends_with(name))
+}
+
+#[inline(never)]
+fn captures_like_std() {
+    std::env::set_var("RUST_BACKTRACE", "0");
+    std::env::set_var("RUST_LIB_BACKTRACE", "1");
+    let mut bt = Backtrace::capture().unwrap();
+    assert!(!bt.frames()[0].is_resolved());
+    bt.resolve();
+    // The frames of `capture` are only left out where `symbol_address` finds
+    // the start of functions, see `skip_inner_frames.rs`.
+    if cfg!(all(
+        debug_assertions,
+        not(any(target_vendor = "apple", target_arch = "arm"))
+    )) {
+        let name = bt.frames()[0].symbols()[0].name().unwrap().to_string();
+        assert!(name.contains("captures_like_std"), "{name}");
+    }
+
+    // The environment is only looked at once.
+    std::env::set_var("RUST_LIB_BACKTRACE", "0");
+    assert!(Backtrace::capture().is_some());
+    assert!(matches!(
+        Backtrace::capture_with_style(),
+        Some((_, PrintFmt::Short))
+    ));
+}
+
+// All of this changes global settings, so it's done in a single test.
+#[test]
+fn std_interop() {
+    // The environment is changed before anything else looks at it.
+    captures_like_std();
+    converts_std_backtraces();
+}
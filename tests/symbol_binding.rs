@@ -0,0 +1,88 @@
+// Only ELF symbol tables record bindings, visibilities and versions.
+#![cfg(target_os = "linux")]
+
+use backtrace::{SymbolBinding, SymbolSource, SymbolSourceMode, SymbolVisibility};
+use std::ffi::c_void;
+
+#[inline(never)]
+fn local_target() -> u32 {
+    unsafe { std::ptr::read_volatile(&42) }
+}
+
+#[no_mangle]
+#[inline(never)]
+pub extern "C" fn symbol_binding_global_target() -> u32 {
+    unsafe { std::ptr::read_volatile(&42) }
+}
+
+extern "C" {
+    fn getpid() -> i32;
+}
+
+fn resolve(
+    addr: usize,
+) -> (
+    Option<SymbolBinding>,
+    Option<SymbolVisibility>,
+    Option<String>,
+) {
+    let mut found = None;
+    // Addresses are resolved as return addresses, one byte back.
+    backtrace::resolve((addr + 1) as *mut c_void, |sym| {
+        if found.is_none() {
+            let version = sym.version().map(|v| v.to_string());
+            found = Some((sym.binding(), sym.visibility(), version));
+        }
+    });
+    found.expect("no symbol found")
+}
+
+// This changes global settings, so it's done in a single test.
+#[test]
+fn symbol_table_metadata() {
+    backtrace::set_symbol_sources(&[SymbolSource::SymbolTable], SymbolSourceMode::First);
+
+    let (binding, visibility, version) = resolve(local_target as *const () as usize);
+    assert_eq!(binding, Some(SymbolBinding::Local));
+    assert!(visibility.is_some());
+    assert_eq!(version, None);
+
+    let (binding, visibility, _) = resolve(symbol_binding_global_target as *const () as usize);
+    assert_eq!(binding, Some(SymbolBinding::Global));
+    assert_eq!(visibility, Some(SymbolVisibility::Default));
+
+    // The C library may have a full symbol table, which takes precedence over
+    // the versioned dynamic one.
+    let (binding, visibility, version) = resolve(getpid as *const () as usize);
+    assert!(binding.is_some());
+    assert_eq!(visibility, Some(SymbolVisibility::Default));
+    if let Some(version) = version {
+        assert!(version.starts_with('@'), "{version}");
+    }
+
+    // Digests of the symbol table keep the metadata. Debug information has
+    // to be loaded for there to be anything to digest.
+    backtrace::set_symbol_sources(
+        &[SymbolSource::SymbolTable, SymbolSource::DebugInfo],
+        SymbolSourceMode::First,
+    );
+    backtrace::set_compact_symbols(true);
+    backtrace::clear_symbol_cache();
+    let (binding, _, _) = resolve(local_target as *const () as usize);
+    assert_eq!(binding, Some(SymbolBinding::Local));
+    let (binding, visibility, _) = resolve(symbol_binding_global_target as *const () as usize);
+    assert_eq!(binding, Some(SymbolBinding::Global));
+    assert_eq!(visibility, Some(SymbolVisibility::Default));
+    backtrace::set_compact_symbols(false);
+    backtrace::clear_symbol_cache();
+
+    // Debug information has no such metadata.
+    backtrace::set_symbol_sources(&[SymbolSource::DebugInfo], SymbolSourceMode::First);
+    backtrace::resolve(
+        (local_target as *const () as usize + 1) as *mut c_void,
+        |sym| {
+            assert_eq!(sym.binding(), None);
+            assert_eq!(sym.version().map(|v| v.to_string()), None);
+        },
+    );
+}
@@ -0,0 +1,63 @@
+// Digests are only cached where symbols are resolved with gimli, for modules
+// with a build ID, which Linux binaries have by default.
+#![cfg(target_os = "linux")]
+
+use std::ffi::c_void;
+use std::fs;
+
+#[inline(never)]
+fn target() -> u32 {
+    unsafe { std::ptr::read_volatile(&42) }
+}
+
+fn symbols() -> Vec<(String, Option<u32>)> {
+    let mut symbols = Vec::new();
+    backtrace::resolve((target as *const () as usize + 1) as *mut c_void, |sym| {
+        symbols.push((sym.name().unwrap().to_string(), sym.lineno()));
+    });
+    symbols
+}
+
+// All of this changes global settings, so it's done in a single test.
+#[test]
+fn digests_are_cached() {
+    let dir = std::env::temp_dir().join(format!("backtrace-symbol-cache-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let uncached = symbols();
+    assert!(uncached[0].0.contains("target"), "{uncached:?}");
+    assert!(uncached[0].1.is_some(), "{uncached:?}");
+
+    // Loading the executable again saves its digest.
+    backtrace::set_symbol_cache_dir(Some(&dir));
+    backtrace::clear_symbol_cache();
+    assert_eq!(symbols(), uncached);
+    let digests = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().map_or(false, |ext| ext == "digest"))
+        .collect::<Vec<_>>();
+    assert!(!digests.is_empty());
+
+    // Later on, the digest is used instead of the executable.
+    backtrace::clear_symbol_cache();
+    assert_eq!(symbols(), uncached);
+    let files = backtrace::symbol_files();
+    assert!(
+        digests.iter().any(|digest| files.contains(digest)),
+        "{files:?}"
+    );
+
+    // Damaged digests are replaced.
+    for digest in &digests {
+        fs::write(digest, b"BTDIGEST garbage").unwrap();
+    }
+    backtrace::clear_symbol_cache();
+    assert_eq!(symbols(), uncached);
+    assert!(digests
+        .iter()
+        .any(|digest| fs::metadata(digest).unwrap().len() > 16));
+
+    backtrace::set_symbol_cache_dir(None);
+    backtrace::clear_symbol_cache();
+    let _ = fs::remove_dir_all(&dir);
+}
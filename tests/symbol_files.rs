@@ -0,0 +1,17 @@
+use std::env;
+use std::fs;
+
+#[test]
+fn includes_current_exe() {
+    let files = backtrace::symbol_files();
+    if cfg!(all(windows, target_env = "msvc")) {
+        assert!(files.is_empty());
+        return;
+    }
+
+    let exe = fs::canonicalize(env::current_exe().unwrap()).unwrap();
+    assert!(files
+        .iter()
+        .any(|f| fs::canonicalize(f).map_or(false, |f| f == exe)));
+    assert!(files.windows(2).all(|w| w[0] < w[1]));
+}
@@ -0,0 +1,65 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::os::raw::c_void;
+use std::path::Path;
+
+#[inline(never)]
+fn dumped_function_marker() -> u32 {
+    unsafe { std::ptr::read_volatile(&42) }
+}
+
+/// Resolves the address of `dumped_function_marker` to its name, if found.
+fn marker_name() -> Option<String> {
+    let mut name = None;
+    let addr = dumped_function_marker as *const () as usize + 1;
+    backtrace::resolve(addr as *mut c_void, |sym| {
+        if name.is_none() {
+            name = sym.name().map(|name| name.to_string());
+        }
+    });
+    name
+}
+
+// All of this changes global settings, so it's done in a single test.
+#[test]
+fn dumps_and_loads() {
+    let path = std::env::temp_dir().join(format!("backtrace-symbol-map-{}", std::process::id()));
+    let res = backtrace::dump_symbol_map(&path);
+    if cfg!(all(windows, target_env = "msvc")) {
+        assert_eq!(res.unwrap_err().kind(), ErrorKind::Unsupported);
+        return;
+    }
+    res.unwrap();
+    assert!(dumped_function_marker() > 0);
+
+    // The symbol tables of the modules are in there.
+    let data = fs::read(&path).unwrap();
+    let marker = b"dumped_function_marker";
+    assert!(data.windows(marker.len()).any(|w| w == marker));
+
+    // Make the files of all modules impossible to open, as if they were gone.
+    // Deleting them isn't enough on Linux, where their images are still read
+    // through `/proc`.
+    backtrace::set_module_path_prefixes(&[(Path::new("/"), Path::new("/nonexistent"))]);
+    backtrace::clear_symbol_cache();
+    let gone = marker_name();
+    assert!(
+        gone.as_ref()
+            .map_or(true, |name| !name.contains("dumped_function_marker")),
+        "{gone:?}"
+    );
+
+    // Now they're resolved from the symbol map instead.
+    backtrace::load_symbol_map(&path).unwrap();
+    backtrace::clear_symbol_cache();
+    let name = marker_name().unwrap();
+    assert!(name.contains("dumped_function_marker"), "{name}");
+    backtrace::set_module_path_prefixes(&[]);
+    backtrace::clear_symbol_cache();
+
+    // Anything else is rejected.
+    fs::write(&path, &data[..data.len() - 1]).unwrap();
+    let err = backtrace::load_symbol_map(&path).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    let _ = fs::remove_file(&path);
+}
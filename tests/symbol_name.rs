@@ -0,0 +1,81 @@
+use backtrace::{DemangleOptions, SymbolLanguage, SymbolName};
+
+// `<alloc::vec::Vec<T> as core::ops::drop::Drop>::drop`
+const VEC_DROP: &[u8] =
+    b"_ZN66_$LT$alloc..vec..Vec$LT$T$GT$$u20$as$u20$core..ops..drop..Drop$GT$4drop17h6d2b5c1d5c4e3f2aE";
+
+fn display(name: &[u8], options: DemangleOptions) -> String {
+    SymbolName::new(name).with_options(options).to_string()
+}
+
+#[test]
+fn default_options_print_full_name() {
+    assert_eq!(
+        display(VEC_DROP, DemangleOptions::new()),
+        "<alloc::vec::Vec<T> as core::ops::drop::Drop>::drop::h6d2b5c1d5c4e3f2a"
+    );
+}
+
+#[test]
+fn strip_hash() {
+    assert_eq!(
+        display(VEC_DROP, DemangleOptions::new().strip_hash(true)),
+        "<alloc::vec::Vec<T> as core::ops::drop::Drop>::drop"
+    );
+}
+
+#[test]
+fn omit_template_args() {
+    let options = DemangleOptions::new()
+        .strip_hash(true)
+        .omit_template_args(true);
+    assert_eq!(
+        display(VEC_DROP, options),
+        "<alloc::vec::Vec as core::ops::drop::Drop>::drop"
+    );
+}
+
+#[test]
+fn max_len() {
+    let options = DemangleOptions::new().strip_hash(true).max_len(Some(12));
+    assert_eq!(display(VEC_DROP, options), "<alloc::v...");
+
+    let options = DemangleOptions::new().max_len(Some(2));
+    assert_eq!(display(VEC_DROP, options), "..");
+
+    let options = DemangleOptions::new().max_len(Some(100));
+    assert_eq!(display(b"not_mangled", options), "not_mangled".to_string());
+
+    // Names of exactly the maximum length aren't elided.
+    let options = DemangleOptions::new().max_len(Some(11));
+    assert_eq!(display(b"not_mangled", options), "not_mangled".to_string());
+    let options = DemangleOptions::new().max_len(Some(10));
+    assert_eq!(display(b"not_mangled", options), "not_man...".to_string());
+    let options = DemangleOptions::new().max_len(Some(2));
+    assert_eq!(display(b"ab", options), "ab".to_string());
+}
+
+#[test]
+fn language() {
+    let language = |name: &[u8]| SymbolName::new(name).language();
+
+    assert_eq!(language(VEC_DROP), SymbolLanguage::RustLegacy);
+    assert_eq!(
+        language(b"_ZN3foo3bar17h05af221e174051e9E.llvm.1234"),
+        SymbolLanguage::RustLegacy
+    );
+    assert_eq!(language(b"_RNvCs1234_7mycrate3foo"), SymbolLanguage::RustV0);
+    assert_eq!(
+        language(b"__RNvCs1234_7mycrate3foo"),
+        SymbolLanguage::RustV0
+    );
+    assert_eq!(language(b"_ZN3foo3barEv"), SymbolLanguage::Cpp);
+    assert_eq!(
+        language(b"__ZNSt6vectorIiSaIiEE9push_backERKi"),
+        SymbolLanguage::Cpp
+    );
+    assert_eq!(language(b"?foo@@YAXXZ"), SymbolLanguage::Msvc);
+    assert_eq!(language(b"$s4main3fooyyF"), SymbolLanguage::Swift);
+    assert_eq!(language(b"_$s4main3fooyyF"), SymbolLanguage::Swift);
+    assert_eq!(language(b"main"), SymbolLanguage::Unknown);
+}
@@ -0,0 +1,119 @@
+// Symbols are only resolved in configurable ways where gimli is used.
+#![cfg(not(all(windows, target_env = "msvc")))]
+
+use backtrace::{SymbolSource, SymbolSourceMode};
+use std::ffi::c_void;
+#[cfg(target_os = "linux")]
+use std::sync::atomic::AtomicUsize;
+
+const UNKNOWN: usize = 0x10;
+
+fn custom(addr: *mut c_void, cb: &mut dyn FnMut(&[u8])) {
+    // Return addresses are moved back into the call instruction.
+    if addr as usize <= UNKNOWN {
+        cb(b"unknown");
+    } else {
+        cb(b"custom");
+    }
+}
+
+fn names(addr: usize) -> Vec<String> {
+    let mut names = Vec::new();
+    backtrace::resolve(addr as *mut c_void, |sym| {
+        names.push(sym.name().unwrap().to_string());
+    });
+    names
+}
+
+#[inline(never)]
+fn target() -> u32 {
+    unsafe { std::ptr::read_volatile(&42) }
+}
+
+#[cfg(target_os = "linux")]
+extern "C" {
+    fn getpid() -> i32;
+}
+
+#[cfg(target_os = "linux")]
+static DATA_SYMBOL: AtomicUsize = AtomicUsize::new(1);
+
+// All of this changes global settings, so it's done in a single test.
+#[test]
+fn configurable_sources() {
+    backtrace::set_custom_symbol_resolver(Some(custom));
+    // Addresses are resolved as return addresses, one byte back.
+    let target = target as *const () as usize + 1;
+
+    // Custom resolvers also know about addresses outside of any module.
+    assert!(names(UNKNOWN).is_empty());
+    backtrace::set_symbol_sources(&[SymbolSource::Custom], SymbolSourceMode::First);
+    assert_eq!(names(UNKNOWN), ["unknown"]);
+    let mut many = Vec::new();
+    backtrace::resolve_many(&[target, UNKNOWN], |i, sym| {
+        many.push((i, sym.name().unwrap().to_string()));
+    });
+    assert_eq!(
+        many,
+        [(0, "custom".to_string()), (1, "unknown".to_string())]
+    );
+
+    // Only the first source which finds anything is used, unless merging.
+    let sources = [SymbolSource::SymbolTable, SymbolSource::Custom];
+    backtrace::set_symbol_sources(&sources, SymbolSourceMode::First);
+    let names_first = names(target);
+    assert_eq!(names_first.len(), 1, "{names_first:?}");
+    assert!(names_first[0].contains("target"), "{names_first:?}");
+    backtrace::set_symbol_sources(&sources, SymbolSourceMode::Merge);
+    let names_merged = names(target);
+    assert_eq!(names_merged, [names_first[0].as_str(), "custom"]);
+
+    // The symbol table has no line numbers.
+    backtrace::set_symbol_sources(&[SymbolSource::SymbolTable], SymbolSourceMode::First);
+    backtrace::resolve(target as *mut c_void, |sym| {
+        assert!(sym.name().is_some());
+        assert!(sym.lineno().is_none());
+    });
+
+    #[cfg(target_os = "linux")]
+    {
+        backtrace::set_symbol_sources(&[SymbolSource::Dladdr], SymbolSourceMode::First);
+        let getpid_names = names(getpid as *const () as usize + 1);
+        if cfg!(feature = "pure-rust") {
+            // The dynamic linker is never asked.
+            assert!(getpid_names.is_empty(), "{getpid_names:?}");
+        } else {
+            // The C library may report an alias, like `__getpid` for glibc.
+            assert_eq!(getpid_names.len(), 1, "{getpid_names:?}");
+            assert!(getpid_names[0].ends_with("getpid"), "{getpid_names:?}");
+
+            // `dladdr` also knows where the symbol and its module start.
+            let getpid_addr = getpid as *const () as usize;
+            let mut found = false;
+            backtrace::resolve((getpid_addr + 3) as *mut c_void, |sym| {
+                assert_eq!(sym.addr(), Some(getpid_addr as *mut c_void));
+                assert_eq!(sym.offset(), Some(2));
+                let base = sym.module_base().unwrap() as usize;
+                assert!(base != 0 && base <= getpid_addr);
+                let path = sym.module_path().unwrap().to_str_lossy().into_owned();
+                assert!(path.contains("libc"), "{path}");
+                found = true;
+            });
+            assert!(found);
+        }
+
+        // Static variables are in data segments, which are skipped by default.
+        let data = &DATA_SYMBOL as *const AtomicUsize as usize + 1;
+        backtrace::set_symbol_sources(&[SymbolSource::SymbolTable], SymbolSourceMode::First);
+        assert!(names(data).is_empty());
+        backtrace::set_resolve_data_addresses(true);
+        let data_names = names(data);
+        backtrace::set_resolve_data_addresses(false);
+        assert!(data_names[0].contains("DATA_SYMBOL"), "{data_names:?}");
+    }
+
+    backtrace::set_symbol_sources(&[], SymbolSourceMode::First);
+    backtrace::set_custom_symbol_resolver(None);
+    assert!(names(UNKNOWN).is_empty());
+    assert!(names(target).iter().any(|name| name.contains("target")));
+}
@@ -0,0 +1,46 @@
+use backtrace::{FileAccess, Symbolizer};
+
+#[inline(never)]
+fn resolve_here(symbolizer: &mut Symbolizer) -> Vec<String> {
+    let mut names = Vec::new();
+    backtrace::trace(|frame| {
+        symbolizer.resolve_frame(frame, |sym| {
+            if let Some(name) = sym.name() {
+                names.push(name.to_string());
+            }
+        });
+        true
+    });
+    names
+}
+
+#[test]
+fn resolves_separately() {
+    let lookups = |s: &backtrace::SymbolizerStats| s.cache_hits() + s.cache_misses();
+    let before = backtrace::symbolizer_stats();
+
+    let mut symbolizer = Symbolizer::new();
+    symbolizer.set_cache_size(1);
+    symbolizer.set_file_access(FileAccess::Read);
+    let names = resolve_here(&mut symbolizer);
+    assert!(
+        names.iter().any(|n| n.contains("resolve_here")),
+        "{names:?}"
+    );
+
+    symbolizer.clear_cache();
+    let names = resolve_here(&mut symbolizer);
+    assert!(
+        names.iter().any(|n| n.contains("resolve_here")),
+        "{names:?}"
+    );
+
+    // None of this went through the global cache.
+    assert_eq!(lookups(&backtrace::symbolizer_stats()), lookups(&before));
+}
+
+#[test]
+fn is_send() {
+    fn assert_send<T: Send>() {}
+    assert_send::<Symbolizer>();
+}
@@ -0,0 +1,77 @@
+use backtrace::Backtrace;
+
+fn names(trace: &Backtrace) -> Vec<String> {
+    trace
+        .frames()
+        .iter()
+        .flat_map(|frame| frame.symbols())
+        .filter_map(|symbol| Some(symbol.name()?.to_string()))
+        .collect()
+}
+
+fn position(names: &[String], name: &str) -> Option<usize> {
+    names.iter().position(|n| n.contains(name))
+}
+
+#[inline(never)]
+fn spawn_outer() -> Backtrace {
+    let trace = Backtrace::new_unresolved();
+    unsafe { std::ptr::read_volatile(&&trace) };
+    trace
+}
+
+#[inline(never)]
+fn spawn_inner() -> Backtrace {
+    let trace = Backtrace::new_unresolved();
+    unsafe { std::ptr::read_volatile(&&trace) };
+    trace
+}
+
+#[inline(never)]
+fn poll_inner() -> Backtrace {
+    let trace = Backtrace::new();
+    unsafe { std::ptr::read_volatile(&&trace) };
+    trace
+}
+
+#[inline(never)]
+fn executor(spawned: &Backtrace, poll: impl FnOnce() -> Backtrace) -> Backtrace {
+    backtrace::in_task(spawned, poll)
+}
+
+#[test]
+fn splices_spawn_backtraces() {
+    let outer = spawn_outer();
+    let inner = executor(&outer, spawn_inner);
+    let trace = executor(&inner, poll_inner);
+    if trace.frames().iter().all(|frame| frame.sp().is_null()) {
+        // Nothing can be spliced without stack pointers.
+        return;
+    }
+
+    let found = names(&trace);
+    let poll = position(&found, "poll_inner").unwrap();
+    let inner = position(&found, "spawn_inner").unwrap();
+    let outer = position(&found, "spawn_outer").unwrap();
+    assert!(poll < inner && inner < outer, "{found:?}");
+    assert_eq!(
+        found.iter().filter(|n| n.contains("executor")).count(),
+        0,
+        "{found:?}"
+    );
+
+    let spawned = trace
+        .frames()
+        .iter()
+        .filter(|frame| frame.metadata().get("task") == Some("spawned"))
+        .count();
+    assert_eq!(spawned, 2);
+}
+
+#[test]
+fn only_splices_inside_tasks() {
+    let spawned = spawn_outer();
+    let _ = executor(&spawned, Backtrace::new);
+    let found = names(&poll_inner());
+    assert!(position(&found, "spawn_outer").is_none(), "{found:?}");
+}
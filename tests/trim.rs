@@ -0,0 +1,66 @@
+use backtrace::Backtrace;
+use std::sync::{Arc, Mutex};
+
+fn names(bt: &Backtrace) -> Vec<String> {
+    bt.frames()
+        .iter()
+        .flat_map(|f| f.symbols())
+        .filter_map(|s| Some(format!("{:#}", s.name()?)))
+        .collect()
+}
+
+#[inline(never)]
+fn panics() {
+    panic!("trimmed");
+}
+
+#[test]
+fn trim_panic() {
+    // Symbol names are needed for any of this to work.
+    if !cfg!(debug_assertions) || names(&Backtrace::new()).is_empty() {
+        return;
+    }
+
+    let captured = Arc::new(Mutex::new(None));
+    let hook_captured = captured.clone();
+    let prev = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |_| {
+        *hook_captured.lock().unwrap() = Some(Backtrace::new());
+    }));
+    let result = std::panic::catch_unwind(panics);
+    std::panic::set_hook(prev);
+    assert!(result.is_err());
+
+    let mut bt = captured.lock().unwrap().take().unwrap();
+    let before = names(&bt);
+    bt.trim();
+    let after = names(&bt);
+    assert!(after.len() < before.len());
+
+    assert!(after[0].starts_with("trim::panics"), "{after:#?}");
+    assert!(
+        !after.iter().any(|n| n.contains("rust_begin_unwind")),
+        "{after:#?}"
+    );
+    assert!(
+        after.iter().any(|n| n.starts_with("trim::trim_panic")),
+        "{after:#?}"
+    );
+    assert!(
+        !after
+            .iter()
+            .any(|n| n.contains("__rust_begin_short_backtrace")),
+        "{after:#?}"
+    );
+}
+
+#[test]
+fn trim_end() {
+    let mut bt = Backtrace::new();
+    let before = bt.frames().len();
+    bt.trim_end();
+    assert!(bt.frames().len() <= before);
+
+    let names = names(&bt);
+    assert!(!names.iter().any(|n| n == "start_thread" || n == "main"));
+}
@@ -0,0 +1,35 @@
+use backtrace::{Backtrace, ResolveError};
+use std::time::Instant;
+
+#[test]
+fn resolves() {
+    let mut result = None;
+    backtrace::trace(|frame| {
+        result = Some(backtrace::try_resolve_frame(frame, |_| {}));
+        false
+    });
+    assert_eq!(result, Some(Ok(())));
+}
+
+#[test]
+fn no_module() {
+    let result = backtrace::try_resolve(std::ptr::null_mut(), |_| panic!());
+    assert_eq!(result, Err(ResolveError::NoModule));
+    assert!(!result.unwrap_err().to_string().is_empty());
+}
+
+#[test]
+fn backtrace() {
+    let mut bt = Backtrace::new_unresolved();
+    let results = bt.try_resolve(Some(Instant::now()));
+    assert_eq!(results.len(), bt.frames().len());
+    assert!(results.iter().all(|r| *r == Err(ResolveError::Timeout)));
+
+    let results = bt.try_resolve(None);
+    assert!(bt.frames().iter().all(|f| f.is_resolved()));
+    assert!(results.iter().any(|r| r.is_ok()));
+    for (frame, result) in bt.frames().iter().zip(&results) {
+        assert_eq!(result.is_ok(), !frame.symbols().is_empty());
+        assert_ne!(*result, Err(ResolveError::Timeout));
+    }
+}
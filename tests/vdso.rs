@@ -0,0 +1,36 @@
+#![cfg(target_os = "linux")]
+
+use std::ffi::c_void;
+
+fn vdso_base() -> Option<usize> {
+    let maps = std::fs::read_to_string("/proc/self/maps").ok()?;
+    let line = maps.lines().find(|line| line.ends_with("[vdso]"))?;
+    let start = line.split('-').next()?;
+    usize::from_str_radix(start, 16).ok()
+}
+
+#[test]
+fn clock_gettime() {
+    let base = match vdso_base() {
+        Some(base) => base,
+        None => return,
+    };
+
+    let mut clock_gettime = None;
+    backtrace::module_symbols(base as *mut c_void, |symbol| {
+        if symbol.name().as_bytes().ends_with(b"clock_gettime") {
+            clock_gettime = Some(symbol.addr());
+        }
+    });
+    let addr = clock_gettime.expect("vDSO symbols weren't found") as usize;
+
+    // Pretend this is a return address in the middle of the function.
+    let mut names = Vec::new();
+    backtrace::resolve((addr + 1) as *mut c_void, |symbol| {
+        names.push(symbol.name().unwrap().to_string());
+    });
+    assert!(
+        names.iter().any(|name| name.ends_with("clock_gettime")),
+        "{names:?}"
+    );
+}